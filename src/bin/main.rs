@@ -1,32 +1,81 @@
+use std::sync::Arc;
+
+use backend::lib::app_state::AppState;
 use backend::lib::config::Config;
 // Файл, который лежит в src/bin/*.rs образует crate-исполняемый файл (main)
 // Из main можно пользоваться только тем, что выставлено наружу (pub) из библиотечного crate и корректно объявлено в lib.rs
 use backend::lib::handlers::{
-    handler_restart_container, handler_return_all_containers, handler_start_container,
-    handler_stop_container,
+    handler_all_container_stats, handler_attach_container, handler_container_logs,
+    handler_container_stats, handler_create_container, handler_exec_container,
+    handler_inspect_container, handler_kill_container, handler_pause_container,
+    handler_remove_container, handler_restart_container, handler_return_all_containers,
+    handler_start_container, handler_stop_container, handler_unpause_container,
 };
 use backend::lib::http_server::Server;
+use backend::lib::server_errors::ServerError;
 // use backend::lib::req_res_structs::{BodyType, Response};
 // use backend::lib::request::Request;
 
 fn main() {
-    let conf = Config::from_env().with_port(8080);
+    // Ошибки запуска (кривой конфиг, занятый порт) печатаем и выходим, без паники
+    let conf = match Config::from_env() {
+        Ok(conf) => conf.with_port(8080),
+        Err(e) => exit_with_error(e),
+    };
 
-    let mut server = Server::with_config(conf).unwrap();
+    let mut server = match Server::with_config(conf) {
+        Ok(server) => server,
+        Err(e) => exit_with_error(e),
+    };
 
     server
         .log
         .debug(&format!("ip_port = {}", server.config.port));
 
-    // регистрация пары path и handlers в Hash-table
-    server.GET("/container/", handler_return_all_containers); // 2ой аргумент это тип HandlerFn
+    // Общее состояние для всех хендлеров: настроенный логгер, конфиг
+    let state = Arc::new(AppState::new(server.config.clone()));
+
+    // регистрация пары path и handlers в дереве маршрутов
+    server.GET("/container/", state.handler(handler_return_all_containers)); // 2ой аргумент -- замыкание, см. HandlerFn
+    server.POST("/container", state.handler(handler_create_container)); // без '/' на конце -- другой маршрут
+    server.GET(
+        "/container/stats",
+        state.handler(handler_all_container_stats),
+    ); // статический сегмент важнее :id
+    server.GET("/container/:id", state.handler(handler_inspect_container));
+    server.GET("/container/:id/logs", state.handler(handler_container_logs));
+    server.GET(
+        "/container/:id/stats",
+        state.handler(handler_container_stats),
+    );
 
-    server.POST("/container/:id/restart", handler_restart_container);
-    server.POST("/container/:id/start", handler_start_container);
-    server.POST("/container/:id/stop", handler_stop_container);
+    server.POST(
+        "/container/:id/restart",
+        state.handler(handler_restart_container),
+    );
+    server.POST(
+        "/container/:id/start",
+        state.handler(handler_start_container),
+    );
+    server.POST("/container/:id/stop", state.handler(handler_stop_container));
+    server.POST(
+        "/container/:id/pause",
+        state.handler(handler_pause_container),
+    );
+    server.POST(
+        "/container/:id/unpause",
+        state.handler(handler_unpause_container),
+    );
+    server.POST("/container/:id/kill", state.handler(handler_kill_container));
+    server.DELETE("/container/:id", state.handler(handler_remove_container));
+    server.POST("/container/:id/exec", state.handler(handler_exec_container));
+    server.GET(
+        "/container/:id/attach",
+        state.handler(handler_attach_container),
+    );
 
     // server.POST("/container/:id/reboot", |r: &Request| Response {
-    //     response_code: 200,
+    //     response_code: StatusCode::OK,
     //     headers: None,
     //     body: Some(BodyType::Plain(format!(
     //         "Container ID is: {}\nRebooting...\nTEST",
@@ -35,7 +84,7 @@ fn main() {
     // });
 
     // server.POST("/container/:id/start", |r: &Request| Response {
-    //     response_code: 200,
+    //     response_code: StatusCode::OK,
     //     headers: None,
     //     body: Some(BodyType::Plain(format!(
     //         "Container ID is: {}\nStarting...\nTEST",
@@ -44,7 +93,7 @@ fn main() {
     // });
 
     // server.POST("/container/:id/stop", |r: &Request| Response {
-    //     response_code: 200,
+    //     response_code: StatusCode::OK,
     //     headers: None,
     //     body: Some(BodyType::Plain(format!(
     //         "Container ID is: {}\nStopping...\nTEST",
@@ -52,36 +101,44 @@ fn main() {
     //     ))),
     // });
 
-    server.start().unwrap();
+    // SIGINT (Ctrl+C) и SIGTERM (docker stop, systemd) -- мягкая остановка сервера:
+    // перестаём принимать подключения, ждём текущие запросы и выходим из start()
+    let shutdown = server.shutdown_handle();
+    let log = server.log;
+    let handler = ctrlc::set_handler(move || {
+        log.info(&"Received termination signal, shutting down...".to_string());
+        shutdown.shutdown();
+    });
+    if let Err(e) = handler {
+        exit_with_error(ServerError::InitError(format!(
+            "Failed to set signal handler: {e}"
+        )));
+    }
+
+    if let Err(e) = server.start() {
+        exit_with_error(e);
+    }
 }
 
-/* Как сейчас выглядит Hash-table
-handlers                          // HashMap<Method, …>
-│
-├── Method::GET ──┐     // 1‑й уровень
-│                 │
-│   +------------------------------------------------+  // 2‑й уровень (HashMap<String, HandlerFn>)
-│   |  "/container/" → handler_return_all_containers |
-│   +------------------------------------------------+
-│
-├── Method::POST ─┐     // 1‑й уровень
-│                 │
-│   +---------------------------------------------+ // 2‑й уровень (HashMap<String, HandlerFn>)
-│   | "/container/:id/restart" → to_be_determined  |
-│   | "/container/:id/start" → to_be_determined   |
-│   | "/container/:id/stop" → to_be_determined    |
-│   +---------------------------------------------+
-│
-├── Method::PUT  ─┐
-│                 │
-│   +---------------------------+
-│   | EMPTY                     |
-│   +---------------------------+
-│
-└── Method::DELETE ─┐
-                  │
-    +---------------------------+
-    | EMPTY                     |
-    +---------------------------+
+fn exit_with_error(e: ServerError) -> ! {
+    eprintln!("{e}");
+    std::process::exit(1);
+}
 
+/* Как сейчас выглядит дерево маршрутов (см. router.rs)
+root
+└── "container"         → { POST: handler_create_container }
+    ├── ""              → { GET: handler_return_all_containers }
+    ├── "stats"         → { GET: handler_all_container_stats }
+    └── :id             → { GET: handler_inspect_container, DELETE: handler_remove_container }
+        ├── "attach"    → { GET: handler_attach_container } (WebSocket)
+        ├── "exec"      → { POST: handler_exec_container }
+        ├── "kill"      → { POST: handler_kill_container }
+        ├── "logs"      → { GET: handler_container_logs }
+        ├── "pause"     → { POST: handler_pause_container }
+        ├── "restart"   → { POST: handler_restart_container }
+        ├── "start"     → { POST: handler_start_container }
+        ├── "stats"     → { GET: handler_container_stats }
+        ├── "stop"      → { POST: handler_stop_container }
+        └── "unpause"   → { POST: handler_unpause_container }
 */
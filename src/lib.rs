@@ -1,12 +1,25 @@
 // это crate-библиотека
 pub mod lib {
+    pub mod api_error;
+    pub mod app_state;
+    pub mod chunked;
     pub mod config;
+    pub mod container_backend;
+    pub mod container_spec;
+    #[cfg(unix)]
+    pub mod docker_api;
     pub mod docker_works;
+    pub mod fake_backend;
     pub mod handlers;
+    pub mod headers;
     pub mod http_server;
     pub mod logger;
     pub mod parse_funcs;
+    pub mod query;
     pub mod req_res_structs;
     pub mod request;
+    pub mod router;
     pub mod server_errors;
+    pub mod thread_pool;
+    pub mod websocket;
 }
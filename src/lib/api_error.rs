@@ -0,0 +1,264 @@
+use std::{
+    sync::{
+        OnceLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::lib::{
+    container_spec::SpecError,
+    docker_works::ContainerError,
+    headers::HeaderMap,
+    req_res_structs::{BodyType, Response, StatusCode},
+    request::Request,
+    server_errors::ServerError,
+};
+
+// Ошибка, которую видит клиент API. Любой ответ с ошибкой -- от хендлера или от самого
+// сервера (404 роутера, 400 на кривой запрос) -- уходит в одном и том же виде:
+//
+// HTTP/1.1 409 Conflict
+// X-Request-Id: 65f1c2a0-000007
+// Content-Type: application/json
+//
+// {
+//   "error": {
+//     "code": "container_restarting",           <- для программ: на это можно матчиться
+//     "message": "Container web1 is restarting", <- для людей
+//     "container_id": "web1",                    <- null, если ошибка не про контейнер
+//     "request_id": "65f1c2a0-000007"            <- тот же id, что в логах сервера
+//   }
+// }
+//
+// Ошибка в теле запроса дополнительно указывает поле: "field": "ports[1].public_port"
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub code: &'static str,
+    pub message: String,
+    pub container_id: Option<String>,
+    pub field: Option<String>, // какое поле тела запроса не так (только для ошибок валидации)
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+            container_id: None,
+            field: None,
+        }
+    }
+
+    pub fn with_container(mut self, container_id: &str) -> Self {
+        self.container_id = Some(container_id.to_string());
+        self
+    }
+
+    pub fn with_field(mut self, field: &str) -> Self {
+        self.field = Some(field.to_string());
+        self
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "bad_request", message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "internal_error", message)
+    }
+
+    pub fn to_json(&self, request_id: &str) -> serde_json::Value {
+        let mut json = serde_json::json!({
+            "error": {
+                "code": self.code,
+                "message": self.message,
+                "container_id": self.container_id,
+                "request_id": request_id,
+            }
+        });
+        if let Some(field) = &self.field {
+            json["error"]["field"] = serde_json::json!(field);
+        }
+        json
+    }
+
+    // Ответ на конкретный запрос: request id берём из запроса (его выставил сервер)
+    pub fn into_response(self, request: &Request) -> Response {
+        self.into_response_with_id(request.request_id())
+    }
+
+    // Ответ, когда Request ещё нет (не смогли прочитать или разобрать запрос)
+    pub fn into_response_with_id(self, request_id: &str) -> Response {
+        let mut headers = HeaderMap::new();
+        if !request_id.is_empty() {
+            let _ = headers.insert(REQUEST_ID_HEADER, request_id);
+        }
+        Response {
+            response_code: self.status,
+            headers,
+            body: Some(BodyType::Json(self.to_json(request_id))),
+        }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.container_id {
+            Some(id) => write!(
+                f,
+                "{} [{}] {} (container {id})",
+                self.status, self.code, self.message
+            ),
+            None => write!(f, "{} [{}] {}", self.status, self.code, self.message),
+        }
+    }
+}
+
+impl From<ServerError> for ApiError {
+    fn from(e: ServerError) -> Self {
+        match e {
+            ServerError::ParseError(s) | ServerError::ReadError(s) => Self::bad_request(s),
+            ServerError::PayloadTooLarge(s) => {
+                Self::new(StatusCode::CONTENT_TOO_LARGE, "payload_too_large", s)
+            }
+            ServerError::HandlerError(s) | ServerError::InitError(s) => Self::internal(s),
+        }
+    }
+}
+
+impl From<ContainerError> for ApiError {
+    fn from(e: ContainerError) -> Self {
+        match e {
+            ContainerError::DockerError(s) => {
+                Self::new(StatusCode::INTERNAL_SERVER_ERROR, "docker_error", s)
+            }
+            ContainerError::ParseError(s) => Self::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "docker_output_invalid",
+                s,
+            ),
+            ContainerError::Unsupported(s) => {
+                Self::new(StatusCode::NOT_IMPLEMENTED, "not_supported", s)
+            }
+            ContainerError::InvalidName(s) => {
+                Self::new(StatusCode::BAD_REQUEST, "invalid_parameter", s)
+            }
+        }
+    }
+}
+
+// Спецификация контейнера не прошла проверку: 400 с путём до поля
+impl From<SpecError> for ApiError {
+    fn from(e: SpecError) -> Self {
+        let error = Self::new(StatusCode::BAD_REQUEST, "invalid_spec", e.message);
+        if e.field.is_empty() {
+            error
+        } else {
+            error.with_field(&e.field)
+        }
+    }
+}
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+// Новый id запроса: "<время старта сервера в hex>-<номер запроса>".
+// Уникален в пределах процесса и не повторяется после перезапуска
+pub fn next_request_id() -> String {
+    static STARTED: OnceLock<u64> = OnceLock::new();
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let started = STARTED.get_or_init(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default()
+    });
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed) + 1;
+
+    format!("{started:08x}-{n:06}")
+}
+
+// Id, присланный клиентом (или прокси перед нами), используем, только если он
+// короткий и без экзотики -- он попадает в логи и в тело ответа
+pub fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 128
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"-_.:".contains(&b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_envelope() {
+        let mut request = Request::default();
+        let _ = request.headers.insert(REQUEST_ID_HEADER, "req-1");
+
+        let response = ApiError::new(
+            StatusCode::CONFLICT,
+            "container_dead",
+            "Container web1 is dead",
+        )
+        .with_container("web1")
+        .into_response(&request);
+
+        assert_eq!(response.response_code, StatusCode::CONFLICT);
+        assert_eq!(response.headers.get("x-request-id"), Some("req-1"));
+        assert_eq!(
+            response.body,
+            Some(BodyType::Json(serde_json::json!({
+                "error": {
+                    "code": "container_dead",
+                    "message": "Container web1 is dead",
+                    "container_id": "web1",
+                    "request_id": "req-1",
+                }
+            })))
+        );
+
+        let json = ApiError::from(SpecError {
+            field: "ports[0].ip".to_string(),
+            message: "\"localhost\" is not an IP address".to_string(),
+        })
+        .to_json("req-2");
+        assert_eq!(json["error"]["code"], "invalid_spec");
+        assert_eq!(json["error"]["field"], "ports[0].ip");
+    }
+
+    #[test]
+    fn server_errors_map_to_statuses() {
+        let e = ApiError::from(ServerError::PayloadTooLarge("too big".to_string()));
+        assert_eq!(e.status, StatusCode::CONTENT_TOO_LARGE);
+        assert_eq!(e.code, "payload_too_large");
+
+        let e = ApiError::from(ServerError::ParseError("bad".to_string()));
+        assert_eq!(e.status, StatusCode::BAD_REQUEST);
+
+        let e = ApiError::from(ContainerError::DockerError("daemon down".to_string()));
+        assert_eq!(e.status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(e.code, "docker_error");
+
+        let e = ApiError::from(ContainerError::Unsupported("no tty".to_string()));
+        assert_eq!(e.status, StatusCode::NOT_IMPLEMENTED);
+        assert_eq!(e.code, "not_supported");
+
+        let e = ApiError::from(ContainerError::InvalidName("\"-rf\"".to_string()));
+        assert_eq!(e.status, StatusCode::BAD_REQUEST);
+        assert_eq!(e.code, "invalid_parameter");
+    }
+
+    #[test]
+    fn request_ids() {
+        assert_ne!(next_request_id(), next_request_id());
+        assert!(is_valid_request_id(&next_request_id()));
+        assert!(!is_valid_request_id("a b"));
+        assert!(!is_valid_request_id(""));
+        assert!(!is_valid_request_id(&"a".repeat(129)));
+    }
+}
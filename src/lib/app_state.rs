@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+#[cfg(unix)]
+use crate::lib::docker_api::DockerApi;
+use crate::lib::{
+    config::{Config, config_enums::ContainerBackendKind},
+    container_backend::ContainerBackend,
+    docker_works::DockerCli,
+    logger::Logger,
+    req_res_structs::Response,
+    request::Request,
+};
+
+// Хендлер, которому нужно общее состояние приложения
+pub type StatefulHandlerFn = fn(&AppState, &Request) -> Response;
+
+// Общее состояние приложения: одно на весь сервер, каждый хендлер получает его по ссылке.
+// Вместо Logger::default() в каждом хендлере -- логгер, настроенный из конфига.
+// С контейнерами хендлеры работают только через backend -- его можно подменить
+pub struct AppState {
+    pub log: Logger,
+    pub config: Config,
+    pub backend: Arc<dyn ContainerBackend>,
+}
+
+impl AppState {
+    // Бэкенд выбирается конфигом: docker CLI (по умолчанию) или Docker Engine API
+    pub fn new(config: Config) -> Self {
+        let backend: Arc<dyn ContainerBackend> = match config.container_backend {
+            #[cfg(unix)]
+            ContainerBackendKind::Api => Arc::new(DockerApi::new(&config.docker_socket)),
+            _ => Arc::new(DockerCli),
+        };
+        Self::with_backend(config, backend)
+    }
+
+    pub fn with_backend(config: Config, backend: Arc<dyn ContainerBackend>) -> Self {
+        Self {
+            log: Logger::with_config(&config),
+            config,
+            backend,
+        }
+    }
+
+    // Превращаем хендлер fn(&AppState, &Request) в замыкание, которое можно зарегистрировать в Server:
+    // server.GET("/container/", state.handler(handler_return_all_containers));
+    pub fn handler(
+        self: &Arc<Self>,
+        handler: StatefulHandlerFn,
+    ) -> impl Fn(&Request) -> Response + Send + Sync + 'static {
+        let state = Arc::clone(self);
+        move |request: &Request| handler(&state, request)
+    }
+}
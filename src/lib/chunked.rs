@@ -0,0 +1,302 @@
+use std::io::{self, BufRead, Read, Write};
+
+use crate::lib::{parse_funcs::MAX_HEAD_SIZE, server_errors::ServerError};
+
+// Максимальная длина строки с размером чанка (hex-число + расширения ;name=value)
+const MAX_CHUNK_LINE: usize = 4096;
+
+// Декодер тела в формате Transfer-Encoding: chunked
+//
+// Формат:
+//   <размер в hex>[;расширения]\r\n
+//   <данные размером в размер байт>\r\n
+//   ...
+//   0\r\n
+//   <трейлеры: "Name: value\r\n">
+//   \r\n
+//
+// ChunkedReader сам реализует Read, поэтому его можно читать как обычный поток,
+// а после конца тела в trailers() лежат заголовки-трейлеры
+pub struct ChunkedReader<R: BufRead> {
+    inner: R,
+    remaining: usize,      // сколько байт осталось в текущем чанке
+    done: bool,            // встретили последний (нулевой) чанк и дочитали трейлеры
+    trailers: Vec<String>, // трейлеры после последнего чанка
+}
+
+impl<R: BufRead> ChunkedReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            remaining: 0,
+            done: false,
+            trailers: Vec::new(),
+        }
+    }
+
+    pub fn trailers(&self) -> &[String] {
+        &self.trailers
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    // Читаем одну строку, заканчивающуюся на \r\n (или \n), без самого перевода строки
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line: Vec<u8> = Vec::new();
+        let read = (&mut self.inner)
+            .take(MAX_CHUNK_LINE as u64)
+            .read_until(b'\n', &mut line)?;
+
+        if read == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Connection closed in the middle of chunked body",
+            ));
+        }
+        if !line.ends_with(b"\n") {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Chunk line is too long",
+            ));
+        }
+
+        String::from_utf8(line)
+            .map(|l| l.trim_end_matches(['\r', '\n']).to_string())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Chunk line is not UTF-8"))
+    }
+
+    // Читаем строку с размером следующего чанка. Расширения (;name=value) игнорируем
+    fn read_chunk_size(&mut self) -> io::Result<usize> {
+        let line = self.read_line()?;
+        let size_str = line.split(';').next().unwrap_or_default().trim();
+
+        usize::from_str_radix(size_str, 16).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid chunk size: {size_str:?}"),
+            )
+        })
+    }
+
+    // После нулевого чанка идут трейлеры до пустой строки.
+    // Суммарно трейлеры не больше MAX_HEAD_SIZE, как и заголовки запроса
+    fn read_trailers(&mut self) -> io::Result<()> {
+        let mut trailers_size: usize = 0;
+        loop {
+            let line = self.read_line()?;
+            if line.is_empty() {
+                return Ok(());
+            }
+            trailers_size += line.len() + 2;
+            if trailers_size > MAX_HEAD_SIZE {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Trailers are larger than {MAX_HEAD_SIZE} bytes"),
+                ));
+            }
+            if !line.contains(':') {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid trailer: {line:?}"),
+                ));
+            }
+            self.trailers.push(line);
+        }
+    }
+
+    // После данных чанка обязательно идёт \r\n
+    fn read_chunk_end(&mut self) -> io::Result<()> {
+        let line = self.read_line()?;
+        if !line.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Missing CRLF after chunk data",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for ChunkedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.remaining == 0 {
+            let size = self.read_chunk_size()?;
+            if size == 0 {
+                self.read_trailers()?;
+                self.done = true;
+                return Ok(0);
+            }
+            self.remaining = size;
+        }
+
+        let max = buf.len().min(self.remaining);
+        let read = self.inner.read(&mut buf[..max])?;
+        if read == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Connection closed in the middle of chunk",
+            ));
+        }
+
+        self.remaining -= read;
+        if self.remaining == 0 {
+            self.read_chunk_end()?;
+        }
+
+        Ok(read)
+    }
+}
+
+// Читаем всё chunked тело запроса целиком (но не больше max_body_size байт)
+// Возвращаем декодированное тело и трейлеры
+pub fn read_chunked_body<R: BufRead>(
+    reader: &mut R,
+    max_body_size: usize,
+) -> Result<(Vec<u8>, Vec<String>), ServerError> {
+    let mut chunked = ChunkedReader::new(reader);
+    let mut body: Vec<u8> = Vec::new();
+
+    (&mut chunked)
+        .take(max_body_size as u64 + 1) // +1 байт, чтобы понять, что лимит превышен
+        .read_to_end(&mut body)
+        .map_err(|e| ServerError::ParseError(format!("Invalid chunked body: {e}")))?;
+
+    if body.len() > max_body_size {
+        return Err(ServerError::PayloadTooLarge(format!(
+            "Chunked body exceeds limit of {max_body_size} bytes"
+        )));
+    }
+
+    Ok((body, chunked.trailers))
+}
+
+// Кодировщик тела ответа в формат Transfer-Encoding: chunked
+// Каждый вызов write() превращается в отдельный чанк,
+// finish() дописывает последний нулевой чанк
+pub struct ChunkedWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> ChunkedWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner }
+    }
+
+    // Завершаем тело: нулевой чанк + трейлеры + пустая строка
+    pub fn finish_with_trailers(mut self, trailers: &[String]) -> io::Result<W> {
+        self.inner.write_all(b"0\r\n")?;
+        for trailer in trailers {
+            self.inner.write_all(trailer.as_bytes())?;
+            self.inner.write_all(b"\r\n")?;
+        }
+        self.inner.write_all(b"\r\n")?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    pub fn finish(self) -> io::Result<W> {
+        self.finish_with_trailers(&[])
+    }
+}
+
+impl<W: Write> Write for ChunkedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // пустой чанк означал бы конец тела, поэтому пустые записи пропускаем
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.inner
+            .write_all(format!("{:X}\r\n", buf.len()).as_bytes())?;
+        self.inner.write_all(buf)?;
+        self.inner.write_all(b"\r\n")?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn decode_chunked_with_trailers() -> Result<(), ServerError> {
+        let raw = "4\r\nWiki\r\n7;ext=1\r\npedia i\r\nB\r\nn \r\nchunks.\r\n0\r\nExpires: never\r\n\r\nNEXT";
+        let mut reader = Cursor::new(raw.as_bytes());
+
+        let (body, trailers) = read_chunked_body(&mut reader, 1024)?;
+
+        assert_eq!(body, b"Wikipedia in \r\nchunks.");
+        assert_eq!(trailers, vec!["Expires: never".to_string()]);
+
+        // всё, что после тела, осталось непрочитанным
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "NEXT");
+
+        Ok(())
+    }
+
+    #[test]
+    fn decode_chunked_too_large() {
+        let raw = "A\r\n0123456789\r\n0\r\n\r\n";
+        let mut reader = Cursor::new(raw.as_bytes());
+
+        assert!(matches!(
+            read_chunked_body(&mut reader, 5),
+            Err(ServerError::PayloadTooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn decode_chunked_invalid_size() {
+        let raw = "zz\r\nabc\r\n0\r\n\r\n";
+        let mut reader = Cursor::new(raw.as_bytes());
+
+        assert!(matches!(
+            read_chunked_body(&mut reader, 1024),
+            Err(ServerError::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn decode_chunked_too_many_trailers() {
+        let mut raw = String::from("3\r\nabc\r\n0\r\n");
+        while raw.len() <= MAX_HEAD_SIZE + 64 {
+            raw.push_str("X-Filler: 0123456789abcdef\r\n");
+        }
+        raw.push_str("\r\n");
+        let mut reader = Cursor::new(raw.as_bytes());
+
+        assert!(matches!(
+            read_chunked_body(&mut reader, 1024),
+            Err(ServerError::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn encode_chunked() -> io::Result<()> {
+        let mut writer = ChunkedWriter::new(Vec::new());
+        writer.write_all(b"Hello, ")?;
+        writer.write_all(b"")?;
+        writer.write_all(b"chunked world!")?;
+        let out = writer.finish_with_trailers(&["X-Done: yes".to_string()])?;
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "7\r\nHello, \r\nE\r\nchunked world!\r\n0\r\nX-Done: yes\r\n\r\n"
+        );
+
+        Ok(())
+    }
+}
@@ -1,4 +1,6 @@
-use std::env;
+use std::{env, str::FromStr, time::Duration};
+
+use crate::lib::server_errors::ServerError;
 
 pub mod config_enums {
     #[derive(Default, Copy, Clone, Debug)]
@@ -25,13 +27,54 @@ pub mod config_enums {
         Warn,
         Error,
     }
+
+    // Через что сервис работает с docker
+    #[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+    pub enum ContainerBackendKind {
+        #[default]
+        Cli, // процесс docker на каждую операцию
+        Api, // Docker Engine API через unix-сокет демона
+    }
 }
-#[derive(Debug, Default)]
+#[derive(Debug, Clone)]
 pub struct Config {
     pub port: u16,
     pub time_format: config_enums::TimeFormat,
     pub date_format: config_enums::DateFormat,
     pub log_level: config_enums::LogLevel,
+    pub max_body_size: usize, // максимальный размер тела запроса в байтах (Content-Length больше -> 413)
+    pub workers: usize,       // количество потоков, обрабатывающих подключения
+    pub queue_size: usize,    // сколько подключений может ждать свободного воркера (больше -> 503)
+    pub keep_alive_timeout: Duration, // сколько ждать следующий запрос на keep-alive соединении
+    pub max_requests_per_connection: usize, // после стольких запросов соединение закрывается
+    pub shutdown_timeout: Duration, // сколько при остановке ждать незавершённые запросы
+    pub container_backend: config_enums::ContainerBackendKind,
+    pub docker_socket: String, // путь к сокету демона для ContainerBackendKind::Api
+    pub stop_timeout: Option<Duration>, // сколько stop/restart ждут после SIGTERM; None -- решает docker
+    pub exec_timeout: Duration,         // сколько по умолчанию ждать команду из exec
+    pub exec_output_limit: usize, // сколько байт вывода exec (stdout + stderr) отдаём, остальное отбрасываем
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            port: 0,
+            time_format: config_enums::TimeFormat::default(),
+            date_format: config_enums::DateFormat::default(),
+            log_level: config_enums::LogLevel::default(),
+            max_body_size: config_constants::DEFAULT_MAX_BODY_SIZE,
+            workers: config_constants::DEFAULT_WORKERS,
+            queue_size: config_constants::DEFAULT_QUEUE_SIZE,
+            keep_alive_timeout: Duration::from_secs(config_constants::DEFAULT_KEEP_ALIVE_TIMEOUT),
+            max_requests_per_connection: config_constants::DEFAULT_MAX_REQUESTS_PER_CONNECTION,
+            shutdown_timeout: Duration::from_secs(config_constants::DEFAULT_SHUTDOWN_TIMEOUT),
+            container_backend: config_enums::ContainerBackendKind::default(),
+            docker_socket: config_constants::DEFAULT_DOCKER_SOCKET.to_string(),
+            stop_timeout: None,
+            exec_timeout: Duration::from_secs(config_constants::DEFAULT_EXEC_TIMEOUT),
+            exec_output_limit: config_constants::DEFAULT_EXEC_OUTPUT_LIMIT,
+        }
+    }
 }
 
 mod config_constants {
@@ -45,36 +88,101 @@ mod config_constants {
     pub const US: &str = "us";
     pub const H12FORMAT: &str = "h12";
     pub const H24FORMAT: &str = "h24";
+    pub const BACKEND_CLI: &str = "cli";
+    pub const BACKEND_API: &str = "api";
+    pub const DEFAULT_MAX_BODY_SIZE: usize = 1024 * 1024; // 1 MiB
+    pub const DEFAULT_WORKERS: usize = 8;
+    pub const DEFAULT_QUEUE_SIZE: usize = 64;
+    pub const DEFAULT_KEEP_ALIVE_TIMEOUT: u64 = 5; // секунд
+    pub const DEFAULT_MAX_REQUESTS_PER_CONNECTION: usize = 100;
+    pub const DEFAULT_SHUTDOWN_TIMEOUT: u64 = 30; // секунд
+    pub const DEFAULT_DOCKER_SOCKET: &str = "/var/run/docker.sock";
+    pub const DEFAULT_EXEC_TIMEOUT: u64 = 30; // секунд
+    pub const DEFAULT_EXEC_OUTPUT_LIMIT: usize = 1024 * 1024; // 1 MiB
 }
 
 impl Config {
-    pub fn from_env() -> Self {
-        Self {
-            date_format: match env::var("DATE_FORMAT").unwrap_or_default().as_str() {
-                config_constants::ISO => config_enums::DateFormat::ISO8601,
-                config_constants::ASIAN => config_enums::DateFormat::Asian,
-                config_constants::EUROPE => config_enums::DateFormat::Europe,
-                config_constants::US => config_enums::DateFormat::US,
-                _ => config_enums::DateFormat::default(),
-            },
+    // Невалидное значение переменной окружения (WORKERS=0, WORKERS=abc и т.п.) -- ошибка запуска,
+    // а не тихая подмена: иначе сервер поднимется не с тем, что просили
+    pub fn from_env() -> Result<Self, ServerError> {
+        Self::from_vars(|name| env::var(name).ok())
+    }
 
-            time_format: match env::var("TIME_FORMAT").unwrap_or_default().as_str() {
-                config_constants::H12FORMAT => config_enums::TimeFormat::H12Format,
-                config_constants::H24FORMAT => config_enums::TimeFormat::H24Format,
-                _ => config_enums::TimeFormat::default(),
+    // Собираем конфиг из переменных, которые отдаёт lookup. Не заданная (или пустая)
+    // переменная -- значение по умолчанию, заданная, но кривая -- InitError с её именем
+    fn from_vars(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, ServerError> {
+        let var = |name: &str| lookup(name).filter(|value| !value.is_empty());
+        let secs = |name: &str| -> Result<Option<u64>, ServerError> {
+            var(name).map(|value| parse_var(name, &value)).transpose()
+        };
+        let size = |name: &str| -> Result<Option<usize>, ServerError> {
+            var(name).map(|value| parse_var(name, &value)).transpose()
+        };
+        let defaults = Self::default();
+
+        let config = Self {
+            date_format: match var("DATE_FORMAT").as_deref() {
+                None => defaults.date_format,
+                Some(config_constants::ISO) => config_enums::DateFormat::ISO8601,
+                Some(config_constants::ASIAN) => config_enums::DateFormat::Asian,
+                Some(config_constants::EUROPE) => config_enums::DateFormat::Europe,
+                Some(config_constants::US) => config_enums::DateFormat::US,
+                Some(other) => return Err(invalid_var("DATE_FORMAT", other)),
+            },
+            time_format: match var("TIME_FORMAT").as_deref() {
+                None => defaults.time_format,
+                Some(config_constants::H12FORMAT) => config_enums::TimeFormat::H12Format,
+                Some(config_constants::H24FORMAT) => config_enums::TimeFormat::H24Format,
+                Some(other) => return Err(invalid_var("TIME_FORMAT", other)),
             },
-            log_level: match env::var("LOG_LEVEL").unwrap_or_default().as_str() {
-                config_constants::DBUG => config_enums::LogLevel::Dbug,
-                config_constants::INFO => config_enums::LogLevel::Info,
-                config_constants::WARN => config_enums::LogLevel::Warn,
-                config_constants::ERROR => config_enums::LogLevel::Error,
-                _ => config_enums::LogLevel::default(),
+            log_level: match var("LOG_LEVEL").as_deref() {
+                None => defaults.log_level,
+                Some(config_constants::DBUG) => config_enums::LogLevel::Dbug,
+                Some(config_constants::INFO) => config_enums::LogLevel::Info,
+                Some(config_constants::WARN) => config_enums::LogLevel::Warn,
+                Some(config_constants::ERROR) => config_enums::LogLevel::Error,
+                Some(other) => return Err(invalid_var("LOG_LEVEL", other)),
             },
-            port: std::env::var("PORT")
-                .unwrap_or_default()
-                .parse()
-                .unwrap_or_default(),
+            port: var("PORT")
+                .map(|value| parse_var("PORT", &value))
+                .transpose()?
+                .unwrap_or(defaults.port),
+            max_body_size: size("MAX_BODY_SIZE")?.unwrap_or(defaults.max_body_size),
+            workers: size("WORKERS")?.unwrap_or(defaults.workers),
+            queue_size: size("QUEUE_SIZE")?.unwrap_or(defaults.queue_size),
+            keep_alive_timeout: secs("KEEP_ALIVE_TIMEOUT")?
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.keep_alive_timeout),
+            max_requests_per_connection: size("MAX_REQUESTS_PER_CONNECTION")?
+                .unwrap_or(defaults.max_requests_per_connection),
+            shutdown_timeout: secs("SHUTDOWN_TIMEOUT")?
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.shutdown_timeout),
+            container_backend: match var("CONTAINER_BACKEND").as_deref() {
+                None => defaults.container_backend,
+                Some(config_constants::BACKEND_CLI) => config_enums::ContainerBackendKind::Cli,
+                Some(config_constants::BACKEND_API) => config_enums::ContainerBackendKind::Api,
+                Some(other) => return Err(invalid_var("CONTAINER_BACKEND", other)),
+            },
+            docker_socket: var("DOCKER_SOCKET").unwrap_or(defaults.docker_socket),
+            stop_timeout: secs("STOP_TIMEOUT")?.map(Duration::from_secs),
+            exec_timeout: secs("EXEC_TIMEOUT")?
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.exec_timeout),
+            exec_output_limit: size("EXEC_OUTPUT_LIMIT")?.unwrap_or(defaults.exec_output_limit),
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    // Проверяем настройки, без которых сервер не сможет работать
+    pub fn validate(&self) -> Result<(), ServerError> {
+        if self.workers == 0 {
+            return Err(ServerError::InitError(
+                "WORKERS must be at least 1".to_string(),
+            ));
         }
+        Ok(())
     }
 
     pub fn with_port(mut self, port: u16) -> Self {
@@ -96,4 +204,149 @@ impl Config {
         self.log_level = log_level;
         self
     }
+
+    pub fn with_max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    pub fn with_queue_size(mut self, queue_size: usize) -> Self {
+        self.queue_size = queue_size;
+        self
+    }
+
+    pub fn with_keep_alive_timeout(mut self, keep_alive_timeout: Duration) -> Self {
+        self.keep_alive_timeout = keep_alive_timeout;
+        self
+    }
+
+    pub fn with_max_requests_per_connection(mut self, max_requests: usize) -> Self {
+        self.max_requests_per_connection = max_requests;
+        self
+    }
+
+    pub fn with_shutdown_timeout(mut self, shutdown_timeout: Duration) -> Self {
+        self.shutdown_timeout = shutdown_timeout;
+        self
+    }
+
+    pub fn with_container_backend(
+        mut self,
+        container_backend: config_enums::ContainerBackendKind,
+    ) -> Self {
+        self.container_backend = container_backend;
+        self
+    }
+
+    pub fn with_docker_socket(mut self, docker_socket: &str) -> Self {
+        self.docker_socket = docker_socket.to_string();
+        self
+    }
+
+    pub fn with_stop_timeout(mut self, stop_timeout: Duration) -> Self {
+        self.stop_timeout = Some(stop_timeout);
+        self
+    }
+
+    pub fn with_exec_timeout(mut self, exec_timeout: Duration) -> Self {
+        self.exec_timeout = exec_timeout;
+        self
+    }
+
+    pub fn with_exec_output_limit(mut self, exec_output_limit: usize) -> Self {
+        self.exec_output_limit = exec_output_limit;
+        self
+    }
+}
+
+fn parse_var<T: FromStr>(name: &str, value: &str) -> Result<T, ServerError> {
+    value.parse().map_err(|_| invalid_var(name, value))
+}
+
+fn invalid_var(name: &str, value: &str) -> ServerError {
+    ServerError::InitError(format!("Invalid value of {name}: {value:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_rejects_zero_workers() {
+        assert!(Config::default().validate().is_ok());
+        assert!(
+            Config::default()
+                .with_workers(1)
+                .with_queue_size(0)
+                .validate()
+                .is_ok()
+        );
+        assert!(matches!(
+            Config::default().with_workers(0).validate(),
+            Err(ServerError::InitError(_))
+        ));
+    }
+
+    fn from_pairs(pairs: &[(&str, &str)]) -> Result<Config, ServerError> {
+        Config::from_vars(|name| {
+            pairs
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn from_vars_parses_values() -> Result<(), ServerError> {
+        let config = from_pairs(&[
+            ("PORT", "8080"),
+            ("WORKERS", "2"),
+            ("STOP_TIMEOUT", "7"),
+            ("LOG_LEVEL", "warn"),
+            ("CONTAINER_BACKEND", "api"),
+            ("MAX_BODY_SIZE", ""), // пустая -- как не заданная
+        ])?;
+
+        assert_eq!(config.port, 8080);
+        assert_eq!(config.workers, 2);
+        assert_eq!(config.stop_timeout, Some(Duration::from_secs(7)));
+        assert_eq!(config.log_level, config_enums::LogLevel::Warn);
+        assert_eq!(
+            config.container_backend,
+            config_enums::ContainerBackendKind::Api
+        );
+        assert_eq!(
+            config.max_body_size,
+            config_constants::DEFAULT_MAX_BODY_SIZE
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_vars_rejects_invalid_values() {
+        for (name, value) in [
+            ("WORKERS", "abc"),
+            ("WORKERS", "0"),
+            ("SHUTDOWN_TIMEOUT", "x"),
+            ("MAX_BODY_SIZE", "1e6"),
+            ("EXEC_TIMEOUT", "-1"),
+            ("STOP_TIMEOUT", "soon"),
+            ("PORT", "70000"),
+            ("LOG_LEVEL", "verbose"),
+            ("CONTAINER_BACKEND", "podman"),
+        ] {
+            match from_pairs(&[(name, value)]) {
+                Err(ServerError::InitError(message)) => {
+                    assert!(message.contains(name), "{message}")
+                }
+                other => panic!("{name}={value}: expected InitError, got {other:?}"),
+            }
+        }
+    }
 }
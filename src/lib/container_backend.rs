@@ -0,0 +1,740 @@
+use std::{
+    io::{Read, Write},
+    sync::Arc,
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+
+use crate::lib::{
+    container_spec::{ContainerSpec, ExecSpec},
+    docker_works::{ContainerError, ContainerInfo, PortBinding},
+};
+
+// Всё, что хендлерам нужно от контейнерного рантайма.
+// Хендлеры не знают, как именно это сделано: через docker CLI (DockerCli),
+// через Docker Engine API или в памяти для тестов -- реализация приходит через AppState
+pub trait ContainerBackend: Send + Sync {
+    // Все контейнеры на машине (как docker ps -a)
+    fn list(&self) -> Result<Vec<ContainerInfo>, ContainerError>;
+
+    // То же, но с размером каждого контейнера (ContainerInfo.size) -- это может быть долго
+    fn list_with_size(&self) -> Result<Vec<ContainerInfo>, ContainerError> {
+        self.list()
+    }
+
+    // Текущее состояние одного контейнера (как docker inspect)
+    fn inspect(&self, id: &str) -> Result<ContainerState, ContainerError>;
+
+    // Всё, что docker inspect знает о контейнере: состояние, env, тома, сеть, лимиты
+    fn inspect_details(&self, id: &str) -> Result<ContainerDetails, ContainerError>;
+
+    // Создать контейнер (не запуская его) и вернуть его id. Образ должен уже быть на машине:
+    // ни один бэкенд сам его не скачивает
+    fn create(&self, spec: &ContainerSpec) -> Result<String, ContainerError>;
+
+    fn start(&self, id: &str) -> Result<(), ContainerError>;
+
+    // timeout -- сколько ждать после SIGTERM до SIGKILL; None -- сколько решит docker
+    // (StopTimeout контейнера или 10 секунд)
+    fn stop(&self, id: &str, timeout: Option<Duration>) -> Result<(), ContainerError>;
+
+    fn restart(&self, id: &str, timeout: Option<Duration>) -> Result<(), ContainerError>;
+
+    // Удалить контейнер (как docker rm). Работающий без options.force демон не удалит
+    fn remove(&self, id: &str, options: &RemoveOptions) -> Result<(), ContainerError>;
+
+    // Заморозить все процессы контейнера (cgroup freezer) и разморозить обратно
+    fn pause(&self, id: &str) -> Result<(), ContainerError>;
+
+    fn unpause(&self, id: &str) -> Result<(), ContainerError>;
+
+    // Послать сигнал главному процессу контейнера; signal -- имя из parse_signal ("SIGHUP")
+    fn kill(&self, id: &str, signal: &str) -> Result<(), ContainerError>;
+
+    // Логи контейнера (как docker logs). С options.follow поток не кончается, пока
+    // контейнер работает -- читатель сам решает, когда бросить итератор
+    fn logs(&self, id: &str, options: &LogOptions) -> Result<LogStream, ContainerError>;
+
+    // Запустить команду в работающем контейнере (как docker exec без -i и -t).
+    // Вывод идёт по мере поступления, последний элемент -- код выхода
+    fn exec(&self, id: &str, spec: &ExecSpec) -> Result<ExecStream, ContainerError>;
+
+    // Интерактивная команда с терминалом (как docker exec -it): пишем в её stdin,
+    // читаем вывод терминала, меняем размер окна. Не всякий бэкенд такое умеет
+    fn exec_tty(
+        &self,
+        id: &str,
+        spec: &ExecSpec,
+        size: TtySize,
+    ) -> Result<ExecSession, ContainerError> {
+        let _ = (id, spec, size);
+        Err(ContainerError::Unsupported(
+            "Interactive exec needs CONTAINER_BACKEND=api".to_string(),
+        ))
+    }
+
+    // Снимок потребления ресурсов одним контейнером (как docker stats --no-stream <id>).
+    // У остановленного контейнера всё по нулям
+    fn stats(&self, id: &str) -> Result<ContainerStats, ContainerError>;
+
+    // Снимки всех работающих контейнеров (как docker stats --no-stream)
+    fn stats_all(&self) -> Result<Vec<ContainerStats>, ContainerError> {
+        self.list()?
+            .iter()
+            .filter(|container| container.status.is_running())
+            .map(|container| self.stats(&container.label))
+            .collect()
+    }
+
+    // Есть ли контейнер с таким именем или id
+    fn exists(&self, id: &str) -> Result<bool, ContainerError> {
+        Ok(self.list()?.iter().any(|container| container.matches(id)))
+    }
+}
+
+// Состояние контейнера из docker inspect (.State)
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ContainerState {
+    pub status: String, // created, running, paused, restarting, removing, exited, dead
+    pub is_running: bool,
+    pub is_paused: bool,
+    pub is_restarting: bool,
+    pub is_dead: bool,
+    pub exit_code: Option<i64>, // код завершения последнего запуска
+    pub started_at: Option<DateTime<Utc>>, // None -- ни разу не запускался
+    pub finished_at: Option<DateTime<Utc>>, // None -- ещё ни разу не завершался
+    pub restart_count: u64,     // сколько раз docker перезапускал контейнер по restart policy
+    pub oom_killed: bool,       // последний запуск убит из-за нехватки памяти
+    pub pid: Option<u32>,       // pid главного процесса на хосте; None -- не запущен
+    pub health: Option<String>, // starting, healthy, unhealthy; None -- нет HEALTHCHECK
+    pub error: Option<String>,  // ошибка последнего запуска от демона
+}
+
+// Результат docker inspect целиком (то, что отдаёт GET /container/:id)
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ContainerDetails {
+    pub id: String,
+    pub name: String,
+    pub image: String,
+    pub created: Option<DateTime<Utc>>,
+    pub command: Vec<String>, // Path + Args
+    pub tty: bool,            // запущен с -t: логи без разделения на stdout/stderr
+    pub state: ContainerState,
+    pub health: Option<HealthInfo>,
+    pub env: Vec<(String, String)>, // как есть, маскирует секреты уже хендлер
+    pub mounts: Vec<MountInfo>,
+    pub network: NetworkInfo,
+    pub resources: ResourceLimits,
+    pub restart_policy: RestartPolicy,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct HealthInfo {
+    pub status: String,
+    pub failing_streak: u64,
+    pub last_exit_code: Option<i64>, // результат последней проверки
+    pub last_output: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MountInfo {
+    pub kind: String,         // volume, bind, tmpfs
+    pub name: Option<String>, // имя тома (для bind -- None)
+    pub source: String,
+    pub destination: String,
+    pub read_only: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct NetworkInfo {
+    pub mode: String, // bridge, host, none, container:<id>, имя сети
+    pub ports: Vec<PortBinding>,
+    pub networks: Vec<NetworkEndpoint>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct NetworkEndpoint {
+    pub name: String,
+    pub ip_address: Option<String>,
+    pub gateway: Option<String>,
+    pub mac_address: Option<String>,
+}
+
+// Ограничения ресурсов; None -- не ограничено
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ResourceLimits {
+    pub memory: Option<u64>,             // байт
+    pub memory_reservation: Option<u64>, // байт
+    pub memory_swap: Option<i64>,        // байт, -1 -- без ограничения на swap
+    pub nano_cpus: Option<u64>,          // 1e9 = одно ядро (--cpus)
+    pub cpu_shares: Option<u64>,
+    pub cpu_quota: Option<i64>,
+    pub cpu_period: Option<u64>,
+    pub cpuset_cpus: Option<String>, // "0-3"
+    pub pids_limit: Option<i64>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RestartPolicy {
+    pub name: String, // no, always, unless-stopped, on-failure
+    pub max_retries: u64,
+}
+
+// Потребление ресурсов контейнером в момент read_at. Память и I/O -- в байтах,
+// сеть и диск -- суммарно с момента запуска
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ContainerStats {
+    pub id: String,
+    pub name: String,
+    pub read_at: Option<DateTime<Utc>>,
+    pub cpu_percent: f64, // как в docker stats: 100% -- одно ядро целиком, на 4 ядрах до 400%
+    pub memory_usage: u64, // без файлового кеша
+    pub memory_limit: u64, // без лимита -- вся память машины
+    pub network_rx: u64,
+    pub network_tx: u64,
+    pub block_read: u64,
+    pub block_write: u64,
+    pub pids: u64,
+}
+
+impl ContainerStats {
+    pub fn memory_percent(&self) -> f64 {
+        if self.memory_limit == 0 {
+            return 0.0;
+        }
+        self.memory_usage as f64 / self.memory_limit as f64 * 100.0
+    }
+}
+
+// Как удалять контейнер
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct RemoveOptions {
+    pub force: bool,   // работающий контейнер сначала убить (SIGKILL)
+    pub volumes: bool, // заодно удалить анонимные тома контейнера (именованные остаются)
+}
+
+// Что именно читать из логов
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogOptions {
+    pub tail: Option<usize>, // только последние N строк; None -- все
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub stdout: bool,
+    pub stderr: bool,
+    pub follow: bool, // после старых строк ждать новые
+}
+
+impl Default for LogOptions {
+    fn default() -> Self {
+        Self {
+            tail: None,
+            since: None,
+            until: None,
+            stdout: true,
+            stderr: true,
+            follow: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogSource {
+    Stdout = 0,
+    Stderr = 1,
+}
+
+impl LogSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogSource::Stdout => "stdout",
+            LogSource::Stderr => "stderr",
+        }
+    }
+}
+
+// Одна строка лога. Время docker пишет сам (docker logs -t), бэкенды всегда его запрашивают
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogLine {
+    pub source: LogSource,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub text: String, // без завершающего '\n'
+}
+
+impl LogLine {
+    // "2024-05-01T10:00:00.123456789Z GET / 200" -> время + текст
+    pub fn parse_timestamped(source: LogSource, raw: &str) -> Self {
+        let raw = raw.strip_suffix('\n').unwrap_or(raw);
+        let raw = raw.strip_suffix('\r').unwrap_or(raw);
+        match raw.split_once(' ') {
+            Some((time, text)) if parse_docker_time(time).is_some() => Self {
+                source,
+                timestamp: parse_docker_time(time),
+                text: text.to_string(),
+            },
+            _ => Self {
+                source,
+                timestamp: None,
+                text: raw.to_string(),
+            },
+        }
+    }
+}
+
+// Строки по мере поступления. Ошибка посреди потока (демон отвалился) -- последний элемент
+pub type LogStream = BackendStream<LogLine>;
+
+// Поток от бэкенда, который может подолгу молчать (логи с follow, exec): next() блокируется,
+// пока контейнер или команда ничего не пишут. Поэтому его обычно читают в отдельном потоке, а
+// прервать чтение можно через canceller(): бэкенд убивает процесс docker или закрывает
+// сокет, и next() скоро вернёт конец потока (или ошибку)
+pub struct BackendStream<T> {
+    items: Box<dyn Iterator<Item = Result<T, ContainerError>> + Send>,
+    canceller: Canceller,
+}
+
+impl<T> BackendStream<T> {
+    pub fn new(items: impl Iterator<Item = Result<T, ContainerError>> + Send + 'static) -> Self {
+        Self {
+            items: Box::new(items),
+            canceller: Canceller::default(),
+        }
+    }
+
+    // Без этого поток прерывать нечем: он и так не блокируется (фейк, готовый список)
+    pub fn with_canceller(mut self, cancel: impl Fn() + Send + Sync + 'static) -> Self {
+        self.canceller = Canceller(Some(Arc::new(cancel)));
+        self
+    }
+
+    pub fn canceller(&self) -> Canceller {
+        self.canceller.clone()
+    }
+}
+
+impl<T> Iterator for BackendStream<T> {
+    type Item = Result<T, ContainerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.items.next()
+    }
+}
+
+// Прерывает BackendStream из другого потока. Звать можно сколько угодно раз, в том
+// числе после конца потока
+#[derive(Clone, Default)]
+pub struct Canceller(Option<Arc<dyn Fn() + Send + Sync>>);
+
+impl Canceller {
+    pub fn cancel(&self) {
+        if let Some(cancel) = &self.0 {
+            cancel();
+        }
+    }
+}
+
+// Что делает команда из exec: кусок вывода (как пришёл, не обязательно целые строки)
+// или завершение с кодом выхода
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecEvent {
+    Output(LogSource, Vec<u8>),
+    Exit(i64),
+}
+
+// Вывод команды, в конце -- Exit. Ошибка посреди потока -- последний элемент.
+// Бросить итератор раньше -- только отключиться: остановить команду docker не умеет,
+// процесс в контейнере доработает сам
+pub type ExecStream = BackendStream<ExecEvent>;
+
+// Размер окна терминала в символах
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TtySize {
+    pub rows: u16,
+    pub cols: u16,
+}
+
+impl Default for TtySize {
+    fn default() -> Self {
+        Self { rows: 24, cols: 80 }
+    }
+}
+
+// Интерактивная команда из exec_tty. У терминала один поток вывода: stdout и stderr
+// смешаны, как на экране. Закрыть input -- то же, что Ctrl-D: команда увидит конец stdin
+pub struct ExecSession {
+    pub output: Box<dyn Read + Send>, // кончается, когда команда завершилась
+    pub input: Box<dyn Write + Send>,
+    pub control: Box<dyn ExecControl + Send>,
+}
+
+impl std::fmt::Debug for ExecSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ExecSession")
+    }
+}
+
+// Управление командой в обход её stdin
+pub trait ExecControl {
+    fn resize(&self, size: TtySize) -> Result<(), ContainerError>;
+
+    // Код выхода; None -- команда ещё работает (или бэкенд его не узнал)
+    fn exit_code(&self) -> Result<Option<i64>, ContainerError>;
+}
+
+// Сигналы Linux, которые можно послать через kill
+const SIGNALS: [&str; 31] = [
+    "SIGHUP",
+    "SIGINT",
+    "SIGQUIT",
+    "SIGILL",
+    "SIGTRAP",
+    "SIGABRT",
+    "SIGBUS",
+    "SIGFPE",
+    "SIGKILL",
+    "SIGUSR1",
+    "SIGSEGV",
+    "SIGUSR2",
+    "SIGPIPE",
+    "SIGALRM",
+    "SIGTERM",
+    "SIGSTKFLT",
+    "SIGCHLD",
+    "SIGCONT",
+    "SIGSTOP",
+    "SIGTSTP",
+    "SIGTTIN",
+    "SIGTTOU",
+    "SIGURG",
+    "SIGXCPU",
+    "SIGXFSZ",
+    "SIGVTALRM",
+    "SIGPROF",
+    "SIGWINCH",
+    "SIGIO",
+    "SIGPWR",
+    "SIGSYS",
+];
+
+// "SIGHUP", "hup", "1" -> ("SIGHUP", 1). Что не из списка (в том числе SIGRTMIN+n) -- None
+pub fn parse_signal(raw: &str) -> Option<(&'static str, u8)> {
+    let position = match raw.parse::<usize>() {
+        Ok(number) => number.checked_sub(1).filter(|&i| i < SIGNALS.len())?,
+        Err(_) => {
+            let name = raw.to_ascii_uppercase();
+            let name = name.strip_prefix("SIG").unwrap_or(&name);
+            SIGNALS.iter().position(|signal| signal[3..] == *name)?
+        }
+    };
+    Some((SIGNALS[position], position as u8 + 1))
+}
+
+// Docker отдаёт время в RFC 3339 ("2024-05-01T10:00:00.123456789Z"),
+// а «никогда» -- как нулевую дату "0001-01-01T00:00:00Z"
+pub fn parse_docker_time(raw: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(raw.trim())
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+        .filter(|time| time.timestamp() > 0)
+}
+
+// Разбираем JSON docker inspect (он же ответ GET /containers/{id}/json в Docker Engine API).
+// Чего нет или не того типа -- остаётся пустым: старые версии docker отдают не все поля
+pub fn parse_inspect_json(json: &serde_json::Value) -> ContainerDetails {
+    let text =
+        |value: &serde_json::Value| value.as_str().filter(|s| !s.is_empty()).map(str::to_string);
+    let positive = |value: &serde_json::Value| value.as_u64().filter(|&n| n > 0);
+    let state = &json["State"];
+    let host_config = &json["HostConfig"];
+    let network_settings = &json["NetworkSettings"];
+
+    let health: Option<HealthInfo> = state["Health"].as_object().map(|_| {
+        let last = state["Health"]["Log"]
+            .as_array()
+            .and_then(|log| log.last())
+            .unwrap_or(&serde_json::Value::Null);
+        HealthInfo {
+            status: state["Health"]["Status"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            failing_streak: state["Health"]["FailingStreak"].as_u64().unwrap_or(0),
+            last_exit_code: last["ExitCode"].as_i64(),
+            last_output: text(&last["Output"]).map(|output| output.trim().to_string()),
+        }
+    });
+
+    let container_state = ContainerState {
+        status: state["Status"].as_str().unwrap_or_default().to_string(),
+        is_running: state["Running"].as_bool().unwrap_or(false),
+        is_paused: state["Paused"].as_bool().unwrap_or(false),
+        is_restarting: state["Restarting"].as_bool().unwrap_or(false),
+        is_dead: state["Dead"].as_bool().unwrap_or(false),
+        exit_code: state["ExitCode"].as_i64(),
+        started_at: state["StartedAt"].as_str().and_then(parse_docker_time),
+        finished_at: state["FinishedAt"].as_str().and_then(parse_docker_time),
+        restart_count: json["RestartCount"].as_u64().unwrap_or(0),
+        oom_killed: state["OOMKilled"].as_bool().unwrap_or(false),
+        pid: positive(&state["Pid"]).and_then(|pid| pid.try_into().ok()),
+        health: health.as_ref().map(|health| health.status.clone()),
+        error: text(&state["Error"]),
+    };
+
+    // "Env": ["PATH=/usr/bin", "DEBUG"]
+    let env: Vec<(String, String)> = json["Config"]["Env"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|var| var.as_str())
+        .map(|var| match var.split_once('=') {
+            Some((name, value)) => (name.to_string(), value.to_string()),
+            None => (var.to_string(), String::new()),
+        })
+        .collect();
+
+    let mounts: Vec<MountInfo> = json["Mounts"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|mount| MountInfo {
+            kind: mount["Type"].as_str().unwrap_or_default().to_string(),
+            name: text(&mount["Name"]),
+            source: mount["Source"].as_str().unwrap_or_default().to_string(),
+            destination: mount["Destination"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            read_only: !mount["RW"].as_bool().unwrap_or(true),
+        })
+        .collect();
+
+    // "Ports": {"80/tcp": [{"HostIp": "0.0.0.0", "HostPort": "8080"}], "443/tcp": null}
+    let mut ports: Vec<PortBinding> = Vec::new();
+    for (port, bindings) in network_settings["Ports"].as_object().into_iter().flatten() {
+        let (private, protocol) = port.split_once('/').unwrap_or((port, "tcp"));
+        let Ok(private_port) = private.parse::<u16>() else {
+            continue;
+        };
+        match bindings.as_array().filter(|b| !b.is_empty()) {
+            Some(bindings) => ports.extend(bindings.iter().map(|binding| PortBinding {
+                ip: text(&binding["HostIp"]),
+                private_port,
+                public_port: binding["HostPort"].as_str().and_then(|p| p.parse().ok()),
+                protocol: protocol.to_string(),
+            })),
+            None => ports.push(PortBinding {
+                ip: None,
+                private_port,
+                public_port: None,
+                protocol: protocol.to_string(),
+            }),
+        }
+    }
+
+    let networks: Vec<NetworkEndpoint> = network_settings["Networks"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(name, endpoint)| NetworkEndpoint {
+            name: name.clone(),
+            ip_address: text(&endpoint["IPAddress"]),
+            gateway: text(&endpoint["Gateway"]),
+            mac_address: text(&endpoint["MacAddress"]),
+        })
+        .collect();
+
+    let resources = ResourceLimits {
+        memory: positive(&host_config["Memory"]),
+        memory_reservation: positive(&host_config["MemoryReservation"]),
+        memory_swap: host_config["MemorySwap"].as_i64().filter(|&n| n != 0),
+        nano_cpus: positive(&host_config["NanoCpus"]),
+        cpu_shares: positive(&host_config["CpuShares"]),
+        cpu_quota: host_config["CpuQuota"].as_i64().filter(|&n| n > 0),
+        cpu_period: positive(&host_config["CpuPeriod"]),
+        cpuset_cpus: text(&host_config["CpusetCpus"]),
+        pids_limit: host_config["PidsLimit"].as_i64().filter(|&n| n > 0),
+    };
+
+    let command: Vec<String> = text(&json["Path"])
+        .into_iter()
+        .chain(
+            json["Args"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|arg| arg.as_str().map(str::to_string)),
+        )
+        .collect();
+
+    ContainerDetails {
+        id: json["Id"].as_str().unwrap_or_default().to_string(),
+        name: json["Name"]
+            .as_str()
+            .unwrap_or_default()
+            .trim_start_matches('/')
+            .to_string(),
+        image: json["Config"]["Image"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        created: json["Created"].as_str().and_then(parse_docker_time),
+        command,
+        tty: json["Config"]["Tty"].as_bool().unwrap_or(false),
+        state: container_state,
+        health,
+        env,
+        mounts,
+        network: NetworkInfo {
+            mode: host_config["NetworkMode"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            ports,
+            networks,
+        },
+        resources,
+        restart_policy: RestartPolicy {
+            name: host_config["RestartPolicy"]["Name"]
+                .as_str()
+                .filter(|name| !name.is_empty())
+                .unwrap_or("no")
+                .to_string(),
+            max_retries: host_config["RestartPolicy"]["MaximumRetryCount"]
+                .as_u64()
+                .unwrap_or(0),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_full_inspect() {
+        let json = serde_json::json!({
+            "Id": "4f1c2a",
+            "Created": "2024-05-01T09:59:58.123Z",
+            "Path": "docker-entrypoint.sh",
+            "Args": ["postgres"],
+            "Name": "/db",
+            "RestartCount": 3,
+            "State": {
+                "Status": "running", "Running": true, "Paused": false, "Restarting": false,
+                "OOMKilled": false, "Dead": false, "Pid": 4242, "ExitCode": 0, "Error": "",
+                "StartedAt": "2024-05-01T10:00:00Z", "FinishedAt": "0001-01-01T00:00:00Z",
+                "Health": {
+                    "Status": "unhealthy",
+                    "FailingStreak": 2,
+                    "Log": [
+                        {"ExitCode": 0, "Output": "ok\n"},
+                        {"ExitCode": 1, "Output": "no response\n"}
+                    ]
+                }
+            },
+            "HostConfig": {
+                "NetworkMode": "backend",
+                "RestartPolicy": {"Name": "on-failure", "MaximumRetryCount": 5},
+                "Memory": 536870912, "MemorySwap": -1, "MemoryReservation": 0,
+                "NanoCpus": 1500000000, "CpuShares": 0, "CpuQuota": 0, "CpuPeriod": 0,
+                "CpusetCpus": "", "PidsLimit": null
+            },
+            "Mounts": [
+                {"Type": "bind", "Source": "/etc/pg.conf", "Destination": "/etc/postgresql.conf", "Mode": "ro", "RW": false},
+                {"Type": "volume", "Name": "pgdata", "Source": "/var/lib/docker/volumes/pgdata/_data", "Destination": "/var/lib/postgresql/data", "RW": true}
+            ],
+            "Config": {
+                "Image": "postgres:16",
+                "Env": ["POSTGRES_PASSWORD=hunter2", "LANG=en_US.utf8=x", "EMPTY"]
+            },
+            "NetworkSettings": {
+                "Ports": {
+                    "5432/tcp": [{"HostIp": "127.0.0.1", "HostPort": "15432"}],
+                    "8080/udp": null
+                },
+                "Networks": {
+                    "backend": {"IPAddress": "172.18.0.2", "Gateway": "172.18.0.1", "MacAddress": "02:42:ac:12:00:02"}
+                }
+            }
+        });
+
+        let details = parse_inspect_json(&json);
+
+        assert_eq!(details.name, "db");
+        assert_eq!(details.image, "postgres:16");
+        assert_eq!(details.command, vec!["docker-entrypoint.sh", "postgres"]);
+        assert!(details.created.is_some());
+
+        assert!(details.state.is_running);
+        assert_eq!(details.state.pid, Some(4242));
+        assert_eq!(details.state.restart_count, 3);
+        assert_eq!(details.state.health.as_deref(), Some("unhealthy"));
+        assert_eq!(details.state.error, None);
+        assert_eq!(details.state.finished_at, None);
+
+        let health = details.health.unwrap();
+        assert_eq!(health.failing_streak, 2);
+        assert_eq!(health.last_exit_code, Some(1));
+        assert_eq!(health.last_output.as_deref(), Some("no response"));
+
+        assert_eq!(
+            details.env,
+            vec![
+                ("POSTGRES_PASSWORD".to_string(), "hunter2".to_string()),
+                ("LANG".to_string(), "en_US.utf8=x".to_string()),
+                ("EMPTY".to_string(), String::new()),
+            ]
+        );
+        assert!(details.mounts[0].read_only);
+        assert_eq!(details.mounts[0].name, None);
+        assert_eq!(details.mounts[1].name.as_deref(), Some("pgdata"));
+
+        assert_eq!(details.network.mode, "backend");
+        assert_eq!(details.network.ports.len(), 2);
+        assert_eq!(details.network.ports[0].public_port, Some(15432));
+        assert_eq!(details.network.ports[1].public_port, None);
+        assert_eq!(details.network.ports[1].protocol, "udp");
+        assert_eq!(
+            details.network.networks[0].ip_address.as_deref(),
+            Some("172.18.0.2")
+        );
+
+        assert_eq!(
+            details.resources,
+            ResourceLimits {
+                memory: Some(536870912),
+                memory_swap: Some(-1),
+                nano_cpus: Some(1_500_000_000),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            details.restart_policy,
+            RestartPolicy {
+                name: "on-failure".to_string(),
+                max_retries: 5
+            }
+        );
+
+        // пустой JSON -- пустые детали, а не паника
+        assert_eq!(
+            parse_inspect_json(&serde_json::Value::Null)
+                .restart_policy
+                .name,
+            "no"
+        );
+    }
+
+    #[test]
+    fn signal_names() {
+        assert_eq!(parse_signal("SIGHUP"), Some(("SIGHUP", 1)));
+        assert_eq!(parse_signal("hup"), Some(("SIGHUP", 1)));
+        assert_eq!(parse_signal("Kill"), Some(("SIGKILL", 9)));
+        assert_eq!(parse_signal("15"), Some(("SIGTERM", 15)));
+        assert_eq!(parse_signal("31"), Some(("SIGSYS", 31)));
+        assert_eq!(parse_signal("0"), None);
+        assert_eq!(parse_signal("32"), None);
+        assert_eq!(parse_signal("SIG"), None);
+        assert_eq!(parse_signal("SIGHUP; rm -rf /"), None);
+    }
+}
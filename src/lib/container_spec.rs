@@ -0,0 +1,875 @@
+use std::{collections::BTreeMap, net::IpAddr};
+
+use serde_json::{Map, Value};
+
+use crate::lib::{
+    container_backend::{MountInfo, ResourceLimits, RestartPolicy},
+    docker_works::{PortBinding, is_valid_label},
+};
+
+// Описание нового контейнера для POST /container. Ключи -- те же, что отдаёт
+// GET /container/:id, так что контейнер можно «переснять» с уже работающего:
+//
+// {
+//   "image": "nginx:1.27",                                   <- единственное обязательное поле
+//   "name": "web1",
+//   "command": ["nginx", "-g", "daemon off;"],
+//   "env": {"NGINX_PORT": "80"},
+//   "ports": [{"private_port": 80, "public_port": 8080, "ip": "127.0.0.1", "protocol": "tcp"}],
+//   "volumes": [{"source": "static", "destination": "/usr/share/nginx/html", "read_only": true}],
+//   "restart_policy": {"name": "on-failure", "max_retries": 3},
+//   "labels": {"team": "web"},
+//   "resources": {"memory": 268435456, "nano_cpus": 500000000, "pids_limit": 100}
+// }
+//
+// Разбор строгий: неизвестный ключ или значение не того типа -- ошибка, а не молчаливый пропуск
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ContainerSpec {
+    pub image: String,
+    pub name: Option<String>, // None -- имя придумает docker
+    pub command: Vec<String>, // пусто -- команда из образа
+    pub env: Vec<(String, String)>,
+    pub ports: Vec<PortBinding>, // public_port: None -- любой свободный порт
+    pub volumes: Vec<MountInfo>, // source с '/' -- bind, иначе именованный том
+    pub restart_policy: RestartPolicy,
+    pub labels: BTreeMap<String, String>,
+    pub resources: ResourceLimits,
+}
+
+// Что не так со спецификацией: путь до поля ("ports[1].public_port") и почему
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpecError {
+    pub field: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for SpecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.field.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.field, self.message)
+        }
+    }
+}
+
+fn error(field: &str, message: impl Into<String>) -> SpecError {
+    SpecError {
+        field: field.to_string(),
+        message: message.into(),
+    }
+}
+
+const SPEC_KEYS: [&str; 9] = [
+    "image",
+    "name",
+    "command",
+    "env",
+    "ports",
+    "volumes",
+    "restart_policy",
+    "labels",
+    "resources",
+];
+const PORT_KEYS: [&str; 4] = ["private_port", "public_port", "ip", "protocol"];
+const VOLUME_KEYS: [&str; 3] = ["source", "destination", "read_only"];
+const RESTART_POLICY_KEYS: [&str; 2] = ["name", "max_retries"];
+const RESOURCE_KEYS: [&str; 9] = [
+    "memory",
+    "memory_reservation",
+    "memory_swap",
+    "nano_cpus",
+    "cpu_shares",
+    "cpu_quota",
+    "cpu_period",
+    "cpuset_cpus",
+    "pids_limit",
+];
+
+// Меньше docker не даст: "Minimum memory limit allowed is 6MB"
+const MIN_MEMORY: u64 = 6 * 1024 * 1024;
+const MAX_IMAGE_LEN: usize = 255;
+
+impl ContainerSpec {
+    pub fn from_json(json: &Value) -> Result<Self, SpecError> {
+        let spec = object(json, "")?;
+        check_keys(spec, &SPEC_KEYS, "")?;
+
+        let image = match spec.get("image") {
+            None | Some(Value::Null) => return Err(error("image", "is required")),
+            Some(image) => string(image, "image")?,
+        };
+        if !is_valid_image(&image) {
+            return Err(error(
+                "image",
+                format!(
+                    "{image:?} is not a valid image reference (nginx, nginx:1.27, registry.local:5000/team/app@sha256:...)"
+                ),
+            ));
+        }
+
+        let name = optional(spec, "name")
+            .map(|name| {
+                let name = string(name, "name")?;
+                if !is_valid_label(&name) {
+                    return Err(error(
+                        "name",
+                        format!("{name:?} is not a valid container name: use ASCII letters, digits, '-', '_' and '.', starting with a letter or digit"),
+                    ));
+                }
+                Ok(name)
+            })
+            .transpose()?;
+
+        let command = match optional(spec, "command") {
+            None => Vec::new(),
+            Some(command) => parse_argv(command, "command")?,
+        };
+
+        let env = match optional(spec, "env") {
+            None => Vec::new(),
+            Some(env) => parse_env(env)?,
+        };
+
+        let ports = match optional(spec, "ports") {
+            None => Vec::new(),
+            Some(ports) => parse_ports(array(ports, "ports")?)?,
+        };
+
+        let volumes = match optional(spec, "volumes") {
+            None => Vec::new(),
+            Some(volumes) => parse_volumes(array(volumes, "volumes")?)?,
+        };
+
+        let restart_policy = match optional(spec, "restart_policy") {
+            None => RestartPolicy {
+                name: "no".to_string(),
+                max_retries: 0,
+            },
+            Some(policy) => parse_restart_policy(policy)?,
+        };
+
+        let labels = match optional(spec, "labels") {
+            None => BTreeMap::new(),
+            Some(labels) => object(labels, "labels")?
+                .iter()
+                .map(|(key, value)| {
+                    let field = format!("labels.{key}");
+                    if key.is_empty() || key.contains(['=', '\0']) {
+                        return Err(error(
+                            &field,
+                            "label keys must be non-empty and without '='",
+                        ));
+                    }
+                    Ok((key.clone(), no_nul(string(value, &field)?, &field)?))
+                })
+                .collect::<Result<_, _>>()?,
+        };
+
+        let resources = match optional(spec, "resources") {
+            None => ResourceLimits::default(),
+            Some(resources) => parse_resources(resources)?,
+        };
+
+        Ok(Self {
+            image,
+            name,
+            command,
+            env,
+            ports,
+            volumes,
+            restart_policy,
+            labels,
+            resources,
+        })
+    }
+}
+
+// Команда для POST /container/:id/exec -- запускается как есть, без shell:
+//
+// {
+//   "argv": ["pg_isready", "-h", "localhost"],               <- единственное обязательное поле
+//   "env": {"PGCONNECT_TIMEOUT": "3"},
+//   "workdir": "/var/lib/postgresql",
+//   "user": "postgres"
+// }
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ExecSpec {
+    pub argv: Vec<String>, // argv[0] -- программа (ищется в PATH контейнера)
+    pub env: Vec<(String, String)>, // добавляются к переменным контейнера
+    pub workdir: Option<String>, // None -- рабочий каталог контейнера
+    pub user: Option<String>, // "postgres", "1000", "1000:1000"; None -- пользователь контейнера
+}
+
+const EXEC_KEYS: [&str; 4] = ["argv", "env", "workdir", "user"];
+
+impl ExecSpec {
+    pub fn from_json(json: &Value) -> Result<Self, SpecError> {
+        let spec = object(json, "")?;
+        check_keys(spec, &EXEC_KEYS, "")?;
+
+        let argv = match optional(spec, "argv") {
+            None => return Err(error("argv", "is required")),
+            Some(argv) => parse_argv(argv, "argv")?,
+        };
+        if argv.first().is_none_or(|program| program.is_empty()) {
+            return Err(error("argv", "must start with a non-empty program name"));
+        }
+
+        let env = match optional(spec, "env") {
+            None => Vec::new(),
+            Some(env) => parse_env(env)?,
+        };
+
+        let workdir = optional(spec, "workdir")
+            .map(|workdir| {
+                let workdir = no_nul(string(workdir, "workdir")?, "workdir")?;
+                if !workdir.starts_with('/') {
+                    return Err(error(
+                        "workdir",
+                        "must be an absolute path inside the container",
+                    ));
+                }
+                Ok(workdir)
+            })
+            .transpose()?;
+
+        let user = optional(spec, "user")
+            .map(|user| {
+                let user = string(user, "user")?;
+                if !is_valid_user(&user) {
+                    return Err(error(
+                        "user",
+                        format!(
+                            "{user:?} is not a user like \"postgres\", \"1000\" or \"1000:1000\""
+                        ),
+                    ));
+                }
+                Ok(user)
+            })
+            .transpose()?;
+
+        Ok(Self {
+            argv,
+            env,
+            workdir,
+            user,
+        })
+    }
+}
+
+// ["nginx", "-g", "daemon off;"] -- аргументы строками, без NUL
+fn parse_argv(argv: &Value, field: &str) -> Result<Vec<String>, SpecError> {
+    array(argv, field)?
+        .iter()
+        .enumerate()
+        .map(|(i, arg)| {
+            let field = format!("{field}[{i}]");
+            no_nul(string(arg, &field)?, &field)
+        })
+        .collect()
+}
+
+// {"NGINX_PORT": "80"} -> [("NGINX_PORT", "80")]
+fn parse_env(env: &Value) -> Result<Vec<(String, String)>, SpecError> {
+    object(env, "env")?
+        .iter()
+        .map(|(name, value)| {
+            let field = format!("env.{name}");
+            if !is_valid_env_name(name) {
+                return Err(error(
+                    &field,
+                    "variable names are letters, digits and '_', not starting with a digit",
+                ));
+            }
+            Ok((name.clone(), no_nul(string(value, &field)?, &field)?))
+        })
+        .collect()
+}
+
+fn parse_ports(ports: &[Value]) -> Result<Vec<PortBinding>, SpecError> {
+    let mut parsed: Vec<PortBinding> = Vec::new();
+    for (i, port) in ports.iter().enumerate() {
+        let path = format!("ports[{i}]");
+        let fields = object(port, &path)?;
+        check_keys(fields, &PORT_KEYS, &path)?;
+
+        let port_number = |key: &str| -> Result<Option<u16>, SpecError> {
+            optional(fields, key)
+                .map(|value| {
+                    value
+                        .as_u64()
+                        .filter(|port| (1..=65535).contains(port))
+                        .map(|port| port as u16)
+                        .ok_or(error(
+                            &format!("{path}.{key}"),
+                            "must be a port number from 1 to 65535",
+                        ))
+                })
+                .transpose()
+        };
+
+        let private_port = port_number("private_port")?
+            .ok_or(error(&format!("{path}.private_port"), "is required"))?;
+        let public_port = port_number("public_port")?;
+        let ip = optional(fields, "ip")
+            .map(|ip| {
+                let ip = string(ip, &format!("{path}.ip"))?;
+                ip.parse::<IpAddr>().map(|_| ip.clone()).map_err(|_| {
+                    error(
+                        &format!("{path}.ip"),
+                        format!("{ip:?} is not an IP address"),
+                    )
+                })
+            })
+            .transpose()?;
+        if ip.is_some() && public_port.is_none() {
+            // docker такое примет, но слушать будет случайный порт -- почти наверняка опечатка
+            return Err(error(
+                &format!("{path}.public_port"),
+                "is required when ip is set",
+            ));
+        }
+        let protocol = match optional(fields, "protocol") {
+            None => "tcp".to_string(),
+            Some(protocol) => string(protocol, &format!("{path}.protocol"))?,
+        };
+        if !matches!(protocol.as_str(), "tcp" | "udp" | "sctp") {
+            return Err(error(
+                &format!("{path}.protocol"),
+                "must be tcp, udp or sctp",
+            ));
+        }
+
+        let binding = PortBinding {
+            ip,
+            private_port,
+            public_port,
+            protocol,
+        };
+        // один и тот же порт хоста дважды не займёшь
+        if binding.public_port.is_some()
+            && parsed.iter().any(|other| {
+                other.public_port == binding.public_port
+                    && other.protocol == binding.protocol
+                    && (other.ip.is_none() || binding.ip.is_none() || other.ip == binding.ip)
+            })
+        {
+            return Err(error(
+                &format!("{path}.public_port"),
+                format!(
+                    "host port {}/{} is already bound",
+                    public_port.unwrap_or_default(),
+                    binding.protocol
+                ),
+            ));
+        }
+        parsed.push(binding);
+    }
+    Ok(parsed)
+}
+
+fn parse_volumes(volumes: &[Value]) -> Result<Vec<MountInfo>, SpecError> {
+    let mut parsed: Vec<MountInfo> = Vec::new();
+    for (i, volume) in volumes.iter().enumerate() {
+        let path = format!("volumes[{i}]");
+        let fields = object(volume, &path)?;
+        check_keys(fields, &VOLUME_KEYS, &path)?;
+
+        let required = |key: &str| -> Result<String, SpecError> {
+            let field = format!("{path}.{key}");
+            let value = string(
+                optional(fields, key).ok_or(error(&field, "is required"))?,
+                &field,
+            )?;
+            // ',' и '"' ломают синтаксис docker --mount
+            if value.contains([',', '"', '\0']) {
+                return Err(error(&field, "must not contain ',', '\"' or NUL"));
+            }
+            Ok(value)
+        };
+
+        let source = required("source")?;
+        let destination = required("destination")?;
+        if !destination.starts_with('/') || destination == "/" {
+            return Err(error(
+                &format!("{path}.destination"),
+                "must be an absolute path inside the container (not \"/\")",
+            ));
+        }
+        let (kind, name) = if source.starts_with('/') {
+            ("bind", None)
+        } else if is_valid_label(&source) {
+            ("volume", Some(source.clone()))
+        } else {
+            return Err(error(
+                &format!("{path}.source"),
+                "must be an absolute host path or a volume name",
+            ));
+        };
+        let read_only = match optional(fields, "read_only") {
+            None => false,
+            Some(read_only) => read_only
+                .as_bool()
+                .ok_or(error(&format!("{path}.read_only"), "must be a boolean"))?,
+        };
+
+        if parsed.iter().any(|other| other.destination == destination) {
+            return Err(error(
+                &format!("{path}.destination"),
+                format!("{destination} is already mounted"),
+            ));
+        }
+        parsed.push(MountInfo {
+            kind: kind.to_string(),
+            name,
+            source,
+            destination,
+            read_only,
+        });
+    }
+    Ok(parsed)
+}
+
+fn parse_restart_policy(policy: &Value) -> Result<RestartPolicy, SpecError> {
+    let path = "restart_policy";
+    let fields = object(policy, path)?;
+    check_keys(fields, &RESTART_POLICY_KEYS, path)?;
+
+    let name = match optional(fields, "name") {
+        None => "no".to_string(),
+        Some(name) => string(name, "restart_policy.name")?,
+    };
+    if !matches!(
+        name.as_str(),
+        "no" | "always" | "unless-stopped" | "on-failure"
+    ) {
+        return Err(error(
+            "restart_policy.name",
+            "must be no, always, unless-stopped or on-failure",
+        ));
+    }
+    let max_retries = match optional(fields, "max_retries") {
+        None => 0,
+        Some(_) if name != "on-failure" => {
+            return Err(error(
+                "restart_policy.max_retries",
+                "is only allowed with on-failure",
+            ));
+        }
+        Some(retries) => retries.as_u64().ok_or(error(
+            "restart_policy.max_retries",
+            "must be a non-negative integer",
+        ))?,
+    };
+    Ok(RestartPolicy { name, max_retries })
+}
+
+fn parse_resources(resources: &Value) -> Result<ResourceLimits, SpecError> {
+    let path = "resources";
+    let fields = object(resources, path)?;
+    check_keys(fields, &RESOURCE_KEYS, path)?;
+
+    // неотрицательное число в пределах [min, max]
+    let unsigned = |key: &str, min: u64, max: u64| -> Result<Option<u64>, SpecError> {
+        optional(fields, key)
+            .map(|value| {
+                value
+                    .as_u64()
+                    .filter(|n| (min..=max).contains(n))
+                    .ok_or(error(
+                        &format!("resources.{key}"),
+                        format!("must be an integer from {min} to {max}"),
+                    ))
+            })
+            .transpose()
+    };
+    // то же, но ещё можно -1 -- «без ограничения»
+    let unlimited = |key: &str, min: i64| -> Result<Option<i64>, SpecError> {
+        optional(fields, key)
+            .map(|value| {
+                value.as_i64().filter(|&n| n == -1 || n >= min).ok_or(error(
+                    &format!("resources.{key}"),
+                    format!("must be -1 (unlimited) or an integer from {min}"),
+                ))
+            })
+            .transpose()
+    };
+
+    let limits = ResourceLimits {
+        memory: unsigned("memory", MIN_MEMORY, u64::MAX)?,
+        memory_reservation: unsigned("memory_reservation", MIN_MEMORY, u64::MAX)?,
+        memory_swap: unlimited("memory_swap", 1)?,
+        nano_cpus: unsigned("nano_cpus", 1, u64::MAX)?,
+        cpu_shares: unsigned("cpu_shares", 2, 262_144)?,
+        cpu_quota: unlimited("cpu_quota", 1000)?,
+        cpu_period: unsigned("cpu_period", 1000, 1_000_000)?,
+        cpuset_cpus: optional(fields, "cpuset_cpus")
+            .map(|cpus| {
+                let cpus = string(cpus, "resources.cpuset_cpus")?;
+                if !is_valid_cpuset(&cpus) {
+                    return Err(error(
+                        "resources.cpuset_cpus",
+                        format!("{cpus:?} is not a CPU list like \"0-3\" or \"0,2\""),
+                    ));
+                }
+                Ok(cpus)
+            })
+            .transpose()?,
+        pids_limit: unlimited("pids_limit", 1)?,
+    };
+
+    // те же проверки, что делает демон -- чтобы ошибка пришла с именем поля
+    if let (Some(reservation), Some(memory)) = (limits.memory_reservation, limits.memory)
+        && reservation > memory
+    {
+        return Err(error(
+            "resources.memory_reservation",
+            "must not be greater than memory",
+        ));
+    }
+    if let Some(swap) = limits.memory_swap {
+        match limits.memory {
+            None => {
+                return Err(error("resources.memory_swap", "requires memory to be set"));
+            }
+            Some(memory) if swap != -1 && (swap as u64) < memory => {
+                return Err(error(
+                    "resources.memory_swap",
+                    "must be -1 or not less than memory (it counts memory plus swap)",
+                ));
+            }
+            _ => {}
+        }
+    }
+    if limits.nano_cpus.is_some() && (limits.cpu_quota.is_some() || limits.cpu_period.is_some()) {
+        return Err(error(
+            "resources.nano_cpus",
+            "conflicts with cpu_quota and cpu_period, use one or the other",
+        ));
+    }
+    Ok(limits)
+}
+
+// Ссылка на образ: [registry[:port]/]repo[/repo...][:tag][@digest]. Проверяем только
+// алфавит и начало -- остальное скажет демон, но флаг вместо образа так не пролезет
+fn is_valid_image(image: &str) -> bool {
+    !image.is_empty()
+        && image.len() <= MAX_IMAGE_LEN
+        && image.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && image
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._-/:@".contains(c))
+}
+
+fn is_valid_env_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// user[:group], каждая часть -- имя или число, как у docker exec --user
+fn is_valid_user(user: &str) -> bool {
+    let mut parts = user.splitn(2, ':');
+    parts.all(|part| {
+        part.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_')
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "._-".contains(c))
+    })
+}
+
+// "0-3", "0,2", "0-1,4-5"
+fn is_valid_cpuset(cpus: &str) -> bool {
+    cpus.split(',').all(|part| {
+        let mut bounds = part.splitn(2, '-');
+        let is_number = |s: Option<&str>| {
+            s.is_some_and(|s| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit()))
+        };
+        match (bounds.next(), bounds.next()) {
+            (first, None) => is_number(first),
+            (first, second) => is_number(first) && is_number(second),
+        }
+    })
+}
+
+// ------ общие проверки типов ---------------------------------------
+
+fn object<'a>(json: &'a Value, field: &str) -> Result<&'a Map<String, Value>, SpecError> {
+    json.as_object()
+        .ok_or(error(field, "must be a JSON object"))
+}
+
+fn array<'a>(json: &'a Value, field: &str) -> Result<&'a Vec<Value>, SpecError> {
+    json.as_array().ok_or(error(field, "must be a JSON array"))
+}
+
+fn string(json: &Value, field: &str) -> Result<String, SpecError> {
+    json.as_str()
+        .map(str::to_string)
+        .ok_or(error(field, "must be a string"))
+}
+
+fn no_nul(value: String, field: &str) -> Result<String, SpecError> {
+    if value.contains('\0') {
+        return Err(error(field, "must not contain NUL"));
+    }
+    Ok(value)
+}
+
+// null -- то же, что ключа нет
+fn optional<'a>(object: &'a Map<String, Value>, key: &str) -> Option<&'a Value> {
+    object.get(key).filter(|value| !value.is_null())
+}
+
+fn check_keys(object: &Map<String, Value>, allowed: &[&str], path: &str) -> Result<(), SpecError> {
+    match object.keys().find(|key| !allowed.contains(&key.as_str())) {
+        None => Ok(()),
+        Some(key) => {
+            let field = if path.is_empty() {
+                key.clone()
+            } else {
+                format!("{path}.{key}")
+            };
+            Err(error(
+                &field,
+                format!("unknown field, expected one of: {}", allowed.join(", ")),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn field_error(json: Value) -> String {
+        match ContainerSpec::from_json(&json) {
+            Ok(spec) => panic!("spec should be rejected: {spec:?}"),
+            Err(e) => e.field,
+        }
+    }
+
+    #[test]
+    fn full_spec() -> Result<(), SpecError> {
+        let spec = ContainerSpec::from_json(&json!({
+            "image": "registry.local:5000/team/nginx:1.27",
+            "name": "web2",
+            "command": ["nginx", "-g", "daemon off;"],
+            "env": {"NGINX_PORT": "80", "EMPTY": ""},
+            "ports": [
+                {"private_port": 80, "public_port": 8080, "ip": "127.0.0.1"},
+                {"private_port": 53, "protocol": "udp"},
+            ],
+            "volumes": [
+                {"source": "static", "destination": "/usr/share/nginx/html", "read_only": true},
+                {"source": "/var/log/web2", "destination": "/var/log/nginx"},
+            ],
+            "restart_policy": {"name": "on-failure", "max_retries": 3},
+            "labels": {"team": "web"},
+            "resources": {"memory": 268435456, "memory_swap": -1, "nano_cpus": 500000000, "pids_limit": 100, "cpuset_cpus": "0-1,3"},
+        }))?;
+
+        assert_eq!(spec.name.as_deref(), Some("web2"));
+        assert_eq!(spec.command.len(), 3);
+        assert_eq!(
+            spec.env,
+            vec![
+                ("EMPTY".to_string(), String::new()),
+                ("NGINX_PORT".to_string(), "80".to_string())
+            ]
+        );
+        assert_eq!(
+            spec.ports[0],
+            PortBinding {
+                ip: Some("127.0.0.1".to_string()),
+                private_port: 80,
+                public_port: Some(8080),
+                protocol: "tcp".to_string(),
+            }
+        );
+        assert_eq!(spec.ports[1].public_port, None);
+        assert_eq!(spec.volumes[0].kind, "volume");
+        assert_eq!(spec.volumes[0].name.as_deref(), Some("static"));
+        assert!(spec.volumes[0].read_only);
+        assert_eq!(spec.volumes[1].kind, "bind");
+        assert_eq!(spec.restart_policy.max_retries, 3);
+        assert_eq!(spec.resources.memory, Some(256 * 1024 * 1024));
+        assert_eq!(spec.resources.memory_swap, Some(-1));
+        assert_eq!(spec.resources.pids_limit, Some(100));
+
+        let minimal = ContainerSpec::from_json(&json!({"image": "alpine", "name": null}))?;
+        assert_eq!(minimal.name, None);
+        assert_eq!(minimal.restart_policy.name, "no");
+        Ok(())
+    }
+
+    #[test]
+    fn strict_validation() {
+        assert_eq!(field_error(json!([])), "");
+        assert_eq!(field_error(json!({})), "image");
+        assert_eq!(field_error(json!({"image": "--privileged"})), "image");
+        assert_eq!(field_error(json!({"image": "alpine", "nmae": "x"})), "nmae");
+        for name in ["", "-rf", "_x", "web/1", "a b"] {
+            assert_eq!(
+                field_error(json!({"image": "alpine", "name": name})),
+                "name",
+                "{name:?}"
+            );
+        }
+        assert_eq!(
+            field_error(json!({"image": "alpine", "name": "x".repeat(129)})),
+            "name"
+        );
+        assert_eq!(
+            field_error(json!({"image": "alpine", "command": "sh -c"})),
+            "command"
+        );
+        assert_eq!(
+            field_error(json!({"image": "alpine", "command": ["sh", 1]})),
+            "command[1]"
+        );
+        assert_eq!(
+            field_error(json!({"image": "alpine", "env": {"1X": "a"}})),
+            "env.1X"
+        );
+        assert_eq!(
+            field_error(json!({"image": "alpine", "env": {"X": 1}})),
+            "env.X"
+        );
+        assert_eq!(
+            field_error(json!({"image": "alpine", "ports": [{"public_port": 80}]})),
+            "ports[0].private_port"
+        );
+        assert_eq!(
+            field_error(json!({"image": "alpine", "ports": [{"private_port": 70000}]})),
+            "ports[0].private_port"
+        );
+        assert_eq!(
+            field_error(
+                json!({"image": "alpine", "ports": [{"private_port": 80, "ip": "localhost", "public_port": 80}]})
+            ),
+            "ports[0].ip"
+        );
+        assert_eq!(
+            field_error(
+                json!({"image": "alpine", "ports": [{"private_port": 80, "protocol": "icmp"}]})
+            ),
+            "ports[0].protocol"
+        );
+        assert_eq!(
+            field_error(json!({"image": "alpine", "ports": [
+                {"private_port": 80, "public_port": 8080},
+                {"private_port": 81, "public_port": 8080, "ip": "127.0.0.1"},
+            ]})),
+            "ports[1].public_port"
+        );
+        assert_eq!(
+            field_error(
+                json!({"image": "alpine", "volumes": [{"source": "data", "destination": "data"}]})
+            ),
+            "volumes[0].destination"
+        );
+        assert_eq!(
+            field_error(
+                json!({"image": "alpine", "volumes": [{"source": "../etc", "destination": "/etc"}]})
+            ),
+            "volumes[0].source"
+        );
+        assert_eq!(
+            field_error(
+                json!({"image": "alpine", "volumes": [{"source": "/a,b", "destination": "/x"}]})
+            ),
+            "volumes[0].source"
+        );
+        assert_eq!(
+            field_error(
+                json!({"image": "alpine", "restart_policy": {"name": "always", "max_retries": 3}})
+            ),
+            "restart_policy.max_retries"
+        );
+        assert_eq!(
+            field_error(json!({"image": "alpine", "restart_policy": "always"})),
+            "restart_policy"
+        );
+        assert_eq!(
+            field_error(json!({"image": "alpine", "labels": {"a=b": "c"}})),
+            "labels.a=b"
+        );
+        assert_eq!(
+            field_error(json!({"image": "alpine", "resources": {"memory": 1024}})),
+            "resources.memory"
+        );
+        assert_eq!(
+            field_error(json!({"image": "alpine", "resources": {"memory_swap": -1}})),
+            "resources.memory_swap"
+        );
+        assert_eq!(
+            field_error(
+                json!({"image": "alpine", "resources": {"nano_cpus": 1000000000, "cpu_quota": 50000}})
+            ),
+            "resources.nano_cpus"
+        );
+        assert_eq!(
+            field_error(json!({"image": "alpine", "resources": {"cpuset_cpus": "0-"}})),
+            "resources.cpuset_cpus"
+        );
+        assert_eq!(
+            field_error(json!({"image": "alpine", "resources": {"cpus": 2}})),
+            "resources.cpus"
+        );
+    }
+
+    #[test]
+    fn exec_spec() -> Result<(), SpecError> {
+        let spec = ExecSpec::from_json(&json!({
+            "argv": ["pg_isready", "-h", "localhost"],
+            "env": {"PGCONNECT_TIMEOUT": "3"},
+            "workdir": "/var/lib/postgresql",
+            "user": "postgres:postgres",
+        }))?;
+        assert_eq!(
+            spec,
+            ExecSpec {
+                argv: vec![
+                    "pg_isready".to_string(),
+                    "-h".to_string(),
+                    "localhost".to_string()
+                ],
+                env: vec![("PGCONNECT_TIMEOUT".to_string(), "3".to_string())],
+                workdir: Some("/var/lib/postgresql".to_string()),
+                user: Some("postgres:postgres".to_string()),
+            }
+        );
+
+        let spec = ExecSpec::from_json(&json!({"argv": ["nginx", "-t"], "user": "1000"}))?;
+        assert_eq!(spec.argv, vec!["nginx", "-t"]);
+        assert_eq!(spec.user.as_deref(), Some("1000"));
+        assert_eq!((spec.env.len(), spec.workdir), (0, None));
+
+        let exec_error = |json: Value| match ExecSpec::from_json(&json) {
+            Ok(spec) => panic!("exec should be rejected: {spec:?}"),
+            Err(e) => e.field,
+        };
+        assert_eq!(exec_error(json!({"env": {}})), "argv");
+        assert_eq!(exec_error(json!({"argv": []})), "argv");
+        assert_eq!(exec_error(json!({"argv": [""]})), "argv");
+        assert_eq!(exec_error(json!({"argv": "nginx -t"})), "argv");
+        assert_eq!(exec_error(json!({"argv": ["ls", 1]})), "argv[1]");
+        assert_eq!(
+            exec_error(json!({"argv": ["ls"], "env": {"1X": "y"}})),
+            "env.1X"
+        );
+        assert_eq!(
+            exec_error(json!({"argv": ["ls"], "workdir": "tmp"})),
+            "workdir"
+        );
+        assert_eq!(
+            exec_error(json!({"argv": ["ls"], "user": "--privileged"})),
+            "user"
+        );
+        assert_eq!(exec_error(json!({"argv": ["ls"], "tty": true})), "tty");
+        Ok(())
+    }
+}
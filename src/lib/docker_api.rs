@@ -0,0 +1,1487 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io::{BufRead, BufReader, Read, Write},
+    net::Shutdown,
+    os::unix::net::UnixStream,
+    path::PathBuf,
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+
+use crate::lib::{
+    chunked::ChunkedReader,
+    container_backend::{
+        ContainerBackend, ContainerDetails, ContainerState, ContainerStats, ExecControl, ExecEvent,
+        ExecSession, ExecStream, LogLine, LogOptions, LogSource, LogStream, RemoveOptions, TtySize,
+        parse_docker_time, parse_inspect_json,
+    },
+    container_spec::{ContainerSpec, ExecSpec},
+    docker_works::{
+        ContainerError, ContainerInfo, ContainerSize, ContainerStatus, PortBinding, check_label,
+        parse_status_text,
+    },
+    headers::HeaderMap,
+};
+
+// Путь к сокету демона по умолчанию
+pub const DEFAULT_DOCKER_SOCKET: &str = "/var/run/docker.sock";
+
+// Версия API, под формат JSON которой написан разбор (Docker 20.10+)
+const API_PREFIX: &str = "/v1.41";
+
+// Максимальный размер ответа демона, который читаем целиком (список контейнеров, inspect)
+const MAX_RESPONSE_SIZE: u64 = 64 * 1024 * 1024;
+
+// Реализация ContainerBackend через Docker Engine REST API.
+// Запросы идут прямо в unix-сокет демона, без запуска процесса docker на каждую операцию,
+// а ответы -- JSON с точным состоянием, кодами выхода и временем:
+//
+//   GET  /v1.41/containers/json?all=1     -> list
+//   GET  /v1.41/containers/{id}/json      -> inspect
+//   POST /v1.41/containers/create         -> create
+//   POST /v1.41/containers/{id}/start     -> start
+//   POST /v1.41/containers/{id}/stop      -> stop
+//   POST /v1.41/containers/{id}/restart   -> restart
+//   DELETE /v1.41/containers/{id}         -> remove
+//   POST /v1.41/containers/{id}/pause     -> pause (и unpause)
+//   POST /v1.41/containers/{id}/kill      -> kill
+//   POST /v1.41/containers/{id}/exec      -> exec (дальше /exec/{exec_id}/start и /json)
+//                                            и exec_tty (start с Upgrade: tcp, /exec/{exec_id}/resize)
+#[derive(Debug, Clone)]
+pub struct DockerApi {
+    socket_path: PathBuf,
+    timeout: Duration, // таймаут на чтение/запись в сокет (stop сам по себе может ждать ~10 секунд)
+}
+
+// Ответ демона: статус, заголовки и тело, которое ещё не прочитано
+pub struct ApiResponse {
+    pub status: u16,
+    pub headers: HeaderMap,
+    pub body: Box<dyn Read + Send>,
+}
+
+impl ApiResponse {
+    // Читаем тело целиком
+    pub fn into_bytes(self) -> Result<Vec<u8>, ContainerError> {
+        let mut body: Vec<u8> = Vec::new();
+        self.body
+            .take(MAX_RESPONSE_SIZE)
+            .read_to_end(&mut body)
+            .map_err(|e| {
+                ContainerError::DockerError(format!("Failed to read Docker API response: {e}"))
+            })?;
+        Ok(body)
+    }
+
+    pub fn into_json(self) -> Result<serde_json::Value, ContainerError> {
+        let body = self.into_bytes()?;
+        serde_json::from_slice(&body).map_err(|e| {
+            ContainerError::ParseError(format!("Docker API returned invalid JSON: {e}"))
+        })
+    }
+
+    // 4xx/5xx -> ошибка с сообщением демона ({"message": "No such container: web1"})
+    fn error_for_status(self) -> Result<Self, ContainerError> {
+        if self.status < 400 {
+            return Ok(self);
+        }
+        let status = self.status;
+        let body = self.into_bytes().unwrap_or_default();
+        let message = serde_json::from_slice::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|json| json["message"].as_str().map(str::to_string))
+            .unwrap_or_else(|| String::from_utf8_lossy(&body).trim().to_string());
+
+        Err(ContainerError::DockerError(format!(
+            "Docker API error {status}: {message}"
+        )))
+    }
+}
+
+impl Default for DockerApi {
+    fn default() -> Self {
+        Self::new(DEFAULT_DOCKER_SOCKET)
+    }
+}
+
+impl DockerApi {
+    pub fn new(socket_path: impl Into<PathBuf>) -> Self {
+        Self {
+            socket_path: socket_path.into(),
+            timeout: Duration::from_secs(60),
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    // Отправляем запрос демону и читаем статус и заголовки ответа.
+    // Тело не читаем: для потоковых ответов (логи, статистика) его читают по мере поступления
+    pub fn send(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&serde_json::Value>,
+    ) -> Result<ApiResponse, ContainerError> {
+        let stream = self.open(method, path, body, "Connection: close\r\n")?;
+        read_response(BufReader::new(stream))
+    }
+
+    // То же, но тело ответа можно ждать сколько угодно (docker logs -f, docker stats):
+    // таймаут действует только до заголовков ответа. Вместе с ответом отдаём сокет --
+    // закрыв его, можно прервать чтение тела из другого потока
+    pub fn send_streaming(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&serde_json::Value>,
+    ) -> Result<(ApiResponse, UnixStream), ContainerError> {
+        let stream = self.open(method, path, body, "Connection: close\r\n")?;
+        let socket = stream.try_clone().map_err(|e| {
+            ContainerError::DockerError(format!("Failed to clone Docker API socket: {e}"))
+        })?;
+        let response = read_response(BufReader::new(stream))?;
+        let _ = socket.set_read_timeout(None);
+        Ok((response, socket))
+    }
+
+    // Запрос, после которого демон отдаёт соединение под сырой поток в обе стороны
+    // (как docker exec -it): в ответ 101 UPGRADED (или 200 у старых демонов), дальше --
+    // из тела читаем вывод, а в возвращённый сокет пишем stdin
+    fn hijack(
+        &self,
+        path: &str,
+        body: &serde_json::Value,
+    ) -> Result<(ApiResponse, UnixStream), ContainerError> {
+        let stream = self.open(
+            "POST",
+            path,
+            Some(body),
+            "Connection: Upgrade\r\nUpgrade: tcp\r\n",
+        )?;
+        let writer = stream.try_clone().map_err(|e| {
+            ContainerError::DockerError(format!("Failed to clone Docker API socket: {e}"))
+        })?;
+        let response = read_response(BufReader::new(stream))?.error_for_status()?;
+        if response.status != 101 && response.status != 200 {
+            return Err(ContainerError::DockerError(format!(
+                "Docker API did not upgrade the connection: {}",
+                response.status
+            )));
+        }
+        // интерактивная сессия может молчать сколько угодно
+        let _ = writer.set_read_timeout(None);
+        let _ = writer.set_write_timeout(None);
+        Ok((response, writer))
+    }
+
+    // Подключаемся к демону и отправляем запрос; connection -- заголовки Connection/Upgrade
+    fn open(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&serde_json::Value>,
+        connection: &str,
+    ) -> Result<UnixStream, ContainerError> {
+        let mut stream = UnixStream::connect(&self.socket_path).map_err(|e| {
+            ContainerError::DockerError(format!(
+                "Failed to connect to {}: {e}",
+                self.socket_path.display()
+            ))
+        })?;
+        let _ = stream.set_read_timeout(Some(self.timeout));
+        let _ = stream.set_write_timeout(Some(self.timeout));
+
+        let body: Vec<u8> = body
+            .map(|json| json.to_string().into_bytes())
+            .unwrap_or_default();
+        let mut raw_request =
+            format!("{method} {API_PREFIX}{path} HTTP/1.1\r\nHost: docker\r\n{connection}");
+        if !body.is_empty() {
+            raw_request.push_str("Content-Type: application/json\r\n");
+        }
+        raw_request.push_str(&format!("Content-Length: {}\r\n\r\n", body.len()));
+
+        stream
+            .write_all(raw_request.as_bytes())
+            .and_then(|_| stream.write_all(&body))
+            .map_err(|e| ContainerError::DockerError(format!("Failed to send request: {e}")))?;
+        Ok(stream)
+    }
+
+    // Запрос с ответом-JSON (или пустым ответом -> Null)
+    fn call(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&serde_json::Value>,
+    ) -> Result<serde_json::Value, ContainerError> {
+        let response = self.send(method, path, body)?.error_for_status()?;
+        if response.status == 204 || response.status == 304 {
+            return Ok(serde_json::Value::Null);
+        }
+        response.into_json()
+    }
+
+    fn list_containers(&self, path: &str) -> Result<Vec<ContainerInfo>, ContainerError> {
+        let json = self.call("GET", path, None)?;
+        json.as_array()
+            .ok_or(ContainerError::ParseError(
+                "Docker API returned a non-array container list".to_string(),
+            ))?
+            .iter()
+            .map(parse_container_summary)
+            .collect()
+    }
+}
+
+// Разбираем ответ демона: стартовая строка, заголовки, а тело оборачиваем
+// в ридер по Content-Length / chunked / до закрытия соединения
+fn read_response<R: BufRead + Send + 'static>(
+    mut reader: R,
+) -> Result<ApiResponse, ContainerError> {
+    let read_err = |e: std::io::Error| {
+        ContainerError::DockerError(format!("Failed to read Docker API response: {e}"))
+    };
+
+    let mut status_line = String::new();
+    reader.read_line(&mut status_line).map_err(read_err)?;
+    // "HTTP/1.1 200 OK"
+    let status: u16 = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or(ContainerError::ParseError(format!(
+            "Invalid status line from Docker API: {status_line:?}"
+        )))?;
+
+    let mut header_lines: Vec<String> = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(read_err)? == 0 {
+            break;
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            break;
+        }
+        header_lines.push(line.to_string());
+    }
+    let headers = HeaderMap::from_lines(&header_lines)
+        .map_err(|e| ContainerError::ParseError(format!("Docker API: {e}")))?;
+
+    let body: Box<dyn Read + Send> = if headers.has_token("transfer-encoding", "chunked") {
+        Box::new(ChunkedReader::new(reader))
+    } else if let Some(length) = headers.get("content-length") {
+        let length: u64 = length.parse().map_err(|_| {
+            ContainerError::ParseError(format!("Invalid Content-Length from Docker API: {length}"))
+        })?;
+        Box::new(reader.take(length))
+    } else {
+        Box::new(reader) // Connection: close -- тело до конца соединения
+    };
+
+    Ok(ApiResponse {
+        status,
+        headers,
+        body,
+    })
+}
+
+// Имя контейнера попадает прямо в путь запроса -- проверяем его так же, как для CLI
+fn container_path(id: &str, action: &str) -> Result<String, ContainerError> {
+    check_label(id)?;
+    Ok(format!("/containers/{id}/{action}"))
+}
+
+// Один элемент из GET /containers/json
+fn parse_container_summary(json: &serde_json::Value) -> Result<ContainerInfo, ContainerError> {
+    let field = |name: &str| json[name].as_str().unwrap_or_default().to_string();
+
+    // "Names": ["/web1"]
+    let label: String = json["Names"][0]
+        .as_str()
+        .map(|name| name.trim_start_matches('/').to_string())
+        .ok_or(ContainerError::ParseError(format!(
+            "Container without name in Docker API response: {json}"
+        )))?;
+
+    let state: String = field("State");
+    let status: ContainerStatus = ContainerStatus::from_docker_state(&state).ok_or(
+        ContainerError::ParseError(format!("Unknown state {state:?} of container {label}")),
+    )?;
+
+    let status_text: String = field("Status");
+    let (uptime, exit_code) = parse_status_text(&status_text);
+
+    // "Ports": [{"IP": "0.0.0.0", "PrivatePort": 80, "PublicPort": 8080, "Type": "tcp"}, {"PrivatePort": 443, "Type": "tcp"}]
+    let ports: Vec<PortBinding> = json["Ports"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|port| {
+            Some(PortBinding {
+                ip: port["IP"].as_str().map(str::to_string),
+                private_port: port["PrivatePort"].as_u64()?.try_into().ok()?,
+                public_port: port["PublicPort"].as_u64().and_then(|p| p.try_into().ok()),
+                protocol: port["Type"].as_str().unwrap_or("tcp").to_string(),
+            })
+        })
+        .collect();
+
+    let labels: BTreeMap<String, String> = json["Labels"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(key, value)| (key.clone(), value.as_str().unwrap_or_default().to_string()))
+        .collect();
+
+    // как в docker ps: имя тома, а для bind mount -- путь на хосте
+    let mounts: Vec<String> = json["Mounts"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|mount| {
+            mount["Name"]
+                .as_str()
+                .filter(|name| !name.is_empty())
+                .or(mount["Source"].as_str())
+                .map(str::to_string)
+        })
+        .collect();
+
+    let networks: Vec<String> = json["NetworkSettings"]["Networks"]
+        .as_object()
+        .map(|networks| networks.keys().cloned().collect())
+        .unwrap_or_default();
+
+    // SizeRw/SizeRootFs есть только в ответе на ?size=1
+    let size: Option<ContainerSize> = json["SizeRw"].as_u64().map(|rw| ContainerSize {
+        rw,
+        root_fs: json["SizeRootFs"].as_u64(),
+    });
+
+    Ok(ContainerInfo {
+        id: field("Id"),
+        label,
+        status,
+        status_text,
+        command: field("Command"),
+        image: field("Image"),
+        created: json["Created"]
+            .as_i64()
+            .and_then(|secs| DateTime::from_timestamp(secs, 0)),
+        uptime,
+        exit_code,
+        ports,
+        labels,
+        mounts,
+        networks,
+        size,
+    })
+}
+
+impl ContainerBackend for DockerApi {
+    fn list(&self) -> Result<Vec<ContainerInfo>, ContainerError> {
+        self.list_containers("/containers/json?all=1")
+    }
+
+    fn list_with_size(&self) -> Result<Vec<ContainerInfo>, ContainerError> {
+        self.list_containers("/containers/json?all=1&size=1")
+    }
+
+    fn inspect(&self, id: &str) -> Result<ContainerState, ContainerError> {
+        Ok(self.inspect_details(id)?.state)
+    }
+
+    fn inspect_details(&self, id: &str) -> Result<ContainerDetails, ContainerError> {
+        let json = self.call("GET", &container_path(id, "json")?, None)?;
+        Ok(parse_inspect_json(&json))
+    }
+
+    fn create(&self, spec: &ContainerSpec) -> Result<String, ContainerError> {
+        let path = match &spec.name {
+            Some(name) => {
+                check_label(name)?;
+                format!("/containers/create?name={name}")
+            }
+            None => "/containers/create".to_string(),
+        };
+        let json = self.call("POST", &path, Some(&create_body(spec)))?;
+        json["Id"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or(ContainerError::ParseError(
+                "Docker API returned no id for the created container".to_string(),
+            ))
+    }
+
+    // 304 Not Modified (уже запущен / уже остановлен) -- тоже успех
+    fn start(&self, id: &str) -> Result<(), ContainerError> {
+        self.call("POST", &container_path(id, "start")?, None)
+            .map(|_| ())
+    }
+
+    fn stop(&self, id: &str, timeout: Option<Duration>) -> Result<(), ContainerError> {
+        self.stop_action("stop", id, timeout)
+    }
+
+    fn restart(&self, id: &str, timeout: Option<Duration>) -> Result<(), ContainerError> {
+        self.stop_action("restart", id, timeout)
+    }
+
+    fn remove(&self, id: &str, options: &RemoveOptions) -> Result<(), ContainerError> {
+        check_label(id)?;
+        let path = format!(
+            "/containers/{id}?force={}&v={}",
+            u8::from(options.force),
+            u8::from(options.volumes)
+        );
+        self.call("DELETE", &path, None).map(|_| ())
+    }
+
+    fn pause(&self, id: &str) -> Result<(), ContainerError> {
+        self.call("POST", &container_path(id, "pause")?, None)
+            .map(|_| ())
+    }
+
+    fn unpause(&self, id: &str) -> Result<(), ContainerError> {
+        self.call("POST", &container_path(id, "unpause")?, None)
+            .map(|_| ())
+    }
+
+    fn kill(&self, id: &str, signal: &str) -> Result<(), ContainerError> {
+        let path = format!("{}?signal={signal}", container_path(id, "kill")?);
+        self.call("POST", &path, None).map(|_| ())
+    }
+
+    fn logs(&self, id: &str, options: &LogOptions) -> Result<LogStream, ContainerError> {
+        // без TTY stdout и stderr приходят вперемешку кадрами, с TTY -- просто текстом
+        let tty = self.inspect_details(id)?.tty;
+
+        let mut query: Vec<String> = vec![
+            format!("stdout={}", u8::from(options.stdout)),
+            format!("stderr={}", u8::from(options.stderr)),
+            "timestamps=1".to_string(),
+        ];
+        if let Some(tail) = options.tail {
+            query.push(format!("tail={tail}"));
+        }
+        if let Some(since) = options.since {
+            query.push(format!("since={}", unix_time(since)));
+        }
+        if let Some(until) = options.until {
+            query.push(format!("until={}", unix_time(until)));
+        }
+        if options.follow {
+            query.push("follow=1".to_string());
+        }
+
+        let path = format!("{}?{}", container_path(id, "logs")?, query.join("&"));
+        let (response, socket) = self.send_streaming("GET", &path, None)?;
+        let response = response.error_for_status()?;
+        Ok(
+            LogStream::new(ApiLogStream::new(response.body, tty)).with_canceller(move || {
+                let _ = socket.shutdown(Shutdown::Both);
+            }),
+        )
+    }
+
+    // Exec в три запроса: создать (получить его id), запустить с чтением вывода,
+    // а когда вывод кончится -- спросить код выхода
+    fn exec(&self, id: &str, spec: &ExecSpec) -> Result<ExecStream, ContainerError> {
+        let exec_id = self.create_exec(id, &exec_body(spec))?;
+
+        let start = serde_json::json!({"Detach": false, "Tty": false});
+        let (response, socket) =
+            self.send_streaming("POST", &format!("/exec/{exec_id}/start"), Some(&start))?;
+        let response = response.error_for_status()?;
+        // закрытый сокет только отключает нас от вывода: команда в контейнере доработает сама
+        Ok(ExecStream::new(ApiExecStream {
+            api: self.clone(),
+            exec_id,
+            body: BufReader::new(response.body),
+            finished: false,
+        })
+        .with_canceller(move || {
+            let _ = socket.shutdown(Shutdown::Both);
+        }))
+    }
+
+    // Тот же exec, но с терминалом и stdin: start забирает соединение целиком (hijack),
+    // размер окна выставляем сразу после старта -- как docker CLI
+    fn exec_tty(
+        &self,
+        id: &str,
+        spec: &ExecSpec,
+        size: TtySize,
+    ) -> Result<ExecSession, ContainerError> {
+        let mut body = exec_body(spec);
+        body["AttachStdin"] = serde_json::json!(true);
+        body["Tty"] = serde_json::json!(true);
+        body["ConsoleSize"] = serde_json::json!([size.rows, size.cols]);
+        let exec_id = self.create_exec(id, &body)?;
+
+        let start = serde_json::json!({"Detach": false, "Tty": true});
+        let (response, writer) = self.hijack(&format!("/exec/{exec_id}/start"), &start)?;
+        let control = ApiExecControl {
+            api: self.clone(),
+            exec_id,
+        };
+        let _ = control.resize(size); // exec мог уже завершиться -- это не ошибка
+        Ok(ExecSession {
+            output: response.body,
+            input: Box::new(ApiExecInput(writer)),
+            control: Box::new(control),
+        })
+    }
+
+    // stream=0: демон сам снимает два замера (~1-2 секунды), чтобы в precpu_stats было с чем сравнить
+    fn stats(&self, id: &str) -> Result<ContainerStats, ContainerError> {
+        let path = format!("{}?stream=0", container_path(id, "stats")?);
+        Ok(parse_stats_json(&self.call("GET", &path, None)?))
+    }
+
+    // Каждый замер ждёт секунду-другую -- опрашиваем контейнеры параллельно
+    fn stats_all(&self) -> Result<Vec<ContainerStats>, ContainerError> {
+        let running: Vec<ContainerInfo> = self
+            .list_containers("/containers/json")? // без all=1 -- только работающие
+            .into_iter()
+            .filter(|container| container.status.is_running())
+            .collect();
+
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = running
+                .iter()
+                .map(|container| scope.spawn(|| self.stats(&container.label)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle.join().unwrap_or(Err(ContainerError::DockerError(
+                        "stats thread panicked".to_string(),
+                    )))
+                })
+                .collect()
+        })
+    }
+}
+
+// Ответ GET /containers/{id}/stats -> ContainerStats, считаем так же, как docker stats:
+// CPU -- доля прироста времени контейнера от прироста времени всей системы, умноженная на число ядер;
+// память -- без неактивного файлового кеша (cgroup v2: inactive_file, v1: total_inactive_file)
+pub fn parse_stats_json(json: &serde_json::Value) -> ContainerStats {
+    let number = |value: &serde_json::Value| value.as_u64().unwrap_or(0);
+    let cpu = &json["cpu_stats"];
+    let precpu = &json["precpu_stats"];
+
+    let cpu_delta = number(&cpu["cpu_usage"]["total_usage"])
+        .saturating_sub(number(&precpu["cpu_usage"]["total_usage"]));
+    let system_delta =
+        number(&cpu["system_cpu_usage"]).saturating_sub(number(&precpu["system_cpu_usage"]));
+    let online_cpus = match number(&cpu["online_cpus"]) {
+        0 => cpu["cpu_usage"]["percpu_usage"]
+            .as_array()
+            .map_or(1, |cpus| cpus.len().max(1) as u64),
+        cpus => cpus,
+    };
+    let cpu_percent = if system_delta > 0 {
+        cpu_delta as f64 / system_delta as f64 * online_cpus as f64 * 100.0
+    } else {
+        0.0
+    };
+
+    let memory = &json["memory_stats"];
+    let cache = match &memory["stats"]["inactive_file"] {
+        serde_json::Value::Null => number(&memory["stats"]["total_inactive_file"]),
+        inactive => number(inactive),
+    };
+
+    // сеть -- сумма по всем интерфейсам
+    let (mut network_rx, mut network_tx) = (0, 0);
+    if let Some(networks) = json["networks"].as_object() {
+        for network in networks.values() {
+            network_rx += number(&network["rx_bytes"]);
+            network_tx += number(&network["tx_bytes"]);
+        }
+    }
+
+    // диск -- сумма по всем устройствам; op бывает "Read"/"read" в зависимости от cgroup
+    let (mut block_read, mut block_write) = (0, 0);
+    if let Some(entries) = json["blkio_stats"]["io_service_bytes_recursive"].as_array() {
+        for entry in entries {
+            match entry["op"].as_str().map(str::to_ascii_lowercase).as_deref() {
+                Some("read") => block_read += number(&entry["value"]),
+                Some("write") => block_write += number(&entry["value"]),
+                _ => {}
+            }
+        }
+    }
+
+    ContainerStats {
+        id: json["id"].as_str().unwrap_or_default().to_string(),
+        name: json["name"]
+            .as_str()
+            .unwrap_or_default()
+            .trim_start_matches('/')
+            .to_string(),
+        read_at: json["read"].as_str().and_then(parse_docker_time),
+        cpu_percent,
+        memory_usage: number(&memory["usage"]).saturating_sub(cache),
+        memory_limit: number(&memory["limit"]),
+        network_rx,
+        network_tx,
+        block_read,
+        block_write,
+        pids: number(&json["pids_stats"]["current"]),
+    }
+}
+
+impl DockerApi {
+    // POST /containers/{id}/exec -> id exec-а (он потом попадает в путь запроса)
+    fn create_exec(&self, id: &str, body: &serde_json::Value) -> Result<String, ContainerError> {
+        let json = self.call("POST", &container_path(id, "exec")?, Some(body))?;
+        let exec_id: String =
+            json["Id"]
+                .as_str()
+                .map(str::to_string)
+                .ok_or(ContainerError::ParseError(
+                    "Docker API returned no id for the exec instance".to_string(),
+                ))?;
+        check_label(&exec_id)?;
+        Ok(exec_id)
+    }
+
+    // stop и restart с ?t=N. Демон отвечает только после остановки, поэтому
+    // таймаут сокета продлеваем на время, которое контейнеру дали на завершение
+    fn stop_action(
+        &self,
+        action: &str,
+        id: &str,
+        timeout: Option<Duration>,
+    ) -> Result<(), ContainerError> {
+        let mut path = container_path(id, action)?;
+        let mut api = self.clone();
+        if let Some(timeout) = timeout {
+            path.push_str(&format!("?t={}", timeout.as_secs()));
+            api.timeout += timeout;
+        }
+        api.call("POST", &path, None).map(|_| ())
+    }
+}
+
+// Тело POST /containers/create: то, что в docker create задаётся флагами,
+// здесь в Config (образ, команда, env, метки) и HostConfig (порты, тома, лимиты)
+fn create_body(spec: &ContainerSpec) -> serde_json::Value {
+    let port_key = |port: &PortBinding| format!("{}/{}", port.private_port, port.protocol);
+
+    let mut exposed = serde_json::Map::new();
+    let mut bindings = serde_json::Map::new();
+    for port in &spec.ports {
+        exposed.insert(port_key(port), serde_json::json!({}));
+        let binding = serde_json::json!({
+            "HostIp": port.ip.clone().unwrap_or_default(),
+            "HostPort": port.public_port.map(|p| p.to_string()).unwrap_or_default(),
+        });
+        match bindings
+            .entry(port_key(port))
+            .or_insert_with(|| serde_json::json!([]))
+        {
+            serde_json::Value::Array(list) => list.push(binding),
+            _ => unreachable!("port bindings are always arrays"),
+        }
+    }
+
+    let resources = &spec.resources;
+    let mut host_config = serde_json::json!({
+        "PortBindings": bindings,
+        "Mounts": spec.volumes.iter().map(|volume| serde_json::json!({
+            "Type": volume.kind,
+            "Source": volume.source,
+            "Target": volume.destination,
+            "ReadOnly": volume.read_only,
+        })).collect::<Vec<serde_json::Value>>(),
+        "RestartPolicy": {
+            "Name": spec.restart_policy.name,
+            "MaximumRetryCount": spec.restart_policy.max_retries,
+        },
+    });
+    // незаданные лимиты не отправляем вовсе -- демон подставит свои значения
+    let limits = [
+        ("Memory", resources.memory.map(serde_json::Value::from)),
+        (
+            "MemoryReservation",
+            resources.memory_reservation.map(serde_json::Value::from),
+        ),
+        (
+            "MemorySwap",
+            resources.memory_swap.map(serde_json::Value::from),
+        ),
+        ("NanoCpus", resources.nano_cpus.map(serde_json::Value::from)),
+        (
+            "CpuShares",
+            resources.cpu_shares.map(serde_json::Value::from),
+        ),
+        ("CpuQuota", resources.cpu_quota.map(serde_json::Value::from)),
+        (
+            "CpuPeriod",
+            resources.cpu_period.map(serde_json::Value::from),
+        ),
+        (
+            "CpusetCpus",
+            resources.cpuset_cpus.clone().map(serde_json::Value::from),
+        ),
+        (
+            "PidsLimit",
+            resources.pids_limit.map(serde_json::Value::from),
+        ),
+    ];
+    for (key, value) in limits {
+        if let Some(value) = value {
+            host_config[key] = value;
+        }
+    }
+
+    let mut body = serde_json::json!({
+        "Image": spec.image,
+        "Env": spec.env.iter().map(|(name, value)| format!("{name}={value}")).collect::<Vec<String>>(),
+        "Labels": spec.labels,
+        "ExposedPorts": exposed,
+        "HostConfig": host_config,
+    });
+    // пустой Cmd -- команда из образа
+    if !spec.command.is_empty() {
+        body["Cmd"] = serde_json::json!(spec.command);
+    }
+    body
+}
+
+// Тело POST /containers/{id}/exec: без stdin и TTY, stdout и stderr -- кадрами
+fn exec_body(spec: &ExecSpec) -> serde_json::Value {
+    let mut body = serde_json::json!({
+        "AttachStdin": false,
+        "AttachStdout": true,
+        "AttachStderr": true,
+        "Tty": false,
+        "Cmd": spec.argv,
+        "Env": spec.env.iter().map(|(name, value)| format!("{name}={value}")).collect::<Vec<String>>(),
+    });
+    if let Some(workdir) = &spec.workdir {
+        body["WorkingDir"] = serde_json::json!(workdir);
+    }
+    if let Some(user) = &spec.user {
+        body["User"] = serde_json::json!(user);
+    }
+    body
+}
+
+// Вывод /exec/{id}/start -- те же кадры, что у логов без TTY, до закрытия соединения.
+// Код выхода демон отдаёт отдельно в GET /exec/{id}/json
+struct ApiExecStream {
+    api: DockerApi,
+    exec_id: String,
+    body: BufReader<Box<dyn Read + Send>>,
+    finished: bool,
+}
+
+impl ApiExecStream {
+    fn exit_code(&self) -> Result<i64, ContainerError> {
+        wait_exec_exit(&self.api, &self.exec_id)?.ok_or(ContainerError::DockerError(format!(
+            "exec {} is still running after its output ended",
+            self.exec_id
+        )))
+    }
+}
+
+// Соединение закрывается чуть раньше, чем демон отмечает exec завершённым --
+// как и docker CLI, немного ждём, пока Running не станет false. None -- так и не завершился
+fn wait_exec_exit(api: &DockerApi, exec_id: &str) -> Result<Option<i64>, ContainerError> {
+    let path = format!("/exec/{exec_id}/json");
+    for _ in 0..EXEC_EXIT_POLLS {
+        let json = api.call("GET", &path, None)?;
+        if json["Running"] != serde_json::Value::Bool(true)
+            && let Some(code) = json["ExitCode"].as_i64()
+        {
+            return Ok(Some(code));
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    Ok(None)
+}
+
+// stdin интерактивного exec. Закрыли -- закрываем запись в сокет: демон передаст
+// команде конец stdin, а вывод продолжит идти, пока она не завершится
+struct ApiExecInput(UnixStream);
+
+impl Write for ApiExecInput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl Drop for ApiExecInput {
+    fn drop(&mut self) {
+        let _ = self.0.shutdown(Shutdown::Write);
+    }
+}
+
+struct ApiExecControl {
+    api: DockerApi,
+    exec_id: String,
+}
+
+impl ExecControl for ApiExecControl {
+    fn resize(&self, size: TtySize) -> Result<(), ContainerError> {
+        let path = format!(
+            "/exec/{}/resize?h={}&w={}",
+            self.exec_id, size.rows, size.cols
+        );
+        // демон отвечает 201 без тела
+        self.api
+            .send("POST", &path, None)?
+            .error_for_status()
+            .map(|_| ())
+    }
+
+    fn exit_code(&self) -> Result<Option<i64>, ContainerError> {
+        wait_exec_exit(&self.api, &self.exec_id)
+    }
+}
+
+const EXEC_EXIT_POLLS: usize = 20;
+
+impl Iterator for ApiExecStream {
+    type Item = Result<ExecEvent, ContainerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match read_frame(&mut self.body) {
+            Ok(Some((source, data))) => Some(Ok(ExecEvent::Output(source, data))),
+            Ok(None) => {
+                self.finished = true;
+                Some(self.exit_code().map(ExecEvent::Exit))
+            }
+            Err(e) => {
+                self.finished = true;
+                Some(Err(ContainerError::DockerError(format!(
+                    "Failed to read exec output: {e}"
+                ))))
+            }
+        }
+    }
+}
+
+// "1714557600.500000000" -- since/until в Engine API
+fn unix_time(time: DateTime<Utc>) -> String {
+    format!("{}.{:09}", time.timestamp(), time.timestamp_subsec_nanos())
+}
+
+// Кадр потока без TTY: [тип, 0, 0, 0, длина (u32 big-endian)] + данные.
+// Тип: 1 -- stdout, 2 -- stderr (0 -- stdin, его отдаём как stdout).
+// None -- поток закончился ровно на границе кадра
+pub fn read_frame<R: Read>(reader: &mut R) -> std::io::Result<Option<(LogSource, Vec<u8>)>> {
+    let mut header = [0u8; 8];
+    match reader.read_exact(&mut header) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let source = if header[0] == 2 {
+        LogSource::Stderr
+    } else {
+        LogSource::Stdout
+    };
+    let size = u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize;
+    let mut data = vec![0u8; size];
+    reader.read_exact(&mut data)?;
+    Ok(Some((source, data)))
+}
+
+// Разбираем тело ответа /logs на строки.
+// Кадр не обязан заканчиваться на '\n', поэтому недописанные строки копим по потокам
+struct ApiLogStream {
+    body: BufReader<Box<dyn Read + Send>>,
+    tty: bool,
+    partial: [Vec<u8>; 2], // stdout, stderr
+    ready: VecDeque<LogLine>,
+    finished: bool,
+}
+
+impl ApiLogStream {
+    fn new(body: Box<dyn Read + Send>, tty: bool) -> Self {
+        Self {
+            body: BufReader::new(body),
+            tty,
+            partial: [Vec::new(), Vec::new()],
+            ready: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    fn push(&mut self, source: LogSource, data: &[u8]) {
+        let partial = &mut self.partial[source as usize];
+        partial.extend_from_slice(data);
+        while let Some(end) = partial.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = partial.drain(..=end).collect();
+            self.ready.push_back(LogLine::parse_timestamped(
+                source,
+                &String::from_utf8_lossy(&line),
+            ));
+        }
+    }
+
+    // Поток кончился: недописанные строки тоже отдаём
+    fn flush(&mut self) {
+        for source in [LogSource::Stdout, LogSource::Stderr] {
+            let rest = std::mem::take(&mut self.partial[source as usize]);
+            if !rest.is_empty() {
+                self.ready.push_back(LogLine::parse_timestamped(
+                    source,
+                    &String::from_utf8_lossy(&rest),
+                ));
+            }
+        }
+    }
+}
+
+impl Iterator for ApiLogStream {
+    type Item = Result<LogLine, ContainerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.ready.is_empty() && !self.finished {
+            let chunk = if self.tty {
+                let mut line: Vec<u8> = Vec::new();
+                self.body
+                    .read_until(b'\n', &mut line)
+                    .map(|n| (n > 0).then_some((LogSource::Stdout, line)))
+            } else {
+                read_frame(&mut self.body)
+            };
+
+            match chunk {
+                Ok(Some((source, data))) => self.push(source, &data),
+                Ok(None) => {
+                    self.finished = true;
+                    self.flush();
+                }
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(ContainerError::DockerError(format!(
+                        "Failed to read logs: {e}"
+                    ))));
+                }
+            }
+        }
+        self.ready.pop_front().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        os::unix::net::UnixListener,
+        sync::atomic::{AtomicUsize, Ordering},
+        thread::{JoinHandle, spawn},
+    };
+
+    use super::*;
+
+    // Фейковый демон: на каждое подключение отвечает следующим ответом из списка,
+    // а пришедшие стартовые строки запросов (с телом через '\n') возвращает из join()
+    fn fake_daemon(responses: Vec<String>) -> (DockerApi, JoinHandle<Vec<String>>) {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "fake-docker-{}-{}.sock",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let thread = spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+
+                let mut start_line = String::new();
+                reader.read_line(&mut start_line).unwrap();
+                requests.push(start_line.trim_end().to_string());
+                // дочитываем заголовки; тело (если есть) добавляем к стартовой строке
+                let mut content_length: usize = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" || line.is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':')
+                        && name.eq_ignore_ascii_case("content-length")
+                    {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+                if content_length > 0 {
+                    let mut body = vec![0u8; content_length];
+                    reader.read_exact(&mut body).unwrap();
+                    let last = requests.last_mut().unwrap();
+                    *last = format!("{last}\n{}", String::from_utf8(body).unwrap());
+                }
+
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+                // chunked-тело без последнего чанка: контейнер молчит, соединение держим,
+                // пока клиент сам его не закроет
+                if response.contains("Transfer-Encoding: chunked")
+                    && !response.ends_with("0\r\n\r\n")
+                {
+                    let _ = reader.read_to_end(&mut Vec::new());
+                }
+            }
+            requests
+        });
+
+        (DockerApi::new(path), thread)
+    }
+
+    fn response(status_line: &str, json: &str) -> String {
+        format!(
+            "HTTP/1.1 {status_line}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{json}",
+            json.len()
+        )
+    }
+
+    // Тот же JSON, но кусками в Transfer-Encoding: chunked
+    fn chunked_response(parts: &[&str]) -> String {
+        let mut raw = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n".to_string();
+        for part in parts {
+            raw.push_str(&format!("{:X}\r\n{part}\r\n", part.len()));
+        }
+        raw + "0\r\n\r\n"
+    }
+
+    #[test]
+    fn list_and_inspect() -> Result<(), ContainerError> {
+        let list = r#"[{"Id":"abc","Names":["/web1"],"Image":"nginx:1.27","Command":"nginx -g 'daemon off;'","State":"running","Status":"Up 3 hours","Created":1714557600,"Ports":[{"IP":"0.0.0.0","PrivatePort":80,"PublicPort":8080,"Type":"tcp"},{"PrivatePort":443,"Type":"tcp"}],"Labels":{"team":"web"},"Mounts":[{"Type":"volume","Name":"static","Source":"/var/lib/docker/volumes/static/_data"},{"Type":"bind","Source":"/var/log/nginx"}],"NetworkSettings":{"Networks":{"bridge":{}}}},{"Id":"def","Names":["/db"],"Image":"mysql","Command":"mysqld","State":"exited","Status":"Exited (1) 2 days ago"}]"#;
+        // inspect приходит чанками, разрезанными посреди строк
+        let inspect_response = chunked_response(&[
+            r#"{"Id":"def","State":{"Status":"ex"#,
+            r#"ited","Running":false,"Paused":false,"Restarting":false,"Dead":false,"ExitCode":1,"#,
+            r#""StartedAt":"2024-05-01T10:00:00Z","FinishedAt":"2024-05-02T10:00:00.5Z"}}"#,
+        ]);
+
+        let (api, daemon) = fake_daemon(vec![response("200 OK", list), inspect_response]);
+
+        let containers = api.list()?;
+        assert_eq!(containers.len(), 2);
+        assert_eq!(containers[0].label, "web1");
+        assert_eq!(containers[0].status, ContainerStatus::Up);
+        assert_eq!(containers[0].image, "nginx:1.27");
+        assert_eq!(containers[0].id, "abc");
+        assert_eq!(containers[0].uptime, Some(3 * 3600));
+        assert_eq!(
+            containers[0].created,
+            parse_docker_time("2024-05-01T10:00:00Z")
+        );
+        assert_eq!(containers[0].ports.len(), 2);
+        assert_eq!(containers[0].ports[0].public_port, Some(8080));
+        assert_eq!(containers[0].ports[1].ip, None);
+        assert_eq!(
+            containers[0].labels.get("team").map(String::as_str),
+            Some("web")
+        );
+        assert_eq!(containers[0].mounts, vec!["static", "/var/log/nginx"]);
+        assert_eq!(containers[0].networks, vec!["bridge"]);
+        assert_eq!(containers[0].size, None);
+        assert_eq!(containers[1].status, ContainerStatus::Exited);
+        assert_eq!(containers[1].exit_code, Some(1));
+
+        let state = api.inspect("db")?;
+        assert_eq!(state.status, "exited");
+        assert!(!state.is_running);
+        assert_eq!(state.exit_code, Some(1));
+        assert_eq!(state.started_at, parse_docker_time("2024-05-01T10:00:00Z"));
+        assert_eq!(
+            state.finished_at,
+            parse_docker_time("2024-05-02T10:00:00.5Z")
+        );
+
+        assert_eq!(
+            daemon.join().unwrap(),
+            vec![
+                "GET /v1.41/containers/json?all=1 HTTP/1.1",
+                "GET /v1.41/containers/db/json HTTP/1.1",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn actions_and_errors() {
+        let (api, daemon) = fake_daemon(vec![
+            "HTTP/1.1 204 No Content\r\n\r\n".to_string(),
+            "HTTP/1.1 304 Not Modified\r\n\r\n".to_string(),
+            response("404 Not Found", r#"{"message":"No such container: ghost"}"#),
+            "HTTP/1.1 204 No Content\r\n\r\n".to_string(),
+            "HTTP/1.1 204 No Content\r\n\r\n".to_string(),
+            response(
+                "409 Conflict",
+                r#"{"message":"Container web1 is not running"}"#,
+            ),
+            "HTTP/1.1 204 No Content\r\n\r\n".to_string(),
+            response(
+                "409 Conflict",
+                r#"{"message":"You cannot remove a running container web1. Stop the container before attempting removal or force remove"}"#,
+            ),
+        ]);
+
+        assert!(api.start("web1").is_ok());
+        assert!(api.stop("web1", None).is_ok()); // уже остановлен
+        match api.restart("ghost", Some(Duration::from_secs(5))) {
+            Err(ContainerError::DockerError(message)) => {
+                assert_eq!(message, "Docker API error 404: No such container: ghost")
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+        assert!(api.pause("web1").is_ok());
+        assert!(api.unpause("web1").is_ok());
+        assert!(api.kill("web1", "SIGHUP").is_err());
+        assert!(
+            api.remove(
+                "db",
+                &RemoveOptions {
+                    force: true,
+                    volumes: true
+                }
+            )
+            .is_ok()
+        );
+        assert!(api.remove("web1", &RemoveOptions::default()).is_err());
+
+        assert_eq!(
+            daemon.join().unwrap(),
+            vec![
+                "POST /v1.41/containers/web1/start HTTP/1.1",
+                "POST /v1.41/containers/web1/stop HTTP/1.1",
+                "POST /v1.41/containers/ghost/restart?t=5 HTTP/1.1",
+                "POST /v1.41/containers/web1/pause HTTP/1.1",
+                "POST /v1.41/containers/web1/unpause HTTP/1.1",
+                "POST /v1.41/containers/web1/kill?signal=SIGHUP HTTP/1.1",
+                "DELETE /v1.41/containers/db?force=1&v=1 HTTP/1.1",
+                "DELETE /v1.41/containers/web1?force=0&v=0 HTTP/1.1",
+            ]
+        );
+
+        // до сокета дело не доходит
+        assert!(api.start("../images/json").is_err());
+        assert!(DockerApi::new("/nonexistent/docker.sock").list().is_err());
+    }
+
+    // Кадр мультиплексированного потока: [тип, 0, 0, 0, длина] + данные
+    fn frame(kind: u8, data: &str) -> String {
+        let mut raw = String::from(char::from(kind));
+        raw.push_str("\0\0\0");
+        raw.push_str(std::str::from_utf8(&(data.len() as u32).to_be_bytes()).unwrap());
+        raw + data
+    }
+
+    #[test]
+    fn logs_canceller_closes_connection() -> Result<(), ContainerError> {
+        let inspect = response(
+            "200 OK",
+            r#"{"Id":"abc","Config":{"Tty":false},"State":{}}"#,
+        );
+        let mut logs = chunked_response(&[&frame(1, "2024-05-01T10:00:00Z hello\n")]);
+        logs.truncate(logs.len() - "0\r\n\r\n".len());
+        let (api, daemon) = fake_daemon(vec![inspect, logs]);
+
+        let options = LogOptions {
+            follow: true,
+            ..Default::default()
+        };
+        let mut lines = api.logs("web1", &options)?;
+        assert_eq!(lines.next().unwrap()?.text, "hello");
+
+        // читатель висит на молчащем сокете, пока его не прервут из другого потока
+        let canceller = lines.canceller();
+        let reader = spawn(move || lines.count());
+        canceller.cancel();
+        assert_eq!(reader.join().unwrap(), 0);
+        daemon.join().unwrap(); // демон увидел, что соединение закрыто
+        Ok(())
+    }
+
+    #[test]
+    fn logs_are_demultiplexed() -> Result<(), ContainerError> {
+        let inspect = response(
+            "200 OK",
+            r#"{"Id":"abc","Config":{"Tty":false},"State":{}}"#,
+        );
+        // строки режутся по кадрам как угодно, последняя -- без '\n'
+        let logs = chunked_response(&[
+            &frame(
+                1,
+                "2024-05-01T10:00:00.000000001Z GET / 200\n2024-05-01T10:00:01Z GET /fa",
+            ),
+            &frame(2, "2024-05-01T10:00:02Z upstream timed out\n"),
+            &frame(1, "vicon.ico 404\n2024-05-01T10:00:03Z bye"),
+        ]);
+        let (api, daemon) = fake_daemon(vec![inspect, logs]);
+
+        let options = LogOptions {
+            tail: Some(10),
+            since: parse_docker_time("2024-05-01T09:00:00Z"),
+            follow: true,
+            ..Default::default()
+        };
+        let lines: Vec<LogLine> = api.logs("web1", &options)?.collect::<Result<_, _>>()?;
+
+        let texts: Vec<(LogSource, &str)> =
+            lines.iter().map(|l| (l.source, l.text.as_str())).collect();
+        assert_eq!(
+            texts,
+            vec![
+                (LogSource::Stdout, "GET / 200"),
+                (LogSource::Stderr, "upstream timed out"),
+                (LogSource::Stdout, "GET /favicon.ico 404"),
+                (LogSource::Stdout, "bye"),
+            ]
+        );
+        assert_eq!(
+            lines[1].timestamp,
+            parse_docker_time("2024-05-01T10:00:02Z")
+        );
+
+        assert_eq!(
+            daemon.join().unwrap(),
+            vec![
+                "GET /v1.41/containers/web1/json HTTP/1.1",
+                "GET /v1.41/containers/web1/logs?stdout=1&stderr=1&timestamps=1&tail=10&since=1714554000.000000000&follow=1 HTTP/1.1",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn exec_streams_output_and_exit_code() -> Result<(), ContainerError> {
+        let created = response("201 Created", r#"{"Id":"e1"}"#);
+        // hijacked-поток: без Content-Length, до закрытия соединения
+        let output = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/vnd.docker.raw-stream\r\n\r\n{}{}",
+            frame(1, "/etc/nginx/nginx.conf syntax is ok\n"),
+            frame(2, "nginx: [emerg] unknown directive\n"),
+        );
+        let running = response("200 OK", r#"{"Running":true,"ExitCode":null}"#);
+        let exited = response("200 OK", r#"{"Running":false,"ExitCode":1}"#);
+        let (api, daemon) = fake_daemon(vec![created, output, running, exited]);
+
+        let spec = ExecSpec {
+            argv: vec!["nginx".to_string(), "-t".to_string()],
+            env: vec![("MODE".to_string(), "check".to_string())],
+            workdir: None,
+            user: Some("101".to_string()),
+        };
+        let events: Vec<ExecEvent> = api.exec("web1", &spec)?.collect::<Result<_, _>>()?;
+        assert_eq!(
+            events,
+            vec![
+                ExecEvent::Output(
+                    LogSource::Stdout,
+                    b"/etc/nginx/nginx.conf syntax is ok\n".to_vec()
+                ),
+                ExecEvent::Output(
+                    LogSource::Stderr,
+                    b"nginx: [emerg] unknown directive\n".to_vec()
+                ),
+                ExecEvent::Exit(1),
+            ]
+        );
+
+        let requests = daemon.join().unwrap();
+        let (start_line, body) = requests[0].split_once('\n').unwrap();
+        assert_eq!(start_line, "POST /v1.41/containers/web1/exec HTTP/1.1");
+        let body: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["Cmd"], serde_json::json!(["nginx", "-t"]));
+        assert_eq!(body["Env"], serde_json::json!(["MODE=check"]));
+        assert_eq!(body["User"], "101");
+        assert!(body.get("WorkingDir").is_none());
+        assert_eq!(
+            requests[1].split_once('\n').unwrap().0,
+            "POST /v1.41/exec/e1/start HTTP/1.1"
+        );
+        assert_eq!(requests[2], "GET /v1.41/exec/e1/json HTTP/1.1");
+        assert_eq!(requests[3], "GET /v1.41/exec/e1/json HTTP/1.1");
+        Ok(())
+    }
+
+    #[test]
+    fn exec_tty_hijacks_connection() -> Result<(), ContainerError> {
+        let created = response("201 Created", r#"{"Id":"e2"}"#);
+        // после 101 -- сырой вывод терминала без кадров
+        let upgraded = "HTTP/1.1 101 UPGRADED\r\nContent-Type: application/vnd.docker.raw-stream\r\nConnection: Upgrade\r\nUpgrade: tcp\r\n\r\n/ # ".to_string();
+        let resized = "HTTP/1.1 201 Created\r\nContent-Length: 0\r\n\r\n".to_string();
+        let exited = response("200 OK", r#"{"Running":false,"ExitCode":130}"#);
+        let (api, daemon) = fake_daemon(vec![created, upgraded, resized, exited]);
+
+        let spec = ExecSpec {
+            argv: vec!["/bin/sh".to_string()],
+            env: Vec::new(),
+            workdir: None,
+            user: None,
+        };
+        let mut session = api.exec_tty(
+            "web1",
+            &spec,
+            TtySize {
+                rows: 40,
+                cols: 120,
+            },
+        )?;
+        let mut output = String::new();
+        session.output.read_to_string(&mut output).unwrap();
+        assert_eq!(output, "/ # ");
+        assert_eq!(session.control.exit_code()?, Some(130));
+
+        let requests = daemon.join().unwrap();
+        let body: serde_json::Value =
+            serde_json::from_str(requests[0].split_once('\n').unwrap().1).unwrap();
+        assert_eq!(body["Tty"], true);
+        assert_eq!(body["AttachStdin"], true);
+        assert_eq!(body["ConsoleSize"], serde_json::json!([40, 120]));
+        let (start_line, start) = requests[1].split_once('\n').unwrap();
+        assert_eq!(start_line, "POST /v1.41/exec/e2/start HTTP/1.1");
+        assert_eq!(start, r#"{"Detach":false,"Tty":true}"#);
+        assert_eq!(
+            requests[2],
+            "POST /v1.41/exec/e2/resize?h=40&w=120 HTTP/1.1"
+        );
+        assert_eq!(requests[3], "GET /v1.41/exec/e2/json HTTP/1.1");
+        Ok(())
+    }
+
+    #[test]
+    fn stats_are_computed() -> Result<(), ContainerError> {
+        let stats = r#"{"read":"2024-05-01T10:00:01Z","id":"abc","name":"/web1",
+            "cpu_stats":{"cpu_usage":{"total_usage":300000000},"system_cpu_usage":2000000000,"online_cpus":4},
+            "precpu_stats":{"cpu_usage":{"total_usage":100000000},"system_cpu_usage":1000000000},
+            "memory_stats":{"usage":12582912,"limit":2147483648,"stats":{"inactive_file":4194304}},
+            "networks":{"eth0":{"rx_bytes":1000,"tx_bytes":200},"eth1":{"rx_bytes":24,"tx_bytes":0}},
+            "blkio_stats":{"io_service_bytes_recursive":[{"major":8,"minor":0,"op":"read","value":4096},
+                {"major":8,"minor":0,"op":"write","value":512},{"major":8,"minor":16,"op":"Read","value":4096}]},
+            "pids_stats":{"current":3}}"#;
+        let (api, daemon) = fake_daemon(vec![response("200 OK", stats)]);
+
+        let stats = api.stats("web1")?;
+        assert_eq!(
+            stats,
+            ContainerStats {
+                id: "abc".to_string(),
+                name: "web1".to_string(),
+                read_at: parse_docker_time("2024-05-01T10:00:01Z"),
+                cpu_percent: 80.0, // 0.2 с из 1 с системного времени на 4 ядрах
+                memory_usage: 8 * 1024 * 1024,
+                memory_limit: 2 * 1024 * 1024 * 1024,
+                network_rx: 1024,
+                network_tx: 200,
+                block_read: 8192,
+                block_write: 512,
+                pids: 3,
+            }
+        );
+
+        // у остановленного контейнера демон отдаёт пустые счётчики
+        let stopped = parse_stats_json(&serde_json::json!({"name": "/db", "memory_stats": {}}));
+        assert_eq!((stopped.cpu_percent, stopped.memory_usage), (0.0, 0));
+
+        assert_eq!(
+            daemon.join().unwrap(),
+            vec!["GET /v1.41/containers/web1/stats?stream=0 HTTP/1.1"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn create_sends_spec() -> Result<(), ContainerError> {
+        let (api, daemon) = fake_daemon(vec![
+            response("201 Created", r#"{"Id":"abc123","Warnings":[]}"#),
+            response(
+                "404 Not Found",
+                r#"{"message":"No such image: ghost:latest"}"#,
+            ),
+        ]);
+
+        let spec = ContainerSpec::from_json(&serde_json::json!({
+            "image": "nginx:1.27",
+            "name": "web2",
+            "command": ["nginx", "-g", "daemon off;"],
+            "env": {"MODE": "prod"},
+            "ports": [
+                {"private_port": 80, "public_port": 8080},
+                {"private_port": 80, "public_port": 8443, "ip": "127.0.0.1"},
+            ],
+            "volumes": [{"source": "/srv/www", "destination": "/usr/share/nginx/html", "read_only": true}],
+            "restart_policy": {"name": "on-failure", "max_retries": 2},
+            "resources": {"memory": 67108864, "pids_limit": 50},
+        }))
+        .unwrap();
+        assert_eq!(api.create(&spec)?, "abc123");
+
+        let missing = ContainerSpec {
+            image: "ghost".to_string(),
+            ..Default::default()
+        };
+        match api.create(&missing) {
+            Err(ContainerError::DockerError(message)) => {
+                assert_eq!(message, "Docker API error 404: No such image: ghost:latest")
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+
+        let requests = daemon.join().unwrap();
+        let (start_line, body) = requests[0].split_once('\n').unwrap();
+        assert_eq!(
+            start_line,
+            "POST /v1.41/containers/create?name=web2 HTTP/1.1"
+        );
+        let body: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["Image"], "nginx:1.27");
+        assert_eq!(
+            body["Cmd"],
+            serde_json::json!(["nginx", "-g", "daemon off;"])
+        );
+        assert_eq!(body["Env"], serde_json::json!(["MODE=prod"]));
+        assert_eq!(body["ExposedPorts"], serde_json::json!({"80/tcp": {}}));
+        let host = &body["HostConfig"];
+        assert_eq!(
+            host["PortBindings"]["80/tcp"],
+            serde_json::json!([
+                {"HostIp": "", "HostPort": "8080"},
+                {"HostIp": "127.0.0.1", "HostPort": "8443"},
+            ])
+        );
+        assert_eq!(
+            host["Mounts"][0],
+            serde_json::json!({"Type": "bind", "Source": "/srv/www", "Target": "/usr/share/nginx/html", "ReadOnly": true})
+        );
+        assert_eq!(
+            host["RestartPolicy"],
+            serde_json::json!({"Name": "on-failure", "MaximumRetryCount": 2})
+        );
+        assert_eq!(host["Memory"], 67108864);
+        assert_eq!(host["PidsLimit"], 50);
+        assert!(host.get("NanoCpus").is_none());
+
+        let (start_line, body) = requests[1].split_once('\n').unwrap();
+        assert_eq!(start_line, "POST /v1.41/containers/create HTTP/1.1");
+        assert!(!body.contains("Cmd"));
+        Ok(())
+    }
+}
@@ -1,16 +1,70 @@
-use std::{fmt::Display, io::BufRead};
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    io::{BufRead, BufReader, Read},
+    process::{Child, ExitStatus, Output, Stdio},
+    sync::{
+        Arc, Mutex,
+        mpsc::{Receiver, Sender, channel},
+    },
+    time::Duration,
+};
 
-#[derive(Debug)]
+use chrono::{DateTime, Utc};
+
+use crate::lib::{
+    container_backend::{
+        ContainerBackend, ContainerDetails, ContainerState, ContainerStats, ExecEvent, ExecStream,
+        LogLine, LogOptions, LogSource, LogStream, RemoveOptions, parse_docker_time,
+        parse_inspect_json,
+    },
+    container_spec::{ContainerSpec, ExecSpec},
+};
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct ContainerInfo
 // информация о контейнере
 {
-    pub label: String,           // название контейнера NAMES
-    pub status: ContainerStatus, // статус контейнера STATUS
-    pub command: String,         // запущенная команда COMMAND
-    pub image: String,           // образ дистрибутива IMAGE
+    pub id: String,                       // полный id контейнера CONTAINER ID
+    pub label: String,                    // название контейнера NAMES
+    pub status: ContainerStatus,          // статус контейнера STATE
+    pub status_text: String,              // статус для людей STATUS: "Up 3 hours (healthy)"
+    pub command: String,                  // запущенная команда COMMAND
+    pub image: String,                    // образ дистрибутива IMAGE
+    pub created: Option<DateTime<Utc>>,   // когда создан CREATED
+    pub uptime: Option<u64>,              // сколько секунд работает (из STATUS, приблизительно)
+    pub exit_code: Option<i64>,           // код выхода для Exited/Restarting (из STATUS)
+    pub ports: Vec<PortBinding>,          // опубликованные порты PORTS
+    pub labels: BTreeMap<String, String>, // метки контейнера LABELS
+    pub mounts: Vec<String>,              // тома и каталоги хоста MOUNTS
+    pub networks: Vec<String>,            // сети NETWORKS
+    pub size: Option<ContainerSize>,      // размер SIZE, только если его запросили
+}
+
+impl ContainerInfo {
+    // Контейнер в путях адресуют как по имени, так и по полному id -- docker принимает оба
+    pub fn matches(&self, id: &str) -> bool {
+        self.label == id || self.id == id
+    }
+}
+
+// Порт контейнера и (если опубликован) порт на хосте: 0.0.0.0:8080->80/tcp
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortBinding {
+    pub ip: Option<String>, // адрес на хосте; None -- порт не опубликован
+    pub private_port: u16,
+    pub public_port: Option<u16>,
+    pub protocol: String, // tcp, udp, sctp
+}
+
+// Размер контейнера в байтах
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContainerSize {
+    pub rw: u64,              // записанное поверх образа
+    pub root_fs: Option<u64>, // вместе с образом (virtual)
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum ContainerStatus
 // статус контейнера
 {
@@ -44,12 +98,52 @@ impl std::fmt::Display for ContainerStatus {
     }
 }
 
-#[derive(Debug)]
+impl ContainerStatus {
+    // Состояние в терминах docker (docker ps --filter status=..., поле State в docker inspect)
+    // "running" -> Up, "removing" -> RemovalInProgress и т.д.
+    pub fn from_docker_state(state: &str) -> Option<Self> {
+        match state.to_ascii_lowercase().as_str() {
+            "created" => Some(ContainerStatus::Created),
+            "running" => Some(ContainerStatus::Up),
+            "paused" => Some(ContainerStatus::Paused),
+            "restarting" => Some(ContainerStatus::Restarting),
+            "removing" => Some(ContainerStatus::RemovalInProgress),
+            "exited" => Some(ContainerStatus::Exited),
+            "dead" => Some(ContainerStatus::Dead),
+            _ => None,
+        }
+    }
+
+    // Обратно: Up -> "running", RemovalInProgress -> "removing" и т.д.
+    pub fn docker_state(&self) -> &'static str {
+        match self {
+            ContainerStatus::Created => "created",
+            ContainerStatus::Up => "running",
+            ContainerStatus::Paused => "paused",
+            ContainerStatus::Restarting => "restarting",
+            ContainerStatus::RemovalInProgress => "removing",
+            ContainerStatus::Exited => "exited",
+            ContainerStatus::Dead => "dead",
+        }
+    }
+
+    // Есть ли у контейнера живой процесс (приостановленный и перезапускающийся -- тоже)
+    pub fn is_running(&self) -> bool {
+        matches!(
+            self,
+            ContainerStatus::Up | ContainerStatus::Paused | ContainerStatus::Restarting
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum ContainerError
 // ошибки связанные с работой с контейнерами
 {
     DockerError(String), // Ошибка самого докера
     ParseError(String),  // Ошибка парсинга
+    Unsupported(String), // Этот бэкенд так не умеет (например, TTY через docker CLI)
+    InvalidName(String), // Такого имени у контейнера быть не может -- ошибка клиента
 }
 
 impl Display for ContainerError {
@@ -61,208 +155,888 @@ impl Display for ContainerError {
             Self::ParseError(val) => {
                 write!(f, "Parse error: {val}")
             }
+            Self::Unsupported(val) => {
+                write!(f, "Not supported: {val}")
+            }
+            Self::InvalidName(val) => {
+                write!(f, "Invalid name: {val}")
+            }
         }
     }
 }
 
 // src/lib/docker_work.rs
 
+// Колонки docker ps: первые четыре -- исходные, остальные добавлены для дашборда.
+// {{.State}} -- точное состояние ("running", "paused"), а {{.Status}} -- текст для людей ("Up 3 hours (Paused)")
+const PS_FORMAT: &str = "{{.Names}}\t{{.Status}}\t{{.Image}}\t{{.Command}}\t{{.ID}}\t{{.State}}\t{{.CreatedAt}}\t{{.Ports}}\t{{.Labels}}\t{{.Mounts}}\t{{.Networks}}";
+const PS_COLUMNS: usize = 11;
+// Если в формате есть {{.Size}}, docker сам включает --size и считает размер каждого контейнера -- это медленно,
+// поэтому размер только по запросу
+const PS_SIZE_COLUMN: &str = "\t{{.Size}}";
+
 // Парсим все докер контейнеры на системе с помощью команды
-// docker ps -a --no-trunc --format "{{.Names}}\t{{.Status}}\t{{.Image}}\t{{.Command}}"
+// docker ps -a --no-trunc --format PS_FORMAT
 // Произошла ошибка докера - Возвращаем ContainerError::DockerError с пояснением
 // Парсим. Ошибка? Возвращаем ContainerError::ParseError
 // Всё окей? Возвращаем вектор информации о контейнерах Vector<ContainerInfo>
 pub fn parse_docker_ps_a() -> Result<Vec<ContainerInfo>, ContainerError> {
-    let cmd_output: std::process::Output = std::process::Command::new("docker") // Команда-объект, которая запускает исполняемый файл docker
-        .args(["ps", "-a", "--no-trunc", "--format"])
-        .arg("{{.Names}}\t{{.Status}}\t{{.Image}}\t{{.Command}}") // .args - добавляем аргументы для командной строки
-        .output() // .output() блокирует текущий поток, пока процесс не будет завершен
-        // output возвращает Result<_,std::io::Error>.
-        // output - запуск нашей команды: docker <action> <label>
-        .map_err(|e: std::io::Error| ContainerError::DockerError(format!("{e}")))?; // Добавил вопрос, поэтому в итоге output имеет итп std::process::Output
-    // .map_err - преобразует системную ошибку в пользовательскую
-    //            e - замыкание, принимающее исходную ошибку
-    //            e превращается из Result<_,std::io::Error> в Result<_,ContainerError>
-
-    if !cmd_output.status.success()
-    // если возвращенный код не успешен, т.е. не = 0
-    {
-        return Err(ContainerError::DockerError(
-            String::from_utf8(cmd_output.stdout).unwrap().to_string(),
-        ));
-    }
+    docker_ps_a(false)
+}
+
+// То же, но с размером контейнеров (ContainerInfo.size)
+pub fn parse_docker_ps_a_with_size() -> Result<Vec<ContainerInfo>, ContainerError> {
+    docker_ps_a(true)
+}
 
-    // let mut parts: Vec<String> = next_line.split('\t').map(str::to_string).collect(); // получим вектор строк, который разделен \t (табуляцией)
-    // 1. .split возвращает итератор, который при каждом вызове метода .next возвращает срез между символами табуляции
-    // 2. .map берёт каждый элемент итератор и применяет к ней функцию в ()
-    // 3. .collect собирает все элементы итератора в одну коллекцию
+fn docker_ps_a(with_size: bool) -> Result<Vec<ContainerInfo>, ContainerError> {
+    let format: String = if with_size {
+        format!("{PS_FORMAT}{PS_SIZE_COLUMN}")
+    } else {
+        PS_FORMAT.to_string()
+    };
+    // ненулевой код возврата -- ошибка с текстом из stderr
+    let cmd_output: Output = run_docker(&["ps", "-a", "--no-trunc", "--format", &format])?;
 
     let mut containers: Vec<ContainerInfo> = Vec::new(); // сюда будем складывать все считанные контейнеры
 
     for line in cmd_output.stdout.lines() {
-        // разобьём одну line по \t:
+        let line = line.map_err(|e| {
+            ContainerError::ParseError(format!("docker ps output is not UTF-8: {e}"))
+        })?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        containers.push(parse_ps_line(&line)?);
+    }
+
+    Ok(containers)
+}
 
-        let line = line.unwrap();
+// Одна строка вывода docker ps --format PS_FORMAT (и, возможно, PS_SIZE_COLUMN)
+pub fn parse_ps_line(line: &str) -> Result<ContainerInfo, ContainerError> {
+    let parts: Vec<&str> = line.split('\t').collect(); // получим вектор строк, который разделен \t (табуляцией)
 
-        let parts: Vec<&str> = line.split('\t').collect();
+    if parts.len() < PS_COLUMNS {
+        return Err(ContainerError::ParseError(format!(
+            "Unexpected columns (expected {PS_COLUMNS}), got {} in {}",
+            parts.len(),
+            line
+        )));
+    }
 
-        // считаем label
-        if parts.len() < 4 {
+    // 1. Получим label
+    let label: String = parts[0].to_string();
+
+    // 2. Получим status: по {{.State}}, а если его нет (старый docker) -- по первому слову {{.Status}}
+    let status: ContainerStatus = match ContainerStatus::from_docker_state(parts[5]) {
+        Some(status) => status,
+        None => match parts[1].split_whitespace().next().unwrap_or("") {
+            "Exited" => ContainerStatus::Exited,
+            "Up" => ContainerStatus::Up,
+            "Created" => ContainerStatus::Created,
+            "Paused" => ContainerStatus::Paused,
+            "Restarting" => ContainerStatus::Restarting,
+            "Removal" => ContainerStatus::RemovalInProgress,
+            "Dead" => ContainerStatus::Dead,
+            _other => {
+                return Err(ContainerError::ParseError(format!(
+                    "Unkown status {} in line {}",
+                    parts[1], line
+                )));
+            }
+        },
+    };
+
+    // 3+4. Получим Image и Command
+    let image: String = parts[2].to_string();
+    let command: String = {
+        let command_str: &str = parts[3];
+        if command_str.len() >= 2 {
+            command_str[1..command_str.len() - 1].to_string() // считываем, не включая первый и последний символ, которые являются "".
+        } else {
             return Err(ContainerError::ParseError(format!(
-                "Unexpected columns (expected 4), got {} in {}",
-                parts.len(),
-                &line
+                "Unknown command {} in line {}",
+                parts[3], line
             )));
         }
+    };
+
+    let (uptime, exit_code) = parse_status_text(parts[1]);
+
+    Ok(ContainerInfo {
+        id: parts[4].to_string(),
+        label,
+        status,
+        status_text: parts[1].to_string(),
+        command,
+        image,
+        created: parse_ps_created(parts[6]),
+        uptime,
+        exit_code,
+        ports: parse_ps_ports(parts[7]),
+        labels: parse_ps_labels(parts[8]),
+        mounts: split_list(parts[9]),
+        networks: split_list(parts[10]),
+        size: parts.get(PS_COLUMNS).and_then(|size| parse_ps_size(size)),
+    })
+}
+
+// Что можно вытащить из текста статуса:
+//   "Up 3 hours (healthy)"          -> аптайм ~10800 секунд
+//   "Exited (137) 2 days ago"       -> код выхода 137
+//   "Restarting (1) 5 seconds ago"  -> код выхода 1
+// Docker округляет время ("About an hour", "3 weeks"), так что аптайм приблизительный
+pub fn parse_status_text(status: &str) -> (Option<u64>, Option<i64>) {
+    let status = status.trim();
+
+    if let Some(rest) = status.strip_prefix("Up ") {
+        // отбрасываем "(healthy)", "(Paused)"
+        let duration = rest.split(" (").next().unwrap_or_default();
+        return (parse_human_duration(duration), None);
+    }
+
+    let exit_code = status
+        .split_once('(')
+        .and_then(|(_, rest)| rest.split_once(')'))
+        .and_then(|(code, _)| code.trim().parse().ok());
+    (None, exit_code)
+}
+
+// "3 hours" -> 10800, "About a minute" -> 60, "Less than a second" -> 0
+fn parse_human_duration(text: &str) -> Option<u64> {
+    let text = text.trim().to_ascii_lowercase();
+    if text.starts_with("less than a second") {
+        return Some(0);
+    }
+
+    let mut words = text.split_whitespace();
+    let count: u64 = match words.next()? {
+        "about" => {
+            words.next()?; // "a" / "an"
+            1
+        }
+        "a" | "an" => 1,
+        number => number.parse().ok()?,
+    };
+    let unit: u64 = match words.next()?.trim_end_matches('s') {
+        "second" => 1,
+        "minute" => 60,
+        "hour" => 60 * 60,
+        "day" => 24 * 60 * 60,
+        "week" => 7 * 24 * 60 * 60,
+        "month" => 30 * 24 * 60 * 60,
+        "year" => 365 * 24 * 60 * 60,
+        _ => return None,
+    };
+    Some(count * unit)
+}
+
+// {{.CreatedAt}}: "2024-05-01 10:00:00 +0000 UTC" (имя зоны в конце chrono не разбирает)
+fn parse_ps_created(raw: &str) -> Option<DateTime<Utc>> {
+    let without_zone_name: Vec<&str> = raw.split_whitespace().take(3).collect();
+    DateTime::parse_from_str(&without_zone_name.join(" "), "%Y-%m-%d %H:%M:%S %z")
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+// {{.Ports}}: "0.0.0.0:8080->80/tcp, :::8080->80/tcp, 443/tcp, 0.0.0.0:7000-7001->7000-7001/udp"
+fn parse_ps_ports(raw: &str) -> Vec<PortBinding> {
+    let mut ports: Vec<PortBinding> = Vec::new();
+
+    for entry in raw.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (host, container) = match entry.split_once("->") {
+            Some((host, container)) => (Some(host), container),
+            None => (None, entry),
+        };
+        let (private, protocol) = container.split_once('/').unwrap_or((container, "tcp"));
+        let Some(private) = parse_port_range(private) else {
+            continue;
+        };
+
+        let (ip, public) = match host.and_then(|host| host.rsplit_once(':')) {
+            Some((ip, public)) => (Some(ip.to_string()), parse_port_range(public)),
+            None => (None, None),
+        };
+
+        // диапазоны раскладываем по одному порту, как в Docker Engine API
+        for (i, private_port) in (private.0..=private.1).enumerate() {
+            ports.push(PortBinding {
+                ip: ip.clone(),
+                private_port,
+                public_port: public.map(|(start, _)| start.saturating_add(i as u16)),
+                protocol: protocol.to_string(),
+            });
+        }
+    }
+    ports
+}
 
-        // 1. Получим label
-        let label: String = parts[0].to_string();
-
-        // 2. Получим status
-        let status: ContainerStatus = {
-            // положим в эту переменную первое слово до пробела
-            let parts_status: &str = parts[1].split_whitespace().next().unwrap_or("");
-
-            match parts_status {
-                "Exited" => ContainerStatus::Exited,
-                "Up" => ContainerStatus::Up,
-                "Created" => ContainerStatus::Created,
-                "Paused" => ContainerStatus::Paused,
-                "Restarting" => ContainerStatus::Restarting,
-                "Removal" => ContainerStatus::RemovalInProgress,
-                "Dead" => ContainerStatus::Dead,
-                _other => {
-                    return Err(ContainerError::ParseError(format!(
-                        "Unkown status {} in line {}",
-                        parts[2], &line
-                    )));
+// "80" -> (80, 80), "7000-7001" -> (7000, 7001)
+fn parse_port_range(raw: &str) -> Option<(u16, u16)> {
+    match raw.split_once('-') {
+        Some((start, end)) => {
+            let (start, end) = (start.parse().ok()?, end.parse().ok()?);
+            (start <= end).then_some((start, end))
+        }
+        None => raw.parse().ok().map(|port| (port, port)),
+    }
+}
+
+// {{.Labels}}: "com.example.team=web,maintainer=ops".
+// Запятая внутри значения неотличима от разделителя -- кусок без '=' считаем продолжением значения
+fn parse_ps_labels(raw: &str) -> BTreeMap<String, String> {
+    let mut labels: BTreeMap<String, String> = BTreeMap::new();
+    let mut last_key: Option<String> = None;
+
+    for part in raw.split(',').filter(|p| !p.is_empty()) {
+        match (part.split_once('='), &last_key) {
+            (Some((key, value)), _) => {
+                labels.insert(key.to_string(), value.to_string());
+                last_key = Some(key.to_string());
+            }
+            (None, Some(key)) => {
+                if let Some(value) = labels.get_mut(key) {
+                    value.push(',');
+                    value.push_str(part);
                 }
             }
+            (None, None) => {
+                labels.insert(part.to_string(), String::new());
+                last_key = Some(part.to_string());
+            }
+        }
+    }
+    labels
+}
+
+// {{.Mounts}}, {{.Networks}}: "data,/var/log/app"
+fn split_list(raw: &str) -> Vec<String> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// {{.Size}}: "2B (virtual 187MB)" -- docker считает в десятичных единицах (1kB = 1000B)
+fn parse_ps_size(raw: &str) -> Option<ContainerSize> {
+    let (rw, rest) = raw.split_once(" (virtual ").unwrap_or((raw, ""));
+    Some(ContainerSize {
+        rw: parse_human_size(rw)?,
+        root_fs: parse_human_size(rest.trim_end_matches(')')),
+    })
+}
+
+fn parse_human_size(raw: &str) -> Option<u64> {
+    let raw = raw.trim();
+    let split = raw.find(|c: char| c.is_ascii_alphabetic())?;
+    let (number, unit) = raw.split_at(split);
+    let number: f64 = number.trim().parse().ok()?;
+    let multiplier: f64 = match unit {
+        "B" => 1.0,
+        "kB" | "KB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "TB" => 1e12,
+        "PB" => 1e15,
+        // docker stats пишет память в двоичных единицах
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        "TiB" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    Some((number * multiplier).round() as u64)
+}
+
+// Реализация ContainerBackend через docker CLI: каждая операция -- отдельный процесс docker
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DockerCli;
+
+// Поля .State через пробел (ни в одном из них пробелов не бывает).
+// Health есть только у контейнеров с HEALTHCHECK, поэтому он последний и может отсутствовать
+const INSPECT_STATE_FORMAT: &str = "{{.State.Status}} {{.State.Running}} {{.State.Paused}} {{.State.Restarting}} {{.State.Dead}} {{.State.ExitCode}} {{.State.StartedAt}} {{.State.FinishedAt}} {{.RestartCount}} {{.State.OOMKilled}} {{.State.Pid}} {{if .State.Health}}{{.State.Health.Status}}{{end}}";
+
+impl ContainerBackend for DockerCli {
+    fn list(&self) -> Result<Vec<ContainerInfo>, ContainerError> {
+        parse_docker_ps_a()
+    }
+
+    fn list_with_size(&self) -> Result<Vec<ContainerInfo>, ContainerError> {
+        parse_docker_ps_a_with_size()
+    }
+
+    fn inspect(&self, id: &str) -> Result<ContainerState, ContainerError> {
+        check_label(id)?;
+        let output = run_docker(&["inspect", "-f", INSPECT_STATE_FORMAT, id])?;
+        Ok(parse_inspect_state(&String::from_utf8_lossy(
+            &output.stdout,
+        )))
+    }
+
+    // docker inspect без -f печатает JSON-массив с одним объектом -- тем же, что отдаёт Engine API
+    fn inspect_details(&self, id: &str) -> Result<ContainerDetails, ContainerError> {
+        check_label(id)?;
+        let output = run_docker(&["inspect", "--type", "container", id])?;
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).map_err(|e| {
+            ContainerError::ParseError(format!("docker inspect returned invalid JSON: {e}"))
+        })?;
+        Ok(parse_inspect_json(&json[0]))
+    }
+
+    // docker create печатает id нового контейнера
+    fn create(&self, spec: &ContainerSpec) -> Result<String, ContainerError> {
+        let output = run_docker(&docker_create_args(spec))?;
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn start(&self, id: &str) -> Result<(), ContainerError> {
+        check_label(id)?;
+        run_docker(&["start", id]).map(|_| ())
+    }
+
+    fn stop(&self, id: &str, timeout: Option<Duration>) -> Result<(), ContainerError> {
+        check_label(id)?;
+        run_docker(&with_stop_timeout("stop", id, timeout)).map(|_| ())
+    }
+
+    fn restart(&self, id: &str, timeout: Option<Duration>) -> Result<(), ContainerError> {
+        check_label(id)?;
+        run_docker(&with_stop_timeout("restart", id, timeout)).map(|_| ())
+    }
+
+    fn remove(&self, id: &str, options: &RemoveOptions) -> Result<(), ContainerError> {
+        check_label(id)?;
+        let mut args: Vec<&str> = vec!["rm"];
+        if options.force {
+            args.push("--force");
+        }
+        if options.volumes {
+            args.push("--volumes");
+        }
+        args.push(id);
+        run_docker(&args).map(|_| ())
+    }
+
+    fn pause(&self, id: &str) -> Result<(), ContainerError> {
+        check_label(id)?;
+        run_docker(&["pause", id]).map(|_| ())
+    }
+
+    fn unpause(&self, id: &str) -> Result<(), ContainerError> {
+        check_label(id)?;
+        run_docker(&["unpause", id]).map(|_| ())
+    }
+
+    fn kill(&self, id: &str, signal: &str) -> Result<(), ContainerError> {
+        check_label(id)?;
+        run_docker(&["kill", "--signal", signal, id]).map(|_| ())
+    }
+
+    fn logs(&self, id: &str, options: &LogOptions) -> Result<LogStream, ContainerError> {
+        check_label(id)?;
+        let stream = CliLogStream::spawn(&docker_logs_args(id, options), options)?;
+        let child = Arc::clone(&stream.child);
+        Ok(LogStream::new(stream).with_canceller(move || kill_child(&child)))
+    }
+
+    fn exec(&self, id: &str, spec: &ExecSpec) -> Result<ExecStream, ContainerError> {
+        check_label(id)?;
+        let stream = CliExecStream::spawn(&docker_exec_args(id, spec))?;
+        let child = Arc::clone(&stream.child);
+        Ok(ExecStream::new(stream).with_canceller(move || kill_child(&child)))
+    }
+
+    fn stats(&self, id: &str) -> Result<ContainerStats, ContainerError> {
+        check_label(id)?;
+        docker_stats(Some(id))?
+            .pop()
+            .ok_or(ContainerError::DockerError(format!(
+                "docker stats printed nothing for {id}"
+            )))
+    }
+
+    // Один docker stats на все контейнеры вместо отдельного процесса на каждый
+    fn stats_all(&self) -> Result<Vec<ContainerStats>, ContainerError> {
+        docker_stats(None)
+    }
+}
+
+// docker stats --no-stream [id]: строка JSON на контейнер. Без id -- только работающие
+fn docker_stats(id: Option<&str>) -> Result<Vec<ContainerStats>, ContainerError> {
+    let mut args: Vec<&str> = vec![
+        "stats",
+        "--no-stream",
+        "--no-trunc",
+        "--format",
+        "{{json .}}",
+    ];
+    args.extend(id);
+    let output = run_docker(&args)?;
+    let read_at = Some(Utc::now());
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut stats = parse_stats_line(line)?;
+            stats.read_at = read_at;
+            Ok(stats)
+        })
+        .collect()
+}
+
+// {"BlockIO":"4.1MB / 0B","CPUPerc":"0.05%","ID":"..","MemUsage":"7.5MiB / 1.9GiB",
+//  "Name":"web1","NetIO":"1.2kB / 648B","PIDs":"3",..} -> ContainerStats.
+// У остановленного контейнера вместо чисел "--" -- считаем нулями
+pub fn parse_stats_line(line: &str) -> Result<ContainerStats, ContainerError> {
+    let json: serde_json::Value = serde_json::from_str(line).map_err(|e| {
+        ContainerError::ParseError(format!("docker stats printed invalid JSON: {e}"))
+    })?;
+    let field = |name: &str| json[name].as_str().unwrap_or_default().trim();
+    // "10MiB / 1.9GiB" -> (10 MiB, 1.9 GiB)
+    let pair = |name: &str| {
+        let (first, second) = field(name).split_once('/').unwrap_or_default();
+        (
+            parse_human_size(first).unwrap_or(0),
+            parse_human_size(second).unwrap_or(0),
+        )
+    };
+
+    let (memory_usage, memory_limit) = pair("MemUsage");
+    let (network_rx, network_tx) = pair("NetIO");
+    let (block_read, block_write) = pair("BlockIO");
+    Ok(ContainerStats {
+        id: field("ID").to_string(),
+        name: field("Name").to_string(),
+        read_at: None,
+        cpu_percent: field("CPUPerc")
+            .trim_end_matches('%')
+            .parse()
+            .unwrap_or(0.0),
+        memory_usage,
+        memory_limit,
+        network_rx,
+        network_tx,
+        block_read,
+        block_write,
+        pids: field("PIDs").parse().unwrap_or(0),
+    })
+}
+
+// docker create [--флаг=значение ...] <image> [command ...].
+// Значения всегда через '=' -- даже начинающееся с '-' значение не станет отдельным флагом.
+// --pull=never: как и Engine API, образ не скачиваем
+fn docker_create_args(spec: &ContainerSpec) -> Vec<String> {
+    let mut args: Vec<String> = vec!["create".to_string(), "--pull=never".to_string()];
+    let mut flag = |name: &str, value: String| args.push(format!("--{name}={value}"));
+
+    if let Some(name) = &spec.name {
+        flag("name", name.clone());
+    }
+    for (name, value) in &spec.env {
+        flag("env", format!("{name}={value}"));
+    }
+    for port in &spec.ports {
+        // [ip:][public:]private/protocol; IPv6 -- в квадратных скобках
+        let host = match (&port.ip, port.public_port) {
+            (Some(ip), public) if ip.contains(':') => {
+                format!(
+                    "[{ip}]:{}:",
+                    public.map(|p| p.to_string()).unwrap_or_default()
+                )
+            }
+            (Some(ip), public) => {
+                format!(
+                    "{ip}:{}:",
+                    public.map(|p| p.to_string()).unwrap_or_default()
+                )
+            }
+            (None, Some(public)) => format!("{public}:"),
+            (None, None) => String::new(),
         };
+        flag(
+            "publish",
+            format!("{host}{}/{}", port.private_port, port.protocol),
+        );
+    }
+    for volume in &spec.volumes {
+        let read_only = if volume.read_only { ",readonly" } else { "" };
+        flag(
+            "mount",
+            format!(
+                "type={},source={},target={}{read_only}",
+                volume.kind, volume.source, volume.destination
+            ),
+        );
+    }
+    let policy = &spec.restart_policy;
+    if policy.name == "on-failure" && policy.max_retries > 0 {
+        flag("restart", format!("on-failure:{}", policy.max_retries));
+    } else {
+        flag("restart", policy.name.clone());
+    }
+    for (key, value) in &spec.labels {
+        flag("label", format!("{key}={value}"));
+    }
 
-        // 3+4. Получим Image и Command
-        let image: String = parts[2].to_string();
-        let command: String = {
-            let command_str: &str = parts[3];
-            if command_str.len() >= 2 {
-                command_str[1..command_str.len() - 1].to_string() // считываем, не включая первый и последний символ, которые являются "".
+    let resources = &spec.resources;
+    if let Some(memory) = resources.memory {
+        flag("memory", memory.to_string());
+    }
+    if let Some(reservation) = resources.memory_reservation {
+        flag("memory-reservation", reservation.to_string());
+    }
+    if let Some(swap) = resources.memory_swap {
+        flag("memory-swap", swap.to_string());
+    }
+    if let Some(nano_cpus) = resources.nano_cpus {
+        flag("cpus", (nano_cpus as f64 / 1e9).to_string());
+    }
+    if let Some(shares) = resources.cpu_shares {
+        flag("cpu-shares", shares.to_string());
+    }
+    if let Some(quota) = resources.cpu_quota {
+        flag("cpu-quota", quota.to_string());
+    }
+    if let Some(period) = resources.cpu_period {
+        flag("cpu-period", period.to_string());
+    }
+    if let Some(cpus) = &resources.cpuset_cpus {
+        flag("cpuset-cpus", cpus.clone());
+    }
+    if let Some(pids) = resources.pids_limit {
+        flag("pids-limit", pids.to_string());
+    }
+
+    // после образа docker флаги уже не разбирает -- дальше команда как есть
+    args.push(spec.image.clone());
+    args.extend(spec.command.iter().cloned());
+    args
+}
+
+// docker stop|restart [--time N] <id>; docker считает таймаут в целых секундах
+fn with_stop_timeout(action: &str, id: &str, timeout: Option<Duration>) -> Vec<String> {
+    let mut args: Vec<String> = vec![action.to_string()];
+    if let Some(timeout) = timeout {
+        args.extend(["--time".to_string(), timeout.as_secs().to_string()]);
+    }
+    args.push(id.to_string());
+    args
+}
+
+// docker logs -t [--tail N] [--since T] [--until T] [-f] <id>
+fn docker_logs_args(id: &str, options: &LogOptions) -> Vec<String> {
+    let mut args: Vec<String> = vec!["logs".to_string(), "--timestamps".to_string()];
+    if let Some(tail) = options.tail {
+        args.extend(["--tail".to_string(), tail.to_string()]);
+    }
+    if let Some(since) = options.since {
+        args.extend(["--since".to_string(), since.to_rfc3339()]);
+    }
+    if let Some(until) = options.until {
+        args.extend(["--until".to_string(), until.to_rfc3339()]);
+    }
+    if options.follow {
+        args.push("--follow".to_string());
+    }
+    args.push(id.to_string());
+    args
+}
+
+// Вывод docker logs: stdout контейнера идёт в stdout процесса, stderr -- в stderr.
+// Каждый pipe читает свой поток и складывает строки в общий канал -- порядок строк
+// между stdout и stderr примерный (как и в самом docker logs).
+// Когда итератор бросили (клиент отключился) или прервали через Canceller,
+// процесс docker logs убиваем
+struct CliLogStream {
+    child: SharedChild,
+    lines: Receiver<LogLine>,
+    finished: bool,
+}
+
+impl CliLogStream {
+    fn spawn(args: &[String], options: &LogOptions) -> Result<Self, ContainerError> {
+        let pipe = |wanted: bool| {
+            if wanted {
+                Stdio::piped()
             } else {
-                return Err(ContainerError::ParseError(format!(
-                    "Unknown command {} in line {}",
-                    parts[3], &line
-                )));
+                Stdio::null()
             }
         };
-        // Добавим считанный контейнер в вектор контейнеров
-        containers.push(ContainerInfo {
-            label: (label),
-            status: (status),
-            command: (command),
-            image: (image),
-        });
+        let mut child: Child = std::process::Command::new("docker")
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(pipe(options.stdout))
+            .stderr(pipe(options.stderr))
+            .spawn()
+            .map_err(|e: std::io::Error| ContainerError::DockerError(format!("{e}")))?;
+
+        let (sender, lines) = channel::<LogLine>();
+        if let Some(stdout) = child.stdout.take() {
+            forward_lines(stdout, LogSource::Stdout, sender.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            forward_lines(stderr, LogSource::Stderr, sender);
+        }
+
+        Ok(Self {
+            child: Arc::new(Mutex::new(child)),
+            lines,
+            finished: false,
+        })
     }
+}
 
-    //println!("{:#?}", containers);
+// Процесс docker, которого ждёт читающий поток, а убить может и другой (Canceller)
+type SharedChild = Arc<Mutex<Child>>;
 
-    Ok(containers)
+// Уже завершённый процесс kill не трогает -- звать можно когда угодно
+fn kill_child(child: &SharedChild) {
+    if let Ok(mut child) = child.lock() {
+        let _ = child.kill();
+    }
 }
 
-fn is_valid_label(label: &str) -> bool // прием параметра по ссылке, чтобы не передавать владение данной функции
-{
-    label
-        .chars()
-        .all(|c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_')
-    //.char - создаёт итератор по строке label в виде char
-    //.all - метод-итератор: выполняет переданную функцию-замыкание для каждого символа
-    //      true - все символы успешно прошли проверку
-    //      false - хотя бы один символ провалил проверку
+fn wait_child(child: &SharedChild) -> std::io::Result<ExitStatus> {
+    child
+        .lock()
+        .map_err(|_| std::io::Error::other("docker process lock is poisoned"))?
+        .wait()
 }
 
-// общая функция для всех действий с docker: start, stop, pause
-fn docker_action(action: &str, label: &str) -> Result<(), ContainerError> {
-    // 1. ПРОВЕРКА КОРРЕКТНОСТИ ИМЕНИ НА ЗАПРЕЩЕННЫЕ СИМВОЛЫ
-    if !is_valid_label(label)
-    // передаю по ссылке
-    {
-        return Err(ContainerError::DockerError(
-            "Invalid label. Use ASCII symbols, '-', '_'".to_string(),
-        ));
-    }
+fn forward_lines<R: Read + Send + 'static>(pipe: R, source: LogSource, sender: Sender<LogLine>) {
+    std::thread::spawn(move || {
+        for line in BufReader::new(pipe).split(b'\n').map_while(Result::ok) {
+            let line = LogLine::parse_timestamped(source, &String::from_utf8_lossy(&line));
+            if sender.send(line).is_err() {
+                break; // итератор уже бросили
+            }
+        }
+    });
+}
 
-    // 2. ПРОВЕРКА НА НАЛИЧИЕ ТАКОГО КОНТЕЙНЕРА В vec<ConteinerInfo>
-    let all: Vec<ContainerInfo> = parse_docker_ps_a()?; // синтаксический сахар для распаковки значений Result для проброски ошибок в помойку
-    if !all
-        .iter()
-        .any(|one_element: &ContainerInfo| one_element.label == label)
-    {
-        return Err(ContainerError::DockerError(format!(
-            "Can't find {label} in list of containers"
-        ))); // или свой вариант ошибки
-    }
-
-    let output: std::process::Output =
-        std::process::Command::new("docker") // Команда-объект, которая запускает исполняемый файл docker
-            .args([action, label]) // .args - добавляем аргументы для командной строки: stop + label. Итого: docker stop <label>
-            // передаем срез, &label чтобы не передавать владение, а передать ссылку + конвертация &String в &str.
-            .output() // .output() блокирует текущий поток, пока процесс не будет завершен
-            // output возвращает Result<_,std::io::Error>.
-            // output - запуск нашей команды: docker <action> <label>
-            .map_err(|e: std::io::Error| ContainerError::DockerError(format!("{e}")))?; // Добавил вопрос, поэтому в итоге output имеет итп std::process::Output
-    // .map_err - преобразует системную ошибку в пользовательскую
-    //            e - замыкание, принимающее исходную ошибку
-    //            e превращается из Result<_,std::io::Error> в Result<_,ContainerError>
-
-    if !output.status.success()
-    // если возвращенный код не успешен, т.е. не = 0
-    {
-        return Err(ContainerError::DockerError(format!(
-            "{label} was found. Unsucsessful attempt to stop {label}"
-        )));
+impl Iterator for CliLogStream {
+    type Item = Result<LogLine, ContainerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.lines.recv() {
+            Ok(line) => Some(Ok(line)),
+            // оба pipe закрыты -- docker logs завершился
+            Err(_) => {
+                self.finished = true;
+                match wait_child(&self.child) {
+                    Ok(status) if status.success() => None,
+                    Ok(status) => Some(Err(ContainerError::DockerError(format!(
+                        "docker logs exited with {status}"
+                    )))),
+                    Err(e) => Some(Err(ContainerError::DockerError(format!("{e}")))),
+                }
+            }
+        }
     }
+}
 
-    Ok(()) // вернем unit в случае успеха
+impl Drop for CliLogStream {
+    fn drop(&mut self) {
+        if !self.finished {
+            kill_child(&self.child);
+            let _ = wait_child(&self.child);
+        }
+    }
 }
 
-// Отправляем команду остановки контейнеру с именем label
-// с помощью docker stop label
-// Ошибка докера? Возвращаем ошибку ContainerError::DockerError
-pub fn stop_container(label: String) -> Result<(), ContainerError> // () - unit
-{
-    docker_action("stop", &label)
+// docker exec [--env=K=V ...] [--workdir=W] [--user=U] <id> <argv...>.
+// После id docker флаги уже не разбирает -- argv уходит команде как есть
+fn docker_exec_args(id: &str, spec: &ExecSpec) -> Vec<String> {
+    let mut args: Vec<String> = vec!["exec".to_string()];
+    for (name, value) in &spec.env {
+        args.push(format!("--env={name}={value}"));
+    }
+    if let Some(workdir) = &spec.workdir {
+        args.push(format!("--workdir={workdir}"));
+    }
+    if let Some(user) = &spec.user {
+        args.push(format!("--user={user}"));
+    }
+    args.push(id.to_string());
+    args.extend(spec.argv.iter().cloned());
+    args
 }
 
-// Отправляем команду запуска контейнеру с именем label
-// с помощью docker start label
-// Ошибка докера? Возвращаем ошибку ContainerError::DockerError
-pub fn start_container(label: String) -> Result<(), ContainerError> {
-    docker_action("start", &label)
+// Вывод docker exec кусками, как его читают pipe -- так же, как CliLogStream, только без
+// разбиения на строки. Код выхода docker exec -- это код самой команды
+// (126 -- не запустилась, 127 -- не нашлась)
+struct CliExecStream {
+    child: SharedChild,
+    chunks: Receiver<(LogSource, Vec<u8>)>,
+    finished: bool,
 }
 
-// ПЕРЕДЕЛАТЬ В ТЕСТЫ!!!!!!!!!!!
+impl CliExecStream {
+    fn spawn(args: &[String]) -> Result<Self, ContainerError> {
+        let mut child: Child = std::process::Command::new("docker")
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e: std::io::Error| ContainerError::DockerError(format!("{e}")))?;
 
-/*
+        let (sender, chunks) = channel::<(LogSource, Vec<u8>)>();
+        if let Some(stdout) = child.stdout.take() {
+            forward_chunks(stdout, LogSource::Stdout, sender.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            forward_chunks(stderr, LogSource::Stderr, sender);
+        }
+
+        Ok(Self {
+            child: Arc::new(Mutex::new(child)),
+            chunks,
+            finished: false,
+        })
+    }
+}
 
-   // ------------ DOCKER_WORKS.RS РАЗДЕЛ ------------
+fn forward_chunks<R: Read + Send + 'static>(
+    mut pipe: R,
+    source: LogSource,
+    sender: Sender<(LogSource, Vec<u8>)>,
+) {
+    std::thread::spawn(move || {
+        let mut buffer = [0u8; 8192];
+        while let Ok(n @ 1..) = pipe.read(&mut buffer) {
+            if sender.send((source, buffer[..n].to_vec())).is_err() {
+                break; // итератор уже бросили
+            }
+        }
+    });
+}
 
-    let containers: Result<Vec<ContainerInfo>, ContainerError> = parse_docker_ps_a();
+impl Iterator for CliExecStream {
+    type Item = Result<ExecEvent, ContainerError>;
 
-    println!("{:#?}", containers);
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.chunks.recv() {
+            Ok((source, data)) => Some(Ok(ExecEvent::Output(source, data))),
+            // оба pipe закрыты -- команда завершилась
+            Err(_) => {
+                self.finished = true;
+                Some(match wait_child(&self.child) {
+                    Ok(status) => status
+                        .code()
+                        .map(|code| ExecEvent::Exit(code.into()))
+                        .ok_or(ContainerError::DockerError(format!(
+                            "docker exec was killed: {status}"
+                        ))),
+                    Err(e) => Err(ContainerError::DockerError(format!("{e}"))),
+                })
+            }
+        }
+    }
+}
 
-    let mut rezult = stop_container("MyNgnix".to_string());
+// Процесс docker exec прибиваем, команда в контейнере при этом доработает сама
+impl Drop for CliExecStream {
+    fn drop(&mut self) {
+        if !self.finished {
+            kill_child(&self.child);
+            let _ = wait_child(&self.child);
+        }
+    }
+}
+
+// Запускаем docker с аргументами и ждём завершения.
+// Ненулевой код возврата -- DockerError с тем, что docker написал в stderr
+fn run_docker<S: AsRef<std::ffi::OsStr>>(args: &[S]) -> Result<Output, ContainerError> {
+    let output: Output = std::process::Command::new("docker")
+        .args(args)
+        .output() // .output() блокирует текущий поток, пока процесс не будет завершен
+        .map_err(|e: std::io::Error| ContainerError::DockerError(format!("{e}")))?;
 
-    match rezult {
-        Ok(()) => println!("Container sucessfully stopped."),
-        Err(_err) => println!("Docker Error! Failed to stop container!"),
+    if !output.status.success() {
+        return Err(ContainerError::DockerError(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
     }
 
-    rezult = start_container("MyNgnix".to_string());
+    Ok(output)
+}
 
-    match rezult {
-        Ok(()) => println!("Container sucessfully started."),
-        Err(_err) => println!("Docker Error! Failed to start container!"),
+// Разбираем вывод docker inspect -f INSPECT_STATE_FORMAT:
+// "running true false false false" -> ContainerState { status: "running", is_running: true, .. }
+// Чего не хватает или не парсится -- считаем false
+pub fn parse_inspect_state(raw: &str) -> ContainerState {
+    let mut parts = raw.split_whitespace(); // .split_whitespace() -- разобьём String по пробелам
+    let status: String = parts.next().unwrap_or_default().to_string();
+
+    let mut next_flag = || {
+        parts
+            .next()
+            .unwrap_or("false")
+            .parse::<bool>()
+            .unwrap_or(false)
+    };
+    // поля заполняются по порядку -- в том же порядке, что и в INSPECT_STATE_FORMAT
+    let mut state = ContainerState {
+        status,
+        is_running: next_flag(),
+        is_paused: next_flag(),
+        is_restarting: next_flag(),
+        is_dead: next_flag(),
+        ..Default::default()
+    };
+
+    state.exit_code = parts.next().and_then(|code| code.parse().ok());
+    state.started_at = parts.next().and_then(parse_docker_time);
+    state.finished_at = parts.next().and_then(parse_docker_time);
+    state.restart_count = parts
+        .next()
+        .and_then(|count| count.parse().ok())
+        .unwrap_or(0);
+    state.oom_killed = parts.next() == Some("true");
+    state.pid = parts
+        .next()
+        .and_then(|pid| pid.parse().ok())
+        .filter(|&pid| pid > 0);
+    state.health = parts.next().map(str::to_string);
+    state
+}
+
+pub(crate) fn check_label(label: &str) -> Result<(), ContainerError> {
+    if !is_valid_label(label) {
+        return Err(ContainerError::InvalidName(format!(
+            "{label:?}. Use ASCII letters, digits, '-', '_', '.', starting with a letter or digit, up to {MAX_LABEL_LEN} symbols"
+        )));
     }
+    Ok(())
+}
 
-*/
+// Длиннее docker не запрещает, но такое имя точно ошибка (и не влезет в hostname)
+pub const MAX_LABEL_LEN: usize = 128;
+
+pub(crate) fn is_valid_label(label: &str) -> bool // прием параметра по ссылке, чтобы не передавать владение данной функции
+{
+    // как у docker ([a-zA-Z0-9][a-zA-Z0-9_.-]*): пустое имя, "-rf", "_x" или "../x"
+    // не пройдут -- имя не должно быть похоже на флаг командной строки или путь
+    label.len() <= MAX_LABEL_LEN
+        && label.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && label
+            .chars()
+            .all(|c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    //.char - создаёт итератор по строке label в виде char
+    //.all - метод-итератор: выполняет переданную функцию-замыкание для каждого символа
+    //      true - все символы успешно прошли проверку
+    //      false - хотя бы один символ провалил проверку
+}
 
 // Название ошибки не пишется не пишется -- убрать DockerError
 /*
@@ -280,3 +1054,271 @@ docker stop label1 && rm -rf /
     получаем все контейнеры, если запрошенный label (параметр функции) не совпадает ни с одним из вектора всех контейнеров, возвращаем ContainerError
 
 */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_rules() {
+        for label in ["web1", "app.v2", "my_app-2", "a"] {
+            assert!(is_valid_label(label), "{label}");
+        }
+        for label in ["", "-rf", "_x", ".hidden", "../etc", "a b", "a;b", "web/1"] {
+            assert!(!is_valid_label(label), "{label}");
+        }
+        assert!(matches!(
+            check_label("-rf"),
+            Err(ContainerError::InvalidName(_))
+        ));
+    }
+
+    #[test]
+    fn parse_inspect_output() {
+        assert_eq!(
+            parse_inspect_state(
+                "running true false false false 0 2024-05-01T10:00:00.5Z 0001-01-01T00:00:00Z\n"
+            ),
+            ContainerState {
+                status: "running".to_string(),
+                is_running: true,
+                exit_code: Some(0),
+                started_at: parse_docker_time("2024-05-01T10:00:00.5Z"),
+                ..Default::default()
+            }
+        );
+        assert!(parse_docker_time("2024-05-01T10:00:00.5Z").is_some());
+        assert_eq!(
+            parse_inspect_state("restarting true false true false"),
+            ContainerState {
+                status: "restarting".to_string(),
+                is_running: true,
+                is_restarting: true,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            parse_inspect_state(
+                "running true false false false 0 2024-05-01T10:00:00Z 0001-01-01T00:00:00Z 3 false 4242 unhealthy"
+            ),
+            ContainerState {
+                status: "running".to_string(),
+                is_running: true,
+                exit_code: Some(0),
+                started_at: parse_docker_time("2024-05-01T10:00:00Z"),
+                restart_count: 3,
+                pid: Some(4242),
+                health: Some("unhealthy".to_string()),
+                ..Default::default()
+            }
+        );
+        // обрезанный или пустой вывод -- всё false
+        assert_eq!(parse_inspect_state(""), ContainerState::default());
+    }
+
+    #[test]
+    fn parse_ps_line_with_details() -> Result<(), ContainerError> {
+        let line = "web1\tUp 3 hours (Paused)\tnginx:1.27\t\"/docker-entrypoint.sh nginx -g 'daemon off;'\"\t\
+            4f1c2a\tpaused\t2024-05-01 10:00:00 +0300 MSK\t\
+            0.0.0.0:8080->80/tcp, :::8080->80/tcp, 0.0.0.0:7000-7001->7000-7001/udp, 443/tcp\t\
+            team=web,hosts=a,b\tstatic,/var/log/nginx\tbridge,backend\t2B (virtual 187MB)";
+        let info = parse_ps_line(line)?;
+
+        assert_eq!(info.label, "web1");
+        assert_eq!(info.id, "4f1c2a");
+        // по тексту это "Up", но {{.State}} точнее
+        assert_eq!(info.status, ContainerStatus::Paused);
+        assert_eq!(info.command, "/docker-entrypoint.sh nginx -g 'daemon off;'");
+        assert_eq!(info.created, parse_docker_time("2024-05-01T07:00:00Z"));
+        assert_eq!(info.uptime, Some(3 * 3600));
+        assert_eq!(info.exit_code, None);
+
+        assert_eq!(info.ports.len(), 5);
+        assert_eq!(
+            info.ports[1],
+            PortBinding {
+                ip: Some("::".to_string()),
+                private_port: 80,
+                public_port: Some(8080),
+                protocol: "tcp".to_string(),
+            }
+        );
+        assert_eq!(info.ports[3].public_port, Some(7001));
+        assert_eq!(info.ports[3].protocol, "udp");
+        assert_eq!(
+            (info.ports[4].ip.clone(), info.ports[4].public_port),
+            (None, None)
+        );
+
+        assert_eq!(info.labels.get("hosts").map(String::as_str), Some("a,b"));
+        assert_eq!(info.mounts, vec!["static", "/var/log/nginx"]);
+        assert_eq!(info.networks, vec!["bridge", "backend"]);
+        assert_eq!(
+            info.size,
+            Some(ContainerSize {
+                rw: 2,
+                root_fs: Some(187_000_000)
+            })
+        );
+
+        assert_eq!(
+            parse_status_text("Exited (137) 2 days ago"),
+            (None, Some(137))
+        );
+        assert_eq!(parse_status_text("Up About an hour"), (Some(3600), None));
+        assert_eq!(parse_status_text("Created"), (None, None));
+        assert!(parse_ps_line("web1\tUp 3 hours").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn logs_command_line() {
+        let options = LogOptions {
+            tail: Some(50),
+            since: parse_docker_time("2024-05-01T10:00:00Z"),
+            follow: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            docker_logs_args("web1", &options),
+            vec![
+                "logs",
+                "--timestamps",
+                "--tail",
+                "50",
+                "--since",
+                "2024-05-01T10:00:00+00:00",
+                "--follow",
+                "web1"
+            ]
+        );
+        assert_eq!(
+            LogLine::parse_timestamped(LogSource::Stderr, "2024-05-01T10:00:00.5Z oops\r\n"),
+            LogLine {
+                source: LogSource::Stderr,
+                timestamp: parse_docker_time("2024-05-01T10:00:00.5Z"),
+                text: "oops".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn exec_command_line() {
+        let spec = ExecSpec {
+            argv: vec!["nginx".to_string(), "-t".to_string()],
+            env: vec![("MODE".to_string(), "check".to_string())],
+            workdir: Some("/etc/nginx".to_string()),
+            user: Some("101:101".to_string()),
+        };
+        assert_eq!(
+            docker_exec_args("web1", &spec),
+            vec![
+                "exec",
+                "--env=MODE=check",
+                "--workdir=/etc/nginx",
+                "--user=101:101",
+                "web1",
+                "nginx",
+                "-t"
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_stats_output() -> Result<(), ContainerError> {
+        let stats = parse_stats_line(
+            r#"{"BlockIO":"4.1MB / 0B","CPUPerc":"12.50%","Container":"web1","ID":"4f1c2a","MemPerc":"0.38%","MemUsage":"7.5MiB / 1.5GiB","Name":"web1","NetIO":"1.2kB / 648B","PIDs":"3"}"#,
+        )?;
+        assert_eq!(
+            stats,
+            ContainerStats {
+                id: "4f1c2a".to_string(),
+                name: "web1".to_string(),
+                read_at: None,
+                cpu_percent: 12.5,
+                memory_usage: 7_864_320,
+                memory_limit: 1_610_612_736,
+                network_rx: 1200,
+                network_tx: 648,
+                block_read: 4_100_000,
+                block_write: 0,
+                pids: 3,
+            }
+        );
+
+        // остановленный контейнер
+        let stopped = parse_stats_line(
+            r#"{"BlockIO":"--","CPUPerc":"--","ID":"5e2d","MemUsage":"-- / --","Name":"db","NetIO":"--","PIDs":"--"}"#,
+        )?;
+        assert_eq!(
+            (stopped.cpu_percent, stopped.memory_usage, stopped.pids),
+            (0.0, 0, 0)
+        );
+        assert_eq!(stopped.memory_percent(), 0.0);
+
+        assert!(parse_stats_line("CONTAINER ID   NAME").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn create_command_line() {
+        let spec = ContainerSpec {
+            image: "nginx:1.27".to_string(),
+            name: Some("web2".to_string()),
+            command: vec!["--debug".to_string(), "-g".to_string()],
+            env: vec![("MODE".to_string(), "-x".to_string())],
+            ports: vec![
+                PortBinding {
+                    ip: Some("::1".to_string()),
+                    private_port: 80,
+                    public_port: Some(8080),
+                    protocol: "tcp".to_string(),
+                },
+                PortBinding {
+                    ip: None,
+                    private_port: 53,
+                    public_port: None,
+                    protocol: "udp".to_string(),
+                },
+            ],
+            volumes: vec![crate::lib::container_backend::MountInfo {
+                kind: "volume".to_string(),
+                name: Some("static".to_string()),
+                source: "static".to_string(),
+                destination: "/data".to_string(),
+                read_only: true,
+            }],
+            restart_policy: crate::lib::container_backend::RestartPolicy {
+                name: "on-failure".to_string(),
+                max_retries: 3,
+            },
+            labels: BTreeMap::from([("team".to_string(), "web".to_string())]),
+            resources: crate::lib::container_backend::ResourceLimits {
+                memory: Some(268_435_456),
+                nano_cpus: Some(1_500_000_000),
+                pids_limit: Some(-1),
+                ..Default::default()
+            },
+        };
+        assert_eq!(
+            docker_create_args(&spec),
+            vec![
+                "create",
+                "--pull=never",
+                "--name=web2",
+                "--env=MODE=-x",
+                "--publish=[::1]:8080:80/tcp",
+                "--publish=53/udp",
+                "--mount=type=volume,source=static,target=/data,readonly",
+                "--restart=on-failure:3",
+                "--label=team=web",
+                "--memory=268435456",
+                "--cpus=1.5",
+                "--pids-limit=-1",
+                "nginx:1.27",
+                "--debug",
+                "-g",
+            ]
+        );
+    }
+}
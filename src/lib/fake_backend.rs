@@ -0,0 +1,934 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    io::{self, Read, Write},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{Receiver, RecvTimeoutError, Sender, SyncSender, channel, sync_channel},
+    },
+    time::Duration,
+};
+
+use chrono::Utc;
+
+use crate::lib::{
+    container_backend::{
+        ContainerBackend, ContainerDetails, ContainerState, ContainerStats, ExecControl, ExecEvent,
+        ExecSession, ExecStream, LogLine, LogOptions, LogSource, LogStream, RemoveOptions, TtySize,
+        parse_signal,
+    },
+    container_spec::{ContainerSpec, ExecSpec},
+    docker_works::{ContainerError, ContainerInfo, ContainerStatus, parse_status_text},
+};
+
+// Контейнерный рантайм в памяти -- для тестов хендлеров без живого docker.
+// Контейнеры и их состояния задаются сценарием, действия меняют состояние так же,
+// как это сделал бы демон, а любую операцию можно заставить вернуть ошибку:
+//
+// let fake = Arc::new(
+//     FakeBackend::new()
+//         .with_container("web1", "nginx", ContainerStatus::Up)
+//         .with_container("zombie", "redis", ContainerStatus::Dead),
+// );
+// fake.fail_next(FakeOp::Stop, ContainerError::DockerError("daemon timeout".to_string()));
+// let state = AppState::with_backend(Config::default(), fake.clone());
+// ...
+// assert_eq!(fake.status("web1"), Some(ContainerStatus::Up));
+// assert_eq!(fake.calls(), vec!["inspect web1", "stop web1"]);
+//
+// Параметры действия попадают в вызов: "stop web1 5s", "kill web1 SIGHUP", "remove web1 force",
+// "exec web1 echo hi", "resize web1 40x120". Что умеют команды exec -- см. fake_exec,
+// интерактивный exec_tty -- см. fake_tty_session
+#[derive(Debug, Default)]
+pub struct FakeBackend {
+    containers: Mutex<Vec<FakeContainer>>,
+    failures: Mutex<HashMap<FakeOp, VecDeque<ContainerError>>>,
+    calls: Arc<Mutex<Vec<String>>>, // Arc: resize из сессии exec_tty тоже попадает в calls
+    running_execs: Arc<AtomicUsize>, // сколько потоков exec ещё не бросили
+}
+
+// Операции рантайма, для которых можно подложить ошибку
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FakeOp {
+    List,
+    Inspect,
+    Create,
+    Start,
+    Stop,
+    Restart,
+    Remove,
+    Pause,
+    Unpause,
+    Kill,
+    Logs,
+    Exec,
+    Stats,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FakeContainer {
+    pub info: ContainerInfo,
+    pub state: ContainerState,
+    // остальное, что отдаёт inspect_details (env, тома, сеть, лимиты);
+    // id, имя, образ и состояние берутся из info и state
+    pub details: ContainerDetails,
+    pub logs: Vec<LogLine>, // всё, что контейнер «написал», по порядку
+    // что отдаёт stats, пока контейнер работает (id, имя и время подставляются);
+    // у остановленного -- нули
+    pub stats: ContainerStats,
+}
+
+impl FakeContainer {
+    pub fn new(label: &str, image: &str, status: ContainerStatus) -> Self {
+        let mut container = Self {
+            info: ContainerInfo {
+                id: fake_id(label),
+                label: label.to_string(),
+                status,
+                status_text: String::new(),
+                command: String::new(),
+                image: image.to_string(),
+                created: Some(Utc::now()),
+                uptime: None,
+                exit_code: None,
+                ports: Vec::new(),
+                labels: BTreeMap::new(),
+                mounts: Vec::new(),
+                networks: vec!["bridge".to_string()],
+                size: None,
+            },
+            state: ContainerState::default(),
+            details: ContainerDetails::default(),
+            logs: Vec::new(),
+            stats: ContainerStats::default(),
+        };
+        container.set_status(status);
+        container
+    }
+
+    // Переводим контейнер в статус, выставляя флаги .State так, как их показывает docker inspect
+    // (приостановленный и перезапускающийся контейнер docker тоже считает Running)
+    pub fn set_status(&mut self, status: ContainerStatus) {
+        let now = Some(Utc::now());
+        let was_running = self.state.is_running;
+        let state = &mut self.state;
+
+        self.info.status = status;
+        state.status = status.docker_state().to_string();
+        state.is_running = status.is_running();
+        state.is_paused = status == ContainerStatus::Paused;
+        state.is_restarting = status == ContainerStatus::Restarting;
+        state.is_dead = status == ContainerStatus::Dead;
+        state.pid = state.is_running.then_some(4242);
+
+        if state.is_running && (!was_running || status == ContainerStatus::Restarting) {
+            state.started_at = now;
+        }
+        if !state.is_running && was_running {
+            state.finished_at = now;
+        }
+        state.exit_code = match status {
+            ContainerStatus::Created => None,
+            ContainerStatus::Exited | ContainerStatus::Dead => state.exit_code.or(Some(0)),
+            _ => Some(0),
+        };
+
+        // то же, что показал бы docker ps сразу после перехода
+        let exit_code = state.exit_code.unwrap_or_default();
+        self.info.status_text = match status {
+            ContainerStatus::Up => "Up Less than a second".to_string(),
+            ContainerStatus::Paused => "Up Less than a second (Paused)".to_string(),
+            ContainerStatus::Restarting => {
+                format!("Restarting ({exit_code}) Less than a second ago")
+            }
+            ContainerStatus::Exited => format!("Exited ({exit_code}) Less than a second ago"),
+            ContainerStatus::Created => "Created".to_string(),
+            ContainerStatus::RemovalInProgress => "Removal In Progress".to_string(),
+            ContainerStatus::Dead => "Dead".to_string(),
+        };
+        (self.info.uptime, self.info.exit_code) = parse_status_text(&self.info.status_text);
+    }
+}
+
+// Таймаут остановки в calls(): "5s" или "" (по умолчанию)
+fn timeout_arg(timeout: Option<Duration>) -> String {
+    timeout.map_or(String::new(), |timeout| format!("{}s", timeout.as_secs()))
+}
+
+// Id в формате docker (64 hex-символа), одинаковый для одного и того же имени
+fn fake_id(label: &str) -> String {
+    let hex: String = label.bytes().map(|b| format!("{b:02x}")).collect();
+    format!("{hex:0<64}").chars().take(64).collect()
+}
+
+// Команды, которые «есть» в каждом фейковом контейнере:
+//   echo ARGS   -- ARGS в stdout          true / false -- код 0 / 1
+//   env         -- переменные контейнера и exec, "K=V" на строку
+//   pwd, whoami -- workdir и user из exec ("/" и "root" по умолчанию)
+//   sleep SECS  -- ждёт и выходит с 0 (Canceller будит)   yes -- "y" в stdout без конца
+// Остальное -- как у docker: "executable file not found" в stderr и код 127
+fn fake_exec(container: &FakeContainer, spec: &ExecSpec) -> ExecStream {
+    let out = |source: LogSource, text: String| Ok(ExecEvent::Output(source, text.into_bytes()));
+    let args = &spec.argv[1..];
+
+    let (output, code) = match spec.argv[0].as_str() {
+        "yes" => {
+            return ExecStream::new(std::iter::repeat_with(move || {
+                out(LogSource::Stdout, "y\n".repeat(512))
+            }));
+        }
+        "sleep" => {
+            let secs: f64 = args
+                .first()
+                .and_then(|secs| secs.parse().ok())
+                .unwrap_or(0.0);
+            // как процесс docker exec: прервали -- кода выхода уже не узнать
+            let (wake, woken) = channel::<()>();
+            return ExecStream::new(std::iter::once_with(move || {
+                match woken.recv_timeout(Duration::from_secs_f64(secs)) {
+                    Err(RecvTimeoutError::Timeout) => Ok(ExecEvent::Exit(0)),
+                    _ => Err(ContainerError::DockerError(
+                        "docker exec was killed".to_string(),
+                    )),
+                }
+            }))
+            .with_canceller(move || {
+                let _ = wake.send(());
+            });
+        }
+        "echo" => (
+            vec![out(LogSource::Stdout, format!("{}\n", args.join(" ")))],
+            0,
+        ),
+        "true" => (Vec::new(), 0),
+        "false" => (Vec::new(), 1),
+        "env" => (
+            container
+                .details
+                .env
+                .iter()
+                .chain(&spec.env)
+                .map(|(name, value)| out(LogSource::Stdout, format!("{name}={value}\n")))
+                .collect(),
+            0,
+        ),
+        "pwd" => (
+            vec![out(
+                LogSource::Stdout,
+                format!("{}\n", spec.workdir.as_deref().unwrap_or("/")),
+            )],
+            0,
+        ),
+        "whoami" => (
+            vec![out(
+                LogSource::Stdout,
+                format!("{}\n", spec.user.as_deref().unwrap_or("root")),
+            )],
+            0,
+        ),
+        program => (
+            vec![out(
+                LogSource::Stderr,
+                format!(
+                    "OCI runtime exec failed: exec failed: unable to start container process: exec: \"{program}\": executable file not found in $PATH: unknown\n"
+                ),
+            )],
+            127,
+        ),
+    };
+    ExecStream::new(output.into_iter().chain([Ok(ExecEvent::Exit(code))]))
+}
+
+// Поток exec, который считается в running_execs, пока его не бросят
+struct FakeExecStream {
+    events: ExecStream,
+    running: Arc<AtomicUsize>,
+}
+
+impl Iterator for FakeExecStream {
+    type Item = Result<ExecEvent, ContainerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.events.next()
+    }
+}
+
+impl Drop for FakeExecStream {
+    fn drop(&mut self) {
+        self.running.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// Интерактивная сессия exec_tty. Терминал, как настоящий, повторяет ввод (echo)
+// и переводит строки в "\r\n". Оболочка (sh, bash) читает строки и выполняет их через
+// fake_exec, между командами печатает "$ ", завершается по "exit [N]", Ctrl-D
+// или закрытию stdin. Любая другая команда просто выполняется один раз
+fn fake_tty_session(
+    id: &str,
+    container: FakeContainer,
+    spec: ExecSpec,
+    calls: Arc<Mutex<Vec<String>>>,
+) -> ExecSession {
+    let (input_tx, input_rx) = channel::<Vec<u8>>();
+    // вывод с ограниченной очередью: бесконечный yes ждёт читателя, а не копит память
+    let (output_tx, output_rx) = sync_channel::<Vec<u8>>(16);
+    let exit_code: Arc<Mutex<Option<i64>>> = Arc::new(Mutex::new(None));
+
+    let shell_exit_code = exit_code.clone();
+    std::thread::spawn(move || {
+        let code = run_fake_tty(&container, &spec, &input_rx, &output_tx);
+        // код выставляем до того, как вывод кончится (output_tx падает после)
+        *shell_exit_code.lock().unwrap() = Some(code);
+    });
+
+    ExecSession {
+        output: Box::new(FakeTtyOutput {
+            chunks: output_rx,
+            pending: Vec::new(),
+        }),
+        input: Box::new(FakeTtyInput(input_tx)),
+        control: Box::new(FakeTtyControl {
+            id: id.to_string(),
+            calls,
+            exit_code,
+        }),
+    }
+}
+
+fn run_fake_tty(
+    container: &FakeContainer,
+    spec: &ExecSpec,
+    input: &Receiver<Vec<u8>>,
+    output: &SyncSender<Vec<u8>>,
+) -> i64 {
+    // вывод команды на терминал; false -- читатель ушёл, дальше работать незачем
+    let run = |argv: Vec<String>| -> Option<i64> {
+        let command = ExecSpec {
+            argv,
+            ..spec.clone()
+        };
+        for event in fake_exec(container, &command) {
+            match event {
+                Ok(ExecEvent::Output(_, data)) => {
+                    let text = String::from_utf8_lossy(&data).replace('\n', "\r\n");
+                    output.send(text.into_bytes()).ok()?;
+                }
+                Ok(ExecEvent::Exit(code)) => return Some(code),
+                Err(_) => return Some(1),
+            }
+        }
+        Some(0)
+    };
+
+    let shell = spec.argv[0].rsplit('/').next().unwrap_or_default();
+    if !matches!(shell, "sh" | "bash" | "ash") || spec.argv.len() > 1 {
+        return run(spec.argv.clone()).unwrap_or(0);
+    }
+
+    if output.send(b"$ ".to_vec()).is_err() {
+        return 0;
+    }
+    let mut line: Vec<u8> = Vec::new();
+    let mut last_code: i64 = 0;
+    // stdin закрыт -- как Ctrl-D
+    while let Ok(chunk) = input.recv() {
+        for byte in chunk {
+            match byte {
+                b'\r' | b'\n' => {
+                    let _ = output.send(b"\r\n".to_vec());
+                    let text = String::from_utf8_lossy(&line).to_string();
+                    line.clear();
+                    let argv: Vec<String> = text.split_whitespace().map(str::to_string).collect();
+                    match argv.first().map(String::as_str) {
+                        None => {}
+                        Some("exit") => {
+                            return argv
+                                .get(1)
+                                .and_then(|code| code.parse().ok())
+                                .unwrap_or(last_code);
+                        }
+                        Some(_) => match run(argv) {
+                            Some(code) => last_code = code,
+                            None => return last_code,
+                        },
+                    }
+                    if output.send(b"$ ".to_vec()).is_err() {
+                        return last_code;
+                    }
+                }
+                0x04 if line.is_empty() => return last_code,
+                byte => {
+                    line.push(byte);
+                    let _ = output.send(vec![byte]);
+                }
+            }
+        }
+    }
+    last_code
+}
+
+struct FakeTtyInput(Sender<Vec<u8>>);
+
+impl Write for FakeTtyInput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .send(buf.to_vec())
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "exec has exited"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct FakeTtyOutput {
+    chunks: Receiver<Vec<u8>>,
+    pending: Vec<u8>, // кусок, не влезший в прошлый read
+}
+
+impl Read for FakeTtyOutput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pending.is_empty() {
+            match self.chunks.recv() {
+                Ok(chunk) => self.pending = chunk,
+                Err(_) => return Ok(0), // сессия завершилась
+            }
+        }
+        let n = buf.len().min(self.pending.len());
+        buf[..n].copy_from_slice(&self.pending[..n]);
+        self.pending.drain(..n);
+        Ok(n)
+    }
+}
+
+struct FakeTtyControl {
+    id: String, // как его назвали в exec_tty -- для calls()
+    calls: Arc<Mutex<Vec<String>>>,
+    exit_code: Arc<Mutex<Option<i64>>>,
+}
+
+impl ExecControl for FakeTtyControl {
+    fn resize(&self, size: TtySize) -> Result<(), ContainerError> {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("resize {} {}x{}", self.id, size.rows, size.cols));
+        Ok(())
+    }
+
+    fn exit_code(&self) -> Result<Option<i64>, ContainerError> {
+        Ok(*self.exit_code.lock().unwrap())
+    }
+}
+
+impl FakeBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_container(self, label: &str, image: &str, status: ContainerStatus) -> Self {
+        self.add(FakeContainer::new(label, image, status));
+        self
+    }
+
+    // Добавляем контейнер или заменяем контейнер с тем же именем (на его же месте в списке)
+    pub fn add(&self, container: FakeContainer) {
+        let mut containers = self.containers.lock().unwrap();
+        match containers
+            .iter_mut()
+            .find(|c| c.info.label == container.info.label)
+        {
+            Some(existing) => *existing = container,
+            None => containers.push(container),
+        }
+    }
+
+    // Меняем статус «снаружи» -- как будто контейнер упал или демон начал его перезапуск
+    pub fn set_status(&self, label: &str, status: ContainerStatus) {
+        if let Some(mut container) = self.find(label) {
+            container.set_status(status);
+            self.add(container);
+        }
+    }
+
+    // Следующий вызов op вернёт error (ошибки одной операции отдаются по очереди)
+    pub fn fail_next(&self, op: FakeOp, error: ContainerError) {
+        self.failures
+            .lock()
+            .unwrap()
+            .entry(op)
+            .or_default()
+            .push_back(error);
+    }
+
+    // Контейнер пишет строку в stdout/stderr (с текущим временем)
+    pub fn push_log(&self, label: &str, source: LogSource, text: &str) {
+        if let Some(mut container) = self.find(label) {
+            container.logs.push(LogLine {
+                source,
+                timestamp: Some(Utc::now()),
+                text: text.to_string(),
+            });
+            self.add(container);
+        }
+    }
+
+    // Что покажет stats, пока контейнер работает
+    pub fn set_stats(&self, label: &str, stats: ContainerStats) {
+        if let Some(mut container) = self.find(label) {
+            container.stats = stats;
+            self.add(container);
+        }
+    }
+
+    pub fn status(&self, label: &str) -> Option<ContainerStatus> {
+        self.find(label).map(|c| c.info.status)
+    }
+
+    pub fn container(&self, label: &str) -> Option<FakeContainer> {
+        self.find(label)
+    }
+
+    // Все вызовы рантайма по порядку: "list", "inspect web1", "start web1", "kill web1 SIGHUP", ...
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    // Сколько потоков из exec ещё живы: брошенный поток (клиент ушёл, таймаут) должен
+    // закрываться, а не висеть до конца команды
+    pub fn running_execs(&self) -> usize {
+        self.running_execs.load(Ordering::SeqCst)
+    }
+
+    // Как и docker, находим контейнер и по имени, и по id
+    fn find(&self, label: &str) -> Option<FakeContainer> {
+        self.containers
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|c| c.info.label == label || c.info.id == label)
+            .cloned()
+    }
+
+    // Общее начало каждой операции: записываем вызов и отдаём подложенную ошибку, если есть
+    fn begin(&self, op: FakeOp, id: Option<&str>) -> Result<(), ContainerError> {
+        let name = format!("{op:?}").to_lowercase();
+        self.calls.lock().unwrap().push(match id {
+            Some(id) => format!("{name} {id}"),
+            None => name,
+        });
+
+        match self.failures.lock().unwrap().get_mut(&op) {
+            Some(queue) => queue.pop_front().map_or(Ok(()), Err),
+            None => Ok(()),
+        }
+    }
+
+    fn get(&self, id: &str) -> Result<FakeContainer, ContainerError> {
+        self.find(id).ok_or(ContainerError::DockerError(format!(
+            "No such container: {id}"
+        )))
+    }
+
+    // Действие над контейнером: transition решает, каким станет статус (или что вернёт демон).
+    // args -- параметры действия для calls(), "" если их нет
+    fn action(
+        &self,
+        op: FakeOp,
+        id: &str,
+        args: &str,
+        transition: fn(ContainerStatus) -> Result<Option<ContainerStatus>, String>,
+    ) -> Result<(), ContainerError> {
+        self.begin(op, Some(format!("{id} {args}").trim_end()))?;
+        let mut container = self.get(id)?;
+        // Ok(None) -- демон ничего не делает (304 Not Modified)
+        if let Some(status) =
+            transition(container.info.status).map_err(ContainerError::DockerError)?
+        {
+            container.set_status(status);
+            self.add(container);
+        }
+        Ok(())
+    }
+}
+
+impl ContainerBackend for FakeBackend {
+    fn list(&self) -> Result<Vec<ContainerInfo>, ContainerError> {
+        self.begin(FakeOp::List, None)?;
+        Ok(self
+            .containers
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|c| c.info.clone())
+            .collect())
+    }
+
+    fn inspect(&self, id: &str) -> Result<ContainerState, ContainerError> {
+        self.begin(FakeOp::Inspect, Some(id))?;
+        Ok(self.get(id)?.state)
+    }
+
+    fn inspect_details(&self, id: &str) -> Result<ContainerDetails, ContainerError> {
+        self.begin(FakeOp::Inspect, Some(id))?;
+        let container = self.get(id)?;
+        Ok(ContainerDetails {
+            id: container.info.id,
+            name: container.info.label,
+            image: container.info.image,
+            created: container.info.created,
+            state: container.state,
+            ..container.details
+        })
+    }
+
+    // follow ничего не ждёт: отдаём то, что уже есть, и поток заканчивается
+    fn logs(&self, id: &str, options: &LogOptions) -> Result<LogStream, ContainerError> {
+        self.begin(FakeOp::Logs, Some(id))?;
+        let mut lines: Vec<LogLine> = self
+            .get(id)?
+            .logs
+            .into_iter()
+            .filter(|line| match line.source {
+                LogSource::Stdout => options.stdout,
+                LogSource::Stderr => options.stderr,
+            })
+            .filter(|line| {
+                options
+                    .since
+                    .is_none_or(|since| line.timestamp >= Some(since))
+            })
+            .filter(|line| {
+                options
+                    .until
+                    .is_none_or(|until| line.timestamp < Some(until))
+            })
+            .collect();
+        if let Some(tail) = options.tail {
+            lines.drain(..lines.len().saturating_sub(tail));
+        }
+        Ok(LogStream::new(lines.into_iter().map(Ok)))
+    }
+
+    fn exec(&self, id: &str, spec: &ExecSpec) -> Result<ExecStream, ContainerError> {
+        self.begin(FakeOp::Exec, Some(&format!("{id} {}", spec.argv.join(" "))))?;
+        let container = self.get(id)?;
+        if !container.state.is_running || container.state.is_paused {
+            return Err(ContainerError::DockerError(format!(
+                "container {id} is not running"
+            )));
+        }
+        let events = fake_exec(&container, spec);
+        let canceller = events.canceller();
+        self.running_execs.fetch_add(1, Ordering::SeqCst);
+        Ok(ExecStream::new(FakeExecStream {
+            events,
+            running: self.running_execs.clone(),
+        })
+        .with_canceller(move || canceller.cancel()))
+    }
+
+    // Размер окна фейковому терминалу не важен: он только попадает в calls при resize
+    fn exec_tty(
+        &self,
+        id: &str,
+        spec: &ExecSpec,
+        _size: TtySize,
+    ) -> Result<ExecSession, ContainerError> {
+        self.begin(FakeOp::Exec, Some(&format!("{id} {}", spec.argv.join(" "))))?;
+        let container = self.get(id)?;
+        if !container.state.is_running || container.state.is_paused {
+            return Err(ContainerError::DockerError(format!(
+                "container {id} is not running"
+            )));
+        }
+        Ok(fake_tty_session(
+            id,
+            container,
+            spec.clone(),
+            self.calls.clone(),
+        ))
+    }
+
+    fn stats(&self, id: &str) -> Result<ContainerStats, ContainerError> {
+        self.begin(FakeOp::Stats, Some(id))?;
+        let container = self.get(id)?;
+        let stats = if container.state.is_running {
+            container.stats
+        } else {
+            ContainerStats::default()
+        };
+        Ok(ContainerStats {
+            id: container.info.id,
+            name: container.info.label,
+            read_at: Some(Utc::now()),
+            ..stats
+        })
+    }
+
+    // Как демон: имя занято -- ошибка, без имени -- придумываем своё
+    fn create(&self, spec: &ContainerSpec) -> Result<String, ContainerError> {
+        let name: String = match &spec.name {
+            Some(name) => name.clone(),
+            None => format!("fake_{}", self.containers.lock().unwrap().len() + 1),
+        };
+        self.begin(FakeOp::Create, Some(&name))?;
+        if self.find(&name).is_some() {
+            return Err(ContainerError::DockerError(format!(
+                "Conflict. The container name \"/{name}\" is already in use"
+            )));
+        }
+
+        let mut container = FakeContainer::new(&name, &spec.image, ContainerStatus::Created);
+        container.info.command = spec.command.join(" ");
+        container.info.ports = spec.ports.clone();
+        container.info.labels = spec.labels.clone();
+        container.info.mounts = spec
+            .volumes
+            .iter()
+            .map(|volume| volume.name.clone().unwrap_or(volume.source.clone()))
+            .collect();
+        container.details.command = spec.command.clone();
+        container.details.env = spec.env.clone();
+        container.details.mounts = spec.volumes.clone();
+        container.details.network.ports = spec.ports.clone();
+        container.details.restart_policy = spec.restart_policy.clone();
+        container.details.resources = spec.resources.clone();
+
+        let id = container.info.id.clone();
+        self.add(container);
+        Ok(id)
+    }
+
+    fn start(&self, id: &str) -> Result<(), ContainerError> {
+        self.action(FakeOp::Start, id, "", |status| match status {
+            ContainerStatus::Dead => Err("cannot start a dead container".to_string()),
+            ContainerStatus::RemovalInProgress => {
+                Err("container is marked for removal and cannot be started".to_string())
+            }
+            ContainerStatus::Up | ContainerStatus::Paused | ContainerStatus::Restarting => Ok(None),
+            ContainerStatus::Created | ContainerStatus::Exited => Ok(Some(ContainerStatus::Up)),
+        })
+    }
+
+    fn stop(&self, id: &str, timeout: Option<Duration>) -> Result<(), ContainerError> {
+        self.action(
+            FakeOp::Stop,
+            id,
+            &timeout_arg(timeout),
+            |status| match status {
+                ContainerStatus::Up | ContainerStatus::Paused | ContainerStatus::Restarting => {
+                    Ok(Some(ContainerStatus::Exited))
+                }
+                _ => Ok(None),
+            },
+        )
+    }
+
+    fn restart(&self, id: &str, timeout: Option<Duration>) -> Result<(), ContainerError> {
+        self.action(
+            FakeOp::Restart,
+            id,
+            &timeout_arg(timeout),
+            |status| match status {
+                ContainerStatus::Dead => Err("cannot restart a dead container".to_string()),
+                ContainerStatus::RemovalInProgress => {
+                    Err("container is marked for removal and cannot be started".to_string())
+                }
+                _ => Ok(Some(ContainerStatus::Up)),
+            },
+        )
+    }
+
+    // Работающий контейнер удаляется только с force, удаляемый второй раз -- никогда
+    fn remove(&self, id: &str, options: &RemoveOptions) -> Result<(), ContainerError> {
+        let args = [(options.force, "force"), (options.volumes, "volumes")]
+            .iter()
+            .filter(|(on, _)| *on)
+            .fold(id.to_string(), |call, (_, arg)| format!("{call} {arg}"));
+        self.begin(FakeOp::Remove, Some(&args))?;
+        let container = self.get(id)?;
+        match container.info.status {
+            ContainerStatus::RemovalInProgress => {
+                return Err(ContainerError::DockerError(format!(
+                    "removal of container {id} is already in progress"
+                )));
+            }
+            status if status.is_running() && !options.force => {
+                return Err(ContainerError::DockerError(format!(
+                    "cannot remove running container {id}: stop the container before removing or force remove"
+                )));
+            }
+            _ => {}
+        }
+        self.containers
+            .lock()
+            .unwrap()
+            .retain(|c| c.info.id != container.info.id);
+        Ok(())
+    }
+
+    fn pause(&self, id: &str) -> Result<(), ContainerError> {
+        self.action(FakeOp::Pause, id, "", |status| match status {
+            ContainerStatus::Up => Ok(Some(ContainerStatus::Paused)),
+            ContainerStatus::Paused => Err("container is already paused".to_string()),
+            ContainerStatus::Restarting => {
+                Err("container is restarting, wait until the container is running".to_string())
+            }
+            _ => Err("container is not running".to_string()),
+        })
+    }
+
+    fn unpause(&self, id: &str) -> Result<(), ContainerError> {
+        self.action(FakeOp::Unpause, id, "", |status| match status {
+            ContainerStatus::Paused => Ok(Some(ContainerStatus::Up)),
+            _ => Err("container is not paused".to_string()),
+        })
+    }
+
+    // SIGKILL, SIGTERM и SIGINT завершают контейнер с кодом 128 + номер сигнала,
+    // остальные он «обрабатывает» и продолжает работать (nginx на SIGHUP перечитывает конфиг)
+    fn kill(&self, id: &str, signal: &str) -> Result<(), ContainerError> {
+        self.begin(FakeOp::Kill, Some(&format!("{id} {signal}")))?;
+        let mut container = self.get(id)?;
+        if !container.state.is_running {
+            return Err(ContainerError::DockerError(format!(
+                "container {id} is not running"
+            )));
+        }
+        let (name, number) = parse_signal(signal).ok_or(ContainerError::DockerError(format!(
+            "invalid signal: {signal}"
+        )))?;
+        if matches!(name, "SIGKILL" | "SIGTERM" | "SIGINT") {
+            container.state.exit_code = Some(128 + i64::from(number));
+            container.set_status(ContainerStatus::Exited);
+            self.add(container);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transitions_and_failures() -> Result<(), ContainerError> {
+        let fake = FakeBackend::new()
+            .with_container("web1", "nginx", ContainerStatus::Exited)
+            .with_container("zombie", "redis", ContainerStatus::Dead);
+
+        assert!(!fake.inspect("web1")?.is_running);
+        fake.start("web1")?;
+        let state = fake.inspect("web1")?;
+        assert_eq!(state.status, "running");
+        assert!(state.is_running && state.started_at.is_some());
+
+        fake.stop("web1", Some(Duration::from_secs(5)))?;
+        assert_eq!(fake.status("web1"), Some(ContainerStatus::Exited));
+        assert!(fake.inspect("web1")?.finished_at.is_some());
+
+        assert!(fake.start("zombie").is_err());
+        assert!(fake.inspect("ghost").is_err());
+
+        fake.start("web1")?;
+        assert!(fake.remove("web1", &RemoveOptions::default()).is_err());
+        fake.remove(
+            "web1",
+            &RemoveOptions {
+                force: true,
+                volumes: true,
+            },
+        )?;
+        assert_eq!(fake.status("web1"), None);
+
+        fake.fail_next(
+            FakeOp::List,
+            ContainerError::DockerError("down".to_string()),
+        );
+        assert_eq!(
+            fake.list(),
+            Err(ContainerError::DockerError("down".to_string()))
+        );
+        assert_eq!(fake.list()?.len(), 1); // ошибка была одноразовой
+
+        assert_eq!(
+            fake.calls(),
+            vec![
+                "inspect web1",
+                "start web1",
+                "inspect web1",
+                "stop web1 5s",
+                "inspect web1",
+                "start zombie",
+                "inspect ghost",
+                "start web1",
+                "remove web1",
+                "remove web1 force volumes",
+                "list",
+                "list",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn tty_session() -> Result<(), ContainerError> {
+        let fake = FakeBackend::new().with_container("web1", "alpine", ContainerStatus::Up);
+        let spec = |argv: &[&str]| ExecSpec {
+            argv: argv.iter().map(|arg| arg.to_string()).collect(),
+            env: Vec::new(),
+            workdir: None,
+            user: None,
+        };
+
+        let mut session = fake.exec_tty("web1", &spec(&["/bin/sh"]), TtySize::default())?;
+        session.control.resize(TtySize {
+            rows: 40,
+            cols: 120,
+        })?;
+        session.input.write_all(b"echo hi\rexit 3\r").unwrap();
+        let mut output = String::new();
+        session.output.read_to_string(&mut output).unwrap();
+        assert_eq!(output, "$ echo hi\r\nhi\r\n$ exit 3\r\n");
+        assert_eq!(session.control.exit_code()?, Some(3));
+
+        // не оболочка -- команда выполняется один раз, stdin не нужен
+        let mut session = fake.exec_tty("web1", &spec(&["ls"]), TtySize::default())?;
+        let mut output = String::new();
+        session.output.read_to_string(&mut output).unwrap();
+        assert!(output.contains("executable file not found"));
+        assert_eq!(session.control.exit_code()?, Some(127));
+
+        // закрыли stdin -- оболочка выходит, как по Ctrl-D
+        let session = fake.exec_tty("web1", &spec(&["bash"]), TtySize::default())?;
+        let ExecSession {
+            mut output,
+            input,
+            control,
+        } = session;
+        drop(input);
+        let mut rest = Vec::new();
+        output.read_to_end(&mut rest).unwrap();
+        assert_eq!(control.exit_code()?, Some(0));
+
+        fake.set_status("web1", ContainerStatus::Paused);
+        assert!(
+            fake.exec_tty("web1", &spec(&["sh"]), TtySize::default())
+                .is_err()
+        );
+
+        assert_eq!(
+            fake.calls(),
+            vec![
+                "exec web1 /bin/sh",
+                "resize web1 40x120",
+                "exec web1 ls",
+                "exec web1 bash",
+                "exec web1 sh",
+            ]
+        );
+        Ok(())
+    }
+}
@@ -1,16 +1,50 @@
-use crate::lib::docker_works::{ContainerError, ContainerInfo, parse_docker_ps_a};
+use std::io::{Read, Write};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, sync_channel};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::lib::api_error::ApiError;
+use crate::lib::app_state::AppState;
+use crate::lib::container_backend::{
+    Canceller, ContainerBackend, ContainerDetails, ContainerState, ContainerStats, ExecControl,
+    ExecEvent, ExecSession, ExecStream, LogLine, LogOptions, LogSource, LogStream, RemoveOptions,
+    TtySize, parse_signal,
+};
+use crate::lib::container_spec::{ContainerSpec, ExecSpec};
+use crate::lib::docker_works::{ContainerError, ContainerInfo, ContainerStatus, PortBinding};
+use crate::lib::headers::HeaderMap;
 use crate::lib::logger::Logger;
 // структура для информации про один мой контейнер
-use crate::lib::req_res_structs::{BodyType, Response}; // стрктура ответа
+use crate::lib::req_res_structs::{BodyType, Response, StatusCode}; // стрктура ответа
 use crate::lib::request::Request; // структура запроса
+use crate::lib::websocket::{self, Handshake, Message, WebSocket, WebSocketSender};
 use serde_json;
 
-pub fn handler_return_all_containers(_request: &Request) -> Response {
+pub fn handler_return_all_containers(state: &AppState, request: &Request) -> Response {
     // Нужно обработать request и вернуть Response
     // Данный handler должен возвращать весь вектор ContainerInfo
+    // (или только подходящие под фильтры: /container/?status=running&image=nginx)
+
+    let filters: ListFilters = match ListFilters::from_request(request) {
+        Ok(filters) => filters,
+        Err(e) => {
+            state.log.debug(&format!("Invalid container filters: {e}"));
+            return e.into_response(request);
+        }
+    };
+
+    // ?size=true -- с размером контейнеров (docker считает его долго, поэтому только по запросу)
+    let with_size: bool = match request.query.get_bool("size") {
+        Ok(with_size) => with_size.unwrap_or(false),
+        Err(e) => return ApiError::from(e).into_response(request),
+    };
 
-    // parse_docker_ps_a возвращает Result<Vec<ContainerInfo>, ContainerError>
-    let result: Result<Vec<ContainerInfo>, ContainerError> = parse_docker_ps_a();
+    // backend.list() возвращает Result<Vec<ContainerInfo>, ContainerError>
+    let result: Result<Vec<ContainerInfo>, ContainerError> = if with_size {
+        state.backend.list_with_size()
+    } else {
+        state.backend.list()
+    };
 
     match result {
         Ok(all_my_containers) => {
@@ -31,13 +65,29 @@ pub fn handler_return_all_containers(_request: &Request) -> Response {
                 Object(Map<String, Value>),
             }
             */
-            for one_container in all_my_containers {
+            for one_container in all_my_containers
+                .into_iter()
+                .filter(|container| filters.matches(container))
+            {
                 // итератор по вектору с контейнерами
                 let description_for_label: serde_json::Value = serde_json::json!({ // это один объект типа serde_json:Value
                     "name": one_container.label,                  // вносим имя (name, он же label)
                     "status": format!("{}",one_container.status), // преобразуем сначала в String с помощью пользовательского вывода
                     "command": one_container.command,             // вносим command
                     "image": one_container.image,                 // вносим image
+                    "id": one_container.id,
+                    "status_text": one_container.status_text,     // "Up 3 hours (healthy)" как в docker ps
+                    "created": one_container.created.map(|time| time.to_rfc3339()),
+                    "uptime_seconds": one_container.uptime,       // null, если не запущен
+                    "exit_code": one_container.exit_code,         // null, если ещё работает
+                    "ports": one_container.ports.iter().map(port_json).collect::<Vec<serde_json::Value>>(),
+                    "labels": one_container.labels,
+                    "mounts": one_container.mounts,
+                    "networks": one_container.networks,
+                    "size": one_container.size.map(|size| serde_json::json!({ // null без ?size=true
+                        "rw": size.rw,
+                        "root_fs": size.root_fs,
+                    })),
                 });
 
                 arr.push(description_for_label);
@@ -80,7 +130,7 @@ pub fn handler_return_all_containers(_request: &Request) -> Response {
             // Оборачиваем наш Map<String, serde_json::Value> в Value::Object,
             // чтобы получить единый JSON‑объект (serde_json::Value), с которым
             // уже могут работать все функции сериализации из serde_json. */
-            println!("{json_body:?}");
+            state.log.debug(&format!("{json_body:?}"));
 
             // подсчиатем количество символов в serde_json::Value
             //let string: String = serde_json::to_string(&json_body).expect("serde_json::to_string error"); // чтобы отбросить ошибку
@@ -89,240 +139,304 @@ pub fn handler_return_all_containers(_request: &Request) -> Response {
 
             let resp: Response = Response {
                 // мой возвращаемый Response
-                response_code: 200,
-                headers: None, // заголовки Content-Type: application/json и Content-Length: {number} будут добавлены в функции deser_response
+                response_code: StatusCode::OK,
+                headers: HeaderMap::new(), // заголовки Content-Type: application/json и Content-Length: {number} будут добавлены в функции deser_response
                 body: Some(BodyType::Json(json_body)),
             };
 
             resp
         }
         Err(e) => {
-            let logger = Logger::default();
-            logger.error(&format!("Container error: {e}"));
-            Response {
-                // мой возвращаемый Response
-                response_code: 500,
-                headers: None, // заголовки Content-Type: application/json и Content-Length: {number} будут добавлены в функции deser_response
-                body: None,
-            }
+            state.log.error(&format!("Container error: {e}"));
+            ApiError::from(e).into_response(request)
         }
     }
 }
 
-fn check_existence_container(container_id: &str) -> Response {
-    let rezult: Result<Vec<ContainerInfo>, ContainerError> = parse_docker_ps_a();
+// Фильтры для списка контейнеров из строки запроса.
+// Несколько значений одного фильтра объединяются через ИЛИ, разные фильтры -- через И:
+// ?status=running&status=paused&image=nginx -- (running или paused) и образ nginx
+#[derive(Default)]
+struct ListFilters {
+    statuses: Vec<ContainerStatus>,
+    images: Vec<String>,
+}
+
+impl ListFilters {
+    fn from_request(request: &Request) -> Result<Self, ApiError> {
+        let statuses = request
+            .query
+            .get_all("status")
+            .into_iter()
+            .map(|status| {
+                ContainerStatus::from_docker_state(status).ok_or(ApiError::new(
+                    StatusCode::BAD_REQUEST,
+                    "invalid_filter",
+                    format!("Unknown container status: {status:?}"),
+                ))
+            })
+            .collect::<Result<Vec<ContainerStatus>, ApiError>>()?;
+
+        let images = request
+            .query
+            .get_all("image")
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+
+        Ok(Self { statuses, images })
+    }
+
+    fn matches(&self, container: &ContainerInfo) -> bool {
+        let status_ok = self.statuses.is_empty() || self.statuses.contains(&container.status);
+        // "nginx" подходит и для "nginx", и для "nginx:1.27"
+        let image_ok = self.images.is_empty()
+            || self.images.iter().any(|image| {
+                container.image == *image
+                    || container
+                        .image
+                        .strip_prefix(image.as_str())
+                        .is_some_and(|tag| tag.starts_with(':'))
+            });
+
+        status_ok && image_ok
+    }
+}
+
+fn check_existence_container(state: &AppState, container_id: &str) -> Result<(), ApiError> {
+    let rezult: Result<Vec<ContainerInfo>, ContainerError> = state.backend.list();
 
     match rezult {
         Ok(all_my_containers) => {
-            let mut flag: bool = false;
-            for one_container in all_my_containers {
-                if one_container.label.as_str() == container_id {
-                    flag = true;
-                }
-            }
+            // :id -- имя или полный id контейнера
+            let flag: bool = all_my_containers
+                .iter()
+                .any(|one_container| one_container.matches(container_id));
 
             if !flag {
-                let logger = Logger::default();
-                logger.error(&format!("Can't find container {container_id}:"));
-                return Response {
-                    // мой возвращаемый Response
-                    response_code: 500, // Internal Server Error
-                    headers: None, // заголовки Content-Type: application/json и Content-Length: {number} будут добавлены в функции deser_response
-                    body: None,
-                };
+                // клиент ошибся в id -- это не сбой сервера
+                state
+                    .log
+                    .warn(&format!("Can't find container {container_id}"));
+                return Err(ApiError::new(
+                    StatusCode::NOT_FOUND,
+                    "container_not_found",
+                    format!("Container {container_id} does not exist"),
+                )
+                .with_container(container_id));
             }
         }
         Err(e) => {
-            let logger = Logger::default();
-            logger.error(&format!("Container error: {e}"));
-            return Response {
-                // мой возвращаемый Response
-                response_code: 500, // Internal Server Error
-                headers: None, // заголовки Content-Type: application/json и Content-Length: {number} будут добавлены в функции deser_response
-                body: None,
-            };
+            state.log.error(&format!("Container error: {e}"));
+            return Err(ApiError::from(e).with_container(container_id));
         }
     };
-    Response {
-        // мой возвращаемый Response
-        response_code: 200, // Ok - нашли
-        headers: None, // заголовки Content-Type: application/json и Content-Length: {number} будут добавлены в функции deser_response
-        body: None,
-    }
+    Ok(()) // нашли
 }
 
-fn get_container_id(request: &Request) -> Result<& str, Response> {
+fn get_container_id<'a>(state: &AppState, request: &'a Request) -> Result<&'a str, ApiError> {
     request
         .rest_params
         .get("id")
         .map(|s| s.as_str())
         .ok_or_else(|| {
-            let logger = Logger::default();
-            logger.warn(&format!("Failed to find container_id (name)!"));
-            Response {
-                response_code: 400,
-                headers: None,
-                body: None,
-            }
+            state
+                .log
+                .warn(&"Failed to find container_id (name)!".to_string());
+            ApiError::bad_request("Container id is missing in the path")
         })
 }
 
-struct ReadStatus {
-    status: String,
-    is_running: bool,
-    is_paused: bool,
-    is_restarting: bool,
-    is_dead: bool,
-}
-
-impl Default for ReadStatus {
-    fn default() -> Self {
-        Self {
-            status: String::default(),
-            is_running: false,
-            is_paused: false,
-            is_restarting: false,
-            is_dead: false,
-        }
-    }
+// 409 Conflict: контейнер в состоянии, в котором действие невозможно
+fn conflict(
+    request: &Request,
+    container_id: &str,
+    code: &'static str,
+    message: String,
+) -> Response {
+    ApiError::new(StatusCode::CONFLICT, code, message)
+        .with_container(container_id)
+        .into_response(request)
 }
 
-fn fill_struct_read_status(container_id: &str) -> Result<ReadStatus, Response> {
-    // ------------------------------------------------------------------
-    // ------ №1. Запускаем docker inspect ------------------------------
-    // ------------------------------------------------------------------
-    let inspect: Result<std::process::Output, std::io::Error> =
-        std::process::Command::new("docker")
-            .arg("inspect") // positional agumets: для осмотра контейнера (выводит всю возможную информацию)
-            .arg("-f") // short flag: format = в каком формате искать
-            .arg("{{.State.Status}} {{.State.Running}} {{.State.Paused}} {{.State.Restarting}} {{.State.Dead}}") // значение для опции -f: вернёт поле State.Running
-            .arg(container_id)
-            .output();
-
+fn fill_struct_read_status(
+    state: &AppState,
+    container_id: &str,
+) -> Result<ContainerDetails, ApiError> {
     /* Что сделает `docker start`:
     + 1. `Dead = true`       ⇒ контейнер «мертв», поднять его не получится → 409 Conflict
     + 2. `Restarting = true` ⇒ контейнер уже в состоянии запуска/перезапуска → 409 Conflict
+    Кроме флагов состояния тут же всё остальное из docker inspect -- для GET /container/:id
     */
-
-    // Распакуем inspect:
-    let inspect_output = match inspect {
-        Ok(out) => out,
-        Err(_e) => {
-            return Err(Response {
-                // мой возвращаемый Responce (используем return, чтобы ничего не вернуть в переменную inspect_output, а сразу выйти из функции)
-                response_code: 500, // Internal Server Error
-                headers: None,
-                body: None,
-            });
-        }
-    };
-    // ------------------------------------------------------------------
-    // ------ №2. Преобразуем stdout в String и разбиваем по пробелам ---
-    // ------------------------------------------------------------------
-    //let raw = &inspect_output.stdout;          // &[u8]
-    let state_str: String = String::from_utf8_lossy(&inspect_output.stdout).into_owned();
-    // &output.stdout -- обращаюсь к памяти, в которой хранится std::process::Output
-    // from_utf8_lossy -- вернет Result<String, FromUtf8Error>
-    // .into_owned() -- Извлекает данные (String)
-
-    let mut parts: std::str::SplitWhitespace<'_> = state_str.split_whitespace(); // .slit_whitespace() -- разобьём String по пробелам
-    // ------------------------------------------------------------------
-    // ------ №3. Наполняем ReadStatus ------------------------------------
-    // ------------------------------------------------------------------
-    let mut new_data: ReadStatus = ReadStatus::default();
-    new_data.status = parts.next().unwrap().to_string();
-    // .next() возвращает Option<&str> т.е. следующая подстрока из split_whitespace() (в обертке Option)
-    // .unwrap_or()
-    new_data.is_running = parts
-        .next() // .next() -- возвращает Option<&str> т.е. следующая подстрока из split_whitespace() (в обертке Option)
-        .unwrap_or("false") // .unwrap_or("false") -- подставим false, если Option::None
-        .parse::<bool>() // .parse::<bool>() -- конвертируем true/false в bool (возвращает Result<bool, ParseBoolError>)
-        .unwrap_or(false); // .unwrap_or(false) -- если .parse вернула error, то будем использовать false по умолчанию
-    new_data.is_paused = parts
-        .next()
-        .unwrap_or("false")
-        .parse::<bool>()
-        .unwrap_or(false);
-    new_data.is_restarting = parts
-        .next()
-        .unwrap_or("false")
-        .parse::<bool>()
-        .unwrap_or(false);
-    new_data.is_dead = parts
-        .next()
-        .unwrap_or("false")
-        .parse::<bool>()
-        .unwrap_or(false);
-    return Ok(new_data);
+    state.backend.inspect_details(container_id).map_err(|e| {
+        state
+            .log
+            .error(&format!("Failed to inspect container {container_id}: {e}"));
+        ApiError::from(e).with_container(container_id)
+    })
 }
 
 fn do_docker_command(
+    state: &AppState,
+    request: &Request,
     container_id: &str,
     word_in_present_simple: &str,
     word_in_past_simple: &str,
+    command: impl FnOnce(&dyn ContainerBackend, &str) -> Result<(), ContainerError>,
 ) -> Response {
-    let docker_start: Result<std::process::Output, std::io::Error> =
-        std::process::Command::new("docker")
-            .arg(word_in_present_simple.to_string()) // аргумент для stop контейнера
-            .arg(container_id) // аргумент для имени контейнера, который stop
-            .output(); // .output() блокирует текущий поток, пока процесс не будет завершен
-    // output возвращает Result<_,std::io::Error>.
-    // output - запуск нашей команды: docker <action> <label>
-
-    match docker_start {
-        Ok(_) => {
-            let logger: Logger = Logger::default();
-            logger.info(&format!(
+    // command -- сама операция: |backend, id| backend.start(id) и т.п.
+    match command(state.backend.as_ref(), container_id) {
+        Ok(()) => {
+            state.log.info(&format!(
                 "Sucessfully {word_in_past_simple} container {container_id}!",
             ));
             Response {
-                response_code: 200, // Ok (успешно stopped)
-                headers: None,
+                response_code: StatusCode::OK, // Ok (успешно stopped)
+                headers: HeaderMap::new(),
                 body: None,
             }
         }
+        // рантайм вернул ошибку -- её текст и отдаём клиенту
         Err(e) => {
-            let logger: Logger = Logger::default();
-            logger.error(&format!(
+            state.log.error(&format!(
                 "Failed to {word_in_present_simple} container {container_id}: {e}",
             ));
-            Response {
-                response_code: 500, // Internal Server Error
-                headers: None,
-                body: None,
+            ApiError::from(e)
+                .with_container(container_id)
+                .into_response(request)
+        }
+    }
+}
+
+pub fn handler_create_container(state: &AppState, request: &Request) -> Response {
+    // POST /container с JSON-спецификацией (см. ContainerSpec) -- создаём контейнер, не запуская.
+    // 201 Created: {"id": "...", "name": "web2"} и Location: /container/web2
+
+    // ------------------------------------------------------------------
+    // ------ №1. Разберём и проверим спецификацию из тела --------------
+    // ------------------------------------------------------------------
+    let json: &serde_json::Value = match &request.body {
+        Some(BodyType::Json(json)) => json,
+        _ => {
+            state
+                .log
+                .debug(&"Container spec is missing or is not JSON".to_string());
+            return ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "invalid_spec",
+                "Request body must be a JSON container spec",
+            )
+            .into_response(request);
+        }
+    };
+
+    let spec: ContainerSpec = match ContainerSpec::from_json(json) {
+        Ok(spec) => spec,
+        Err(e) => {
+            state.log.debug(&format!("Invalid container spec: {e}"));
+            return ApiError::from(e).into_response(request);
+        }
+    };
+
+    // ------------------------------------------------------------------
+    // ------ №2. Проверим, что имя ещё не занято -----------------------
+    // ------------------------------------------------------------------
+    if let Some(name) = &spec.name {
+        match state.backend.exists(name) {
+            Ok(false) => {}
+            Ok(true) => {
+                state.log.warn(&format!("Container {name} already exists!"));
+                return conflict(
+                    request,
+                    name,
+                    "container_exists",
+                    format!("Container {name} already exists"),
+                );
+            }
+            Err(e) => {
+                state.log.error(&format!("Container error: {e}"));
+                return ApiError::from(e)
+                    .with_container(name)
+                    .into_response(request);
+            }
+        }
+    }
+
+    // ------------------------------------------------------------------
+    // ------ №3. Создадим контейнер ------------------------------------
+    // ------------------------------------------------------------------
+    let id: String = match state.backend.create(&spec) {
+        Ok(id) => id,
+        Err(e) => {
+            state.log.error(&format!(
+                "Failed to create container from {}: {e}",
+                spec.image
+            ));
+            let error = ApiError::from(e);
+            return match &spec.name {
+                Some(name) => error.with_container(name),
+                None => error,
             }
+            .into_response(request);
         }
+    };
+
+    // без имени в спецификации его придумал docker -- спросим какое
+    let name: String = match &spec.name {
+        Some(name) => name.clone(),
+        None => match state.backend.inspect_details(&id) {
+            Ok(details) => details.name,
+            Err(e) => {
+                state
+                    .log
+                    .error(&format!("Failed to inspect created container {id}: {e}"));
+                return ApiError::from(e).into_response(request);
+            }
+        },
+    };
+    state.log.info(&format!(
+        "Sucessfully created container {name} ({id}) from {}!",
+        spec.image
+    ));
+
+    let mut headers = HeaderMap::new();
+    let _ = headers.insert("Location", &format!("/container/{name}"));
+    Response {
+        response_code: StatusCode::CREATED,
+        headers,
+        body: Some(BodyType::Json(serde_json::json!({
+            "id": id,
+            "name": name,
+        }))),
     }
 }
 
-pub fn handler_start_container(request: &Request) -> Response {
+pub fn handler_start_container(state: &AppState, request: &Request) -> Response {
     // Нужно передать команду на start контейнеру с определенным label = :id
     // :id хранится в request в поле rest_params (это Hash-table)
 
     // ------------------------------------------------------------------
     // ------ №1. Получим id контейнера из request ----------------------
     // ------------------------------------------------------------------
-    let container_id = match get_container_id(request) {
+    let container_id = match get_container_id(state, request) {
         Ok(id) => id,
-        Err(resp) => return resp,
+        Err(e) => return e.into_response(request),
     };
 
     // ------------------------------------------------------------------
     // ------ №2. Проверим, что такой id (<label>) существует------------
     // ------------------------------------------------------------------
-    let resp_check: Response = check_existence_container(container_id);
-    if resp_check.response_code == 500 {
-        // Internal Server Error
-        return resp_check;
+    if let Err(e) = check_existence_container(state, container_id) {
+        return e.into_response(request);
     }
 
     // ------------------------------------------------------------------
     // ------ №3. Проверим статусы контейнера ---------------------------
     // ------------------------------------------------------------------
 
-    let my_data: ReadStatus = match fill_struct_read_status(container_id) {
-        Ok(data) => data,
-        Err(resp) => return resp,
+    let my_data: ContainerState = match fill_struct_read_status(state, container_id) {
+        Ok(data) => data.state,
+        Err(e) => return e.into_response(request),
     };
 
     // ------------------------------------------------------------------
@@ -335,76 +449,87 @@ pub fn handler_start_container(request: &Request) -> Response {
     + 3. `Running = true`    ⇒ контейнер уже запущен → 409 Conflict */
 
     if my_data.is_dead {
-        let logger: Logger = Logger::default();
-        logger.warn(&format!(
+        state.log.warn(&format!(
             "Failed to start container {container_id}. It is dead!"
         ));
-        return Response {
-            response_code: 409, // Conflict
-            headers: None,
-            body: None,
-        };
+        return conflict(
+            request,
+            container_id,
+            "container_dead",
+            format!("Container {container_id} is dead and can't be started"),
+        );
     }
 
     if my_data.is_restarting {
-        let logger: Logger = Logger::default();
-        logger.warn(&format!(
+        state.log.warn(&format!(
             "Failed to start container {container_id}. It is restarting!"
         ));
-        return Response {
-            response_code: 409, // Conflict
-            headers: None,
-            body: None,
-        };
+        return conflict(
+            request,
+            container_id,
+            "container_restarting",
+            format!("Container {container_id} is restarting"),
+        );
     }
 
     if my_data.is_running {
-        let logger: Logger = Logger::default();
-        logger.warn(&format!(
+        state.log.warn(&format!(
             "Failed to start container {container_id}. It is alredy running! Use restart instead!"
         ));
-        return Response {
-            response_code: 409, // Conflict
-            headers: None,
-            body: None,
-        };
+        return conflict(
+            request,
+            container_id,
+            "container_running",
+            format!("Container {container_id} is already running, use restart instead"),
+        );
     }
 
     // ------------------------------------------------------------------
     // ------ №5. Выполним команду docker <command> <label> -------------
     // ------------------------------------------------------------------
-    do_docker_command(container_id, "start", "started")
+    do_docker_command(
+        state,
+        request,
+        container_id,
+        "start",
+        "started",
+        |backend, id| backend.start(id),
+    )
     // все остальные случаи: просто выполняем команду docker start <label>
 }
 
-pub fn handler_stop_container(request: &Request) -> Response {
+pub fn handler_stop_container(state: &AppState, request: &Request) -> Response {
     // Нужно передать команду stop контейнеру с определенным label = :id
     // :id хранится в request в поле rest_params (это Hash-table)
+    // ?timeout=N -- сколько секунд ждать после SIGTERM до SIGKILL (иначе из конфига)
 
     // ------------------------------------------------------------------
-    // ------ №1. Получим id контейнера из request ----------------------
+    // ------ №1. Получим id контейнера и таймаут из request ------------
     // ------------------------------------------------------------------
-    let container_id = match get_container_id(request) {
+    let container_id = match get_container_id(state, request) {
         Ok(id) => id,
-        Err(resp) => return resp,
+        Err(e) => return e.into_response(request),
+    };
+
+    let timeout: Option<Duration> = match stop_timeout(state, request) {
+        Ok(timeout) => timeout,
+        Err(e) => return e.with_container(container_id).into_response(request),
     };
 
     // ------------------------------------------------------------------
     // ------ №2. Проверим, что такой id (<label>) существует------------
     // ------------------------------------------------------------------
-    let resp_check: Response = check_existence_container(container_id);
-    if resp_check.response_code == 500 {
-        // Internal Server Error
-        return resp_check;
+    if let Err(e) = check_existence_container(state, container_id) {
+        return e.into_response(request);
     }
 
     // ------------------------------------------------------------------
     // ------ №3. Проверим статусы контейнера ---------------------------
     // ------------------------------------------------------------------
 
-    let my_data: ReadStatus = match fill_struct_read_status(container_id) {
-        Ok(data) => data,
-        Err(resp) => return resp,
+    let my_data: ContainerState = match fill_struct_read_status(state, container_id) {
+        Ok(data) => data.state,
+        Err(e) => return e.into_response(request),
     };
 
     // ------------------------------------------------------------------
@@ -420,25 +545,27 @@ pub fn handler_stop_container(request: &Request) -> Response {
     5. `Running = true` → шлёт SIGTERM (и по таймауту SIGKILL) → 200 OK */
 
     if my_data.status.starts_with("exited") {
-        let logger: Logger = Logger::default();
-        logger.warn(&format!("Container {container_id} is already stopped!"));
-        return Response {
-            response_code: 409, // Conflict
-            headers: None,
-            body: None,
-        };
+        state
+            .log
+            .warn(&format!("Container {container_id} is already stopped!"));
+        return conflict(
+            request,
+            container_id,
+            "container_not_running",
+            format!("Container {container_id} is already stopped"),
+        );
     }
 
     if my_data.is_dead {
-        let logger: Logger = Logger::default();
-        logger.warn(&format!(
+        state.log.warn(&format!(
             "Failed to stop container {container_id}. It is dead!"
         ));
-        return Response {
-            response_code: 409, // Conflict
-            headers: None,
-            body: None,
-        };
+        return conflict(
+            request,
+            container_id,
+            "container_dead",
+            format!("Container {container_id} is dead and can't be stopped"),
+        );
     }
 
     // ------------------------------------------------------------------
@@ -446,37 +573,48 @@ pub fn handler_stop_container(request: &Request) -> Response {
     // ------------------------------------------------------------------
 
     // все остальные случаи: просто выполняем команду docker stop <label>
-    do_docker_command(container_id, "stop", "stopped")
+    do_docker_command(
+        state,
+        request,
+        container_id,
+        "stop",
+        "stopped",
+        |backend, id| backend.stop(id, timeout),
+    )
 }
 
-pub fn handler_restart_container(request: &Request) -> Response {
-    // Нужно передать команду stop контейнеру с определенным label = :id
+pub fn handler_restart_container(state: &AppState, request: &Request) -> Response {
+    // Нужно передать команду restart контейнеру с определенным label = :id
     // :id хранится в request в поле rest_params (это Hash-table)
+    // ?timeout=N -- как у stop: сколько ждать остановки перед новым запуском
 
     // ------------------------------------------------------------------
-    // ------ №1. Получим id контейнера из request ----------------------
+    // ------ №1. Получим id контейнера и таймаут из request ------------
     // ------------------------------------------------------------------
-    let container_id = match get_container_id(request) {
+    let container_id = match get_container_id(state, request) {
         Ok(id) => id,
-        Err(resp) => return resp,
+        Err(e) => return e.into_response(request),
+    };
+
+    let timeout: Option<Duration> = match stop_timeout(state, request) {
+        Ok(timeout) => timeout,
+        Err(e) => return e.with_container(container_id).into_response(request),
     };
 
     // ------------------------------------------------------------------
     // ------ №2. Проверим, что такой id (<label>) существует------------
     // ------------------------------------------------------------------
-    let resp_check: Response = check_existence_container(container_id);
-    if resp_check.response_code == 500 {
-        // Internal Server Error
-        return resp_check;
+    if let Err(e) = check_existence_container(state, container_id) {
+        return e.into_response(request);
     }
 
     // ------------------------------------------------------------------
     // ------ №3. Проверим статусы контейнера ---------------------------
     // ------------------------------------------------------------------
 
-    let my_data: ReadStatus = match fill_struct_read_status(container_id) {
-        Ok(data) => data,
-        Err(resp) => return resp,
+    let my_data: ContainerState = match fill_struct_read_status(state, container_id) {
+        Ok(data) => data.state,
+        Err(e) => return e.into_response(request),
     };
 
     // ------------------------------------------------------------------
@@ -491,32 +629,3411 @@ pub fn handler_restart_container(request: &Request) -> Response {
     4. Paused = true => docker restart сделает unpaused, затем restart.
     5. Exited/Created => docker restart сделает start.*/
     if my_data.is_dead {
-        let logger: Logger = Logger::default();
-        logger.warn(&format!(
+        state.log.warn(&format!(
             "Failed to restart container {container_id}. It is dead!"
         ));
-        return Response {
-            response_code: 409, // Conflict
-            headers: None,
-            body: None,
-        };
+        return conflict(
+            request,
+            container_id,
+            "container_dead",
+            format!("Container {container_id} is dead and can't be restarted"),
+        );
     }
 
     if my_data.is_restarting {
-        let logger: Logger = Logger::default();
-        logger.warn(&format!(
-            "Failed to restart container {container_id}. It is restarting!"            
+        state.log.warn(&format!(
+            "Failed to restart container {container_id}. It is restarting!"
         ));
-        return Response {
-            response_code: 409, // Conflict
-            headers: None,
-            body: None,
-        };
+        return conflict(
+            request,
+            container_id,
+            "container_restarting",
+            format!("Container {container_id} is already restarting"),
+        );
     }
     // ------------------------------------------------------------------
     // ------ №5. Выполним команду docker <command> <label> -------------
     // ------------------------------------------------------------------
 
     // все остальные случаи: просто выполняем команду docker restart <label>
-    do_docker_command(container_id, "restart", "restarted")
+    do_docker_command(
+        state,
+        request,
+        container_id,
+        "restart",
+        "restarted",
+        |backend, id| backend.restart(id, timeout),
+    )
+}
+
+pub fn handler_pause_container(state: &AppState, request: &Request) -> Response {
+    // Нужно заморозить все процессы контейнера с label = :id (docker pause)
+
+    // ------------------------------------------------------------------
+    // ------ №1. Получим id контейнера из request ----------------------
+    // ------------------------------------------------------------------
+    let container_id = match get_container_id(state, request) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(request),
+    };
+
+    // ------------------------------------------------------------------
+    // ------ №2. Проверим, что такой id (<label>) существует------------
+    // ------------------------------------------------------------------
+    if let Err(e) = check_existence_container(state, container_id) {
+        return e.into_response(request);
+    }
+
+    // ------------------------------------------------------------------
+    // ------ №3. Проверим статусы контейнера ---------------------------
+    // ------------------------------------------------------------------
+    let my_data: ContainerState = match fill_struct_read_status(state, container_id) {
+        Ok(data) => data.state,
+        Err(e) => return e.into_response(request),
+    };
+
+    // ------------------------------------------------------------------
+    // ------ №4. Обработаем интересующие статусы контейнера ------------
+    // ------------------------------------------------------------------
+
+    /* Что сделает `docker pause`:
+    1. `Dead = true`       → процесса нет, замораживать нечего → 409 Conflict
+    2. `Paused = true`     → “Container is already paused” → 409 Conflict
+    3. `Restarting = true` → “Container is restarting, wait until the container is running” → 409 Conflict
+    4. `Running = false` (exited, created) → “Container is not running” → 409 Conflict
+    Остаётся только работающий контейнер → 200 OK */
+
+    if my_data.is_dead {
+        state.log.warn(&format!(
+            "Failed to pause container {container_id}. It is dead!"
+        ));
+        return conflict(
+            request,
+            container_id,
+            "container_dead",
+            format!("Container {container_id} is dead and can't be paused"),
+        );
+    }
+
+    if my_data.is_paused {
+        state
+            .log
+            .warn(&format!("Container {container_id} is already paused!"));
+        return conflict(
+            request,
+            container_id,
+            "container_paused",
+            format!("Container {container_id} is already paused"),
+        );
+    }
+
+    if my_data.is_restarting {
+        state.log.warn(&format!(
+            "Failed to pause container {container_id}. It is restarting!"
+        ));
+        return conflict(
+            request,
+            container_id,
+            "container_restarting",
+            format!("Container {container_id} is restarting, wait until it is running"),
+        );
+    }
+
+    if !my_data.is_running {
+        state.log.warn(&format!(
+            "Failed to pause container {container_id}. It is not running!"
+        ));
+        return conflict(
+            request,
+            container_id,
+            "container_not_running",
+            format!("Container {container_id} is not running"),
+        );
+    }
+
+    // ------------------------------------------------------------------
+    // ------ №5. Выполним команду docker pause <label> -----------------
+    // ------------------------------------------------------------------
+    do_docker_command(
+        state,
+        request,
+        container_id,
+        "pause",
+        "paused",
+        |backend, id| backend.pause(id),
+    )
+}
+
+pub fn handler_unpause_container(state: &AppState, request: &Request) -> Response {
+    // Нужно разморозить процессы контейнера с label = :id (docker unpause)
+
+    // ------------------------------------------------------------------
+    // ------ №1. Получим id контейнера из request ----------------------
+    // ------------------------------------------------------------------
+    let container_id = match get_container_id(state, request) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(request),
+    };
+
+    // ------------------------------------------------------------------
+    // ------ №2. Проверим, что такой id (<label>) существует------------
+    // ------------------------------------------------------------------
+    if let Err(e) = check_existence_container(state, container_id) {
+        return e.into_response(request);
+    }
+
+    // ------------------------------------------------------------------
+    // ------ №3. Проверим статусы контейнера ---------------------------
+    // ------------------------------------------------------------------
+    let my_data: ContainerState = match fill_struct_read_status(state, container_id) {
+        Ok(data) => data.state,
+        Err(e) => return e.into_response(request),
+    };
+
+    // ------------------------------------------------------------------
+    // ------ №4. Обработаем интересующие статусы контейнера ------------
+    // ------------------------------------------------------------------
+
+    /* Что сделает `docker unpause`:
+    1. `Paused = false` (в том числе dead, exited, created) → “Container is not paused” → 409 Conflict
+    2. `Paused = true`  → размораживает процессы → 200 OK */
+
+    if !my_data.is_paused {
+        state.log.warn(&format!(
+            "Failed to unpause container {container_id}. It is not paused!"
+        ));
+        return conflict(
+            request,
+            container_id,
+            "container_not_paused",
+            format!("Container {container_id} is not paused"),
+        );
+    }
+
+    // ------------------------------------------------------------------
+    // ------ №5. Выполним команду docker unpause <label> ---------------
+    // ------------------------------------------------------------------
+    do_docker_command(
+        state,
+        request,
+        container_id,
+        "unpause",
+        "unpaused",
+        |backend, id| backend.unpause(id),
+    )
+}
+
+pub fn handler_kill_container(state: &AppState, request: &Request) -> Response {
+    // Нужно послать сигнал контейнеру с label = :id (docker kill --signal)
+    // ?signal=SIGHUP (или HUP, или 1); без него -- SIGKILL, как у docker kill
+
+    // ------------------------------------------------------------------
+    // ------ №1. Получим id контейнера и сигнал из request -------------
+    // ------------------------------------------------------------------
+    let container_id = match get_container_id(state, request) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(request),
+    };
+
+    let signal: &str = match request.query.get("signal") {
+        None => "SIGKILL",
+        Some(raw) => {
+            match parse_signal(raw) {
+                Some((name, _)) => name,
+                None => {
+                    state.log.debug(&format!("Invalid signal {raw:?}"));
+                    return ApiError::new(
+                    StatusCode::BAD_REQUEST,
+                    "invalid_parameter",
+                    format!("signal must be a signal name (SIGHUP, TERM) or number 1-31, got {raw:?}"),
+                )
+                .with_container(container_id)
+                .into_response(request);
+                }
+            }
+        }
+    };
+
+    // ------------------------------------------------------------------
+    // ------ №2. Проверим, что такой id (<label>) существует------------
+    // ------------------------------------------------------------------
+    if let Err(e) = check_existence_container(state, container_id) {
+        return e.into_response(request);
+    }
+
+    // ------------------------------------------------------------------
+    // ------ №3. Проверим статусы контейнера ---------------------------
+    // ------------------------------------------------------------------
+    let my_data: ContainerState = match fill_struct_read_status(state, container_id) {
+        Ok(data) => data.state,
+        Err(e) => return e.into_response(request),
+    };
+
+    // ------------------------------------------------------------------
+    // ------ №4. Обработаем интересующие статусы контейнера ------------
+    // ------------------------------------------------------------------
+
+    /* Что сделает `docker kill`:
+    1. `Dead = true`      → процесса нет → 409 Conflict
+    2. `Running = false`  → “Container is not running” → 409 Conflict
+    Сам справится:
+    3. `Paused = true`    → сигнал дойдёт после разморозки (SIGKILL -- сразу) → 200 OK
+    4. `Running = true`   → шлёт сигнал главному процессу → 200 OK */
+
+    if my_data.is_dead {
+        state.log.warn(&format!(
+            "Failed to kill container {container_id}. It is dead!"
+        ));
+        return conflict(
+            request,
+            container_id,
+            "container_dead",
+            format!("Container {container_id} is dead"),
+        );
+    }
+
+    if !my_data.is_running {
+        state.log.warn(&format!(
+            "Failed to kill container {container_id}. It is not running!"
+        ));
+        return conflict(
+            request,
+            container_id,
+            "container_not_running",
+            format!("Container {container_id} is not running"),
+        );
+    }
+
+    // ------------------------------------------------------------------
+    // ------ №5. Выполним команду docker kill --signal <signal> <label> -
+    // ------------------------------------------------------------------
+    do_docker_command(
+        state,
+        request,
+        container_id,
+        &format!("send {signal} to"),
+        &format!("sent {signal} to"),
+        |backend, id| backend.kill(id, signal),
+    )
+}
+
+pub fn handler_remove_container(state: &AppState, request: &Request) -> Response {
+    // Нужно удалить контейнер с label = :id (docker rm)
+    // ?force=true -- работающий сначала убить, ?volumes=true -- заодно удалить его анонимные тома
+
+    // ------------------------------------------------------------------
+    // ------ №1. Получим id контейнера и флаги из request --------------
+    // ------------------------------------------------------------------
+    let container_id = match get_container_id(state, request) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(request),
+    };
+
+    let options = match (
+        request.query.get_bool("force"),
+        request.query.get_bool("volumes"),
+    ) {
+        (Ok(force), Ok(volumes)) => RemoveOptions {
+            force: force.unwrap_or(false),
+            volumes: volumes.unwrap_or(false),
+        },
+        (Err(e), _) | (_, Err(e)) => {
+            state.log.debug(&format!("Invalid remove options: {e}"));
+            return ApiError::from(e)
+                .with_container(container_id)
+                .into_response(request);
+        }
+    };
+
+    // ------------------------------------------------------------------
+    // ------ №2. Проверим, что такой id (<label>) существует------------
+    // ------------------------------------------------------------------
+    if let Err(e) = check_existence_container(state, container_id) {
+        return e.into_response(request);
+    }
+
+    // ------------------------------------------------------------------
+    // ------ №3. Проверим статусы контейнера ---------------------------
+    // ------------------------------------------------------------------
+    let my_data: ContainerState = match fill_struct_read_status(state, container_id) {
+        Ok(data) => data.state,
+        Err(e) => return e.into_response(request),
+    };
+
+    // ------------------------------------------------------------------
+    // ------ №4. Обработаем интересующие статусы контейнера ------------
+    // ------------------------------------------------------------------
+
+    /* Что сделает `docker rm`:
+    1. `Status = removing`  → “removal of container is already in progress” → 409 Conflict (даже с force)
+    2. `Running = true` (в том числе paused, restarting) без --force → “stop the container before removing” → 409 Conflict
+    Сам справится:
+    3. `Running = true` с --force → убивает (SIGKILL) и удаляет → 200 OK
+    4. exited, created, dead → удаляет → 200 OK */
+
+    if my_data.status == ContainerStatus::RemovalInProgress.docker_state() {
+        state.log.warn(&format!(
+            "Failed to remove container {container_id}. It is already being removed!"
+        ));
+        return conflict(
+            request,
+            container_id,
+            "container_removing",
+            format!("Removal of container {container_id} is already in progress"),
+        );
+    }
+
+    if my_data.is_running && !options.force {
+        state.log.warn(&format!(
+            "Failed to remove container {container_id}. It is running!"
+        ));
+        return conflict(
+            request,
+            container_id,
+            "container_running",
+            format!("Container {container_id} is running: stop it first or remove with force=true"),
+        );
+    }
+
+    // ------------------------------------------------------------------
+    // ------ №5. Выполним команду docker rm [--force] [--volumes] <label>
+    // ------------------------------------------------------------------
+    do_docker_command(
+        state,
+        request,
+        container_id,
+        "remove",
+        "removed",
+        |backend, id| backend.remove(id, &options),
+    )
+}
+
+// ?timeout=N для stop и restart (целые секунды, как у docker stop -t), иначе -- из конфига
+fn stop_timeout(state: &AppState, request: &Request) -> Result<Option<Duration>, ApiError> {
+    match request.query.get("timeout") {
+        None => Ok(state.config.stop_timeout),
+        Some(raw) => raw
+            .parse::<u64>()
+            .ok()
+            .filter(|&secs| secs <= MAX_STOP_TIMEOUT)
+            .map(|secs| Some(Duration::from_secs(secs)))
+            .ok_or(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "invalid_parameter",
+                format!(
+                    "timeout must be a number of seconds from 0 to {MAX_STOP_TIMEOUT}, got {raw:?}"
+                ),
+            )),
+    }
+}
+
+// Пока контейнер останавливается, воркер занят -- больше часа ждать не даём
+const MAX_STOP_TIMEOUT: u64 = 3600;
+
+pub fn handler_inspect_container(state: &AppState, request: &Request) -> Response {
+    // GET /container/:id -- один контейнер со всем, что знает о нём docker inspect
+
+    // ------ №1. Получим id контейнера из request ----------------------
+    let container_id = match get_container_id(state, request) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(request),
+    };
+
+    // ------ №2. Проверим, что такой id (<label>) существует------------
+    if let Err(e) = check_existence_container(state, container_id) {
+        return e.into_response(request);
+    }
+
+    // ------ №3. Прочитаем всё состояние контейнера --------------------
+    let details: ContainerDetails = match fill_struct_read_status(state, container_id) {
+        Ok(details) => details,
+        Err(e) => return e.into_response(request),
+    };
+
+    let container_state = &details.state;
+    let time = |time: &Option<chrono::DateTime<chrono::Utc>>| time.map(|t| t.to_rfc3339());
+    let json_body: serde_json::Value = serde_json::json!({
+        "id": details.id,
+        "name": details.name,
+        "image": details.image,
+        "created": time(&details.created),
+        "command": details.command,
+        "state": {
+            "status": container_state.status,
+            "running": container_state.is_running,
+            "paused": container_state.is_paused,
+            "restarting": container_state.is_restarting,
+            "dead": container_state.is_dead,
+            "oom_killed": container_state.oom_killed,
+            "pid": container_state.pid,
+            "exit_code": container_state.exit_code,
+            "error": container_state.error,
+            "started_at": time(&container_state.started_at),
+            "finished_at": time(&container_state.finished_at),
+        },
+        "restart_count": container_state.restart_count,
+        "restart_policy": {
+            "name": details.restart_policy.name,
+            "max_retries": details.restart_policy.max_retries,
+        },
+        "health": details.health.as_ref().map(|health| serde_json::json!({
+            "status": health.status,
+            "failing_streak": health.failing_streak,
+            "last_exit_code": health.last_exit_code,
+            "last_output": health.last_output,
+        })),
+        // значения секретов (пароли, токены, ключи) наружу не отдаём
+        "env": details.env.iter().map(|(name, value)| {
+            if is_secret_env(name) {
+                format!("{name}={SECRET_MASK}")
+            } else {
+                format!("{name}={}", mask_url_credentials(value))
+            }
+        }).collect::<Vec<String>>(),
+        "mounts": details.mounts.iter().map(|mount| serde_json::json!({
+            "type": mount.kind,
+            "name": mount.name,
+            "source": mount.source,
+            "destination": mount.destination,
+            "read_only": mount.read_only,
+        })).collect::<Vec<serde_json::Value>>(),
+        "network": {
+            "mode": details.network.mode,
+            "ports": details.network.ports.iter().map(port_json).collect::<Vec<serde_json::Value>>(),
+            "networks": details.network.networks.iter().map(|network| serde_json::json!({
+                "name": network.name,
+                "ip_address": network.ip_address,
+                "gateway": network.gateway,
+                "mac_address": network.mac_address,
+            })).collect::<Vec<serde_json::Value>>(),
+        },
+        "resources": {
+            "memory": details.resources.memory,
+            "memory_reservation": details.resources.memory_reservation,
+            "memory_swap": details.resources.memory_swap,
+            "nano_cpus": details.resources.nano_cpus,
+            "cpu_shares": details.resources.cpu_shares,
+            "cpu_quota": details.resources.cpu_quota,
+            "cpu_period": details.resources.cpu_period,
+            "cpuset_cpus": details.resources.cpuset_cpus,
+            "pids_limit": details.resources.pids_limit,
+        },
+    });
+
+    Response {
+        response_code: StatusCode::OK,
+        headers: HeaderMap::new(),
+        body: Some(BodyType::Json(json_body)),
+    }
+}
+
+const SECRET_MASK: &str = "********";
+
+// Переменные окружения, значения которых похожи на секреты:
+// DB_PASSWORD, GITHUB_TOKEN, AWS_SECRET_ACCESS_KEY, API_KEY, authToken, ...
+// Маркер должен быть целым словом имени: COMPASS_URL, PASSENGER_PORT или KEYBOARD -- не секреты
+fn is_secret_env(name: &str) -> bool {
+    const MARKERS: [&str; 13] = [
+        "PASSWORD",
+        "PASSWORDS",
+        "PASSWD",
+        "PASS",
+        "SECRET",
+        "SECRETS",
+        "TOKEN",
+        "TOKENS",
+        "KEY",
+        "KEYS",
+        "CREDENTIAL",
+        "CREDENTIALS",
+        "AUTH",
+    ];
+    env_name_words(name)
+        .iter()
+        .any(|word| MARKERS.contains(&word.as_str()))
+}
+
+// Слова имени переменной в верхнем регистре: разделители '_', '-', '.' и граница
+// camelCase -- "jwt_secret" -> [JWT, SECRET], "AuthToken" -> [AUTH, TOKEN]
+fn env_name_words(name: &str) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    let mut word = String::new();
+    let mut prev_lower = false;
+    for c in name.chars() {
+        let separator = matches!(c, '_' | '-' | '.');
+        if (separator || (c.is_ascii_uppercase() && prev_lower)) && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        if !separator {
+            word.push(c.to_ascii_uppercase());
+        }
+        prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+// Пароль может прийти и в значении с обычным именем: DATABASE_URL=postgres://u:pw@db/app.
+// Логин и пароль (userinfo) в каждом URL заменяем маской: postgres://********@db/app
+fn mask_url_credentials(value: &str) -> String {
+    let mut masked = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("://") {
+        let (head, tail) = rest.split_at(start + "://".len());
+        masked.push_str(head);
+        // authority -- до пути, query, фрагмента или следующего URL в списке
+        let authority_end = tail.find(['/', '?', '#', ',', ' ']).unwrap_or(tail.len());
+        rest = match tail[..authority_end].rfind('@') {
+            Some(at) => {
+                masked.push_str(SECRET_MASK);
+                &tail[at..]
+            }
+            None => tail,
+        };
+    }
+    masked.push_str(rest);
+    masked
+}
+
+// Порт в том же виде, что и в списке контейнеров
+fn port_json(port: &PortBinding) -> serde_json::Value {
+    serde_json::json!({
+        "ip": port.ip,
+        "private_port": port.private_port,
+        "public_port": port.public_port,
+        "protocol": port.protocol,
+    })
+}
+
+pub fn handler_container_logs(state: &AppState, request: &Request) -> Response {
+    // GET /container/:id/logs?tail=100&since=10m&timestamps=true&stderr=false&follow=true
+    // Строки уходят клиенту по мере чтения (chunked), с follow -- пока клиент не отключится.
+    // Accept: text/event-stream (или ?format=sse) -- вместо текста Server-Sent Events
+
+    // ------ №1. Получим id контейнера и параметры из request ----------
+    let container_id = match get_container_id(state, request) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(request),
+    };
+
+    let params: LogParams = match LogParams::from_request(request) {
+        Ok(params) => params,
+        Err(e) => {
+            state.log.debug(&format!("Invalid log parameters: {e}"));
+            return e.with_container(container_id).into_response(request);
+        }
+    };
+
+    // ------ №2. Проверим, что такой id (<label>) существует------------
+    if let Err(e) = check_existence_container(state, container_id) {
+        return e.into_response(request);
+    }
+
+    // ------ №3. Откроем поток логов -----------------------------------
+    let lines: LogStream = match state.backend.logs(container_id, &params.options) {
+        Ok(lines) => lines,
+        Err(e) => {
+            state.log.error(&format!(
+                "Failed to read logs of container {container_id}: {e}"
+            ));
+            return ApiError::from(e)
+                .with_container(container_id)
+                .into_response(request);
+        }
+    };
+
+    state.log.debug(&format!(
+        "Streaming logs of container {container_id} (follow: {})",
+        params.options.follow
+    ));
+
+    let timestamps: bool = params.timestamps;
+    let event = move |line: LogLine| (line.source.as_str(), format_log_line(&line, timestamps));
+    // с follow контейнер может молчать сколько угодно -- пока строк нет, шлём heartbeat
+    let events: EventStream = if params.options.follow {
+        Box::new(
+            FollowLines::start(lines, LOG_HEARTBEAT_INTERVAL)
+                .map(move |line| line.map(|line| line.map(event))),
+        )
+    } else {
+        Box::new(lines.map(move |line| line.map(|line| Some(event(line)))))
+    };
+    EventBody::response(
+        events,
+        params.sse,
+        "text/plain; charset=utf-8",
+        state.log,
+        format!("logs of container {container_id}"),
+    )
+}
+
+// Как часто напоминаем о себе клиенту, пока логи с follow молчат
+const LOG_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+// Логи с follow читаем в отдельном потоке: иначе на молчащем контейнере воркер навсегда
+// застрянет в чтении и так и не узнает, что клиент давно ушёл -- это видно только при
+// записи. Пока строк нет, раз в interval отдаём None (EventBody шлёт heartbeat).
+// Итератор бросили (запись не удалась) -- прерываем поток бэкенда через Canceller
+struct FollowLines {
+    lines: Receiver<Result<LogLine, ContainerError>>,
+    interval: Duration,
+    canceller: Canceller,
+}
+
+impl FollowLines {
+    fn start(stream: LogStream, interval: Duration) -> Self {
+        let canceller = stream.canceller();
+        let (sender, lines) = sync_channel(16);
+        std::thread::spawn(move || {
+            for line in stream {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            lines,
+            interval,
+            canceller,
+        }
+    }
+}
+
+impl Iterator for FollowLines {
+    type Item = Result<Option<LogLine>, ContainerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.lines.recv_timeout(self.interval) {
+            Ok(line) => Some(line.map(Some)),
+            Err(RecvTimeoutError::Timeout) => Some(Ok(None)),
+            Err(RecvTimeoutError::Disconnected) => None,
+        }
+    }
+}
+
+impl Drop for FollowLines {
+    fn drop(&mut self) {
+        self.canceller.cancel();
+    }
+}
+
+// Параметры GET /container/:id/logs
+struct LogParams {
+    options: LogOptions,
+    timestamps: bool, // печатать время перед строкой (как docker logs -t)
+    sse: bool,
+}
+
+impl LogParams {
+    fn from_request(request: &Request) -> Result<Self, ApiError> {
+        let query = &request.query;
+        let invalid =
+            |message: String| ApiError::new(StatusCode::BAD_REQUEST, "invalid_parameter", message);
+
+        let tail: Option<usize> =
+            match query.get("tail") {
+                None | Some("all") => None,
+                Some(tail) => Some(tail.parse().map_err(|_| {
+                    invalid(format!("tail must be a number or \"all\", got {tail:?}"))
+                })?),
+            };
+        let now = chrono::Utc::now();
+        let time = |name: &str| -> Result<Option<chrono::DateTime<chrono::Utc>>, ApiError> {
+            query
+                .get(name)
+                .map(|raw| {
+                    parse_log_time(raw, now).ok_or(invalid(format!(
+                        "{name} must be RFC 3339, unix time or relative (10m, 2h), got {raw:?}"
+                    )))
+                })
+                .transpose()
+        };
+
+        let options = LogOptions {
+            tail,
+            since: time("since")?,
+            until: time("until")?,
+            stdout: query.get_bool("stdout")?.unwrap_or(true),
+            stderr: query.get_bool("stderr")?.unwrap_or(true),
+            follow: query.get_bool("follow")?.unwrap_or(false),
+        };
+        if !options.stdout && !options.stderr {
+            return Err(invalid(
+                "At least one of stdout and stderr must be selected".to_string(),
+            ));
+        }
+
+        let sse: bool = wants_sse(request)?;
+
+        Ok(Self {
+            options,
+            timestamps: query.get_bool("timestamps")?.unwrap_or(false),
+            sse,
+        })
+    }
+}
+
+// Время для since/until, как у docker logs:
+// "2024-05-01T10:00:00Z", "1714557600" (unix), "1714557600.5", "10m" / "2h" / "30s" / "1d" (столько назад)
+fn parse_log_time(
+    raw: &str,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Option<chrono::DateTime<chrono::Utc>> {
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(raw) {
+        return Some(time.with_timezone(&chrono::Utc));
+    }
+    if let Ok(secs) = raw.parse::<f64>() {
+        return (secs >= 0.0).then(|| {
+            chrono::DateTime::from_timestamp(secs.trunc() as i64, (secs.fract() * 1e9) as u32)
+        })?;
+    }
+
+    // последний символ -- по границе char: raw.len() - 1 на "1é" попал бы внутрь 'é'
+    let (unit_at, _) = raw.char_indices().last()?;
+    let (count, unit) = raw.split_at(unit_at);
+    let count: i64 = count.parse().ok().filter(|&n| n >= 0)?;
+    let ago = match unit {
+        "s" => chrono::Duration::try_seconds(count),
+        "m" => chrono::Duration::try_minutes(count),
+        "h" => chrono::Duration::try_hours(count),
+        "d" => chrono::Duration::try_days(count),
+        _ => None,
+    }?;
+    now.checked_sub_signed(ago)
+}
+
+pub fn handler_container_stats(state: &AppState, request: &Request) -> Response {
+    // GET /container/:id/stats -- CPU, память, сеть, диск и число процессов одного контейнера.
+    // ?stream=true -- снимок каждые interval секунд (NDJSON или SSE), пока клиент не отключится
+
+    let container_id = match get_container_id(state, request) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(request),
+    };
+
+    let params: StatsParams = match StatsParams::from_request(request) {
+        Ok(params) => params,
+        Err(e) => {
+            state.log.debug(&format!("Invalid stats parameters: {e}"));
+            return e.with_container(container_id).into_response(request);
+        }
+    };
+
+    if let Err(e) = check_existence_container(state, container_id) {
+        return e.into_response(request);
+    }
+
+    // первый снимок берём сразу: ошибка рантайма ещё может стать статусом ответа
+    let first: ContainerStats = match state.backend.stats(container_id) {
+        Ok(stats) => stats,
+        Err(e) => {
+            state.log.error(&format!(
+                "Failed to read stats of container {container_id}: {e}"
+            ));
+            return ApiError::from(e)
+                .with_container(container_id)
+                .into_response(request);
+        }
+    };
+
+    if !params.stream {
+        return json_response(stats_json(&first));
+    }
+
+    state.log.debug(&format!(
+        "Streaming stats of container {container_id} every {:?}",
+        params.interval
+    ));
+    let backend = state.backend.clone();
+    let id: String = container_id.to_string();
+    let snapshots = poll_every(params.interval, first, move || backend.stats(&id));
+    EventBody::response(
+        Box::new(
+            snapshots
+                .map(|stats| stats.map(|stats| Some(("stats", stats_json(&stats).to_string())))),
+        ),
+        params.sse,
+        NDJSON,
+        state.log,
+        format!("stats of container {container_id}"),
+    )
+}
+
+pub fn handler_all_container_stats(state: &AppState, request: &Request) -> Response {
+    // GET /container/stats -- то же для всех работающих контейнеров: массив снимков,
+    // в потоке -- массив на каждый опрос
+
+    let params: StatsParams = match StatsParams::from_request(request) {
+        Ok(params) => params,
+        Err(e) => {
+            state.log.debug(&format!("Invalid stats parameters: {e}"));
+            return e.into_response(request);
+        }
+    };
+
+    let first: Vec<ContainerStats> = match state.backend.stats_all() {
+        Ok(all) => all,
+        Err(e) => {
+            state
+                .log
+                .error(&format!("Failed to read container stats: {e}"));
+            return ApiError::from(e).into_response(request);
+        }
+    };
+
+    if !params.stream {
+        return json_response(all_stats_json(&first));
+    }
+
+    state.log.debug(&format!(
+        "Streaming stats of all containers every {:?}",
+        params.interval
+    ));
+    let backend = state.backend.clone();
+    let snapshots = poll_every(params.interval, first, move || backend.stats_all());
+    EventBody::response(
+        Box::new(
+            snapshots.map(|all| all.map(|all| Some(("stats", all_stats_json(&all).to_string())))),
+        ),
+        params.sse,
+        NDJSON,
+        state.log,
+        "stats of all containers".to_string(),
+    )
+}
+
+pub fn handler_exec_container(state: &AppState, request: &Request) -> Response {
+    // POST /container/:id/exec с JSON-командой (см. ExecSpec) -- запускаем её в работающем контейнере:
+    // {"exit_code": 0, "stdout": "...", "stderr": "...", "timed_out": false, "truncated": false, "duration_ms": 12}
+    // ?timeout=N -- сколько ждать команду (секунды), ?stream=true -- вывод по мере поступления (NDJSON или SSE)
+
+    // ------------------------------------------------------------------
+    // ------ №1. Получим id контейнера, команду и параметры ------------
+    // ------------------------------------------------------------------
+    let container_id = match get_container_id(state, request) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(request),
+    };
+
+    let spec: ExecSpec = match &request.body {
+        Some(BodyType::Json(json)) => match ExecSpec::from_json(json) {
+            Ok(spec) => spec,
+            Err(e) => {
+                state.log.debug(&format!("Invalid exec spec: {e}"));
+                return ApiError::from(e)
+                    .with_container(container_id)
+                    .into_response(request);
+            }
+        },
+        _ => {
+            state
+                .log
+                .debug(&"Exec spec is missing or is not JSON".to_string());
+            return ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "invalid_spec",
+                "Request body must be a JSON exec spec",
+            )
+            .with_container(container_id)
+            .into_response(request);
+        }
+    };
+
+    let params: ExecParams = match ExecParams::from_request(state, request) {
+        Ok(params) => params,
+        Err(e) => {
+            state.log.debug(&format!("Invalid exec parameters: {e}"));
+            return e.with_container(container_id).into_response(request);
+        }
+    };
+
+    // ------------------------------------------------------------------
+    // ------ №2. Проверим, что такой id (<label>) существует------------
+    // ------------------------------------------------------------------
+    if let Err(e) = check_existence_container(state, container_id) {
+        return e.into_response(request);
+    }
+
+    // ------------------------------------------------------------------
+    // ------ №3. Проверим статусы контейнера ---------------------------
+    // ------------------------------------------------------------------
+    if let Err(response) = check_can_exec(state, request, container_id) {
+        return response;
+    }
+
+    // ------------------------------------------------------------------
+    // ------ №4. Запустим команду --------------------------------------
+    // ------------------------------------------------------------------
+    let stream: ExecStream = match state.backend.exec(container_id, &spec) {
+        Ok(stream) => stream,
+        Err(e) => {
+            state.log.error(&format!(
+                "Failed to exec {:?} in container {container_id}: {e}",
+                spec.argv
+            ));
+            return ApiError::from(e)
+                .with_container(container_id)
+                .into_response(request);
+        }
+    };
+    state.log.info(&format!(
+        "Running {:?} in container {container_id} (timeout: {:?})",
+        spec.argv, params.timeout
+    ));
+    let run = ExecRun::start(stream, params.timeout, state.config.exec_output_limit);
+
+    // ------------------------------------------------------------------
+    // ------ №5. Отдадим вывод: потоком или целиком --------------------
+    // ------------------------------------------------------------------
+    if params.stream {
+        return EventBody::response(
+            exec_events(run),
+            params.sse,
+            NDJSON,
+            state.log,
+            format!("exec in container {container_id}"),
+        );
+    }
+
+    let mut output: [Vec<u8>; 2] = [Vec::new(), Vec::new()]; // stdout, stderr
+    for item in run {
+        match item {
+            Ok(ExecItem::Output(source, data)) => output[source as usize].extend(data),
+            Ok(ExecItem::Finished(outcome)) => {
+                log_exec_outcome(state, container_id, &outcome);
+                let mut json_body = outcome.to_json();
+                json_body["stdout"] = String::from_utf8_lossy(&output[0]).into();
+                json_body["stderr"] = String::from_utf8_lossy(&output[1]).into();
+                return json_response(json_body);
+            }
+            Err(e) => {
+                state
+                    .log
+                    .error(&format!("Exec in container {container_id} broke: {e}"));
+                return ApiError::from(e)
+                    .with_container(container_id)
+                    .into_response(request);
+            }
+        }
+    }
+    // ExecRun всегда заканчивается итогом или ошибкой
+    ApiError::internal("Exec ended without a result")
+        .with_container(container_id)
+        .into_response(request)
+}
+
+// Команду (exec и attach) можно запустить только в работающем контейнере; иначе -- 409
+fn check_can_exec(state: &AppState, request: &Request, container_id: &str) -> Result<(), Response> {
+    let my_data: ContainerState = match fill_struct_read_status(state, container_id) {
+        Ok(data) => data.state,
+        Err(e) => return Err(e.into_response(request)),
+    };
+
+    /* Что сделает `docker exec`:
+    1. `Paused = true`     → “Container is paused, unpause the container before exec” → 409 Conflict
+    2. `Restarting = true` → “Container is restarting, wait until the container is running” → 409 Conflict
+    3. `Running = false` (exited, created, dead) → “Container is not running” → 409 Conflict
+    Остаётся только работающий контейнер → 200 OK, что бы ни вернула сама команда */
+
+    if my_data.is_paused {
+        state.log.warn(&format!(
+            "Failed to exec in container {container_id}. It is paused!"
+        ));
+        return Err(conflict(
+            request,
+            container_id,
+            "container_paused",
+            format!("Container {container_id} is paused, unpause it before exec"),
+        ));
+    }
+
+    if my_data.is_restarting {
+        state.log.warn(&format!(
+            "Failed to exec in container {container_id}. It is restarting!"
+        ));
+        return Err(conflict(
+            request,
+            container_id,
+            "container_restarting",
+            format!("Container {container_id} is restarting, wait until it is running"),
+        ));
+    }
+
+    if !my_data.is_running {
+        state.log.warn(&format!(
+            "Failed to exec in container {container_id}. It is not running!"
+        ));
+        return Err(conflict(
+            request,
+            container_id,
+            "container_not_running",
+            format!("Container {container_id} is not running"),
+        ));
+    }
+
+    Ok(())
+}
+
+fn log_exec_outcome(state: &AppState, container_id: &str, outcome: &ExecOutcome) {
+    match outcome.exit_code {
+        Some(code) => state.log.info(&format!(
+            "Exec in container {container_id} exited with {code} in {:?}",
+            outcome.duration
+        )),
+        None => state.log.warn(&format!(
+            "Exec in container {container_id} timed out after {:?}, it keeps running inside",
+            outcome.duration
+        )),
+    }
+}
+
+// Параметры POST /container/:id/exec
+struct ExecParams {
+    timeout: Duration,
+    stream: bool,
+    sse: bool,
+}
+
+// Дольше часа воркер командой не занимаем
+const MAX_EXEC_TIMEOUT: f64 = 3600.0;
+
+impl ExecParams {
+    fn from_request(state: &AppState, request: &Request) -> Result<Self, ApiError> {
+        let query = &request.query;
+        // как и interval у stats, можно дробное: ?timeout=0.5
+        let timeout: Duration = match query.get("timeout") {
+            None => state.config.exec_timeout,
+            Some(raw) => raw
+                .parse()
+                .ok()
+                .filter(|secs: &f64| secs.is_finite() && *secs > 0.0 && *secs <= MAX_EXEC_TIMEOUT)
+                .map(Duration::from_secs_f64)
+                .ok_or(ApiError::new(
+                    StatusCode::BAD_REQUEST,
+                    "invalid_parameter",
+                    format!(
+                        "timeout must be a number of seconds above 0 and up to {MAX_EXEC_TIMEOUT}, got {raw:?}"
+                    ),
+                ))?,
+        };
+
+        Ok(Self {
+            timeout,
+            stream: query.get_bool("stream")?.unwrap_or(false),
+            sse: wants_sse(request)?,
+        })
+    }
+}
+
+// Что отдаёт ExecRun: вывод (уже в пределах лимита), а в конце -- итог
+enum ExecItem {
+    Output(LogSource, Vec<u8>),
+    Finished(ExecOutcome),
+}
+
+struct ExecOutcome {
+    exit_code: Option<i64>, // None -- не дождались (timed_out)
+    truncated: bool,        // часть вывода отброшена по лимиту
+    duration: Duration,
+}
+
+impl ExecOutcome {
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "exit_code": self.exit_code,
+            "timed_out": self.exit_code.is_none(),
+            "truncated": self.truncated,
+            "duration_ms": self.duration.as_millis() as u64,
+        })
+    }
+}
+
+// Вывод exec с таймаутом и лимитом. Бэкенд читаем в отдельном потоке: он блокируется
+// на чтении, а ждать дольше deadline нельзя. По таймауту или когда ExecRun бросают
+// (клиент отключился) прерываем поток бэкенда через Canceller -- иначе читающий поток
+// и процесс docker exec висели бы, пока молчащая команда не напишет что-нибудь.
+// Вывод сверх лимита отбрасываем, но код выхода всё равно ждём -- ради него exec и зовут
+struct ExecRun {
+    events: Receiver<Result<ExecEvent, ContainerError>>,
+    canceller: Canceller,
+    started: Instant,
+    deadline: Instant,
+    output_left: usize, // сколько байт ещё можно отдать
+    truncated: bool,
+    finished: bool,
+}
+
+impl ExecRun {
+    fn start(stream: ExecStream, timeout: Duration, output_limit: usize) -> Self {
+        let canceller = stream.canceller();
+        // буфер небольшой: если клиент читает поток медленно, вывод не копится в памяти
+        let (sender, events) = sync_channel(16);
+        std::thread::spawn(move || {
+            for event in stream {
+                if sender.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let started = Instant::now();
+        Self {
+            events,
+            canceller,
+            started,
+            deadline: started + timeout,
+            output_left: output_limit,
+            truncated: false,
+            finished: false,
+        }
+    }
+
+    fn finish(&mut self, exit_code: Option<i64>) -> ExecItem {
+        self.finished = true;
+        ExecItem::Finished(ExecOutcome {
+            exit_code,
+            truncated: self.truncated,
+            duration: self.started.elapsed(),
+        })
+    }
+}
+
+impl Iterator for ExecRun {
+    type Item = Result<ExecItem, ContainerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.finished {
+            let wait = self.deadline.saturating_duration_since(Instant::now());
+            match self.events.recv_timeout(wait) {
+                Ok(Ok(ExecEvent::Output(source, mut data))) => {
+                    if data.len() > self.output_left {
+                        data.truncate(self.output_left);
+                        self.truncated = true;
+                    }
+                    self.output_left -= data.len();
+                    if !data.is_empty() {
+                        return Some(Ok(ExecItem::Output(source, data)));
+                    }
+                }
+                Ok(Ok(ExecEvent::Exit(code))) => return Some(Ok(self.finish(Some(code)))),
+                Ok(Err(e)) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    self.canceller.cancel();
+                    return Some(Ok(self.finish(None)));
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.finished = true;
+                    return Some(Err(ContainerError::DockerError(
+                        "exec output ended without an exit code".to_string(),
+                    )));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl Drop for ExecRun {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.canceller.cancel();
+        }
+    }
+}
+
+// Поток exec для EventBody: {"stream": "stdout", "data": "..."} на каждый кусок вывода
+// и {"exit_code": 0, ...} в конце. Символ UTF-8, разрезанный между кусками, склеиваем
+fn exec_events(run: ExecRun) -> EventStream {
+    let mut pending: [Vec<u8>; 2] = [Vec::new(), Vec::new()]; // stdout, stderr
+    let output = |source: LogSource, text: String| {
+        Ok(Some((
+            source.as_str(),
+            serde_json::json!({"stream": source.as_str(), "data": text}).to_string(),
+        )))
+    };
+
+    Box::new(run.flat_map(move |item| match item {
+        Ok(ExecItem::Output(source, data)) => {
+            let pending = &mut pending[source as usize];
+            pending.extend(data);
+            let text = take_utf8(pending);
+            if text.is_empty() {
+                Vec::new()
+            } else {
+                vec![output(source, text)]
+            }
+        }
+        Ok(ExecItem::Finished(outcome)) => {
+            // оборванный символ в конце так и не дописали -- отдаём как есть
+            let mut events: Vec<_> = [LogSource::Stdout, LogSource::Stderr]
+                .into_iter()
+                .filter_map(|source| {
+                    let rest = std::mem::take(&mut pending[source as usize]);
+                    (!rest.is_empty())
+                        .then(|| output(source, String::from_utf8_lossy(&rest).into_owned()))
+                })
+                .collect();
+            events.push(Ok(Some(("exit", outcome.to_json().to_string()))));
+            events
+        }
+        Err(e) => vec![Err(e)],
+    }))
+}
+
+// Целые символы из начала буфера; недописанный символ в конце остаётся ждать продолжения
+fn take_utf8(pending: &mut Vec<u8>) -> String {
+    let complete: usize = match std::str::from_utf8(pending) {
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        _ => pending.len(),
+    };
+    let rest: Vec<u8> = pending.split_off(complete);
+    let text = String::from_utf8_lossy(pending).into_owned();
+    *pending = rest;
+    text
+}
+
+pub fn handler_attach_container(state: &AppState, request: &Request) -> Response {
+    // GET /container/:id/attach -- WebSocket с интерактивной командой в контейнере (как docker exec -it)
+    // ?cmd=/bin/sh (по умолчанию; аргументы -- повтором: ?cmd=top&cmd=-d&cmd=1), ?user=, ?workdir=,
+    // ?rows=24&cols=80 -- начальный размер терминала
+    //
+    // Клиент -> сервер: бинарное сообщение -- байты в stdin как есть, текстовое -- JSON:
+    //   {"type": "input", "data": "ls\r"}   {"type": "resize", "rows": 40, "cols": 120}
+    // Сервер -> клиент: бинарные сообщения -- вывод терминала, в конце текстом
+    //   {"type": "exit", "exit_code": 0} и close 1000; на кривое сообщение -- {"type": "error", "message": "..."}
+
+    // ------------------------------------------------------------------
+    // ------ №1. Получим id контейнера, команду и проверим рукопожатие -
+    // ------------------------------------------------------------------
+    let container_id = match get_container_id(state, request) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(request),
+    };
+
+    let handshake: Handshake = match websocket::accept(request) {
+        Ok(handshake) => handshake,
+        Err(e) => {
+            state.log.debug(&format!("Attach without WebSocket: {e}"));
+            return e.into_response(request);
+        }
+    };
+
+    let (spec, size): (ExecSpec, TtySize) = match attach_params(request) {
+        Ok(params) => params,
+        Err(e) => {
+            state.log.debug(&format!("Invalid attach parameters: {e}"));
+            return e.with_container(container_id).into_response(request);
+        }
+    };
+
+    // ------------------------------------------------------------------
+    // ------ №2. Проверим, что такой id (<label>) существует------------
+    // ------------------------------------------------------------------
+    if let Err(e) = check_existence_container(state, container_id) {
+        return e.into_response(request);
+    }
+
+    // ------------------------------------------------------------------
+    // ------ №3. Проверим статусы контейнера ---------------------------
+    // ------------------------------------------------------------------
+    if let Err(response) = check_can_exec(state, request, container_id) {
+        return response;
+    }
+
+    // ------------------------------------------------------------------
+    // ------ №4. Запустим команду с терминалом -------------------------
+    // ------------------------------------------------------------------
+    // запускаем до ответа 101: если не вышло, клиент получит обычную HTTP-ошибку
+    let session: ExecSession = match state.backend.exec_tty(container_id, &spec, size) {
+        Ok(session) => session,
+        Err(e) => {
+            state.log.error(&format!(
+                "Failed to attach {:?} in container {container_id}: {e}",
+                spec.argv
+            ));
+            return ApiError::from(e)
+                .with_container(container_id)
+                .into_response(request);
+        }
+    };
+    state.log.info(&format!(
+        "Attached {:?} in container {container_id} ({}x{})",
+        spec.argv, size.rows, size.cols
+    ));
+
+    // ------------------------------------------------------------------
+    // ------ №5. Переключаемся на WebSocket и связываем его с терминалом
+    // ------------------------------------------------------------------
+    let log: Logger = state.log;
+    let container_id: String = container_id.to_string();
+    handshake.into_response(move |ws| bridge_tty(ws, session, log, container_id))
+}
+
+// Параметры GET /container/:id/attach: команда (проверяется как тело exec) и размер окна
+fn attach_params(request: &Request) -> Result<(ExecSpec, TtySize), ApiError> {
+    let query = &request.query;
+    let mut argv: Vec<&str> = query.get_all("cmd");
+    if argv.is_empty() {
+        argv.push(DEFAULT_ATTACH_COMMAND);
+    }
+    let mut json = serde_json::json!({ "argv": argv });
+    if let Some(user) = query.get("user") {
+        json["user"] = user.into();
+    }
+    if let Some(workdir) = query.get("workdir") {
+        json["workdir"] = workdir.into();
+    }
+    let spec = ExecSpec::from_json(&json)?;
+
+    let default = TtySize::default();
+    let size = TtySize {
+        rows: tty_dimension(query.get("rows"), "rows")?.unwrap_or(default.rows),
+        cols: tty_dimension(query.get("cols"), "cols")?.unwrap_or(default.cols),
+    };
+    Ok((spec, size))
+}
+
+const DEFAULT_ATTACH_COMMAND: &str = "/bin/sh";
+
+// Строк и колонок у терминала от 1 до 65535
+fn tty_dimension(raw: Option<&str>, name: &str) -> Result<Option<u16>, ApiError> {
+    raw.map(|raw| {
+        raw.parse::<u16>()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "invalid_parameter",
+                format!("{name} must be a number from 1 to 65535, got {raw:?}"),
+            ))
+    })
+    .transpose()
+}
+
+// Что клиент может прислать текстом
+#[derive(Debug, PartialEq)]
+enum AttachMessage {
+    Input(Vec<u8>),
+    Resize(TtySize),
+}
+
+fn parse_attach_message(text: &str) -> Result<AttachMessage, String> {
+    let json: serde_json::Value =
+        serde_json::from_str(text).map_err(|e| format!("Message must be JSON: {e}"))?;
+    let dimension = |name: &str| {
+        json[name]
+            .as_u64()
+            .and_then(|n| u16::try_from(n).ok())
+            .filter(|&n| n > 0)
+            .ok_or(format!("resize needs {name} from 1 to 65535"))
+    };
+
+    match json["type"].as_str() {
+        Some("input") => json["data"]
+            .as_str()
+            .map(|data| AttachMessage::Input(data.as_bytes().to_vec()))
+            .ok_or("input needs a string data".to_string()),
+        Some("resize") => Ok(AttachMessage::Resize(TtySize {
+            rows: dimension("rows")?,
+            cols: dimension("cols")?,
+        })),
+        _ => Err("Unknown message type, expected input or resize".to_string()),
+    }
+}
+
+// Гоняем байты между WebSocket и терминалом, пока кто-то из них не закончит.
+// Вывод читает отдельный поток: оба конца блокируются на чтении.
+// Команда завершилась -- отдаём код выхода и закрываем WebSocket.
+// Клиент ушёл -- закрываем stdin: оболочка увидит его конец и выйдет
+// (то, что stdin не читает, доработает само -- как после отключения docker attach)
+fn bridge_tty(mut ws: WebSocket, session: ExecSession, log: Logger, container_id: String) {
+    let ExecSession {
+        mut output,
+        mut input,
+        control,
+    } = session;
+    // ExecControl нужен обоим потокам: resize -- здесь, код выхода -- в потоке вывода
+    let control: Arc<Mutex<Box<dyn ExecControl + Send>>> = Arc::new(Mutex::new(control));
+
+    let sender: WebSocketSender = ws.sender();
+    let output_control = control.clone();
+    let output_container_id = container_id.clone();
+    std::thread::spawn(move || {
+        let mut buffer = [0u8; 8192];
+        let broken: Option<std::io::Error> = loop {
+            match output.read(&mut buffer) {
+                Ok(0) => break None,
+                Ok(n) => {
+                    if sender.send_binary(&buffer[..n]).is_err() {
+                        return; // клиент ушёл -- вывод больше некому читать
+                    }
+                }
+                Err(e) => break Some(e),
+            }
+        };
+
+        if let Some(e) = broken {
+            log.error(&format!(
+                "Attach output in container {output_container_id} broke: {e}"
+            ));
+            let _ = sender.close(websocket::CLOSE_INTERNAL_ERROR, "Exec output broke");
+            return;
+        }
+
+        let exit_code: Option<i64> = output_control
+            .lock()
+            .ok()
+            .and_then(|control| control.exit_code().ok())
+            .flatten();
+        log.info(&format!(
+            "Attached exec in container {output_container_id} exited with {exit_code:?}"
+        ));
+        let exit = serde_json::json!({"type": "exit", "exit_code": exit_code});
+        let _ = sender.send_text(&exit.to_string());
+        let _ = sender.close(websocket::CLOSE_NORMAL, "Exec exited");
+    });
+
+    loop {
+        let message = match ws.recv() {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(e) => {
+                log.debug(&format!(
+                    "Attach WebSocket in container {container_id} closed: {e}"
+                ));
+                break;
+            }
+        };
+
+        let data: Vec<u8> = match message {
+            Message::Binary(data) => data,
+            Message::Text(text) => match parse_attach_message(&text) {
+                Ok(AttachMessage::Input(data)) => data,
+                Ok(AttachMessage::Resize(size)) => {
+                    if let Ok(control) = control.lock()
+                        && let Err(e) = control.resize(size)
+                    {
+                        log.debug(&format!(
+                            "Failed to resize terminal in container {container_id}: {e}"
+                        ));
+                    }
+                    continue;
+                }
+                Err(message) => {
+                    let error = serde_json::json!({"type": "error", "message": message});
+                    let _ = ws.send_text(&error.to_string());
+                    continue;
+                }
+            },
+        };
+        // команда уже завершилась -- stdin некому читать, ждём close от потока вывода
+        let _ = input.write_all(&data).and_then(|_| input.flush());
+    }
+    drop(input);
+    log.info(&format!("Detached from exec in container {container_id}"));
+}
+
+// JSON-объект на строку -- так удобно читать поток снимков построчно
+const NDJSON: &str = "application/x-ndjson";
+
+fn json_response(json_body: serde_json::Value) -> Response {
+    Response {
+        response_code: StatusCode::OK,
+        headers: HeaderMap::new(),
+        body: Some(BodyType::Json(json_body)),
+    }
+}
+
+// Снимок в том же стиле, что и элемент списка контейнеров: name, id и числа
+fn stats_json(stats: &ContainerStats) -> serde_json::Value {
+    // проценты -- с точностью до сотых, как в docker stats
+    let percent = |value: f64| (value * 100.0).round() / 100.0;
+    serde_json::json!({
+        "name": stats.name,
+        "id": stats.id,
+        "read_at": stats.read_at.map(|time| time.to_rfc3339()),
+        "cpu_percent": percent(stats.cpu_percent),
+        "memory_usage": stats.memory_usage,
+        "memory_limit": stats.memory_limit,
+        "memory_percent": percent(stats.memory_percent()),
+        "network_rx": stats.network_rx,
+        "network_tx": stats.network_tx,
+        "block_read": stats.block_read,
+        "block_write": stats.block_write,
+        "pids": stats.pids,
+    })
+}
+
+fn all_stats_json(all: &[ContainerStats]) -> serde_json::Value {
+    serde_json::Value::Array(all.iter().map(stats_json).collect())
+}
+
+// Бесконечный поток снимков: first, потом poll() раз в interval.
+// После первой ошибки поток кончается (контейнер удалили, демон отвалился)
+fn poll_every<T: Send + 'static>(
+    interval: std::time::Duration,
+    first: T,
+    poll: impl Fn() -> Result<T, ContainerError> + Send + 'static,
+) -> impl Iterator<Item = Result<T, ContainerError>> + Send + 'static {
+    let mut first: Option<T> = Some(first);
+    let mut failed: bool = false;
+    std::iter::from_fn(move || {
+        if failed {
+            return None;
+        }
+        if let Some(first) = first.take() {
+            return Some(Ok(first));
+        }
+        std::thread::sleep(interval);
+        let next = poll();
+        failed = next.is_err();
+        Some(next)
+    })
+}
+
+// Чаще раза в полсекунды опрашивать нельзя: каждый снимок -- это docker stats
+// (для всех контейнеров -- по запросу на каждый), а interval=0 крутил бы их без остановки
+const MIN_STATS_INTERVAL: f64 = 0.5;
+const MAX_STATS_INTERVAL: f64 = 3600.0;
+
+// Параметры GET /container/stats и /container/:id/stats
+struct StatsParams {
+    stream: bool,
+    interval: std::time::Duration, // пауза между снимками в потоке
+    sse: bool,
+}
+
+impl StatsParams {
+    fn from_request(request: &Request) -> Result<Self, ApiError> {
+        let query = &request.query;
+        let interval: f64 = match query.get("interval") {
+            None => 1.0,
+            Some(raw) => raw
+                .parse()
+                .ok()
+                .filter(|secs: &f64| {
+                    secs.is_finite() && (MIN_STATS_INTERVAL..=MAX_STATS_INTERVAL).contains(secs)
+                })
+                .ok_or(ApiError::new(
+                    StatusCode::BAD_REQUEST,
+                    "invalid_parameter",
+                    format!(
+                        "interval must be a number of seconds from {MIN_STATS_INTERVAL} to {MAX_STATS_INTERVAL}, got {raw:?}"
+                    ),
+                ))?,
+        };
+
+        Ok(Self {
+            stream: query.get_bool("stream")?.unwrap_or(false),
+            interval: std::time::Duration::from_secs_f64(interval),
+            sse: wants_sse(request)?,
+        })
+    }
+}
+
+// "2024-05-01T10:00:00.000000000Z GET / 200" с timestamps, иначе просто текст
+fn format_log_line(line: &LogLine, timestamps: bool) -> String {
+    match (timestamps, line.timestamp) {
+        (true, Some(time)) => format!(
+            "{} {}",
+            time.to_rfc3339_opts(chrono::SecondsFormat::Nanos, true),
+            line.text
+        ),
+        _ => line.text.clone(),
+    }
+}
+
+// Отдавать ли поток как Server-Sent Events: ?format=sse|text, иначе по Accept
+fn wants_sse(request: &Request) -> Result<bool, ApiError> {
+    match request.query.get("format") {
+        None => Ok(request.headers.has_token("accept", "text/event-stream")),
+        Some("text") => Ok(false),
+        Some("sse") => Ok(true),
+        Some(format) => Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "invalid_parameter",
+            format!("format must be \"text\" or \"sse\", got {format:?}"),
+        )),
+    }
+}
+
+// События для EventBody: (имя события для SSE, одна строка текста).
+// None -- событий давно не было: пора проверить, не отключился ли клиент. В SSE для этого
+// уходит heartbeat-комментарий, а в текст и NDJSON ничего не пишем (пустая строка была бы
+// лишней записью) -- вместо этого WouldBlock, и сервер сам заглядывает в сокет
+type EventStream =
+    Box<dyn Iterator<Item = Result<Option<(&'static str, String)>, ContainerError>> + Send>;
+
+// Потоковое тело ответа (логи, статистика): события по одному при каждом чтении.
+// Текстом -- строка на событие, в SSE -- "event: <имя>\ndata: <строка>\n\n"
+struct EventBody {
+    events: EventStream,
+    sse: bool,
+    log: Logger,
+    what: String,    // для лога: "logs of container web1"
+    buffer: Vec<u8>, // текущее отформатированное событие
+    position: usize, // сколько из него уже отдали
+}
+
+impl EventBody {
+    // text_type -- Content-Type без SSE: текст для логов, NDJSON для статистики
+    fn response(
+        events: EventStream,
+        sse: bool,
+        text_type: &str,
+        log: Logger,
+        what: String,
+    ) -> Response {
+        let mut headers = HeaderMap::new();
+        let content_type: &str = if sse {
+            let _ = headers.insert("Cache-Control", "no-cache");
+            "text/event-stream"
+        } else {
+            text_type
+        };
+
+        Response {
+            response_code: StatusCode::OK,
+            headers,
+            body: Some(BodyType::stream(
+                content_type,
+                EventBody {
+                    events,
+                    sse,
+                    log,
+                    what,
+                    buffer: Vec::new(),
+                    position: 0,
+                },
+            )),
+        }
+    }
+
+    fn format(&self, event: &str, text: &str) -> String {
+        if self.sse {
+            // в data не должно быть переводов строк -- они разделяют поля события
+            format!(
+                "event: {event}\ndata: {}\n\n",
+                text.replace(['\r', '\n'], " ")
+            )
+        } else {
+            format!("{text}\n")
+        }
+    }
+}
+
+impl std::io::Read for EventBody {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.position >= self.buffer.len() {
+            let next: String = match self.events.next() {
+                Some(Ok(Some((event, text)))) => self.format(event, &text),
+                // в SSE -- комментарий, его клиенты пропускают
+                Some(Ok(None)) if self.sse => ": heartbeat\n\n".to_string(),
+                Some(Ok(None)) => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::WouldBlock,
+                        "No events yet",
+                    ));
+                }
+                // ошибку посреди потока статусом уже не передать: в SSE -- событие error, в тексте -- просто конец
+                Some(Err(e)) => {
+                    self.log
+                        .error(&format!("Stream of {} broke: {e}", self.what));
+                    if !self.sse {
+                        return Ok(0);
+                    }
+                    self.format("error", &e.to_string())
+                }
+                None => return Ok(0),
+            };
+            self.buffer = next.into_bytes();
+            self.position = 0;
+        }
+
+        let n = buf.len().min(self.buffer.len() - self.position);
+        buf[..n].copy_from_slice(&self.buffer[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::lib::{
+        app_state::StatefulHandlerFn,
+        config::Config,
+        container_backend::{
+            ContainerStats, HealthInfo, LogSource, MountInfo, ResourceLimits, RestartPolicy,
+        },
+        docker_works::{ContainerSize, PortBinding},
+        fake_backend::{FakeBackend, FakeContainer, FakeOp},
+        query::QueryParams,
+    };
+
+    fn state_with(fake: &Arc<FakeBackend>) -> AppState {
+        AppState::with_backend(Config::default(), fake.clone())
+    }
+
+    // Тот же набор контейнеров во всех тестах: по одному на каждый интересный статус
+    fn fake() -> Arc<FakeBackend> {
+        Arc::new(
+            FakeBackend::new()
+                .with_container("web1", "nginx:1.27", ContainerStatus::Up)
+                .with_container("db", "mysql", ContainerStatus::Exited)
+                .with_container("fresh", "alpine", ContainerStatus::Created)
+                .with_container("zombie", "redis", ContainerStatus::Dead)
+                .with_container("flappy", "nginx", ContainerStatus::Restarting)
+                .with_container("sleepy", "busybox", ContainerStatus::Paused),
+        )
+    }
+
+    fn request_for(id: &str) -> Request {
+        let mut request = Request::default();
+        request.rest_params.insert("id".to_string(), id.to_string());
+        let _ = request.headers.insert("X-Request-Id", "test");
+        request
+    }
+
+    // Код ответа и "code" из тела ошибки (None для успешных ответов без тела)
+    fn outcome(response: &Response) -> (StatusCode, Option<String>) {
+        let code = match &response.body {
+            Some(BodyType::Json(json)) => json["error"]["code"].as_str().map(str::to_string),
+            _ => None,
+        };
+        (response.response_code, code)
+    }
+
+    fn docker_error() -> ContainerError {
+        ContainerError::DockerError("daemon is not responding".to_string())
+    }
+
+    #[test]
+    fn list_returns_all_and_filters() {
+        let fake = fake();
+        let state = state_with(&fake);
+
+        let response = handler_return_all_containers(&state, &Request::default());
+        assert_eq!(response.response_code, StatusCode::OK);
+        let Some(BodyType::Json(json)) = response.body else {
+            panic!("expected JSON body");
+        };
+        assert_eq!(json.as_array().unwrap().len(), 6);
+        assert_eq!(json[0]["name"], "web1");
+        assert_eq!(json[0]["status"], "Up");
+
+        let request = Request {
+            query: QueryParams::parse("status=running&status=paused&image=nginx").unwrap(),
+            ..Default::default()
+        };
+        let Some(BodyType::Json(json)) = handler_return_all_containers(&state, &request).body
+        else {
+            panic!("expected JSON body");
+        };
+        assert_eq!(json.as_array().unwrap().len(), 1);
+        assert_eq!(json[0]["name"], "web1");
+        assert_eq!(json[0]["image"], "nginx:1.27");
+    }
+
+    #[test]
+    fn list_details() {
+        let mut web = FakeContainer::new("web1", "nginx", ContainerStatus::Up);
+        web.info.ports = vec![PortBinding {
+            ip: Some("0.0.0.0".to_string()),
+            private_port: 80,
+            public_port: Some(8080),
+            protocol: "tcp".to_string(),
+        }];
+        web.info
+            .labels
+            .insert("team".to_string(), "web".to_string());
+        web.info.mounts = vec!["static".to_string()];
+        web.info.size = Some(ContainerSize {
+            rw: 2,
+            root_fs: Some(187_000_000),
+        });
+        let fake = Arc::new(FakeBackend::new());
+        fake.add(web);
+        let state = state_with(&fake);
+
+        let request = Request {
+            query: QueryParams::parse("size=true").unwrap(),
+            ..Default::default()
+        };
+        let Some(BodyType::Json(json)) = handler_return_all_containers(&state, &request).body
+        else {
+            panic!("expected JSON body");
+        };
+        let web = &json[0];
+        assert_eq!(web["id"].as_str().unwrap().len(), 64);
+        assert!(web["created"].is_string());
+        assert_eq!(web["status_text"], "Up Less than a second");
+        assert_eq!(web["uptime_seconds"], 0);
+        assert!(web["exit_code"].is_null());
+        assert_eq!(
+            web["ports"],
+            serde_json::json!([{"ip": "0.0.0.0", "private_port": 80, "public_port": 8080, "protocol": "tcp"}])
+        );
+        assert_eq!(web["labels"], serde_json::json!({"team": "web"}));
+        assert_eq!(web["mounts"], serde_json::json!(["static"]));
+        assert_eq!(web["networks"], serde_json::json!(["bridge"]));
+        assert_eq!(
+            web["size"],
+            serde_json::json!({"rw": 2, "root_fs": 187_000_000})
+        );
+
+        let request = Request {
+            query: QueryParams::parse("size=maybe").unwrap(),
+            ..Default::default()
+        };
+        let response = handler_return_all_containers(&state, &request);
+        assert_eq!(response.response_code, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn list_errors() {
+        let fake = fake();
+        let state = state_with(&fake);
+
+        let request = Request {
+            query: QueryParams::parse("status=sleeping").unwrap(),
+            ..Default::default()
+        };
+        let response = handler_return_all_containers(&state, &request);
+        assert_eq!(
+            outcome(&response),
+            (StatusCode::BAD_REQUEST, Some("invalid_filter".to_string()))
+        );
+        assert!(fake.calls().is_empty()); // до рантайма дело не дошло
+
+        fake.fail_next(FakeOp::List, docker_error());
+        let response = handler_return_all_containers(&state, &Request::default());
+        assert_eq!(
+            outcome(&response),
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Some("docker_error".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn actions_fail_before_touching_container() {
+        let handlers: [StatefulHandlerFn; 6] = [
+            handler_start_container,
+            handler_stop_container,
+            handler_restart_container,
+            handler_pause_container,
+            handler_unpause_container,
+            handler_kill_container,
+        ];
+
+        for handler in handlers {
+            let fake = fake();
+            let state = state_with(&fake);
+
+            // нет :id в пути
+            let response = handler(&state, &Request::default());
+            assert_eq!(
+                outcome(&response),
+                (StatusCode::BAD_REQUEST, Some("bad_request".to_string()))
+            );
+
+            // неизвестный контейнер
+            let response = handler(&state, &request_for("ghost"));
+            assert_eq!(
+                outcome(&response),
+                (
+                    StatusCode::NOT_FOUND,
+                    Some("container_not_found".to_string())
+                )
+            );
+
+            // docker list упал
+            fake.fail_next(FakeOp::List, docker_error());
+            let response = handler(&state, &request_for("web1"));
+            assert_eq!(
+                outcome(&response),
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Some("docker_error".to_string())
+                )
+            );
+
+            // docker inspect упал
+            fake.fail_next(FakeOp::Inspect, docker_error());
+            let response = handler(&state, &request_for("web1"));
+            assert_eq!(
+                outcome(&response),
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Some("docker_error".to_string())
+                )
+            );
+
+            assert_eq!(fake.calls(), vec!["list", "list", "list", "inspect web1"]);
+            assert_eq!(fake.status("web1"), Some(ContainerStatus::Up));
+        }
+    }
+
+    #[test]
+    fn container_addressed_by_id() {
+        let fake = fake();
+        let state = state_with(&fake);
+        let id = fake.container("web1").unwrap().info.id;
+
+        let response = handler_stop_container(&state, &request_for(&id));
+        assert_eq!(outcome(&response), (StatusCode::OK, None));
+        assert_eq!(fake.status("web1"), Some(ContainerStatus::Exited));
+
+        let response = handler_inspect_container(&state, &request_for(&id));
+        assert_eq!(outcome(&response), (StatusCode::OK, None));
+
+        // префикс id -- не id
+        let response = handler_start_container(&state, &request_for(&id[..12]));
+        assert_eq!(
+            outcome(&response),
+            (
+                StatusCode::NOT_FOUND,
+                Some("container_not_found".to_string())
+            )
+        );
+    }
+
+    // Для каждого статуса контейнера: что ответит хендлер и в каком статусе окажется контейнер
+    fn check_table(
+        handler: StatefulHandlerFn,
+        table: &[(&str, StatusCode, Option<&str>, ContainerStatus)],
+    ) {
+        for &(id, status, code, after) in table {
+            let fake = fake();
+            let state = state_with(&fake);
+
+            let response = handler(&state, &request_for(id));
+            assert_eq!(
+                outcome(&response),
+                (status, code.map(str::to_string)),
+                "container {id}"
+            );
+            assert_eq!(fake.status(id), Some(after), "container {id}");
+            if let Some(BodyType::Json(json)) = &response.body {
+                assert_eq!(json["error"]["container_id"], id);
+                assert_eq!(json["error"]["request_id"], "test");
+            }
+        }
+    }
+
+    #[test]
+    fn start_by_status() {
+        check_table(
+            handler_start_container,
+            &[
+                ("db", StatusCode::OK, None, ContainerStatus::Up),
+                ("fresh", StatusCode::OK, None, ContainerStatus::Up),
+                (
+                    "zombie",
+                    StatusCode::CONFLICT,
+                    Some("container_dead"),
+                    ContainerStatus::Dead,
+                ),
+                (
+                    "flappy",
+                    StatusCode::CONFLICT,
+                    Some("container_restarting"),
+                    ContainerStatus::Restarting,
+                ),
+                (
+                    "web1",
+                    StatusCode::CONFLICT,
+                    Some("container_running"),
+                    ContainerStatus::Up,
+                ),
+                // docker считает приостановленный контейнер запущенным
+                (
+                    "sleepy",
+                    StatusCode::CONFLICT,
+                    Some("container_running"),
+                    ContainerStatus::Paused,
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn stop_by_status() {
+        check_table(
+            handler_stop_container,
+            &[
+                ("web1", StatusCode::OK, None, ContainerStatus::Exited),
+                ("sleepy", StatusCode::OK, None, ContainerStatus::Exited),
+                ("flappy", StatusCode::OK, None, ContainerStatus::Exited),
+                // created ещё ни разу не запускался -- docker stop ничего не делает
+                ("fresh", StatusCode::OK, None, ContainerStatus::Created),
+                (
+                    "db",
+                    StatusCode::CONFLICT,
+                    Some("container_not_running"),
+                    ContainerStatus::Exited,
+                ),
+                (
+                    "zombie",
+                    StatusCode::CONFLICT,
+                    Some("container_dead"),
+                    ContainerStatus::Dead,
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn restart_by_status() {
+        check_table(
+            handler_restart_container,
+            &[
+                ("web1", StatusCode::OK, None, ContainerStatus::Up),
+                ("db", StatusCode::OK, None, ContainerStatus::Up),
+                ("fresh", StatusCode::OK, None, ContainerStatus::Up),
+                ("sleepy", StatusCode::OK, None, ContainerStatus::Up),
+                (
+                    "zombie",
+                    StatusCode::CONFLICT,
+                    Some("container_dead"),
+                    ContainerStatus::Dead,
+                ),
+                (
+                    "flappy",
+                    StatusCode::CONFLICT,
+                    Some("container_restarting"),
+                    ContainerStatus::Restarting,
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn pause_by_status() {
+        check_table(
+            handler_pause_container,
+            &[
+                ("web1", StatusCode::OK, None, ContainerStatus::Paused),
+                (
+                    "sleepy",
+                    StatusCode::CONFLICT,
+                    Some("container_paused"),
+                    ContainerStatus::Paused,
+                ),
+                (
+                    "flappy",
+                    StatusCode::CONFLICT,
+                    Some("container_restarting"),
+                    ContainerStatus::Restarting,
+                ),
+                (
+                    "db",
+                    StatusCode::CONFLICT,
+                    Some("container_not_running"),
+                    ContainerStatus::Exited,
+                ),
+                (
+                    "fresh",
+                    StatusCode::CONFLICT,
+                    Some("container_not_running"),
+                    ContainerStatus::Created,
+                ),
+                (
+                    "zombie",
+                    StatusCode::CONFLICT,
+                    Some("container_dead"),
+                    ContainerStatus::Dead,
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn unpause_by_status() {
+        check_table(
+            handler_unpause_container,
+            &[
+                ("sleepy", StatusCode::OK, None, ContainerStatus::Up),
+                (
+                    "web1",
+                    StatusCode::CONFLICT,
+                    Some("container_not_paused"),
+                    ContainerStatus::Up,
+                ),
+                (
+                    "db",
+                    StatusCode::CONFLICT,
+                    Some("container_not_paused"),
+                    ContainerStatus::Exited,
+                ),
+                (
+                    "zombie",
+                    StatusCode::CONFLICT,
+                    Some("container_not_paused"),
+                    ContainerStatus::Dead,
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn kill_by_status() {
+        // без ?signal -- SIGKILL
+        check_table(
+            handler_kill_container,
+            &[
+                ("web1", StatusCode::OK, None, ContainerStatus::Exited),
+                ("sleepy", StatusCode::OK, None, ContainerStatus::Exited),
+                ("flappy", StatusCode::OK, None, ContainerStatus::Exited),
+                (
+                    "db",
+                    StatusCode::CONFLICT,
+                    Some("container_not_running"),
+                    ContainerStatus::Exited,
+                ),
+                (
+                    "fresh",
+                    StatusCode::CONFLICT,
+                    Some("container_not_running"),
+                    ContainerStatus::Created,
+                ),
+                (
+                    "zombie",
+                    StatusCode::CONFLICT,
+                    Some("container_dead"),
+                    ContainerStatus::Dead,
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn remove_by_status() {
+        // (контейнер, query, код ответа, code ошибки, удалён ли)
+        let table: &[(&str, &str, StatusCode, Option<&str>, bool)] = &[
+            ("db", "", StatusCode::OK, None, true),
+            ("fresh", "", StatusCode::OK, None, true),
+            ("zombie", "", StatusCode::OK, None, true),
+            (
+                "web1",
+                "",
+                StatusCode::CONFLICT,
+                Some("container_running"),
+                false,
+            ),
+            (
+                "sleepy",
+                "force=false",
+                StatusCode::CONFLICT,
+                Some("container_running"),
+                false,
+            ),
+            (
+                "flappy",
+                "",
+                StatusCode::CONFLICT,
+                Some("container_running"),
+                false,
+            ),
+            ("web1", "force", StatusCode::OK, None, true),
+            ("sleepy", "force=true&volumes=1", StatusCode::OK, None, true),
+            (
+                "ghost",
+                "force=true",
+                StatusCode::NOT_FOUND,
+                Some("container_not_found"),
+                false,
+            ),
+            (
+                "db",
+                "force=maybe",
+                StatusCode::BAD_REQUEST,
+                Some("bad_request"),
+                false,
+            ),
+        ];
+        for &(id, query, status, code, removed) in table {
+            let fake = fake();
+            let state = state_with(&fake);
+            let existed = fake.status(id).is_some();
+
+            let response = handler_remove_container(&state, &logs_request(id, query));
+            assert_eq!(
+                outcome(&response),
+                (status, code.map(str::to_string)),
+                "{id}?{query}"
+            );
+            assert_eq!(
+                fake.status(id).is_none(),
+                removed || !existed,
+                "{id}?{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn remove_options_and_removal_in_progress() {
+        let fake = fake();
+        let state = state_with(&fake);
+
+        let response = handler_remove_container(&state, &logs_request("web1", "force&volumes"));
+        assert_eq!(response.response_code, StatusCode::OK);
+        assert!(
+            fake.calls()
+                .contains(&"remove web1 force volumes".to_string())
+        );
+
+        // второй раз удалять нечего, и force тут не поможет
+        fake.set_status("db", ContainerStatus::RemovalInProgress);
+        let response = handler_remove_container(&state, &logs_request("db", "force=true"));
+        assert_eq!(
+            outcome(&response),
+            (StatusCode::CONFLICT, Some("container_removing".to_string()))
+        );
+        assert_eq!(fake.status("db"), Some(ContainerStatus::RemovalInProgress));
+
+        // ошибка демона (например, том занят) -- 500 с его текстом
+        fake.fail_next(
+            FakeOp::Remove,
+            ContainerError::DockerError("volume is in use".to_string()),
+        );
+        let response = handler_remove_container(&state, &request_for("fresh"));
+        assert_eq!(response.response_code, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(fake.status("fresh"), Some(ContainerStatus::Created));
+    }
+
+    #[test]
+    fn kill_signals_and_stop_timeouts() {
+        let fake = fake();
+        let state = state_with(&fake);
+
+        // nginx на SIGHUP перечитывает конфиг и продолжает работать
+        let response = handler_kill_container(&state, &logs_request("web1", "signal=hup"));
+        assert_eq!(response.response_code, StatusCode::OK);
+        assert_eq!(fake.status("web1"), Some(ContainerStatus::Up));
+
+        let response = handler_kill_container(&state, &logs_request("web1", "signal=15"));
+        assert_eq!(response.response_code, StatusCode::OK);
+        assert_eq!(fake.container("web1").unwrap().state.exit_code, Some(143));
+
+        for query in ["signal=SIGFOO", "signal=0", "signal="] {
+            let response = handler_kill_container(&state, &logs_request("sleepy", query));
+            assert_eq!(
+                outcome(&response),
+                (
+                    StatusCode::BAD_REQUEST,
+                    Some("invalid_parameter".to_string())
+                ),
+                "{query}"
+            );
+        }
+        for query in ["timeout=-1", "timeout=soon", "timeout=3601"] {
+            let response = handler_stop_container(&state, &logs_request("sleepy", query));
+            assert_eq!(response.response_code, StatusCode::BAD_REQUEST, "{query}");
+            let response = handler_restart_container(&state, &logs_request("sleepy", query));
+            assert_eq!(response.response_code, StatusCode::BAD_REQUEST, "{query}");
+        }
+        assert_eq!(fake.status("sleepy"), Some(ContainerStatus::Paused));
+
+        let response = handler_restart_container(&state, &logs_request("web1", "timeout=0"));
+        assert_eq!(response.response_code, StatusCode::OK);
+        let response = handler_stop_container(&state, &logs_request("web1", "timeout=30"));
+        assert_eq!(response.response_code, StatusCode::OK);
+
+        // без ?timeout -- таймаут из конфига
+        let state = AppState::with_backend(
+            Config::default().with_stop_timeout(Duration::from_secs(5)),
+            fake.clone(),
+        );
+        let response = handler_stop_container(&state, &request_for("sleepy"));
+        assert_eq!(response.response_code, StatusCode::OK);
+
+        let actions: Vec<String> = fake
+            .calls()
+            .into_iter()
+            .filter(|call| !call.starts_with("list") && !call.starts_with("inspect"))
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                "kill web1 SIGHUP",
+                "kill web1 SIGTERM",
+                "restart web1 0s",
+                "stop web1 30s",
+                "stop sleepy 5s",
+            ]
+        );
+    }
+
+    #[test]
+    fn runtime_failure_during_action() {
+        let fake = fake();
+        let state = state_with(&fake);
+
+        fake.fail_next(FakeOp::Start, docker_error());
+        let response = handler_start_container(&state, &request_for("db"));
+        assert_eq!(
+            outcome(&response),
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Some("docker_error".to_string())
+            )
+        );
+        assert_eq!(fake.status("db"), Some(ContainerStatus::Exited));
+
+        fake.fail_next(FakeOp::Stop, docker_error());
+        let response = handler_stop_container(&state, &request_for("web1"));
+        assert_eq!(response.response_code, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(fake.status("web1"), Some(ContainerStatus::Up));
+
+        fake.fail_next(FakeOp::Restart, docker_error());
+        let response = handler_restart_container(&state, &request_for("web1"));
+        assert_eq!(response.response_code, StatusCode::INTERNAL_SERVER_ERROR);
+
+        fake.fail_next(FakeOp::Pause, docker_error());
+        let response = handler_pause_container(&state, &request_for("web1"));
+        assert_eq!(response.response_code, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(fake.status("web1"), Some(ContainerStatus::Up));
+
+        fake.fail_next(FakeOp::Kill, docker_error());
+        let response = handler_kill_container(&state, &request_for("web1"));
+        assert_eq!(response.response_code, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(fake.status("web1"), Some(ContainerStatus::Up));
+
+        // ошибки были одноразовыми: повтор проходит
+        let response = handler_start_container(&state, &request_for("db"));
+        assert_eq!(response.response_code, StatusCode::OK);
+        assert!(fake.container("db").unwrap().state.is_running);
+    }
+
+    #[test]
+    fn state_follows_actions() {
+        let fake = fake();
+        let state = state_with(&fake);
+        let web1 = request_for("web1");
+
+        // running -> stop -> exited -> stop ещё раз: уже остановлен
+        assert_eq!(
+            handler_stop_container(&state, &web1).response_code,
+            StatusCode::OK
+        );
+        let stopped = fake.container("web1").unwrap().state;
+        assert_eq!(stopped.status, "exited");
+        assert!(!stopped.is_running && stopped.finished_at.is_some());
+        assert_eq!(
+            outcome(&handler_stop_container(&state, &web1)).1.as_deref(),
+            Some("container_not_running")
+        );
+
+        // exited -> start -> running -> start ещё раз: уже запущен
+        assert_eq!(
+            handler_start_container(&state, &web1).response_code,
+            StatusCode::OK
+        );
+        assert_eq!(fake.status("web1"), Some(ContainerStatus::Up));
+        assert_eq!(
+            outcome(&handler_start_container(&state, &web1))
+                .1
+                .as_deref(),
+            Some("container_running")
+        );
+
+        // контейнер упал «сам по себе»: start его не поднимет, а restart теперь запрещён
+        fake.set_status("web1", ContainerStatus::Dead);
+        assert_eq!(
+            outcome(&handler_restart_container(&state, &web1))
+                .1
+                .as_deref(),
+            Some("container_dead")
+        );
+
+        // каждое действие -- list, inspect и (если дошло) сама команда
+        let calls = fake.calls();
+        assert_eq!(calls.iter().filter(|c| c.starts_with("stop")).count(), 1);
+        assert_eq!(calls.iter().filter(|c| c.starts_with("start")).count(), 1);
+        assert!(!calls.iter().any(|c| c.starts_with("restart")));
+    }
+
+    fn create_request(spec: serde_json::Value) -> Request {
+        Request {
+            body: Some(BodyType::Json(spec)),
+            ..request_for("")
+        }
+    }
+
+    #[test]
+    fn create_from_spec() {
+        let fake = fake();
+        let state = state_with(&fake);
+
+        let response = handler_create_container(
+            &state,
+            &create_request(serde_json::json!({
+                "image": "nginx:1.27",
+                "name": "web2",
+                "env": {"DB_PASSWORD": "hunter2"},
+                "ports": [{"private_port": 80, "public_port": 8081}],
+                "restart_policy": {"name": "always"},
+                "resources": {"memory": 67108864},
+            })),
+        );
+        assert_eq!(response.response_code, StatusCode::CREATED);
+        assert_eq!(response.headers.get("location"), Some("/container/web2"));
+        let json = json_body(&response);
+        assert_eq!(json["name"], "web2");
+        assert_eq!(json["id"], fake.container("web2").unwrap().info.id);
+
+        // создан, но не запущен; всё из спецификации видно через inspect
+        assert_eq!(fake.status("web2"), Some(ContainerStatus::Created));
+        let inspect = json_body(&handler_inspect_container(&state, &request_for("web2")));
+        assert_eq!(inspect["env"], serde_json::json!(["DB_PASSWORD=********"]));
+        assert_eq!(inspect["network"]["ports"][0]["public_port"], 8081);
+        assert_eq!(inspect["restart_policy"]["name"], "always");
+        assert_eq!(inspect["resources"]["memory"], 67108864);
+
+        // без имени -- имя от рантайма
+        let response = handler_create_container(
+            &state,
+            &create_request(serde_json::json!({"image": "alpine"})),
+        );
+        assert_eq!(response.response_code, StatusCode::CREATED);
+        let name = json_body(&response)["name"].as_str().unwrap().to_string();
+        assert_eq!(fake.status(&name), Some(ContainerStatus::Created));
+        assert_eq!(
+            fake.calls()
+                .into_iter()
+                .filter(|call| call.starts_with("create"))
+                .collect::<Vec<String>>(),
+            vec!["create web2".to_string(), format!("create {name}")]
+        );
+    }
+
+    #[test]
+    fn create_errors() {
+        let fake = fake();
+        let state = state_with(&fake);
+
+        // тела нет или это не JSON
+        for body in [None, Some(BodyType::Plain("image=nginx".to_string()))] {
+            let request = Request {
+                body,
+                ..Default::default()
+            };
+            let response = handler_create_container(&state, &request);
+            assert_eq!(
+                outcome(&response),
+                (StatusCode::BAD_REQUEST, Some("invalid_spec".to_string()))
+            );
+        }
+
+        let response = handler_create_container(
+            &state,
+            &create_request(serde_json::json!({"image": "nginx", "name": "web 2"})),
+        );
+        assert_eq!(
+            outcome(&response),
+            (StatusCode::BAD_REQUEST, Some("invalid_spec".to_string()))
+        );
+        assert_eq!(json_body(&response)["error"]["field"], "name");
+        assert_eq!(json_body(&response)["error"]["request_id"], "test");
+        assert!(fake.calls().is_empty()); // до рантайма дело не дошло
+
+        // имя занято
+        let response = handler_create_container(
+            &state,
+            &create_request(serde_json::json!({"image": "nginx", "name": "web1"})),
+        );
+        assert_eq!(
+            outcome(&response),
+            (StatusCode::CONFLICT, Some("container_exists".to_string()))
+        );
+        assert_eq!(json_body(&response)["error"]["container_id"], "web1");
+
+        fake.fail_next(FakeOp::Create, docker_error());
+        let response = handler_create_container(
+            &state,
+            &create_request(serde_json::json!({"image": "nginx", "name": "web2"})),
+        );
+        assert_eq!(
+            outcome(&response),
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Some("docker_error".to_string())
+            )
+        );
+        assert_eq!(fake.status("web2"), None);
+
+        fake.fail_next(FakeOp::List, docker_error());
+        let response = handler_create_container(
+            &state,
+            &create_request(serde_json::json!({"image": "nginx", "name": "web2"})),
+        );
+        assert_eq!(response.response_code, StatusCode::INTERNAL_SERVER_ERROR);
+        // create позвали только один раз -- когда рантайм вернул ошибку
+        assert_eq!(
+            fake.calls()
+                .iter()
+                .filter(|call| *call == "create web2")
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn inspect_single_container() {
+        let mut db = FakeContainer::new("db", "postgres:16", ContainerStatus::Up);
+        db.state.restart_count = 2;
+        db.state.health = Some("healthy".to_string());
+        db.details = ContainerDetails {
+            command: vec!["docker-entrypoint.sh".to_string(), "postgres".to_string()],
+            health: Some(HealthInfo {
+                status: "healthy".to_string(),
+                failing_streak: 0,
+                last_exit_code: Some(0),
+                last_output: Some("accepting connections".to_string()),
+            }),
+            env: vec![
+                ("POSTGRES_PASSWORD".to_string(), "hunter2".to_string()),
+                ("GITHUB_TOKEN".to_string(), "ghp_123".to_string()),
+                ("AWS_SECRET_ACCESS_KEY".to_string(), "abc".to_string()),
+                ("PGDATA".to_string(), "/var/lib/postgresql/data".to_string()),
+                (
+                    "REPLICA_URL".to_string(),
+                    "postgres://repl:pw@primary/app".to_string(),
+                ),
+                ("TZ".to_string(), "UTC".to_string()),
+            ],
+            mounts: vec![MountInfo {
+                kind: "volume".to_string(),
+                name: Some("pgdata".to_string()),
+                source: "/var/lib/docker/volumes/pgdata/_data".to_string(),
+                destination: "/var/lib/postgresql/data".to_string(),
+                read_only: false,
+            }],
+            resources: ResourceLimits {
+                memory: Some(512 * 1024 * 1024),
+                nano_cpus: Some(1_500_000_000),
+                ..Default::default()
+            },
+            restart_policy: RestartPolicy {
+                name: "on-failure".to_string(),
+                max_retries: 5,
+            },
+            ..Default::default()
+        };
+        let fake = Arc::new(FakeBackend::new());
+        fake.add(db);
+        let state = state_with(&fake);
+
+        let response = handler_inspect_container(&state, &request_for("db"));
+        assert_eq!(response.response_code, StatusCode::OK);
+        let Some(BodyType::Json(json)) = response.body else {
+            panic!("expected JSON body");
+        };
+        assert_eq!(json["name"], "db");
+        assert_eq!(json["image"], "postgres:16");
+        assert_eq!(json["state"]["status"], "running");
+        assert_eq!(json["state"]["running"], true);
+        assert_eq!(json["state"]["dead"], false);
+        assert_eq!(json["state"]["pid"], 4242);
+        assert_eq!(json["restart_count"], 2);
+        assert_eq!(json["restart_policy"]["name"], "on-failure");
+        assert_eq!(json["health"]["status"], "healthy");
+        assert_eq!(json["health"]["last_output"], "accepting connections");
+        assert_eq!(
+            json["env"],
+            serde_json::json!([
+                "POSTGRES_PASSWORD=********",
+                "GITHUB_TOKEN=********",
+                "AWS_SECRET_ACCESS_KEY=********",
+                "PGDATA=/var/lib/postgresql/data",
+                "REPLICA_URL=postgres://********@primary/app",
+                "TZ=UTC",
+            ])
+        );
+        assert_eq!(json["mounts"][0]["name"], "pgdata");
+        assert_eq!(json["mounts"][0]["read_only"], false);
+        assert_eq!(json["resources"]["memory"], 512 * 1024 * 1024);
+        assert!(json["resources"]["pids_limit"].is_null());
+
+        let response = handler_inspect_container(&state, &request_for("ghost"));
+        assert_eq!(
+            outcome(&response),
+            (
+                StatusCode::NOT_FOUND,
+                Some("container_not_found".to_string())
+            )
+        );
+
+        fake.fail_next(FakeOp::Inspect, docker_error());
+        let response = handler_inspect_container(&state, &request_for("db"));
+        assert_eq!(
+            outcome(&response),
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Some("docker_error".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn secret_env_names() {
+        for name in [
+            "DB_PASSWORD",
+            "MYSQL_ROOT_PASSWORD",
+            "API_KEY",
+            "jwt_secret",
+            "AuthToken",
+            "clientSecret",
+            "AWS_SECRET_ACCESS_KEY",
+            "GOOGLE_APPLICATION_CREDENTIALS",
+        ] {
+            assert!(is_secret_env(name), "{name}");
+        }
+        for name in [
+            "PATH",
+            "HOME",
+            "PGDATA",
+            "LANG",
+            "NGINX_PORT",
+            "COMPASS_URL",
+            "PASSENGER_PORT",
+            "KEYBOARD",
+            "MONKEY_MODE",
+        ] {
+            assert!(!is_secret_env(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn url_credentials_masked() {
+        assert_eq!(
+            mask_url_credentials("postgres://u:pw@h:5432/app"),
+            "postgres://********@h:5432/app"
+        );
+        assert_eq!(
+            mask_url_credentials("amqp://guest:guest@a,amqp://b:c@d"),
+            "amqp://********@a,amqp://********@d"
+        );
+        // '@' в пути -- не userinfo
+        assert_eq!(
+            mask_url_credentials("https://example.com/@user?x=@"),
+            "https://example.com/@user?x=@"
+        );
+        assert_eq!(
+            mask_url_credentials("redis://cache:6379"),
+            "redis://cache:6379"
+        );
+        assert_eq!(mask_url_credentials("plain@value"), "plain@value");
+    }
+
+    fn read_body(response: Response) -> String {
+        let Some(BodyType::Stream {
+            content_type,
+            mut stream,
+        }) = response.body
+        else {
+            panic!("expected streamed body");
+        };
+        let mut body = String::new();
+        std::io::Read::read_to_string(&mut stream, &mut body).unwrap();
+        format!("{content_type}\n{body}")
+    }
+
+    fn logs_request(id: &str, query: &str) -> Request {
+        Request {
+            query: QueryParams::parse(query).unwrap(),
+            ..request_for(id)
+        }
+    }
+
+    #[test]
+    fn logs_text_and_sse() {
+        let fake = fake();
+        fake.push_log("web1", LogSource::Stdout, "GET / 200");
+        fake.push_log("web1", LogSource::Stderr, "upstream timed out");
+        fake.push_log("web1", LogSource::Stdout, "GET /health 200");
+        let state = state_with(&fake);
+
+        let response = handler_container_logs(&state, &request_for("web1"));
+        assert_eq!(response.response_code, StatusCode::OK);
+        assert_eq!(
+            read_body(response),
+            "text/plain; charset=utf-8\nGET / 200\nupstream timed out\nGET /health 200\n"
+        );
+
+        let response = handler_container_logs(&state, &logs_request("web1", "stderr=false&tail=1"));
+        assert_eq!(
+            read_body(response),
+            "text/plain; charset=utf-8\nGET /health 200\n"
+        );
+
+        let response = handler_container_logs(&state, &logs_request("web1", "stdout=0&timestamps"));
+        let body = read_body(response);
+        let (time, text) = body.lines().nth(1).unwrap().split_once(' ').unwrap();
+        assert!(chrono::DateTime::parse_from_rfc3339(time).is_ok());
+        assert_eq!(text, "upstream timed out");
+
+        let mut request = logs_request("web1", "tail=2&follow=true");
+        let _ = request.headers.insert("Accept", "text/event-stream");
+        let response = handler_container_logs(&state, &request);
+        assert_eq!(response.headers.get("cache-control"), Some("no-cache"));
+        assert_eq!(
+            read_body(response),
+            "text/event-stream\n\
+             event: stderr\ndata: upstream timed out\n\n\
+             event: stdout\ndata: GET /health 200\n\n"
+        );
+
+        // всё из будущего -- пусто
+        let response =
+            handler_container_logs(&state, &logs_request("web1", "since=2999-01-01T00:00:00Z"));
+        assert_eq!(read_body(response), "text/plain; charset=utf-8\n");
+    }
+
+    #[test]
+    fn follow_logs_heartbeat() {
+        // поток, который молчит, пока тест не пришлёт строку; отмену только запоминаем
+        let (sender, lines) = std::sync::mpsc::channel::<LogLine>();
+        let cancelled = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let stream = {
+            let cancelled = cancelled.clone();
+            LogStream::new(lines.into_iter().map(Ok)).with_canceller(move || {
+                cancelled.store(true, std::sync::atomic::Ordering::SeqCst);
+            })
+        };
+
+        let mut follow = FollowLines::start(stream, Duration::from_millis(20));
+        assert!(matches!(follow.next(), Some(Ok(None))));
+        sender
+            .send(LogLine::parse_timestamped(LogSource::Stdout, "GET / 200"))
+            .unwrap();
+        assert!(matches!(follow.next(), Some(Ok(Some(line))) if line.text == "GET / 200"));
+
+        // клиент ушёл -- поток бэкенда прерываем
+        drop(follow);
+        assert!(cancelled.load(std::sync::atomic::Ordering::SeqCst));
+
+        // heartbeat: в SSE -- комментарий, в тексте ничего не пишем, только WouldBlock
+        let events = || -> EventStream {
+            Box::new(vec![Ok(None), Ok(Some(("stdout", "GET / 200".to_string())))].into_iter())
+        };
+        let response = EventBody::response(
+            events(),
+            true,
+            "text/plain",
+            Logger::default(),
+            "logs".to_string(),
+        );
+        assert_eq!(
+            read_body(response),
+            "text/event-stream\n: heartbeat\n\nevent: stdout\ndata: GET / 200\n\n"
+        );
+        let response = EventBody::response(
+            events(),
+            false,
+            "text/plain",
+            Logger::default(),
+            "logs".to_string(),
+        );
+        let Some(BodyType::Stream { mut stream, .. }) = response.body else {
+            panic!("expected streamed body");
+        };
+        let mut buf = [0u8; 64];
+        let idle = std::io::Read::read(&mut stream, &mut buf).unwrap_err();
+        assert_eq!(idle.kind(), std::io::ErrorKind::WouldBlock);
+        let mut body = String::new();
+        std::io::Read::read_to_string(&mut stream, &mut body).unwrap();
+        assert_eq!(body, "GET / 200\n");
+    }
+
+    #[test]
+    fn logs_errors() {
+        let fake = fake();
+        let state = state_with(&fake);
+
+        for query in [
+            "tail=-1",
+            "since=yesterday",
+            "until=10x",
+            "stdout=false&stderr=false",
+            "format=xml",
+            "follow=maybe",
+        ] {
+            let response = handler_container_logs(&state, &logs_request("web1", query));
+            assert_eq!(response.response_code, StatusCode::BAD_REQUEST, "{query}");
+        }
+        assert!(fake.calls().is_empty()); // до рантайма дело не дошло
+
+        let response = handler_container_logs(&state, &request_for("ghost"));
+        assert_eq!(outcome(&response).1.as_deref(), Some("container_not_found"));
+
+        fake.fail_next(FakeOp::Logs, docker_error());
+        let response = handler_container_logs(&state, &request_for("web1"));
+        assert_eq!(
+            outcome(&response),
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Some("docker_error".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn log_times() {
+        let now = chrono::DateTime::parse_from_rfc3339("2024-05-01T10:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let at = |raw: &str| parse_log_time(raw, now).map(|t| t.to_rfc3339());
+
+        assert_eq!(
+            at("2024-05-01T12:00:00+02:00").as_deref(),
+            Some("2024-05-01T10:00:00+00:00")
+        );
+        assert_eq!(
+            at("1714557600").as_deref(),
+            Some("2024-05-01T10:00:00+00:00")
+        );
+        assert_eq!(at("10m").as_deref(), Some("2024-05-01T09:50:00+00:00"));
+        assert_eq!(at("1d").as_deref(), Some("2024-04-30T10:00:00+00:00"));
+        assert_eq!(at("-5"), None);
+        assert_eq!(at("m"), None);
+        assert_eq!(at(""), None);
+        assert_eq!(at("1é"), None); // последний символ -- не один байт
+        assert_eq!(at("é"), None);
+    }
+
+    fn json_body(response: &Response) -> serde_json::Value {
+        match &response.body {
+            Some(BodyType::Json(json)) => json.clone(),
+            _ => panic!("expected JSON body"),
+        }
+    }
+
+    // Первые count строк бесконечного потока
+    fn read_lines(response: Response, count: usize) -> Vec<String> {
+        let Some(BodyType::Stream { mut stream, .. }) = response.body else {
+            panic!("expected streamed body");
+        };
+        std::io::BufRead::lines(std::io::BufReader::new(&mut stream))
+            .take(count)
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    #[test]
+    fn stats_snapshot_and_stream() {
+        let fake = fake();
+        fake.set_stats(
+            "web1",
+            ContainerStats {
+                cpu_percent: 12.345,
+                memory_usage: 256,
+                memory_limit: 1024,
+                network_rx: 1200,
+                pids: 3,
+                ..Default::default()
+            },
+        );
+        let state = state_with(&fake);
+
+        let response = handler_container_stats(&state, &request_for("web1"));
+        assert_eq!(response.response_code, StatusCode::OK);
+        let json = json_body(&response);
+        assert_eq!(json["name"], "web1");
+        assert_eq!(json["id"], fake.container("web1").unwrap().info.id);
+        assert_eq!(json["cpu_percent"], 12.35);
+        assert_eq!(json["memory_percent"], 25.0);
+        assert_eq!(json["network_rx"], 1200);
+        assert_eq!(json["pids"], 3);
+        assert!(json["read_at"].is_string());
+
+        // остановленный -- нули, но не ошибка
+        let json = json_body(&handler_container_stats(&state, &request_for("db")));
+        assert_eq!(
+            (json["cpu_percent"].clone(), json["pids"].clone()),
+            (0.0.into(), 0.into())
+        );
+
+        // все работающие: web1, flappy, sleepy
+        let json = json_body(&handler_all_container_stats(&state, &Request::default()));
+        let names: Vec<&str> = json
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|stats| stats["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["web1", "flappy", "sleepy"]);
+
+        let response =
+            handler_container_stats(&state, &logs_request("web1", "stream=true&interval=0.5"));
+        let Some(BodyType::Stream { content_type, .. }) = &response.body else {
+            panic!("expected streamed body");
+        };
+        assert_eq!(content_type, NDJSON);
+        let lines = read_lines(response, 3);
+        for line in &lines {
+            let json: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(json["pids"], 3);
+        }
+
+        let mut request = logs_request("stats", "stream&interval=0.5");
+        request.rest_params.clear();
+        let _ = request.headers.insert("Accept", "text/event-stream");
+        let lines = read_lines(handler_all_container_stats(&state, &request), 3);
+        assert_eq!(lines[0], "event: stats");
+        let snapshot: serde_json::Value =
+            serde_json::from_str(lines[1].strip_prefix("data: ").unwrap()).unwrap();
+        assert_eq!(snapshot[0]["name"], "web1");
+        assert_eq!(snapshot.as_array().map(Vec::len), Some(3));
+        assert_eq!(lines[2], "");
+    }
+
+    #[test]
+    fn stats_errors() {
+        let fake = fake();
+        let state = state_with(&fake);
+
+        for query in [
+            "interval=-1",
+            "interval=0",
+            "interval=0.1",
+            "interval=3601",
+            "interval=soon",
+            "stream=maybe",
+            "format=xml",
+        ] {
+            let response = handler_container_stats(&state, &logs_request("web1", query));
+            assert_eq!(response.response_code, StatusCode::BAD_REQUEST, "{query}");
+            let response = handler_all_container_stats(&state, &logs_request("web1", query));
+            assert_eq!(response.response_code, StatusCode::BAD_REQUEST, "{query}");
+        }
+        assert!(fake.calls().is_empty());
+
+        let response = handler_container_stats(&state, &request_for("ghost"));
+        assert_eq!(outcome(&response).1.as_deref(), Some("container_not_found"));
+
+        fake.fail_next(FakeOp::Stats, docker_error());
+        let response = handler_container_stats(&state, &request_for("web1"));
+        assert_eq!(
+            outcome(&response),
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Some("docker_error".to_string())
+            )
+        );
+
+        // ошибка посреди потока его завершает: в тексте -- просто конец, в SSE -- событие error
+        let response =
+            handler_container_stats(&state, &logs_request("web1", "stream=true&interval=0.5"));
+        fake.fail_next(FakeOp::Stats, docker_error());
+        assert_eq!(read_lines(response, 10).len(), 1);
+
+        let response = handler_container_stats(
+            &state,
+            &logs_request("web1", "stream=true&interval=0.5&format=sse"),
+        );
+        fake.fail_next(FakeOp::Stats, docker_error());
+        let lines = read_lines(response, 10);
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[3], "event: error");
+
+        fake.fail_next(FakeOp::List, docker_error());
+        let response = handler_all_container_stats(&state, &Request::default());
+        assert_eq!(response.response_code, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    fn exec_request(id: &str, query: &str, spec: serde_json::Value) -> Request {
+        Request {
+            body: Some(BodyType::Json(spec)),
+            ..logs_request(id, query)
+        }
+    }
+
+    #[test]
+    fn exec_runs_command() {
+        let fake = fake();
+        let state = state_with(&fake);
+
+        let response = handler_exec_container(
+            &state,
+            &exec_request("web1", "", serde_json::json!({"argv": ["echo", "ready"]})),
+        );
+        assert_eq!(response.response_code, StatusCode::OK);
+        let json = json_body(&response);
+        assert_eq!(json["exit_code"], 0);
+        assert_eq!(json["stdout"], "ready\n");
+        assert_eq!(json["stderr"], "");
+        assert_eq!(
+            (json["timed_out"].as_bool(), json["truncated"].as_bool()),
+            (Some(false), Some(false))
+        );
+        assert!(json["duration_ms"].is_u64());
+
+        // env, workdir и user доходят до команды
+        for (argv, expected) in [
+            ("env", "MODE=check\n"),
+            ("pwd", "/etc/nginx\n"),
+            ("whoami", "nginx\n"),
+        ] {
+            let response = handler_exec_container(
+                &state,
+                &exec_request(
+                    "web1",
+                    "",
+                    serde_json::json!({"argv": [argv], "env": {"MODE": "check"}, "workdir": "/etc/nginx", "user": "nginx"}),
+                ),
+            );
+            assert_eq!(json_body(&response)["stdout"], expected, "{argv}");
+        }
+
+        // код выхода команды -- это не ошибка API
+        let response = handler_exec_container(
+            &state,
+            &exec_request("web1", "", serde_json::json!({"argv": ["false"]})),
+        );
+        assert_eq!(response.response_code, StatusCode::OK);
+        assert_eq!(json_body(&response)["exit_code"], 1);
+
+        let response = handler_exec_container(
+            &state,
+            &exec_request("web1", "", serde_json::json!({"argv": ["pg_isready"]})),
+        );
+        let json = json_body(&response);
+        assert_eq!(json["exit_code"], 127);
+        assert!(
+            json["stderr"]
+                .as_str()
+                .unwrap()
+                .contains("\"pg_isready\": executable file not found")
+        );
+
+        assert_eq!(
+            fake.calls()
+                .iter()
+                .filter(|call| call.starts_with("exec"))
+                .collect::<Vec<_>>(),
+            vec![
+                "exec web1 echo ready",
+                "exec web1 env",
+                "exec web1 pwd",
+                "exec web1 whoami",
+                "exec web1 false",
+                "exec web1 pg_isready",
+            ]
+        );
+    }
+
+    #[test]
+    fn exec_timeout_and_output_limit() {
+        let fake = fake();
+        let state = AppState::with_backend(
+            Config::default()
+                .with_exec_timeout(Duration::from_millis(200))
+                .with_exec_output_limit(1000),
+            fake.clone(),
+        );
+
+        // команда не успела -- кода нет, но ответ есть
+        let response = handler_exec_container(
+            &state,
+            &exec_request("web1", "", serde_json::json!({"argv": ["sleep", "5"]})),
+        );
+        assert_eq!(response.response_code, StatusCode::OK);
+        let json = json_body(&response);
+        assert_eq!(json["exit_code"], serde_json::Value::Null);
+        assert_eq!(json["timed_out"], true);
+
+        let response = handler_exec_container(
+            &state,
+            &exec_request(
+                "web1",
+                "timeout=2",
+                serde_json::json!({"argv": ["sleep", "0.05"]}),
+            ),
+        );
+        assert_eq!(json_body(&response)["exit_code"], 0);
+
+        // бесконечный вывод обрезан по лимиту
+        let response = handler_exec_container(
+            &state,
+            &exec_request("web1", "", serde_json::json!({"argv": ["yes"]})),
+        );
+        let json = json_body(&response);
+        assert_eq!(json["stdout"].as_str().unwrap().len(), 1000);
+        assert_eq!(
+            (json["truncated"].as_bool(), json["timed_out"].as_bool()),
+            (Some(true), Some(true))
+        );
+
+        for query in [
+            "timeout=0",
+            "timeout=-1",
+            "timeout=3601",
+            "timeout=soon",
+            "stream=maybe",
+        ] {
+            let response = handler_exec_container(
+                &state,
+                &exec_request("web1", query, serde_json::json!({"argv": ["true"]})),
+            );
+            assert_eq!(response.response_code, StatusCode::BAD_REQUEST, "{query}");
+        }
+    }
+
+    // Поток бэкенда бросают не сразу: читающий поток сначала должен проснуться
+    fn wait_for_no_execs(fake: &FakeBackend) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while fake.running_execs() > 0 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        fake.running_execs() == 0
+    }
+
+    #[test]
+    fn exec_timeout_cancels_silent_command() {
+        let fake = fake();
+        let state = state_with(&fake);
+
+        // команда молчит: без отмены поток бэкенда ждал бы все 60 секунд
+        let response = handler_exec_container(
+            &state,
+            &exec_request(
+                "web1",
+                "timeout=0.1",
+                serde_json::json!({"argv": ["sleep", "60"]}),
+            ),
+        );
+        assert_eq!(json_body(&response)["timed_out"], true);
+        assert!(wait_for_no_execs(&fake));
+
+        // клиент ушёл, не дочитав поток
+        let response = handler_exec_container(
+            &state,
+            &exec_request(
+                "web1",
+                "stream=true",
+                serde_json::json!({"argv": ["sleep", "60"]}),
+            ),
+        );
+        assert_eq!(fake.running_execs(), 1);
+        drop(response);
+        assert!(wait_for_no_execs(&fake));
+    }
+
+    #[test]
+    fn exec_stream() {
+        let fake = fake();
+        let state = state_with(&fake);
+
+        let response = handler_exec_container(
+            &state,
+            &exec_request(
+                "web1",
+                "stream=true",
+                serde_json::json!({"argv": ["pg_isready"]}),
+            ),
+        );
+        let Some(BodyType::Stream { content_type, .. }) = &response.body else {
+            panic!("expected streamed body");
+        };
+        assert_eq!(content_type, NDJSON);
+        let lines: Vec<serde_json::Value> = read_lines(response, 10)
+            .iter()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["stream"], "stderr");
+        assert_eq!(lines[1]["exit_code"], 127);
+
+        let response = handler_exec_container(
+            &state,
+            &exec_request(
+                "web1",
+                "stream=true&format=sse",
+                serde_json::json!({"argv": ["echo", "hi"]}),
+            ),
+        );
+        let lines = read_lines(response, 10);
+        assert_eq!(lines[0], "event: stdout");
+        assert_eq!(lines[1], r#"data: {"data":"hi\n","stream":"stdout"}"#);
+        assert_eq!(lines[3], "event: exit");
+
+        // символ, разрезанный между кусками вывода, не портится
+        let mut pending = "дом".as_bytes()[..3].to_vec();
+        assert_eq!(take_utf8(&mut pending), "д");
+        assert_eq!(pending, vec![0xd0]);
+        pending.extend(&"дом".as_bytes()[3..]);
+        assert_eq!(take_utf8(&mut pending), "ом");
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn exec_errors() {
+        for (id, status, code) in [
+            ("sleepy", StatusCode::CONFLICT, "container_paused"),
+            ("flappy", StatusCode::CONFLICT, "container_restarting"),
+            ("db", StatusCode::CONFLICT, "container_not_running"),
+            ("zombie", StatusCode::CONFLICT, "container_not_running"),
+            ("ghost", StatusCode::NOT_FOUND, "container_not_found"),
+        ] {
+            let fake = fake();
+            let state = state_with(&fake);
+            let response = handler_exec_container(
+                &state,
+                &exec_request(id, "", serde_json::json!({"argv": ["true"]})),
+            );
+            assert_eq!(outcome(&response), (status, Some(code.to_string())), "{id}");
+            assert!(!fake.calls().iter().any(|call| call.starts_with("exec")));
+        }
+
+        let fake = fake();
+        let state = state_with(&fake);
+        let response = handler_exec_container(
+            &state,
+            &exec_request("web1", "", serde_json::json!({"argv": "nginx -t"})),
+        );
+        assert_eq!(
+            outcome(&response),
+            (StatusCode::BAD_REQUEST, Some("invalid_spec".to_string()))
+        );
+        assert_eq!(json_body(&response)["error"]["field"], "argv");
+        let response = handler_exec_container(&state, &request_for("web1"));
+        assert_eq!(
+            outcome(&response),
+            (StatusCode::BAD_REQUEST, Some("invalid_spec".to_string()))
+        );
+        assert!(fake.calls().is_empty());
+
+        fake.fail_next(FakeOp::Exec, docker_error());
+        let response = handler_exec_container(
+            &state,
+            &exec_request("web1", "", serde_json::json!({"argv": ["true"]})),
+        );
+        assert_eq!(
+            outcome(&response),
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Some("docker_error".to_string())
+            )
+        );
+    }
+
+    fn attach_request(id: &str, query: &str) -> Request {
+        let mut request = logs_request(id, query);
+        for (name, value) in [
+            ("Upgrade", "websocket"),
+            ("Connection", "Upgrade"),
+            ("Sec-WebSocket-Key", "dGhlIHNhbXBsZSBub25jZQ=="),
+            ("Sec-WebSocket-Version", "13"),
+        ] {
+            request.headers.insert(name, value).unwrap();
+        }
+        request
+    }
+
+    #[test]
+    fn attach_starts_tty_session() {
+        let fake = fake();
+        let state = state_with(&fake);
+
+        let response = handler_attach_container(
+            &state,
+            &attach_request("web1", "cmd=bash&user=www-data&rows=40&cols=120"),
+        );
+        assert_eq!(response.response_code, StatusCode::SWITCHING_PROTOCOLS);
+        assert_eq!(
+            response.headers.get("sec-websocket-accept"),
+            Some("s3pPLMBiTxaQ9kYGzzhZRbK+xOo=")
+        );
+        assert!(matches!(response.body, Some(BodyType::Upgrade(_))));
+        // команду запускаем ещё до 101, чтобы ошибку можно было вернуть по HTTP
+        assert_eq!(fake.calls(), vec!["list", "inspect web1", "exec web1 bash"]);
+
+        let response = handler_attach_container(&state, &attach_request("web1", ""));
+        assert_eq!(response.response_code, StatusCode::SWITCHING_PROTOCOLS);
+        assert_eq!(fake.calls().last().unwrap(), "exec web1 /bin/sh");
+    }
+
+    #[test]
+    fn attach_errors() {
+        for (id, status, code) in [
+            ("sleepy", StatusCode::CONFLICT, "container_paused"),
+            ("flappy", StatusCode::CONFLICT, "container_restarting"),
+            ("db", StatusCode::CONFLICT, "container_not_running"),
+            ("ghost", StatusCode::NOT_FOUND, "container_not_found"),
+        ] {
+            let fake = fake();
+            let state = state_with(&fake);
+            let response = handler_attach_container(&state, &attach_request(id, ""));
+            assert_eq!(outcome(&response), (status, Some(code.to_string())), "{id}");
+        }
+
+        let fake = fake();
+        let state = state_with(&fake);
+
+        // обычный HTTP-запрос -- 426 с подсказкой, контейнер даже не ищем
+        let response = handler_attach_container(&state, &request_for("web1"));
+        assert_eq!(
+            outcome(&response),
+            (
+                StatusCode::UPGRADE_REQUIRED,
+                Some("upgrade_required".to_string())
+            )
+        );
+        assert_eq!(response.headers.get("upgrade"), Some("websocket"));
+
+        for query in ["rows=0", "cols=70000", "workdir=relative", "cmd="] {
+            let response = handler_attach_container(&state, &attach_request("web1", query));
+            assert_eq!(response.response_code, StatusCode::BAD_REQUEST, "{query}");
+        }
+        assert!(fake.calls().is_empty());
+
+        // docker CLI терминал не даёт
+        fake.fail_next(
+            FakeOp::Exec,
+            ContainerError::Unsupported("needs CONTAINER_BACKEND=api".to_string()),
+        );
+        let response = handler_attach_container(&state, &attach_request("web1", ""));
+        assert_eq!(
+            outcome(&response),
+            (
+                StatusCode::NOT_IMPLEMENTED,
+                Some("not_supported".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn attach_messages() {
+        assert_eq!(
+            parse_attach_message(r#"{"type": "input", "data": "ls\r"}"#),
+            Ok(AttachMessage::Input(b"ls\r".to_vec()))
+        );
+        assert_eq!(
+            parse_attach_message(r#"{"type": "resize", "rows": 40, "cols": 120}"#),
+            Ok(AttachMessage::Resize(TtySize {
+                rows: 40,
+                cols: 120
+            }))
+        );
+        for broken in [
+            "ls",
+            r#"{"type": "input"}"#,
+            r#"{"type": "resize", "rows": 0, "cols": 80}"#,
+            r#"{"type": "resize", "rows": 24, "cols": 65536}"#,
+            r#"{"type": "signal", "name": "SIGINT"}"#,
+        ] {
+            assert!(parse_attach_message(broken).is_err(), "{broken}");
+        }
+    }
 }
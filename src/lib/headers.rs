@@ -0,0 +1,179 @@
+use crate::lib::server_errors::ServerError;
+
+// Заголовки запроса/ответа
+//
+// Имена сравниваются без учёта регистра, один заголовок может встречаться несколько раз
+// (Set-Cookie, Vary, ...), поэтому храним пары в порядке добавления:
+//   [("Content-Type", "application/json"), ("Set-Cookie", "a=1"), ("Set-Cookie", "b=2")]
+//
+// Имя и значение проверяются при добавлении, поэтому в HeaderMap не может попасть
+// "X-Id: 1\r\nSet-Cookie: admin=1" -- deser_response выводит заголовки как есть
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct HeaderMap {
+    headers: Vec<(String, String)>,
+}
+
+impl HeaderMap {
+    pub const fn new() -> Self {
+        Self {
+            headers: Vec::new(),
+        }
+    }
+
+    // Собираем из сырых строк "Name: value" (заголовки запроса, трейлеры)
+    pub fn from_lines<I, S>(lines: I) -> Result<Self, ServerError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut headers = Self::new();
+        for line in lines {
+            let line = line.as_ref();
+            let (name, value) = line.split_once(':').ok_or(ServerError::ParseError(format!(
+                "Invalid header line: {line:?}"
+            )))?;
+            headers.append(name, value)?;
+        }
+        Ok(headers)
+    }
+
+    // Добавляем ещё одно значение, не трогая уже существующие
+    pub fn append(&mut self, name: &str, value: &str) -> Result<(), ServerError> {
+        let (name, value) = validate(name, value)?;
+        self.headers.push((name.to_string(), value.to_string()));
+        Ok(())
+    }
+
+    // Заменяем все значения заголовка одним
+    pub fn insert(&mut self, name: &str, value: &str) -> Result<(), ServerError> {
+        let (name, value) = validate(name, value)?;
+        self.remove(name);
+        self.headers.push((name.to_string(), value.to_string()));
+        Ok(())
+    }
+
+    // Удаляем заголовок целиком, возвращаем true, если он был
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.headers.len();
+        self.headers
+            .retain(|(key, _)| !key.eq_ignore_ascii_case(name));
+        self.headers.len() != len
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.headers
+            .iter()
+            .any(|(key, _)| key.eq_ignore_ascii_case(name))
+    }
+
+    // Первое значение заголовка
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    // Все значения заголовка в порядке появления
+    pub fn get_all(&self, name: &str) -> Vec<&str> {
+        self.headers
+            .iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    // Есть ли токен в списке через запятую: has_token("connection", "close")
+    // для "Connection: keep-alive, Close" вернёт true
+    pub fn has_token(&self, name: &str, token: &str) -> bool {
+        self.get_all(name).iter().any(|value| {
+            value
+                .split(',')
+                .any(|t| t.trim().eq_ignore_ascii_case(token))
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.headers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+}
+
+// Имя -- token из RFC 9110: буквы, цифры и !#$%&'*+-.^_`|~
+// Значение -- видимые символы, пробелы и табуляция; CR, LF и прочие управляющие запрещены.
+// Пробелы по краям значения не считаются его частью и обрезаются, а вот в имени
+// (в том числе перед двоеточием: "Host : x") они запрещены -- RFC 9112, 5.1
+fn validate<'a>(name: &'a str, value: &'a str) -> Result<(&'a str, &'a str), ServerError> {
+    let value = value.trim_matches([' ', '\t']);
+
+    let valid_name = !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b));
+    if !valid_name {
+        return Err(ServerError::ParseError(format!(
+            "Invalid header name: {name:?}"
+        )));
+    }
+
+    if value.bytes().any(|b| (b < 0x20 && b != b'\t') || b == 0x7f) {
+        return Err(ServerError::ParseError(format!(
+            "Invalid value for header '{name}': {value:?}"
+        )));
+    }
+
+    Ok((name, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn case_insensitive_multi_value() -> Result<(), ServerError> {
+        let mut headers =
+            HeaderMap::from_lines(["Host: localhost", "Connection: keep-alive, Close"])?;
+        headers.append("Set-Cookie", "a=1")?;
+        headers.append("set-cookie", "b=2")?;
+
+        assert_eq!(headers.get("HOST"), Some("localhost"));
+        assert_eq!(headers.get_all("SET-COOKIE"), vec!["a=1", "b=2"]);
+        assert!(headers.has_token("connection", "close"));
+        assert!(!headers.has_token("connection", "upgrade"));
+
+        headers.insert("Set-Cookie", "c=3")?;
+        assert_eq!(headers.get_all("set-cookie"), vec!["c=3"]);
+
+        assert!(headers.remove("host"));
+        assert!(!headers.contains("Host"));
+        assert_eq!(headers.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_header_injection() {
+        let mut headers = HeaderMap::new();
+
+        assert!(headers.append("X-Id", "1\r\nSet-Cookie: admin=1").is_err());
+        assert!(headers.append("X-Id", "1\n").is_err());
+        assert!(headers.append("X-Id\r\nEvil", "1").is_err());
+        assert!(headers.append("X Id", "1").is_err());
+        assert!(headers.append("", "1").is_err());
+        assert!(headers.append("X-Id ", "1").is_err());
+        assert!(headers.is_empty());
+
+        assert!(HeaderMap::from_lines(["no colon here"]).is_err());
+        assert!(HeaderMap::from_lines(["Host : x"]).is_err());
+        assert!(HeaderMap::from_lines(["Host:x"]).is_ok());
+    }
+}
@@ -1,224 +1,235 @@
 use crate::lib::config;
 
 use std::{
-    collections::HashMap,
-    io::{BufRead, BufReader, Write},
-    net::{SocketAddr, TcpListener},
+    io::{self, BufReader, Cursor, ErrorKind, Read},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr, TcpListener, TcpStream},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
 };
 
 use crate::lib::{
+    api_error::{ApiError, REQUEST_ID_HEADER, is_valid_request_id, next_request_id},
+    headers::HeaderMap,
     logger::Logger,
-    parse_funcs::{deser_response, parse_request},
-    req_res_structs::{Method, Response},
+    parse_funcs::{parse_request, read_request, write_response},
+    req_res_structs::{
+        BodyStream, BodyType, Method, Response, StatusCode, Upgrade, UpgradedConnection, Version,
+    },
     request::Request,
+    router::Router,
     server_errors::ServerError,
+    thread_pool::ThreadPool,
 };
 
-type HandlerFn = fn(&Request) -> Response;
+pub type HandlerFn = Arc<dyn Fn(&Request) -> Response + Send + Sync>;
 // То есть, например, handle_home(req) принимает на вход Request и возвращает Response.
+// Хендлер -- любое замыкание (может захватить конфиг, логгер, AppState...).
+// Arc, потому что хендлеры общие для всех воркеров
+
+// Ответ, когда все воркеры заняты: просим клиента повторить запрос через секунду
+fn service_unavailable_response() -> Response {
+    let mut response = ApiError::new(
+        StatusCode::SERVICE_UNAVAILABLE,
+        "server_busy",
+        "All workers are busy, retry later",
+    )
+    .into_response_with_id(&next_request_id());
+    let _ = response.headers.insert("Retry-After", "1");
+    response
+}
 
-const BAD_REQUEST_RESPONSE: Response = Response {
-    response_code: 404,
-    headers: None,
-    body: None,
-};
+// Сколько accept-цикл готов потратить на вычитывание запроса, которому ответили 503
+const BUSY_DRAIN_TIMEOUT: Duration = Duration::from_millis(100);
+const BUSY_DRAIN_LIMIT: usize = 64 * 1024;
+
+// Отвечаем 503 и закрываем соединение. Запрос клиента мы не читали: если закрыть
+// сокет с непрочитанными данными, ядро отправит RST вместо FIN, и клиент может
+// потерять уже отправленный ему ответ. Поэтому сначала закрываем запись (FIN),
+// а затем недолго вычитываем то, что клиент успел прислать
+fn reject_busy(mut stream: TcpStream) {
+    let _ = write_response(
+        &mut stream,
+        service_unavailable_response(),
+        Version::Http11,
+        false,
+    );
+    let _ = stream.shutdown(Shutdown::Write);
+
+    let deadline = Instant::now() + BUSY_DRAIN_TIMEOUT;
+    let mut drained = 0;
+    let mut buf = [0u8; 4096];
+    while drained < BUSY_DRAIN_LIMIT {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() || stream.set_read_timeout(Some(remaining)).is_err() {
+            break;
+        }
+        match stream.read(&mut buf) {
+            Ok(0) | Err(_) => break, // клиент закрыл соединение или молчит
+            Ok(n) => drained += n,
+        }
+    }
+}
 
-const NOT_FOUND_RESPONSE: Response = Response {
-    response_code: 404,
-    headers: None,
-    body: None,
-};
+// Как часто простаивающее keep-alive соединение проверяет, не останавливается ли сервер
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(100);
 
-#[derive(Debug)]
 pub struct Server {
     listener: TcpListener,
-    handlers: HashMap<Method, HashMap<&'static str, HandlerFn>>,
+    router: Router,
+    shutdown: ShutdownHandle,
     pub log: Logger,
     pub config: config::Config,
 }
 
-/*
-handlers                          // HashMap<Method, …>
-│
-├── Method::GET ──┐               // 1‑й уровень
-│                 │
-│   +---------------------------+  // 2‑й уровень (HashMap<String, HandlerFn>)
-│   |  "/home"    → handle_home |
-│   |  "/about"   → handle_about|
-│   |  "/contact" → handle_contact
-│   +---------------------------+
-│
-├── Method::POST ─┐
-│                 │
-│   +---------------------------+
-│   | "/submit"   → handle_submit
-│   +---------------------------+
-│
-├── Method::PUT  ─┐
-│                 │
-│   +---------------------------+
-│   | "/api/user" → handle_put_user
-│   +---------------------------+
-│
-└── Method::DELETE ─┐
-                  │
-    +---------------------------+
-    | "/api/user" → handle_del_user
-    +---------------------------+
-
-*/
+// Замыкания не реализуют Debug, поэтому показываем только зарегистрированные пути
+impl std::fmt::Debug for Server {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Server")
+            .field("listener", &self.listener)
+            .field("routes", &self.router.routes())
+            .field("shutdown", &self.shutdown)
+            .field("log", &self.log)
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+// Ручка для остановки сервера из другого потока (обработчик сигналов, тесты).
+// Её можно клонировать и отдавать куда угодно: все копии управляют одним сервером
+#[derive(Debug, Clone)]
+pub struct ShutdownHandle {
+    requested: Arc<AtomicBool>,
+    addr: SocketAddr, // адрес listener-а: на него стучимся, чтобы разбудить accept()
+}
+
+impl ShutdownHandle {
+    // Просим сервер остановиться: перестать принимать подключения,
+    // дать текущим запросам доработать (не дольше config.shutdown_timeout)
+    // и вернуться из start()
+    pub fn shutdown(&self) {
+        if self.requested.swap(true, Ordering::SeqCst) {
+            return; // уже остановлен
+        }
+
+        // accept() блокирующий и сам флаг не увидит -- будим его пустым подключением к себе
+        let ip = match self.addr.ip() {
+            IpAddr::V4(ip) if ip.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(ip) if ip.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            ip => ip,
+        };
+        let _ = TcpStream::connect_timeout(
+            &SocketAddr::new(ip, self.addr.port()),
+            Duration::from_secs(1),
+        );
+    }
+
+    pub fn is_shutdown(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+}
 
 impl Server {
     pub fn with_config(config: config::Config) -> Result<Server, ServerError> {
+        config.validate()?;
         let log = Logger::with_config(&config);
 
         let listener = TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], config.port)))
             .map_err(|e| ServerError::InitError(format!("Failed to init TCP listener: {e}")))?;
 
-        // Инициализируем нашу Hash-map таблицу, которая будет хранить handlers для различных путей
-        let mut handlers: HashMap<Method, HashMap<&str, HandlerFn>> = HashMap::new();
-
-        handlers.insert(Method::GET, HashMap::new());
-        handlers.insert(Method::POST, HashMap::new());
-        handlers.insert(Method::PUT, HashMap::new());
-        handlers.insert(Method::DELETE, HashMap::new());
-        handlers.insert(Method::OTHER, HashMap::new());
+        let shutdown = ShutdownHandle {
+            requested: Arc::new(AtomicBool::new(false)),
+            addr: listener
+                .local_addr()
+                .map_err(|e| ServerError::InitError(format!("Failed to get local address: {e}")))?,
+        };
 
         // Возвращаем наш объект сервера
         Ok(Self {
             listener,
-            handlers,
+            router: Router::new(), // дерево маршрутов, см. router.rs
+            shutdown,
             log,
             config,
         })
     }
 
+    // Адрес, на котором реально слушает сервер (полезно, если в конфиге порт 0)
+    pub fn local_addr(&self) -> SocketAddr {
+        self.shutdown.addr
+    }
+
+    // Ручка, через которую можно остановить запущенный сервер из другого потока
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
     // Новый экземпляр сервера
     pub fn new() -> Result<Server, ServerError> {
         Self::with_config(config::Config::default())
     }
 
-    pub fn add_handler(
+    pub fn add_handler<F>(
         &mut self,
         method: Method,
         path: &'static str,
-        handler: HandlerFn,
-    ) -> Result<(), ServerError> {
-        let paths: &mut HashMap<&str, HandlerFn> = self.handlers.get_mut(&method).unwrap(); // Получаем Hash-map таблицу с путями и handlers
-        if paths.contains_key(&path) {
-            // в Hash-map таблице уже есть такой путь? лови ошибку
-
-            let err_msg = format!("{method} handler with path '{path}' already registered!");
-
-            self.log.info(&err_msg);
-            return Err(ServerError::HandlerError(err_msg));
+        handler: F,
+    ) -> Result<(), ServerError>
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
+        // конфликт с уже зарегистрированным маршрутом? лови ошибку
+        if let Err(e) = self.router.add(method, path, Arc::new(handler)) {
+            self.log.info(&format!("{e}"));
+            return Err(e);
         }
 
-        paths.insert(path, handler); // добавляем handler в Hash-map таблицу по заданному пути
         self.log
             .info(&format!("📌 Handler registered: {method} {path}"));
         Ok(())
     }
 
     #[allow(non_snake_case)]
-    pub fn GET(&mut self, path: &'static str, handler: HandlerFn) {
+    pub fn GET<F>(&mut self, path: &'static str, handler: F)
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
         self.add_handler(Method::GET, path, handler).unwrap()
     }
 
     #[allow(non_snake_case)]
-    pub fn POST(&mut self, path: &'static str, handler: HandlerFn) {
+    pub fn POST<F>(&mut self, path: &'static str, handler: F)
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
         self.add_handler(Method::POST, path, handler).unwrap()
     }
 
     #[allow(non_snake_case)]
-    pub fn PUT(&mut self, path: &'static str, handler: HandlerFn) {
+    pub fn PUT<F>(&mut self, path: &'static str, handler: F)
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
         self.add_handler(Method::PUT, path, handler).unwrap()
     }
 
     #[allow(non_snake_case)]
-    pub fn DELETE(&mut self, path: &'static str, handler: HandlerFn) {
-        self.add_handler(Method::DELETE, path, handler).unwrap()
+    pub fn PATCH<F>(&mut self, path: &'static str, handler: F)
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
+        self.add_handler(Method::PATCH, path, handler).unwrap()
     }
 
-    /*
-    Пусть у Hash-map таблицы есть объем в 8 едениниц, т.е.
-    InnerMap (capacity = 8):
-
-    Index  0 │  []
-    Index  1 │  []
-    Index  2 │  []
-    Index  3 │  []
-    Index  4 │  []
-    Index  5 │  []
-    Index  6 │  []
-    Index  7 │  []
-
-    №1.Вставка первого обработчика событий GET
-    server.GET("/home".to_sting(), handler_home)?;
-
-    1. Вычисляем hash("/home"). Пусть получилось h = 11.
-    2. Берём bucket_idx = h % 8 = 11 % 8 = 3.
-    3. Идём в бакет под индексом 3 и кладём туда пару ("/home", handler_home).
-
-    Index  0 │  []
-    Index  1 │  []
-    Index  2 │  []
-    Index  3 │  [ ("/home", handler_home) ]
-    Index  4 │  []
-    Index  5 │  []
-    Index  6 │  []
-    Index  7 │  []
-
-    №2. Вставка второго обработчика событий GET
-    server.GET("/about".to_sting(), handler_about)?;
-
-    1. Вычисляем hash("/about"). Пусть получилось h = 1.
-    2. Берём bucket_idx = h % 8 = 1 % 8 = 1.
-    3. Идём в бакет под индексом 1 и кладём туда пару ("/about", handler_about).
-
-    Index  0 │  []
-    Index  1 │  [ ("/about", handler_about) ]
-    Index  2 │  []
-    Index  3 │  [ ("/home", handler_home) ]
-    Index  4 │  []
-    Index  5 │  []
-    Index  6 │  []
-    Index  7 │  []
-
-    №3. Вставка третьего обработчика событий GET
-    server.GET("/contact".into(), handler_contact)?;
-
-    1. Вычисляем hash("/about"). Пусть получилось h = 3.
-    2. Берём bucket_idx = h % 8 = 3 % 8 = 1.
-    3. Идём в бакет под индексом 3 и кладём туда пару ("/about", handler_about). Но с коллизией
-
-    Index  0 │  []
-    Index  1 │  [ ("/about",   handler_about) ]
-    Index  2 │  []
-    Index  3 │  [ ("/home",    handler_home)
-            └─ ("/contact", handler_contact) ]
-    Index  4 │  []
-    Index  4 │  []
-    Index  5 │  []
-    Index  6 │  []
-    Index  7 │  []
-
-    После заполнения Hash-map таблицы на 90% произойдет расширение: 8 -> 16
-    + произойдет рехеширование и перераспределение элементов.
-
-
-    New InnerMap (capacity = 16):
-
-    Index  0 │  []
-    Index  1 │  [ ("/about",   handler_about) ]
-    Index  2 │  []
-    Index  3 │  [ ("/contact", handler_contact) ]
-    Index  4 │  []
-    …
-    Index 11 │  [ ("/home",    handler_home) ]
-    …
-    Index 15 │  []
-     */
+    #[allow(non_snake_case)]
+    pub fn DELETE<F>(&mut self, path: &'static str, handler: F)
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
+        self.add_handler(Method::DELETE, path, handler).unwrap()
+    }
 
     // ПОКА НИ НАДА
     // pub fn middleware<F>(&mut self, middleware: F)
@@ -241,101 +252,50 @@ impl Server {
     pub fn start(&self) -> Result<(), ServerError> {
         Logger::motd();
 
-        let port = self.listener.local_addr().unwrap().port();
+        let port = self.local_addr().port();
 
         self.log.info(&format!("Server started at port: {port}"));
 
+        // Всё, что нужно воркерам, копируем в общий для потоков контекст
+        let context = Arc::new(ServerContext {
+            router: self.router.clone(),
+            log: self.log,
+            config: self.config.clone(),
+            shutdown: self.shutdown.clone(),
+        });
+
+        // Подключения обрабатывают воркеры пула, а этот поток только принимает их
+        let pool: ThreadPool<TcpStream> = {
+            let context = Arc::clone(&context);
+            ThreadPool::new(
+                self.config.workers,
+                self.config.queue_size,
+                self.log,
+                move |stream: TcpStream| context.handle_connection(stream),
+            )?
+        };
+
+        self.log.info(&format!(
+            "Worker pool started: {} workers, queue size {}",
+            self.config.workers, self.config.queue_size
+        ));
+
         // Проходимся по бесконечному итератору входящих подключений
         // Почему бесконечный? Потому-что даже когда подключения закончатся,
-        // Он будет ожидать дальнейших подключений
+        // Он будет ожидать дальнейших подключений.
+        // Выходим из него только по ShutdownHandle::shutdown()
         for stream in self.listener.incoming() {
+            if self.shutdown.is_shutdown() {
+                break;
+            }
+
             match stream {
-                Ok(mut stream) => {
-                    // если подключение по кайфу установлено и получили поток информации
-                    let bufreader = BufReader::new(&stream); // создаём буферный читатель из нашего TCP потока
-
-                    /*
-                    cap -- сколько байт сейчас лежит в буфере
-                    pos -- картека, индекс следующего байта в данном диапазоне
-
-                    BufReader<R> хранит в себе следующие компоненты:
-
-                    1. Внутренний ридер (inner: R)
-                    2. Буфер (buf: Vec<u8>)
-                    3. Индексы состояния (pos: usize и cap: usize)
-
-                    Внутренний ридер -- это источник информации. Если в буфере пусто, тогда cap = 0, а pos = 0. Т.к. pos>=cap , я запрошу информацию от внутреннего ридера. При этом он оценит количество байт и это будет моё новое значение cap (т.е. я могу не заполнить весь буфер)
-
-                    Кареткой я буду считывать до тех пор, пока вновь не выполнится pos>=cap.
-                    BufReader
-                    ├─ inner: TcpStream { … }
-                    ├─ buf: Vec<u8> (capacity 8192)
-                    └─ [raw]: (pos: 0, cap: 0)*/
-                    let raw_request: String = bufreader
-                        .lines() // возвращает итератор по строкам из буферизированного читателя (ридера)
-                        // итератор выдает элементы типа Result<String, std::io::Error>
-                        // удаляет символ /n. Если перед ним был /r тоже удаляет
-                        .map(|result| result.unwrap() + "\r\n") // кратко: к каждой строке добавляем символ новой строки ("\r\n")
-                        /* подробно: result.unwrap() извлекает String из Ok(String) или ломается, если при чтении произошел Error
-                        т.е. распаковываем успешный результат чтения строки + добавляем \r\n, т.к. .lines убирает \r\n
-                        в итоге получаем после .map преобразование итератора:
-                        Исходный итератор был: Iterator<Item = Result<String, std::io::Error>>
-                        В результате стал: Iterator<Item = String> */
-                        .take_while(|line| !line.trim_end().is_empty()) // Обрабатываем итератор, пока не встретим пустую строку
-                        // т.е. берем строки из итератора, пока не встретим пустую строку, т.е. CRLF-строку в raw-HTTP request (т.е. пока не встретим \r\n, которая означает конец заголовков)
-                        // take_while останавливает итерацию, когда встретится пустая строка "". Сама пустая строка в результат не попадает
-                        // + .take_while не изменяет строки
-                        .collect(); // Собираем всё в тип String
-                    // собирает все оставшиеся элементы итератора и скеивает их в контейнер нужного типа (String, т.к. мы его явно задали при let raw_request: String)
-                    // у нас остаётся \r\n в конце каждой строки, т.к. мы вернули эту последовательность в map, а .take_while не изменяет строки
-                    // println!("{}",raw_request);
-                    match parse_request(raw_request) {
-                        // Если получилось нормально спарсить запрос
-                        Ok(mut request) => {
-                            let mut found_path = false;
-
-                            for (key, value) in self.handlers.get(&request.method).unwrap() {
-                                // self.handlers - это HashMap<Method, HashMap<String, HandlerFn>> (поле struct Server)
-
-                                // self.handlers.get(&request.method)
-                                // попытка найти на 1м уровне HashMap запись по ключу типа Method
-                                // например .get(POST) ищу на 1м уровне HashMap table ключ = Method::POST
-                                //           возвращает Option<&HashMap<String, HandlerFn>>
-                                //           если запись есть, возвращается Some(HashMap<String, HanlerFn>)
-                                //           если нет, возвращаю None
-
-                                // .unwrap() достаёт ссылку на значение из 1-го уровня HashMap table (т.е. из Some) => &HashMap<String, HandlerFn>
-
-                                // Далее происходит итерирование по 2му уровню HashMap table
-
-                                if request.is_similar(key) {
-                                    request.parse_args(key);
-
-                                    let response = value(&request);
-
-                                    self.log.info(&format!(
-                                        "Handler triggered for route: {} {}",
-                                        request.method, request.path
-                                    ));
-
-                                    let deserialized_response = deser_response(response);
-
-                                    let _ = stream.write_all(deserialized_response.as_bytes());
-                                    found_path = true;
-                                    break;
-                                }
-                            }
-
-                            if !found_path {
-                                let _ =
-                                    stream.write_all(deser_response(NOT_FOUND_RESPONSE).as_bytes());
-                            }
-                        }
-                        Err(e) => {
-                            self.log.debug(&format!("Server error: {e}"));
-                            let _ =
-                                stream.write_all(deser_response(BAD_REQUEST_RESPONSE).as_bytes());
-                        }
+                Ok(stream) => {
+                    // все воркеры заняты и очередь заполнена -- не ждём, а сразу отвечаем 503
+                    if let Err(stream) = pool.try_execute(stream) {
+                        self.log
+                            .warn(&"Connection queue is full, responding 503".to_string());
+                        reject_busy(stream);
                     }
                 }
                 Err(e) => {
@@ -344,6 +304,426 @@ impl Server {
                 }
             }
         }
+
+        // Новые подключения больше не принимаем, ждём текущие запросы (docker stop и т.п.)
+        self.log.info(&format!(
+            "Shutting down, waiting up to {}s for in-flight requests",
+            self.config.shutdown_timeout.as_secs()
+        ));
+        if !pool.shutdown(self.config.shutdown_timeout) {
+            self.log
+                .warn(&"Shutdown deadline exceeded, abandoning unfinished requests".to_string());
+        }
+        self.log.info(&"Server stopped".to_string());
+
         Ok(())
     }
 }
+
+// Общий для всех воркеров контекст сервера (только для чтения)
+struct ServerContext {
+    router: Router,
+    log: Logger,
+    config: config::Config,
+    shutdown: ShutdownHandle,
+}
+
+impl ServerContext {
+    // Обработка одного подключения (HTTP/1.1 keep-alive):
+    // читаем запросы один за другим, пока клиент не попросит закрыть соединение,
+    // не истечёт таймаут простоя или не кончится лимит запросов на соединение.
+    // Ответы пишем строго в порядке запросов, поэтому pipelining работает сам собой
+    fn handle_connection(&self, stream: TcpStream) {
+        // сколько ждать следующий запрос, прежде чем закрыть простаивающее соединение
+        let _ = stream.set_read_timeout(Some(self.config.keep_alive_timeout));
+
+        // если подключение по кайфу установлено и получили поток информации
+        let mut bufreader = BufReader::new(&stream); // создаём буферный читатель из нашего TCP потока
+        let mut writer = &stream; // писать в TcpStream можно и через &TcpStream
+
+        /*
+        cap -- сколько байт сейчас лежит в буфере
+        pos -- картека, индекс следующего байта в данном диапазоне
+
+        BufReader<R> хранит в себе следующие компоненты:
+
+        1. Внутренний ридер (inner: R)
+        2. Буфер (buf: Vec<u8>)
+        3. Индексы состояния (pos: usize и cap: usize)
+
+        Внутренний ридер -- это источник информации. Если в буфере пусто, тогда cap = 0, а pos = 0. Т.к. pos>=cap , я запрошу информацию от внутреннего ридера. При этом он оценит количество байт и это будет моё новое значение cap (т.е. я могу не заполнить весь буфер)
+
+        Кареткой я буду считывать до тех пор, пока вновь не выполнится pos>=cap.
+        BufReader
+        ├─ inner: TcpStream { … }
+        ├─ buf: Vec<u8> (capacity 8192)
+        └─ [raw]: (pos: 0, cap: 0)
+
+        Один и тот же BufReader живёт всё соединение: если клиент прислал
+        несколько запросов разом (pipelining), то «лишние» байты остаются
+        в буфере и будут прочитаны на следующей итерации*/
+
+        let mut served: usize = 0; // сколько запросов уже обслужили на этом соединении
+
+        loop {
+            // следующий запрос уже лежит в буфере (pipelining) или ждём его из сокета
+            if bufreader.buffer().is_empty() && !self.wait_for_request(&stream) {
+                return;
+            }
+
+            // Читаем заголовки до пустой строки, а затем тело
+            let raw_request: Vec<u8> = match read_request(&mut bufreader, self.config.max_body_size)
+            {
+                Ok(Some(raw)) => raw,
+                Ok(None) => return, // клиент закрыл соединение или молчал дольше таймаута
+                Err(e) => {
+                    // после ошибки чтения непонятно, где начинается следующий запрос -- закрываем
+                    self.log.debug(&format!("Server error: {e}"));
+                    let response = ApiError::from(e).into_response_with_id(&next_request_id());
+                    let response = with_connection_headers(response, false, None);
+                    let _ = write_response(&mut writer, response, Version::Http11, false);
+                    return;
+                }
+            };
+
+            served += 1;
+
+            let (mut response, keep_alive, version, is_head) = match parse_request(raw_request) {
+                // Если получилось нормально спарсить запрос
+                Ok(mut request) => {
+                    let keep_alive = request.wants_keep_alive()
+                        && served < self.config.max_requests_per_connection
+                        && !self.shutdown.is_shutdown(); // сервер останавливается -- закрываемся после ответа
+                    assign_request_id(&mut request);
+                    let mut response = self.dispatch(&mut request);
+                    // по X-Request-Id клиент найдёт свой запрос в логах сервера
+                    if !response.headers.contains(REQUEST_ID_HEADER) {
+                        let _ = response
+                            .headers
+                            .insert(REQUEST_ID_HEADER, request.request_id());
+                    }
+                    // хендлер сам может попросить закрыть соединение.
+                    // Потоковое тело HTTP/1.0 клиенту заканчивается закрытием соединения
+                    let keep_alive = keep_alive
+                        && !closes_connection(&response)
+                        && !(request.version == Version::Http10 && is_stream(&response));
+
+                    let keep_alive_params = (request.version == Version::Http10).then_some((
+                        self.config.keep_alive_timeout.as_secs(),
+                        self.config
+                            .max_requests_per_connection
+                            .saturating_sub(served),
+                    ));
+                    (
+                        with_connection_headers(response, keep_alive, keep_alive_params),
+                        keep_alive,
+                        request.version,
+                        request.method == Method::HEAD,
+                    )
+                }
+                Err(e) => {
+                    self.log.debug(&format!("Server error: {e}"));
+                    (
+                        with_connection_headers(
+                            ApiError::from(e).into_response_with_id(&next_request_id()),
+                            false,
+                            None,
+                        ),
+                        false,
+                        Version::Http11,
+                        false,
+                    )
+                }
+            };
+
+            let upgrade = take_upgrade(&mut response);
+            let response = watch_client(response, &stream, self.config.keep_alive_timeout);
+
+            // на HEAD отвечаем только статусом и заголовками
+            if let Err(e) = write_response(&mut writer, response, version, is_head) {
+                // клиент ушёл или потоковое тело оборвалось на середине --
+                // ответ уже не исправить, соединение только закрыть
+                self.log.debug(&format!("Failed to write response: {e}"));
+                return;
+            }
+
+            // 101 Switching Protocols: дальше по соединению HTTP уже нет, отдаём его хендлеру
+            // целиком. Воркер занят, пока хендлер не закончит; соединение потом закрываем
+            if let Some(upgrade) = upgrade {
+                let (Ok(read_half), Ok(socket)) = (stream.try_clone(), stream.try_clone()) else {
+                    return;
+                };
+                // таймауты теперь забота того, кто говорит на новом протоколе
+                let _ = stream.set_read_timeout(None);
+                // что клиент прислал вслед за запросом, уже лежит в буфере -- отдаём и это
+                let reader = Cursor::new(bufreader.buffer().to_vec()).chain(read_half);
+                let connection = UpgradedConnection {
+                    reader: Box::new(reader),
+                    stream: socket,
+                    shutdown: self.shutdown.clone(),
+                };
+                if !upgrade.run(connection) {
+                    self.log
+                        .debug(&"Upgrade handler has already been used".to_string());
+                }
+                return;
+            }
+
+            if !keep_alive {
+                return;
+            }
+        }
+    }
+
+    // Ждём первый байт следующего запроса не дольше keep_alive_timeout.
+    // Ждём короткими интервалами, чтобы простаивающее соединение
+    // не задерживало остановку сервера. false -- соединение пора закрыть
+    fn wait_for_request(&self, stream: &TcpStream) -> bool {
+        let started = Instant::now();
+        let _ = stream.set_read_timeout(Some(SHUTDOWN_POLL_INTERVAL));
+
+        let ready = loop {
+            match stream.peek(&mut [0u8; 1]) {
+                Ok(0) => break false, // клиент закрыл соединение
+                Ok(_) => break true,
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    if self.shutdown.is_shutdown()
+                        || started.elapsed() >= self.config.keep_alive_timeout
+                    {
+                        break false;
+                    }
+                }
+                Err(_) => break false,
+            }
+        };
+
+        // сам запрос читаем уже с обычным таймаутом
+        let _ = stream.set_read_timeout(Some(self.config.keep_alive_timeout));
+        ready
+    }
+
+    // Ищем хендлер для запроса и вызываем его:
+    // 1. есть хендлер для этого метода и пути -- вызываем
+    // 2. HEAD без своего хендлера -- вызываем GET хендлер (тело потом отрежем)
+    // 3. OPTIONS без своего хендлера -- отвечаем списком разрешённых методов
+    // 4. путь есть, но под другими методами -- 405 + Allow
+    // 5. пути нет -- 404
+    fn dispatch(&self, request: &mut Request) -> Response {
+        // self.router -- дерево маршрутов: статические сегменты важнее :параметров,
+        // :параметры важнее *wildcard, поэтому результат не зависит от порядка регистрации
+        let found = self.router.find(request.method, &request.path).or_else(|| {
+            (request.method == Method::HEAD)
+                .then(|| self.router.find(Method::GET, &request.path))
+                .flatten()
+        });
+
+        if let Some(found) = found {
+            request.rest_params = found.params;
+
+            let response = (found.handler)(request);
+
+            self.log.info(&format!(
+                "Handler triggered for route: {} {} ({}) [{}]",
+                request.method,
+                request.path,
+                found.pattern,
+                request.request_id()
+            ));
+
+            return response;
+        }
+
+        // OPTIONS * -- вопрос про сервер целиком, а не про конкретный путь
+        let allowed: Vec<Method> = if request.method == Method::OPTIONS && request.path == "*" {
+            self.router.routes().into_iter().map(|(m, _)| m).collect()
+        } else {
+            self.router.allowed_methods(&request.path)
+        };
+
+        if allowed.is_empty() {
+            return ApiError::new(
+                StatusCode::NOT_FOUND,
+                "route_not_found",
+                format!("No route for {} {}", request.method, request.path),
+            )
+            .into_response(request);
+        }
+
+        // список методов собираем сами -- проверку заголовка он проходит всегда
+        let allow = allow_list(allowed);
+
+        if request.method == Method::OPTIONS {
+            let mut headers = HeaderMap::new();
+            let _ = headers.insert("Allow", &allow);
+            return Response {
+                response_code: StatusCode::NO_CONTENT,
+                headers,
+                body: None,
+            };
+        }
+
+        self.log.debug(&format!(
+            "Method {} not allowed for {}",
+            request.method, request.path
+        ));
+        let mut response = ApiError::new(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "method_not_allowed",
+            format!(
+                "Method {} is not allowed for {}",
+                request.method, request.path
+            ),
+        )
+        .into_response(request);
+        let _ = response.headers.insert("Allow", &allow);
+        response
+    }
+}
+
+// "GET, HEAD, POST, OPTIONS": где есть GET, там работает и HEAD; OPTIONS работает всегда
+fn allow_list(mut methods: Vec<Method>) -> String {
+    if methods.contains(&Method::GET) {
+        methods.push(Method::HEAD);
+    }
+    methods.push(Method::OPTIONS);
+    methods.sort();
+    methods.dedup();
+
+    methods
+        .iter()
+        .map(|m| m.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+// Каждому запросу -- свой id для логов и ответов с ошибкой.
+// Если клиент (или прокси перед нами) уже прислал X-Request-Id -- используем его
+fn assign_request_id(request: &mut Request) {
+    if request
+        .headers
+        .get(REQUEST_ID_HEADER)
+        .is_some_and(is_valid_request_id)
+    {
+        return;
+    }
+    let _ = request
+        .headers
+        .insert(REQUEST_ID_HEADER, &next_request_id());
+}
+
+// Ответ 101 с BodyType::Upgrade: забираем из него замыкание, тела у 101 всё равно нет.
+// Upgrade в ответе с другим кодом -- ошибка хендлера, такой ответ уходит без тела
+fn take_upgrade(response: &mut Response) -> Option<Upgrade> {
+    if !matches!(response.body, Some(BodyType::Upgrade(_))) {
+        return None;
+    }
+    match response.body.take() {
+        Some(BodyType::Upgrade(upgrade))
+            if response.response_code == StatusCode::SWITCHING_PROTOCOLS =>
+        {
+            Some(upgrade)
+        }
+        _ => None,
+    }
+}
+
+// Сколько ждём ответа сокета, проверяя, на месте ли клиент
+const CLIENT_PEEK_TIMEOUT: Duration = Duration::from_millis(1);
+
+// Потоковое тело, которое присматривает за клиентом. Пока источнику нечего отдать
+// (WouldBlock, см. BodyStream), заглядываем в сокет: клиент закрыл соединение --
+// возвращаем ошибку, запись ответа обрывается, и хендлер бросает свой поток
+struct ClientWatch {
+    body: BodyStream,
+    socket: TcpStream,
+    read_timeout: Duration, // таймаут чтения, который вернём сокету после проверки
+}
+
+impl ClientWatch {
+    fn client_gone(&self) -> bool {
+        let _ = self.socket.set_read_timeout(Some(CLIENT_PEEK_TIMEOUT));
+        let gone = match self.socket.peek(&mut [0u8; 1]) {
+            Ok(0) => true,  // клиент закрыл соединение
+            Ok(_) => false, // прислал следующий запрос -- значит, на месте
+            Err(e) => !matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut),
+        };
+        let _ = self.socket.set_read_timeout(Some(self.read_timeout));
+        gone
+    }
+}
+
+impl Read for ClientWatch {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.body.read(buf) {
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    if self.client_gone() {
+                        return Err(io::Error::new(
+                            ErrorKind::ConnectionAborted,
+                            "Client closed the connection",
+                        ));
+                    }
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+// Потоковое тело отдаём через ClientWatch, остальные ответы -- как есть
+fn watch_client(mut response: Response, stream: &TcpStream, read_timeout: Duration) -> Response {
+    response.body = match response.body.take() {
+        Some(BodyType::Stream {
+            content_type,
+            stream: body,
+        }) => match stream.try_clone() {
+            Ok(socket) => Some(BodyType::stream(
+                &content_type,
+                ClientWatch {
+                    body,
+                    socket,
+                    read_timeout,
+                },
+            )),
+            Err(_) => Some(BodyType::Stream {
+                content_type,
+                stream: body,
+            }),
+        },
+        body => body,
+    };
+    response
+}
+
+fn is_stream(response: &Response) -> bool {
+    matches!(response.body, Some(BodyType::Stream { .. }))
+}
+
+// Хендлер сам выставил Connection: close?
+fn closes_connection(response: &Response) -> bool {
+    response.headers.has_token("connection", "close")
+}
+
+// Добавляем к ответу заголовок Connection (если хендлер не выставил его сам).
+// HTTP/1.0 клиентам keep-alive нужно подтвердить явно, вместе с Keep-Alive: timeout, max
+fn with_connection_headers(
+    mut response: Response,
+    keep_alive: bool,
+    keep_alive_params: Option<(u64, usize)>,
+) -> Response {
+    if response.headers.contains("connection") {
+        return response;
+    }
+
+    // значения собираем сами из чисел -- проверку они проходят всегда
+    let headers = &mut response.headers;
+    if keep_alive {
+        let _ = headers.insert("Connection", "keep-alive");
+        if let Some((timeout, max)) = keep_alive_params {
+            let _ = headers.insert("Keep-Alive", &format!("timeout={timeout}, max={max}"));
+        }
+    } else {
+        let _ = headers.insert("Connection", "close");
+    }
+    response
+}
@@ -55,7 +55,7 @@ mod time_date_utils {
     }
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, Copy)]
 pub struct Logger {
     times: config::config_enums::TimeFormat,
     dates: config::config_enums::DateFormat,
@@ -1,20 +1,232 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+};
 
 use crate::lib::{
-    req_res_structs::{BodyType, Method, Response},
+    chunked::{ChunkedWriter, read_chunked_body},
+    headers::HeaderMap,
+    query::{QueryParams, percent_decode},
+    req_res_structs::{BodyStream, BodyType, Method, Response, Version},
     request::Request,
     server_errors::ServerError, // для структуры SeverError
 };
 
+// Максимальный размер стартовой строки + заголовков. Защита от клиента, который шлёт бесконечные заголовки
+pub(crate) const MAX_HEAD_SIZE: usize = 16 * 1024;
+
+// Поля, которые нельзя принимать из трейлеров (RFC 9110 §6.5.1): от них зависят
+// разбор тела, маршрутизация, авторизация и обработка запроса. Трейлер вида
+// "Content-Length: 0" или "Host: evil" иначе встал бы в заголовки раньше настоящих
+const FORBIDDEN_TRAILERS: [&str; 15] = [
+    "content-length",
+    "transfer-encoding",
+    "content-type",
+    "content-encoding",
+    "content-range",
+    "connection",
+    "keep-alive",
+    "upgrade",
+    "te",
+    "trailer",
+    "host",
+    "expect",
+    "authorization",
+    "cookie",
+    "x-request-id",
+];
+
+fn is_forbidden_trailer(trailer: &str) -> bool {
+    let name = trailer
+        .split_once(':')
+        .map(|(name, _)| name)
+        .unwrap_or(trailer);
+    FORBIDDEN_TRAILERS
+        .iter()
+        .any(|forbidden| name.trim().eq_ignore_ascii_case(forbidden))
+}
+
+// Считываем один сырой HTTP-запрос из буферизированного потока:
+// 1. Читаем строки до пустой строки (конец заголовков)
+// 2. Ищем заголовки Content-Length и Transfer-Encoding
+// 3. Читаем ровно Content-Length байт тела (но не больше max_body_size)
+//    либо декодируем chunked тело. Трейлеры дописываем к заголовкам
+//    (кроме запрещённых FORBIDDEN_TRAILERS), а Transfer-Encoding заменяем
+//    на Content-Length декодированного тела
+// Возвращаем Ok(None), если клиент закрыл соединение, ничего не прислав
+pub fn read_request<R: BufRead>(
+    reader: &mut R,
+    max_body_size: usize,
+) -> Result<Option<Vec<u8>>, ServerError> {
+    let mut raw_request: Vec<u8> = Vec::new();
+    let mut head_size: usize = 0;
+    let mut content_length: Option<usize> = None;
+    let mut is_chunked: bool = false;
+
+    loop {
+        let mut line: Vec<u8> = Vec::new();
+        let read = match reader.read_until(b'\n', &mut line) {
+            // читаем до \n включительно (сам \n остаётся в line)
+            Ok(read) => read,
+            // таймаут простоя (или разрыв) до первого байта запроса -- просто закрываем соединение
+            Err(_) if head_size == 0 && line.is_empty() => return Ok(None),
+            Err(e) => {
+                return Err(ServerError::ReadError(format!(
+                    "Failed to read request head: {e}"
+                )));
+            }
+        };
+
+        if read == 0 {
+            // EOF: если ещё ничего не прочитали -- клиент просто закрыл соединение
+            if head_size == 0 {
+                return Ok(None);
+            }
+            return Err(ServerError::ReadError(
+                "Connection closed before end of headers".to_string(),
+            ));
+        }
+
+        head_size += line.len();
+        if head_size > MAX_HEAD_SIZE {
+            return Err(ServerError::ParseError(format!(
+                "Request head is larger than {MAX_HEAD_SIZE} bytes"
+            )));
+        }
+
+        if line.trim_ascii().is_empty() {
+            // пустая строка (CRLF) -- конец заголовков
+            break;
+        }
+
+        let line_str = String::from_utf8_lossy(&line);
+        if let Some((name, value)) = line_str.split_once(':') {
+            // "Content-Length : 5" или строка-продолжение с пробелом в начале: прокси
+            // может понять такой заголовок иначе, чем мы, поэтому сразу отказываем
+            if name.is_empty() || name.trim() != name {
+                return Err(ServerError::ParseError(format!(
+                    "Invalid header name: {name:?}"
+                )));
+            }
+            let value = value.trim();
+
+            // Content-Length: {number}
+            // Повтор (в том числе списком "5, 5") допустим только с тем же значением:
+            // иначе мы и прокси перед нами можем по-разному понять, где кончается тело
+            if name.eq_ignore_ascii_case("content-length") {
+                for item in value.split(',') {
+                    // только цифры: parse() сам принял бы и "+5"
+                    let item = item.trim();
+                    let length: usize = Some(item)
+                        .filter(|item| !item.is_empty() && item.bytes().all(|b| b.is_ascii_digit()))
+                        .and_then(|item| item.parse().ok())
+                        .ok_or_else(|| {
+                            ServerError::ParseError(format!("Invalid Content-Length: {value}"))
+                        })?;
+                    if content_length.is_some_and(|previous| previous != length) {
+                        return Err(ServerError::ParseError(
+                            "Conflicting Content-Length values".to_string(),
+                        ));
+                    }
+                    content_length = Some(length);
+                }
+            }
+
+            // Transfer-Encoding: gzip, chunked -- chunked обязан быть последним
+            if name.eq_ignore_ascii_case("transfer-encoding") {
+                let last_coding = value.rsplit(',').next().unwrap_or_default().trim();
+                if !last_coding.eq_ignore_ascii_case("chunked") {
+                    return Err(ServerError::ParseError(format!(
+                        "Unsupported Transfer-Encoding: {value}"
+                    )));
+                }
+                is_chunked = true;
+                continue; // тело отдадим уже декодированным, поэтому заголовок не сохраняем
+            }
+        }
+
+        raw_request.extend_from_slice(&line);
+    }
+
+    let body: Vec<u8> = if is_chunked {
+        // Content-Length вместе с chunked -- классический request smuggling, такое не принимаем
+        if content_length.is_some() {
+            return Err(ServerError::ParseError(
+                "Both Content-Length and Transfer-Encoding are present".to_string(),
+            ));
+        }
+
+        let (body, trailers) = read_chunked_body(reader, max_body_size)?;
+        for trailer in trailers.iter().filter(|t| !is_forbidden_trailer(t)) {
+            raw_request.extend_from_slice(trailer.as_bytes());
+            raw_request.extend_from_slice(b"\r\n");
+        }
+        raw_request.extend_from_slice(format!("Content-Length: {}\r\n", body.len()).as_bytes());
+        body
+    } else {
+        let content_length = content_length.unwrap_or(0);
+        if content_length > max_body_size {
+            return Err(ServerError::PayloadTooLarge(format!(
+                "Content-Length {content_length} exceeds limit of {max_body_size} bytes"
+            )));
+        }
+
+        // Читаем тело: ровно content_length байт
+        let mut body: Vec<u8> = vec![0; content_length];
+        reader
+            .read_exact(&mut body)
+            .map_err(|e| ServerError::ReadError(format!("Failed to read request body: {e}")))?;
+        body
+    };
+
+    raw_request.extend_from_slice(b"\r\n");
+    raw_request.extend_from_slice(&body);
+
+    Ok(Some(raw_request))
+}
+
+// Делим сырой запрос на head (стартовая строка + заголовки) и тело по первой пустой строке
+fn split_head_body(raw: &[u8]) -> (&[u8], &[u8]) {
+    if let Some(pos) = raw.windows(4).position(|w| w == b"\r\n\r\n") {
+        return (&raw[..pos + 2], &raw[pos + 4..]);
+    }
+    if let Some(pos) = raw.windows(2).position(|w| w == b"\n\n") {
+        return (&raw[..pos + 1], &raw[pos + 2..]);
+    }
+    (raw, &[])
+}
+
+// Путь раскодируем по сегментам: роутер делит его по '/', и закодированный "/" (%2F)
+// внутри сегмента поменял бы маршрут (web1%2Fstart -> /container/:id/start) -- такое не принимаем
+fn decode_path(raw_path: &str) -> Result<String, ServerError> {
+    let segments = raw_path
+        .split('/')
+        .map(|segment| {
+            let decoded = percent_decode(segment, false)?;
+            if decoded.contains('/') {
+                return Err(ServerError::ParseError(format!(
+                    "Encoded '/' in path segment: {segment:?}"
+                )));
+            }
+            Ok(decoded)
+        })
+        .collect::<Result<Vec<String>, ServerError>>()?;
+    Ok(segments.join("/"))
+}
+
 // функция публичная (pub)
-pub fn parse_request(req_body: String) -> Result<Request, ServerError> {
-    let mut lines = req_body.lines(); // возвращает итератором по подстрокам, т.е. либо по символам 1) \n
+pub fn parse_request(raw_request: impl AsRef<[u8]>) -> Result<Request, ServerError> {
+    let (head_bytes, body_bytes) = split_head_body(raw_request.as_ref());
+
+    let head: &str = std::str::from_utf8(head_bytes)
+        .map_err(|e| ServerError::ParseError(format!("Request head is not UTF-8: {e}")))?;
+
+    let mut lines = head.lines(); // возвращает итератором по подстрокам, т.е. либо по символам 1) \n
     // либо 2) \r\n
     // &str — срез строки, представляет ссылку на участок UTF-8 в уже существующем String (или на статический литерал).
     let start_line = lines.next().ok_or(ServerError::ParseError(format!(
         "Start line not found in iter: {lines:?}"
     )))?; // вызываем метод .next у итератора lines
-
     // ------------ ЧАСТЬ №1 ------------
     // Например, теперь в start_line находится: "GET / HTTP/1.1" или "GET /path HTTP/1.1"
     // Разобьём start_line
@@ -28,23 +240,34 @@ pub fn parse_request(req_body: String) -> Result<Request, ServerError> {
     // Преобразуем &str в Method
     let method: Method = match method_str {
         "GET" => Method::GET,
+        "HEAD" => Method::HEAD,
         "POST" => Method::POST,
         "PUT" => Method::PUT,
+        "PATCH" => Method::PATCH,
         "DELETE" => Method::DELETE,
+        "OPTIONS" => Method::OPTIONS,
         _ => Method::OTHER, // _ это паттер, назыв wildcard (подстановочный знак)
     };
 
-    // Второй — это путь
-    let path: String = parts
-        .next()
-        .ok_or(ServerError::ParseError(format!(
-            "Path not found in parts: {parts:?}"
-        )))?
-        .to_string();
+    // Второй — это путь вместе со строкой запроса: "/container/?status=running"
+    let target: &str = parts.next().ok_or(ServerError::ParseError(format!(
+        "Path not found in parts: {parts:?}"
+    )))?;
+
+    // Отделяем строку запроса и раскодируем %XX в обеих частях
+    let (raw_path, raw_query) = target.split_once('?').unwrap_or((target, ""));
+    let path: String = decode_path(raw_path)?;
+    let query: QueryParams = QueryParams::parse(raw_query)?;
+
+    // Третий -- версия протокола. Нет версии или HTTP/1.0 -- считаем HTTP/1.0
+    let version: Version = match parts.next() {
+        Some("HTTP/1.0") | None => Version::Http10,
+        Some(_) => Version::Http11,
+    };
 
     // ------------ ЧАСТЬ №2 ------------
     // Считаем все headers у сырого http запроса
-    let mut headers = Vec::new(); // изменяемый вектор headers
+    let mut header_lines = Vec::new(); // изменяемый вектор строк с заголовками
 
     for line in &mut lines
     // идем итератором по строкам, разделенным \r\n
@@ -54,28 +277,37 @@ pub fn parse_request(req_body: String) -> Result<Request, ServerError> {
         if line.is_empty() {
             break;
         }
-        headers.push(line.to_string()); // если найденная строка не пустая, конвертируем в String и кладём в вектор headers
+        header_lines.push(line); // если найденная строка не пустая, кладём её в вектор
     }
 
-    let body_str: String = lines.collect::<Vec<&str>>().join("\r\n");
+    // "Name: value" -> HeaderMap. Кривое имя или значение -- ParseError (клиенту 400)
+    let headers: HeaderMap = HeaderMap::from_lines(header_lines)?;
 
-    let body: Option<BodyType> = if body_str.is_empty() {
-        None // Если строка пустая, то ничего -- None
+    // Тело берём байт в байт, как оно пришло от клиента
+    let body: Option<BodyType> = if body_bytes.is_empty() {
+        None // Если тело пустое, то ничего -- None
     } else {
-        match serde_json::from_str::<serde_json::Value>(&body_str) {
-            Ok(val) => Some(BodyType::Json(val)),
-            Err(_) => Some(BodyType::Plain(body_str)),
+        match String::from_utf8(body_bytes.to_vec()) {
+            Ok(body_str) => match serde_json::from_str::<serde_json::Value>(&body_str) {
+                Ok(val) => Some(BodyType::Json(val)),
+                Err(_) => Some(BodyType::Plain(body_str)),
+            },
+            // не текст (tar-архив, gzip...) -- отдаём хендлеру байты как есть
+            Err(e) => Some(BodyType::bytes(
+                headers
+                    .get("content-type")
+                    .unwrap_or("application/octet-stream"),
+                e.into_bytes(),
+            )),
         }
     };
 
     let ret_request = Request {
         method,
         path,
-        headers: if headers.is_empty() {
-            None
-        } else {
-            Some(headers)
-        },
+        query,
+        version,
+        headers,
         body,
         rest_params: HashMap::new(),
     };
@@ -83,89 +315,134 @@ pub fn parse_request(req_body: String) -> Result<Request, ServerError> {
     Ok(ret_request) // Возвращаем успешный результат
 }
 
-fn response_code_phrase(code: usize) -> &'static str {
-    match code {
-        200 => "OK",
-        202 => "Accepted",
-        404 => "Not Found",
-        500 => "Internal Server Error",
-        501 => "Not Implemented",
-        502 => "Bad Gateway",
-        _ => "",
-    }
-}
-
-pub fn deser_response(response: Response) -> String {
+// Пишем ответ прямо в сокет (или в любой другой Write).
+// version -- версия запроса: потоковое тело HTTP/1.1 клиенту отдаём чанками,
+// а HTTP/1.0 клиент чанков не понимает -- ему пишем тело как есть до закрытия соединения
+// head_only -- ответ на HEAD: статус и заголовки (включая Content-Length) как у GET, но без тела
+pub fn write_response<W: Write>(
+    writer: &mut W,
+    response: Response,
+    version: Version,
+    head_only: bool,
+) -> io::Result<()> {
     let mut http_raw_response: String = String::default(); // пустая строка
     // ------------ ЧАСТЬ №1 ------------ Формируем Status-line для http-raw-ответа
     http_raw_response += "HTTP/1.1 "; // += принимает &str, т.е. срез строки 
     // => компилятор разворачивает это в вызов http_raw_response.push_str("HTTP/1.1");
     // метод push.str копирует байты из &str (из среза) в конец буфера String
     http_raw_response += &response.response_code.to_string();
-    // для usize вызываю .to_string() => получаю String
-    // беру ссылку на &String, т.е. превращаю в &str => добавляю к http_raw_response
-    http_raw_response += " ";
-    http_raw_response += response_code_phrase(response.response_code);
-    // функция возвращает &'static str, т.е. ссылку на литерал (например "OK")
-    // => добавляю к http_raw_response
+    // Display у StatusCode выводит код вместе с reason phrase: "404 Not Found"
     http_raw_response += "\r\n"; // добавим перенос на новую строку
 
     // ------------ ЧАСТЬ №2 ------------ Формируем Headers для http-raw-ответа
-    // if let ПАТТЕРН = ВЫРАЖЕНИЕ { … } else { … }
-    let headers_from_struct: Option<Vec<String>> = response.headers;
-
-    if let Some(headers_vector) = headers_from_struct {
-        // if let Some(...) пытается распаковать headers_from_struct
-        // Если headers_from_struct == None, то весь if-блок будет пропущен
-        // Если headers_from_struct == Some(vec), то vec (типа Vec<String>) переходит в переменную headers_vector
-        for header in headers_vector {
-            // итератор header по вектору headers_vector
-            http_raw_response += &header; // добавим каждый разыменованный headers к сырому ответу
-            http_raw_response += "\r\n"; // добавим перенос на новую строку
+    // HeaderMap проверил имена и значения при добавлении, поэтому выводим как есть
+    for (name, value) in response.headers.iter() {
+        http_raw_response += name;
+        http_raw_response += ": ";
+        http_raw_response += value;
+        http_raw_response += "\r\n"; // добавим перенос на новую строку
+    }
+    // хендлер попросил отдать тело чанками (длина заранее неизвестна)
+    let mut is_chunked: bool = response.headers.has_token("transfer-encoding", "chunked");
+    // Content-Type, выставленный хендлером, не перетираем
+    let has_content_type: bool = response.headers.contains("content-type");
+
+    // Тело: либо байты целиком, либо поток, который читаем по мере готовности
+    let (content_type, body): (Option<String>, ResponseBody) = match response.body {
+        None => (None, ResponseBody::Full(Vec::new())),
+        Some(BodyType::Plain(text)) => (
+            Some("text/plain".to_string()),
+            ResponseBody::Full(text.into_bytes()),
+        ),
+        Some(BodyType::Json(value)) => (
+            Some("application/json".to_string()),
+            ResponseBody::Full(serde_json::to_vec(&value).unwrap()),
+        ),
+        Some(BodyType::Bytes { content_type, data }) => {
+            (Some(content_type), ResponseBody::Full(data))
         }
+        Some(BodyType::Stream {
+            content_type,
+            stream,
+        }) => (Some(content_type), ResponseBody::Stream(stream)),
+        // у 101 тела нет, дальше по соединению говорят уже на другом протоколе
+        Some(BodyType::Upgrade(_)) => (None, ResponseBody::Full(Vec::new())),
     };
 
-    // Формируем заголовок с количеством символов в body
+    if let Some(content_type) = content_type
+        && !has_content_type
+    {
+        http_raw_response.push_str(&format!("Content-Type: {content_type}\r\n"));
+    }
 
-    let body_from_struct: String = if let Some(body) = response.body {
-        // если в response.body что-то есть, то это что-то присвоить body и выполнить match
-        match body {
-            // если body имеет тип BodyType::Plain, то
-            BodyType::Plain(text) => {
-                http_raw_response.push_str("Content-Type: text/plain\r\n");
-                text
+    match &body {
+        // длина потока заранее неизвестна: HTTP/1.1 -- чанки, HTTP/1.0 -- до закрытия соединения
+        ResponseBody::Stream(_) => {
+            if version == Version::Http11 && !is_chunked {
+                http_raw_response.push_str("Transfer-Encoding: chunked\r\n");
+                is_chunked = true;
             }
-            // если body имеет тип BodyType::Json, то
-            BodyType::Json(value) => {
-                let json = serde_json::to_string(&value).unwrap();
-                http_raw_response.push_str("Content-Type: application/json\r\n");
-                json
+        }
+        // при Transfer-Encoding: chunked заголовок Content-Length запрещён.
+        // Content-Length пишем всегда (даже 0): на keep-alive соединении только по нему
+        // клиент понимает, где заканчивается ответ. Исключение -- 1xx, 204 и 304, у них тела не бывает
+        ResponseBody::Full(data) => {
+            if !is_chunked && response.response_code.allows_body() {
+                http_raw_response.push_str(&format!("Content-Length: {}\r\n", data.len()));
             }
         }
-    } else {
-        // выполнится, если response.body == None
-        String::new()
-    };
-
-    if !(body_from_struct.is_empty()) {
-        http_raw_response.push_str(&format!("Content-Length: {}\r\n", body_from_struct.len()));
-        // макрос format! возвращает String -- в него можно добавить значение переменной
     }
 
     // ------------ ЧАСТЬ №3 ------------ Формируем CRLF - пустую строку для http-raw-ответа
     http_raw_response += "\r\n"; // добавим перенос на новую строку
+    writer.write_all(http_raw_response.as_bytes())?;
 
-    // ------------ ЧАСТЬ №4 ------------ Формируем Body для http-raw-ответа
-    http_raw_response.push_str(&body_from_struct);
+    if head_only {
+        return writer.flush(); // поток при этом даже не начинаем читать
+    }
 
-    http_raw_response
+    // ------------ ЧАСТЬ №4 ------------ Пишем Body
+    match (body, is_chunked) {
+        (ResponseBody::Full(data), false) => writer.write_all(&data)?,
+        (ResponseBody::Full(data), true) => {
+            let mut chunked = ChunkedWriter::new(&mut *writer);
+            chunked.write_all(&data)?;
+            chunked.finish()?;
+        }
+        // каждый прочитанный из потока кусок сразу уходит клиенту отдельным чанком
+        (ResponseBody::Stream(mut stream), true) => {
+            let mut chunked = ChunkedWriter::new(&mut *writer);
+            io::copy(&mut stream, &mut chunked)?;
+            chunked.finish()?;
+        }
+        (ResponseBody::Stream(mut stream), false) => {
+            io::copy(&mut stream, writer)?;
+        }
+    }
+
+    writer.flush()
+}
+
+enum ResponseBody {
+    Full(Vec<u8>),
+    Stream(BodyStream),
+}
+
+// Весь ответ одним куском байт (для заранее известных ответов и тестов)
+pub fn deser_response(response: Response) -> Vec<u8> {
+    let mut raw: Vec<u8> = Vec::new();
+    // запись в Vec не может упасть; ошибку может вернуть только чтение потокового тела
+    let _ = write_response(&mut raw, response, Version::Http11, false);
+    raw
 }
+
 // Для запуска test нужно написать команду cargo
 #[cfg(test)]
 mod tests {
     use serde_json::json;
 
     use super::*;
+    use crate::lib::req_res_structs::StatusCode;
 
     #[test]
     fn parse_get_no_body() -> Result<(), ServerError> {
@@ -177,14 +454,14 @@ Content-Type: application/json\r\n\
 
         let real_rez: Request = parse_request(req_raw)?;
 
-        let headers: Option<Vec<String>> = Some(vec![
-            "Host: api.example.com".to_string(),
-            "Content-Type: application/json".to_string(),
-        ]);
+        let headers =
+            HeaderMap::from_lines(["Host: api.example.com", "Content-Type: application/json"])?;
 
         let expected: Request = Request {
             method: Method::GET,
             path: "/api/status".to_string(),
+            query: QueryParams::default(),
+            version: Version::Http11,
             headers,
             body: None,
             rest_params: HashMap::new(),
@@ -209,7 +486,9 @@ Content-Type: application/json\r\n\
         let expected_result = Request {
             method: Method::GET,
             path: "/".to_string(),
-            headers: Some(headers),
+            query: QueryParams::default(),
+            version: Version::Http11,
+            headers: HeaderMap::from_lines(headers)?,
             body: None,
             rest_params: HashMap::new(),
         };
@@ -245,7 +524,9 @@ Content-Type: application/json\r\n\
         let expected_result = Request {
             method: Method::POST,
             path: "/api/users".to_string(),
-            headers: Some(headers),
+            query: QueryParams::default(),
+            version: Version::Http11,
+            headers: HeaderMap::from_lines(headers)?,
             body: Some(BodyType::Json(json_body)),
             rest_params: HashMap::new(),
         };
@@ -283,7 +564,9 @@ Accept: *\r\n\
         let expected_result = Request {
             method: Method::PUT,
             path: "/api/items/42".to_string(),
-            headers: Some(headers),
+            query: QueryParams::default(),
+            version: Version::Http11,
+            headers: HeaderMap::from_lines(headers)?,
             body: Some(BodyType::Json(json_body)),
             rest_params: HashMap::new(),
         };
@@ -311,7 +594,9 @@ X-Debug-Mode: true\r\n\
         let expected_result = Request {
             method: Method::DELETE,
             path: "/api/items/42".to_string(),
-            headers: Some(headers),
+            query: QueryParams::default(),
+            version: Version::Http11,
+            headers: HeaderMap::from_lines(headers)?,
             body: None,
             rest_params: HashMap::new(),
         };
@@ -342,8 +627,10 @@ Cookie: session=abcd1234; theme=dark\r\n\
 
         let expected_result = Request {
             method: Method::GET,
-            path: "/search?q=rust+lang&sort=desc".to_string(),
-            headers: Some(headers),
+            path: "/search".to_string(),
+            query: QueryParams::parse("q=rust+lang&sort=desc")?,
+            version: Version::Http11,
+            headers: HeaderMap::from_lines(headers)?,
             body: None,
             rest_params: HashMap::new(),
         };
@@ -353,6 +640,28 @@ Cookie: session=abcd1234; theme=dark\r\n\
         Ok(())
     }
 
+    #[test]
+    fn parse_percent_encoded_target() -> Result<(), ServerError> {
+        let request = parse_request(
+            "GET /container/web%201/logs?since=2024-01-01T00%3A00%3A00&stream=stdout&stream=stderr HTTP/1.1\r\n\r\n",
+        )?;
+
+        assert_eq!(request.path, "/container/web 1/logs");
+        assert_eq!(request.query.get("since"), Some("2024-01-01T00:00:00"));
+        assert_eq!(request.query.get_all("stream"), vec!["stdout", "stderr"]);
+
+        assert!(parse_request("GET /container/%zz HTTP/1.1\r\n\r\n").is_err());
+        // закодированный "/" не должен менять маршрут
+        for target in ["/container/web1%2Fstart", "/container/web1%2fstart"] {
+            assert!(matches!(
+                parse_request(format!("POST {target} HTTP/1.1\r\n\r\n")),
+                Err(ServerError::ParseError(_))
+            ));
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn parse_form_request() -> Result<(), ServerError> {
         let raw_reqwest = "POST /login HTTP/1.1\r\n\
@@ -376,7 +685,9 @@ username=foo&password=bar"
         let expected_result = Request {
             method: Method::POST,
             path: "/login".to_string(),
-            headers: Some(headers),
+            query: QueryParams::default(),
+            version: Version::Http11,
+            headers: HeaderMap::from_lines(headers)?,
             body: Some(BodyType::Plain("username=foo&password=bar".to_string())),
             rest_params: HashMap::new(),
         };
@@ -386,18 +697,207 @@ username=foo&password=bar"
         Ok(())
     }
 
+    #[test]
+    fn read_request_with_content_length() -> Result<(), ServerError> {
+        // после тела лежат байты следующего запроса -- их читать нельзя
+        let raw = "POST /container/ HTTP/1.1\r\n\
+Host: localhost\r\n\
+Content-Length: 13\r\n\
+\r\n\
+line1\nline2\r\n\
+GET / HTTP/1.1\r\n\r\n";
+        let mut reader = std::io::Cursor::new(raw.as_bytes());
+
+        let raw_request = read_request(&mut reader, 1024)?.unwrap();
+        let request = parse_request(raw_request)?;
+
+        assert_eq!(
+            request.body,
+            Some(BodyType::Plain("line1\nline2\r\n".to_string()))
+        );
+        assert_eq!(
+            reader.position() as usize,
+            raw.len() - "GET / HTTP/1.1\r\n\r\n".len()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_request_body_too_large() {
+        let raw = "POST / HTTP/1.1\r\nContent-Length: 100\r\n\r\n";
+        let mut reader = std::io::Cursor::new(raw.as_bytes());
+
+        assert!(matches!(
+            read_request(&mut reader, 10),
+            Err(ServerError::PayloadTooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn read_request_empty_connection() -> Result<(), ServerError> {
+        let mut reader = std::io::Cursor::new(&b""[..]);
+
+        assert_eq!(read_request(&mut reader, 10)?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_request_chunked() -> Result<(), ServerError> {
+        let raw = "POST /container/ HTTP/1.1\r\n\
+Host: localhost\r\n\
+Transfer-Encoding: chunked\r\n\
+\r\n\
+6\r\n{\"a\":1\r\n\
+1\r\n}\r\n\
+0\r\n\
+X-Checksum: 42\r\n\
+\r\n";
+        let mut reader = std::io::Cursor::new(raw.as_bytes());
+
+        let raw_request = read_request(&mut reader, 1024)?.unwrap();
+        let request = parse_request(raw_request)?;
+
+        assert_eq!(
+            request.headers,
+            HeaderMap::from_lines(["Host: localhost", "X-Checksum: 42", "Content-Length: 7"])?
+        );
+        assert_eq!(request.body, Some(BodyType::Json(json!({"a": 1}))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_request_chunked_drops_forbidden_trailers() -> Result<(), ServerError> {
+        let raw = "POST /container/ HTTP/1.1\r\n\
+Host: localhost\r\n\
+Transfer-Encoding: chunked\r\n\
+\r\n\
+2\r\n{}\r\n\
+0\r\n\
+Content-Length: 0\r\n\
+transfer-encoding: chunked\r\n\
+Host: evil\r\n\
+Connection: close\r\n\
+X-Request-Id: spoofed\r\n\
+X-Checksum: 42\r\n\
+\r\n";
+        let mut reader = std::io::Cursor::new(raw.as_bytes());
+
+        let raw_request = read_request(&mut reader, 1024)?.unwrap();
+        let request = parse_request(raw_request)?;
+
+        assert_eq!(
+            request.headers,
+            HeaderMap::from_lines(["Host: localhost", "X-Checksum: 42", "Content-Length: 2"])?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_request_duplicate_content_length() -> Result<(), ServerError> {
+        for conflicting in [
+            "Content-Length: 5\r\nContent-Length: 3\r\n",
+            "Content-Length: 5, 3\r\n",
+        ] {
+            let raw = format!("POST / HTTP/1.1\r\n{conflicting}\r\nhello");
+            let mut reader = std::io::Cursor::new(raw.as_bytes());
+            assert!(matches!(
+                read_request(&mut reader, 1024),
+                Err(ServerError::ParseError(_))
+            ));
+        }
+
+        // одинаковые значения -- по-прежнему одно тело
+        let raw = "POST / HTTP/1.1\r\nContent-Length: 5\r\nContent-Length: 5\r\n\r\nhello";
+        let mut reader = std::io::Cursor::new(raw.as_bytes());
+        let raw_request = read_request(&mut reader, 1024)?.unwrap();
+        assert!(raw_request.ends_with(b"\r\n\r\nhello"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_request_signed_content_length() {
+        for length in ["+5", "-1", "5, +5", ""] {
+            let raw = format!("POST / HTTP/1.1\r\nContent-Length: {length}\r\n\r\nhello");
+            let mut reader = std::io::Cursor::new(raw.as_bytes());
+            assert!(matches!(
+                read_request(&mut reader, 1024),
+                Err(ServerError::ParseError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn read_request_rejects_whitespace_in_header_name() {
+        for head in [
+            "Content-Length : 5\r\n",
+            "Host: x\r\n Content-Length: 5\r\n",
+        ] {
+            let raw = format!("POST / HTTP/1.1\r\n{head}\r\nhello");
+            let mut reader = std::io::Cursor::new(raw.as_bytes());
+            assert!(matches!(
+                read_request(&mut reader, 1024),
+                Err(ServerError::ParseError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn read_request_chunked_with_content_length() {
+        let raw = "POST / HTTP/1.1\r\n\
+Content-Length: 5\r\n\
+Transfer-Encoding: chunked\r\n\
+\r\n\
+0\r\n\r\n";
+        let mut reader = std::io::Cursor::new(raw.as_bytes());
+
+        assert!(matches!(
+            read_request(&mut reader, 1024),
+            Err(ServerError::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn deser_response_chunked() {
+        let response: Response = Response {
+            response_code: StatusCode::OK,
+            headers: HeaderMap::from_lines(["Transfer-Encoding: chunked"]).unwrap(),
+            body: Some(BodyType::Plain("Hello world!".to_string())),
+        };
+
+        let expected_result: String = "HTTP/1.1 200 OK\r\n\
+        Transfer-Encoding: chunked\r\n\
+        Content-Type: text/plain\r\n\
+        \r\n\
+        C\r\n\
+        Hello world!\r\n\
+        0\r\n\
+        \r\n"
+            .to_string();
+
+        assert_eq!(
+            String::from_utf8(deser_response(response)).unwrap(),
+            expected_result
+        );
+    }
+
     #[test]
     fn deser_response_test_plain_text() -> Result<(), ServerError> {
         let response: Response = Response {
-            response_code: 200,
-            headers: Some(vec![
-                "Host: api.example.com".to_string(),
-                "Location: https://example.com/new-resource".to_string(),
-            ]),
+            response_code: StatusCode::OK,
+            headers: HeaderMap::from_lines([
+                "Host: api.example.com",
+                "Location: https://example.com/new-resource",
+            ])
+            .unwrap(),
             body: Some(BodyType::Plain("Hello world!".to_string())),
         };
 
-        let real_result: String = deser_response(response);
+        let real_result: String = String::from_utf8(deser_response(response)).unwrap();
 
         let expected_result: String = "HTTP/1.1 200 OK\r\n\
         Host: api.example.com\r\n\
@@ -419,18 +919,19 @@ username=foo&password=bar"
     #[test]
     fn deser_response_test_with_json_file() -> Result<(), ServerError> {
         let response: Response = Response {
-            response_code: 200,
-            headers: Some(vec![
-                "Host: api.example.com".to_string(),
-                "Location: https://example.com/new-resource".to_string(),
-            ]),
+            response_code: StatusCode::OK,
+            headers: HeaderMap::from_lines([
+                "Host: api.example.com",
+                "Location: https://example.com/new-resource",
+            ])
+            .unwrap(),
             body: Some(BodyType::Json(json!({
                 "price": 19.99,
                 "stock": 100
             }))),
         };
 
-        let real_result: String = deser_response(response);
+        let real_result: String = String::from_utf8(deser_response(response)).unwrap();
 
         let expected_result: String = "HTTP/1.1 200 OK\r\n\
         Host: api.example.com\r\n\
@@ -452,19 +953,21 @@ username=foo&password=bar"
     #[test]
     fn deser_response_test_with_no_json() -> Result<(), ServerError> {
         let response: Response = Response {
-            response_code: 200,
-            headers: Some(vec![
-                "Host: api.example.com".to_string(),
-                "Location: https://example.com/new-resource".to_string(),
-            ]),
+            response_code: StatusCode::OK,
+            headers: HeaderMap::from_lines([
+                "Host: api.example.com",
+                "Location: https://example.com/new-resource",
+            ])
+            .unwrap(),
             body: None,
         };
 
-        let real_result: String = deser_response(response);
+        let real_result: String = String::from_utf8(deser_response(response)).unwrap();
 
         let expected_result: String = "HTTP/1.1 200 OK\r\n\
         Host: api.example.com\r\n\
         Location: https://example.com/new-resource\r\n\
+        Content-Length: 0\r\n\
         \r\n"
             .to_string();
 
@@ -475,6 +978,101 @@ username=foo&password=bar"
 
         Ok(())
     }
+
+    #[test]
+    fn deser_response_keeps_handler_content_type() -> Result<(), ServerError> {
+        let mut headers = HeaderMap::new();
+        headers.insert("Content-Type", "text/csv")?;
+
+        let raw = String::from_utf8(deser_response(Response {
+            response_code: StatusCode::OK,
+            headers,
+            body: Some(BodyType::Plain("a,b".to_string())),
+        }))
+        .unwrap();
+
+        assert_eq!(
+            raw,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/csv\r\nContent-Length: 3\r\n\r\na,b"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn deser_response_binary_body() {
+        let raw = deser_response(Response {
+            response_code: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Some(BodyType::bytes(
+                "application/gzip",
+                vec![0x1f, 0x8b, 0x00, 0xff],
+            )),
+        });
+
+        let mut expected: Vec<u8> =
+            b"HTTP/1.1 200 OK\r\nContent-Type: application/gzip\r\nContent-Length: 4\r\n\r\n"
+                .to_vec();
+        expected.extend_from_slice(&[0x1f, 0x8b, 0x00, 0xff]);
+        assert_eq!(raw, expected);
+    }
+
+    #[test]
+    fn write_response_stream_body() -> io::Result<()> {
+        let stream_response = || Response {
+            response_code: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Some(BodyType::stream(
+                "application/x-tar",
+                std::io::Cursor::new(b"tar data".to_vec()),
+            )),
+        };
+
+        // HTTP/1.1 -- чанками
+        let mut raw: Vec<u8> = Vec::new();
+        write_response(&mut raw, stream_response(), Version::Http11, false)?;
+        assert_eq!(
+            String::from_utf8(raw).unwrap(),
+            "HTTP/1.1 200 OK\r\nContent-Type: application/x-tar\r\nTransfer-Encoding: chunked\r\n\r\n8\r\ntar data\r\n0\r\n\r\n"
+        );
+
+        // HTTP/1.0 -- как есть, конец тела = закрытие соединения
+        let mut raw: Vec<u8> = Vec::new();
+        write_response(&mut raw, stream_response(), Version::Http10, false)?;
+        assert_eq!(
+            String::from_utf8(raw).unwrap(),
+            "HTTP/1.1 200 OK\r\nContent-Type: application/x-tar\r\n\r\ntar data"
+        );
+
+        // HEAD -- только заголовки
+        let mut raw: Vec<u8> = Vec::new();
+        write_response(&mut raw, stream_response(), Version::Http11, true)?;
+        assert!(String::from_utf8(raw).unwrap().ends_with("chunked\r\n\r\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_binary_body() -> Result<(), ServerError> {
+        let mut raw: Vec<u8> =
+            b"PUT /archive HTTP/1.1\r\nContent-Type: application/x-tar\r\nContent-Length: 2\r\n\r\n"
+                .to_vec();
+        raw.extend_from_slice(&[0xff, 0x00]);
+
+        let request = parse_request(raw)?;
+        assert_eq!(
+            request.body,
+            Some(BodyType::bytes("application/x-tar", vec![0xff, 0x00]))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_rejects_invalid_header() {
+        assert!(parse_request("GET / HTTP/1.1\r\nBad Header: 1\r\n\r\n").is_err());
+        assert!(parse_request("GET / HTTP/1.1\r\nno-colon\r\n\r\n").is_err());
+    }
 }
 
 /* Код который проверяет является ли body json файлом только в случае есть есть нужный заголовок content-type: application/jso
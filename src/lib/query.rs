@@ -0,0 +1,167 @@
+use std::str::FromStr;
+
+use crate::lib::server_errors::ServerError;
+
+// Параметры строки запроса: "/container/?status=running&status=paused&image=nginx"
+//
+// Один ключ может встречаться несколько раз, поэтому храним пары в порядке появления,
+// а не HashMap<String, String>:
+//   [("status", "running"), ("status", "paused"), ("image", "nginx")]
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct QueryParams {
+    params: Vec<(String, String)>,
+}
+
+impl QueryParams {
+    // Разбираем строку после '?'. "a=1&b&c=%20x" -> [("a", "1"), ("b", ""), ("c", " x")]
+    pub fn parse(query: &str) -> Result<Self, ServerError> {
+        let mut params = Vec::new();
+
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            params.push((percent_decode(key, true)?, percent_decode(value, true)?));
+        }
+
+        Ok(Self { params })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.params.iter().any(|(key, _)| key == name)
+    }
+
+    // Первое значение параметра
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    // Все значения параметра в порядке появления: ?status=running&status=paused
+    pub fn get_all(&self, name: &str) -> Vec<&str> {
+        self.params
+            .iter()
+            .filter(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    // Первое значение, приведённое к нужному типу: query.get_parsed::<usize>("tail")
+    // Нет параметра -- Ok(None), не парсится -- ParseError (клиенту 400)
+    pub fn get_parsed<T: FromStr>(&self, name: &str) -> Result<Option<T>, ServerError> {
+        self.get(name)
+            .map(|value| {
+                value.parse::<T>().map_err(|_| {
+                    ServerError::ParseError(format!(
+                        "Invalid value {value:?} for query parameter '{name}'"
+                    ))
+                })
+            })
+            .transpose()
+    }
+
+    // Флаг: "true"/"1"/"yes" или просто "?force" без значения -- true, "false"/"0"/"no" -- false
+    pub fn get_bool(&self, name: &str) -> Result<Option<bool>, ServerError> {
+        self.get(name)
+            .map(|value| match value.to_ascii_lowercase().as_str() {
+                "" | "true" | "1" | "yes" => Ok(true),
+                "false" | "0" | "no" => Ok(false),
+                _ => Err(ServerError::ParseError(format!(
+                    "Invalid boolean {value:?} for query parameter '{name}'"
+                ))),
+            })
+            .transpose()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.params
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+}
+
+// Разбираем %XX-последовательности. plus_as_space -- для строки запроса,
+// где '+' означает пробел (в пути '+' остаётся плюсом)
+pub fn percent_decode(input: &str, plus_as_space: bool) -> Result<String, ServerError> {
+    let bytes = input.as_bytes();
+    let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                // from_str_radix сам принимает знак ("+1"), поэтому цифры проверяем явно
+                let hex = bytes
+                    .get(i + 1..i + 3)
+                    .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or(ServerError::ParseError(format!(
+                        "Invalid percent-encoding in {input:?}"
+                    )))?;
+                decoded.push(hex);
+                i += 3;
+            }
+            b'+' if plus_as_space => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8(decoded)
+        .map_err(|_| ServerError::ParseError(format!("Percent-decoded {input:?} is not UTF-8")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_multi_value_query() -> Result<(), ServerError> {
+        let query = QueryParams::parse("status=running&image=nginx%3Alatest&status=paused&all")?;
+
+        assert_eq!(query.get("status"), Some("running"));
+        assert_eq!(query.get_all("status"), vec!["running", "paused"]);
+        assert_eq!(query.get("image"), Some("nginx:latest"));
+        assert_eq!(query.get_bool("all")?, Some(true));
+        assert_eq!(query.get("missing"), None);
+        assert!(query.get_all("missing").is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn typed_getters() -> Result<(), ServerError> {
+        let query = QueryParams::parse("tail=100&force=false&since=yesterday")?;
+
+        assert_eq!(query.get_parsed::<usize>("tail")?, Some(100));
+        assert_eq!(query.get_parsed::<usize>("missing")?, None);
+        assert!(query.get_parsed::<u64>("since").is_err());
+        assert_eq!(query.get_bool("force")?, Some(false));
+        assert!(query.get_bool("since").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn percent_decoding() {
+        assert_eq!(percent_decode("web%201", false).unwrap(), "web 1");
+        assert_eq!(percent_decode("a+b", false).unwrap(), "a+b");
+        assert_eq!(percent_decode("a+b", true).unwrap(), "a b");
+        assert_eq!(percent_decode("%D0%BF%D1%80", false).unwrap(), "пр");
+        assert!(percent_decode("%zz", false).is_err());
+        assert!(percent_decode("abc%2", false).is_err());
+        assert!(percent_decode("%FF", false).is_err());
+        assert!(percent_decode("%+1", false).is_err());
+        assert!(percent_decode("%-1", false).is_err());
+    }
+}
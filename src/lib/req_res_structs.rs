@@ -1,43 +1,364 @@
+use std::{
+    io::{self, Read},
+    net::TcpStream,
+    sync::{Arc, Mutex},
+};
+
+use crate::lib::{headers::HeaderMap, http_server::ShutdownHandle, server_errors::ServerError};
+
 #[derive(Debug, PartialEq)]
 pub struct Response {
     // при формировании экземпляра структуры не надо указывать:
     // 1. заголовок Content-Type: text/plain\r\n или Content-Type: application/json\r\n
     // 2. заголовок Content-Length: {number}
-    // все это формируется в функции deser_response в зависимости от типа body
-    pub response_code: usize,
-    pub headers: Option<Vec<String>>,
+    // все это формируется в функции write_response в зависимости от типа body
+    pub response_code: StatusCode,
+    pub headers: HeaderMap,
     pub body: Option<BodyType>,
 }
 
+// Код ответа. Любое число 100..=599 (RFC 9110 разрешает незарегистрированные коды),
+// для зарегистрированных в IANA кодов есть константы и reason phrase:
+//   StatusCode::CONFLICT                  -> "409 Conflict"
+//   StatusCode::from_u16(499)?            -> "499 Client Error" (фраза по классу кода)
+//   StatusCode::from_u16(1000)            -> Err(...)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StatusCode(u16);
+
+// (код, имя константы, reason phrase) -- реестр IANA HTTP Status Codes
+macro_rules! status_codes {
+    ($($code:literal $name:ident $phrase:literal;)+) => {
+        impl StatusCode {
+            $(pub const $name: StatusCode = StatusCode($code);)+
+
+            // Фраза для стартовой строки: "HTTP/1.1 409 Conflict"
+            pub fn reason_phrase(&self) -> &'static str {
+                match self.0 {
+                    $($code => $phrase,)+
+                    100..=199 => "Informational",
+                    200..=299 => "Success",
+                    300..=399 => "Redirection",
+                    400..=499 => "Client Error",
+                    _ => "Server Error",
+                }
+            }
+        }
+    };
+}
+
+status_codes! {
+    100 CONTINUE "Continue";
+    101 SWITCHING_PROTOCOLS "Switching Protocols";
+    102 PROCESSING "Processing";
+    103 EARLY_HINTS "Early Hints";
+    200 OK "OK";
+    201 CREATED "Created";
+    202 ACCEPTED "Accepted";
+    203 NON_AUTHORITATIVE_INFORMATION "Non-Authoritative Information";
+    204 NO_CONTENT "No Content";
+    205 RESET_CONTENT "Reset Content";
+    206 PARTIAL_CONTENT "Partial Content";
+    207 MULTI_STATUS "Multi-Status";
+    208 ALREADY_REPORTED "Already Reported";
+    226 IM_USED "IM Used";
+    300 MULTIPLE_CHOICES "Multiple Choices";
+    301 MOVED_PERMANENTLY "Moved Permanently";
+    302 FOUND "Found";
+    303 SEE_OTHER "See Other";
+    304 NOT_MODIFIED "Not Modified";
+    305 USE_PROXY "Use Proxy";
+    307 TEMPORARY_REDIRECT "Temporary Redirect";
+    308 PERMANENT_REDIRECT "Permanent Redirect";
+    400 BAD_REQUEST "Bad Request";
+    401 UNAUTHORIZED "Unauthorized";
+    402 PAYMENT_REQUIRED "Payment Required";
+    403 FORBIDDEN "Forbidden";
+    404 NOT_FOUND "Not Found";
+    405 METHOD_NOT_ALLOWED "Method Not Allowed";
+    406 NOT_ACCEPTABLE "Not Acceptable";
+    407 PROXY_AUTHENTICATION_REQUIRED "Proxy Authentication Required";
+    408 REQUEST_TIMEOUT "Request Timeout";
+    409 CONFLICT "Conflict";
+    410 GONE "Gone";
+    411 LENGTH_REQUIRED "Length Required";
+    412 PRECONDITION_FAILED "Precondition Failed";
+    413 CONTENT_TOO_LARGE "Content Too Large";
+    414 URI_TOO_LONG "URI Too Long";
+    415 UNSUPPORTED_MEDIA_TYPE "Unsupported Media Type";
+    416 RANGE_NOT_SATISFIABLE "Range Not Satisfiable";
+    417 EXPECTATION_FAILED "Expectation Failed";
+    421 MISDIRECTED_REQUEST "Misdirected Request";
+    422 UNPROCESSABLE_CONTENT "Unprocessable Content";
+    423 LOCKED "Locked";
+    424 FAILED_DEPENDENCY "Failed Dependency";
+    425 TOO_EARLY "Too Early";
+    426 UPGRADE_REQUIRED "Upgrade Required";
+    428 PRECONDITION_REQUIRED "Precondition Required";
+    429 TOO_MANY_REQUESTS "Too Many Requests";
+    431 REQUEST_HEADER_FIELDS_TOO_LARGE "Request Header Fields Too Large";
+    451 UNAVAILABLE_FOR_LEGAL_REASONS "Unavailable For Legal Reasons";
+    500 INTERNAL_SERVER_ERROR "Internal Server Error";
+    501 NOT_IMPLEMENTED "Not Implemented";
+    502 BAD_GATEWAY "Bad Gateway";
+    503 SERVICE_UNAVAILABLE "Service Unavailable";
+    504 GATEWAY_TIMEOUT "Gateway Timeout";
+    505 HTTP_VERSION_NOT_SUPPORTED "HTTP Version Not Supported";
+    506 VARIANT_ALSO_NEGOTIATES "Variant Also Negotiates";
+    507 INSUFFICIENT_STORAGE "Insufficient Storage";
+    508 LOOP_DETECTED "Loop Detected";
+    510 NOT_EXTENDED "Not Extended";
+    511 NETWORK_AUTHENTICATION_REQUIRED "Network Authentication Required";
+}
+
+impl StatusCode {
+    pub fn from_u16(code: u16) -> Result<Self, ServerError> {
+        if !(100..=599).contains(&code) {
+            return Err(ServerError::ParseError(format!(
+                "Invalid HTTP status code: {code}"
+            )));
+        }
+        Ok(StatusCode(code))
+    }
+
+    pub fn as_u16(&self) -> u16 {
+        self.0
+    }
+
+    pub fn is_informational(&self) -> bool {
+        (100..200).contains(&self.0)
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.0)
+    }
+
+    pub fn is_client_error(&self) -> bool {
+        (400..500).contains(&self.0)
+    }
+
+    pub fn is_server_error(&self) -> bool {
+        (500..600).contains(&self.0)
+    }
+
+    // У 1xx, 204 и 304 тела не бывает -- и заголовка Content-Length тоже
+    pub fn allows_body(&self) -> bool {
+        !self.is_informational()
+            && *self != StatusCode::NO_CONTENT
+            && *self != StatusCode::NOT_MODIFIED
+    }
+}
+
+impl TryFrom<u16> for StatusCode {
+    type Error = ServerError;
+
+    fn try_from(code: u16) -> Result<Self, Self::Error> {
+        StatusCode::from_u16(code)
+    }
+}
+
+impl std::fmt::Display for StatusCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.0, self.reason_phrase())
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum BodyType {
     Json(serde_json::Value),
     Plain(String),
+    // произвольные байты: tar-архив, gzip, картинка...
+    Bytes {
+        content_type: String,
+        data: Vec<u8>,
+    },
+    // тело, длина которого заранее неизвестна (экспорт файловой системы, логи с follow).
+    // Читается прямо в сокет по мере готовности: HTTP/1.1 -- чанками, HTTP/1.0 -- до закрытия соединения
+    Stream {
+        content_type: String,
+        stream: BodyStream,
+    },
+    // тела нет: после ответа 101 Switching Protocols соединение целиком переходит хендлеру (WebSocket)
+    Upgrade(Upgrade),
 }
 
-#[derive(Hash, PartialEq, Eq, Debug, Copy, Clone)]
+impl BodyType {
+    pub fn bytes(content_type: &str, data: Vec<u8>) -> Self {
+        BodyType::Bytes {
+            content_type: content_type.to_string(),
+            data,
+        }
+    }
+
+    pub fn stream<R: Read + Send + 'static>(content_type: &str, reader: R) -> Self {
+        BodyType::Stream {
+            content_type: content_type.to_string(),
+            stream: BodyStream::new(reader),
+        }
+    }
+}
+
+// Источник данных для BodyType::Stream.
+// Ошибка WouldBlock при чтении -- данных пока нет, но поток не кончился: сервер
+// проверяет, на месте ли клиент, и читает снова (так молчащие логи с follow узнают,
+// что клиент ушёл, не дописывая в тело ничего лишнего).
+// Response и BodyType должны оставаться Clone + PartialEq, а Box<dyn Read> не умеет ни того,
+// ни другого -- поэтому ридер лежит за Arc<Mutex<...>>: клоны читают один и тот же поток,
+// а равны между собой только клоны одного и того же потока
+#[derive(Clone)]
+pub struct BodyStream(Arc<Mutex<Box<dyn Read + Send>>>);
+
+impl BodyStream {
+    pub fn new<R: Read + Send + 'static>(reader: R) -> Self {
+        Self(Arc::new(Mutex::new(Box::new(reader))))
+    }
+}
+
+impl Read for BodyStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.0.lock() {
+            Ok(mut reader) => reader.read(buf),
+            Err(_) => Err(io::Error::other("Body stream is poisoned")),
+        }
+    }
+}
+
+impl PartialEq for BodyStream {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl std::fmt::Debug for BodyStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "BodyStream")
+    }
+}
+
+// Что делать с соединением после 101 Switching Protocols. Как и у BodyStream, замыкание
+// лежит за Arc<Mutex<...>>, чтобы BodyType оставался Clone + PartialEq; запустить его
+// можно только один раз -- соединение у клиента одно
+#[derive(Clone)]
+pub struct Upgrade(Arc<Mutex<Option<UpgradeFn>>>);
+
+type UpgradeFn = Box<dyn FnOnce(UpgradedConnection) + Send>;
+
+impl Upgrade {
+    pub fn new<F: FnOnce(UpgradedConnection) + Send + 'static>(on_upgrade: F) -> Self {
+        Self(Arc::new(Mutex::new(Some(Box::new(on_upgrade)))))
+    }
+
+    // Отдаём соединение хендлеру и ждём, пока он с ним закончит.
+    // false -- замыкание уже запускали (или оно упало)
+    pub fn run(&self, connection: UpgradedConnection) -> bool {
+        let on_upgrade = match self.0.lock() {
+            Ok(mut on_upgrade) => on_upgrade.take(),
+            Err(_) => None,
+        };
+        match on_upgrade {
+            Some(on_upgrade) => {
+                on_upgrade(connection);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl PartialEq for Upgrade {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl std::fmt::Debug for Upgrade {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Upgrade")
+    }
+}
+
+// Соединение после смены протокола
+pub struct UpgradedConnection {
+    // то, что клиент успел прислать вслед за запросом, а дальше -- сам сокет
+    pub reader: Box<dyn Read + Send>,
+    pub stream: TcpStream,        // для записи, таймаутов и shutdown
+    pub shutdown: ShutdownHandle, // сервер останавливается -- соединение пора закрывать
+}
+
+// Порядок вариантов = порядок методов в заголовке Allow (поэтому Ord)
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Debug, Copy, Clone)]
 pub enum Method {
     GET,
+    HEAD, // как GET, но без тела ответа
     POST,
     PUT,
+    PATCH,
     DELETE,
+    OPTIONS, // какие методы поддерживает путь
     OTHER,
 }
 impl std::fmt::Display for Method {
     // объявляем реализацию трейта Display из модуля std::fmt
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // &self - само значение Method (GET, POST, PUT, DELETE, OTHER)
+        // &self - само значение Method (GET, HEAD, POST, PUT, PATCH, DELETE, OPTIONS, OTHER)
         // f: &mut fmt::Formatter<'_> -- приемник вывода. Внутри него хранятся все параметры форматирования (ширина, выравнивание, точность) + буфер, куда нужно записать результат
         // сопоставим каждый возможный self с нужным вариантов
         // fmt::Result -- это псевдоним для Result<(), std::fmt::Error>, т.е. это тоже самое. Если все успешно -- вернем Ok(()). Если ошибка - вернем Err(...).
         let stroka: &'static str = match self {
             Method::GET => "GET",
+            Method::HEAD => "HEAD",
             Method::POST => "POST",
             Method::PUT => "PUT",
+            Method::PATCH => "PATCH",
             Method::DELETE => "DELETE",
+            Method::OPTIONS => "OPTIONS",
             Method::OTHER => "OTHER",
         };
         // макрос write! записывает в форматер f строку s
         write!(f, "{stroka}")
     }
 }
+
+// Версия HTTP из стартовой строки запроса
+#[derive(Hash, PartialEq, Eq, Debug, Copy, Clone, Default)]
+pub enum Version {
+    Http10, // HTTP/1.0: по умолчанию соединение закрывается после ответа
+    #[default]
+    Http11, // HTTP/1.1: по умолчанию соединение остаётся открытым (keep-alive)
+}
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let stroka: &'static str = match self {
+            Version::Http10 => "HTTP/1.0",
+            Version::Http11 => "HTTP/1.1",
+        };
+        write!(f, "{stroka}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_codes() {
+        assert_eq!(StatusCode::CONFLICT.to_string(), "409 Conflict");
+        assert_eq!(StatusCode::CONTENT_TOO_LARGE.as_u16(), 413);
+        assert_eq!(
+            StatusCode::from_u16(422).unwrap(),
+            StatusCode::UNPROCESSABLE_CONTENT
+        );
+        assert_eq!(
+            StatusCode::from_u16(499).unwrap().reason_phrase(),
+            "Client Error"
+        );
+
+        assert!(StatusCode::from_u16(99).is_err());
+        assert!(StatusCode::try_from(600).is_err());
+        assert!(StatusCode::from_u16(0).is_err());
+
+        assert!(!StatusCode::NO_CONTENT.allows_body());
+        assert!(!StatusCode::CONTINUE.allows_body());
+        assert!(StatusCode::NOT_FOUND.allows_body());
+        assert!(StatusCode::BAD_GATEWAY.is_server_error());
+    }
+}
@@ -1,13 +1,19 @@
 use std::collections::HashMap;
 
-use crate::lib::req_res_structs::{BodyType, Method};
+use crate::lib::{
+    headers::HeaderMap,
+    query::QueryParams,
+    req_res_structs::{BodyType, Method, Version},
+};
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Request {
     pub method: Method,
-    pub path: String,
-    pub headers: Option<Vec<String>>, // Option - либо Some, либо None
-    pub body: Option<BodyType>,       // Option - либо Some, либо None
+    pub path: String,       // путь без строки запроса, уже percent-decoded
+    pub query: QueryParams, // параметры после '?'
+    pub version: Version,
+    pub headers: HeaderMap,
+    pub body: Option<BodyType>, // Option - либо Some, либо None
     pub rest_params: HashMap<String, String>,
 }
 
@@ -16,7 +22,9 @@ impl Default for Request {
         Self {
             method: Method::GET,
             path: String::default(),
-            headers: None,
+            query: QueryParams::default(),
+            version: Version::default(),
+            headers: HeaderMap::new(),
             body: None,
             rest_params: HashMap::new(),
         }
@@ -24,6 +32,26 @@ impl Default for Request {
 }
 
 impl Request {
+    // Значение заголовка по имени (без учёта регистра)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)
+    }
+
+    // Id запроса для логов и ответов с ошибкой (сервер выставляет его перед вызовом хендлера)
+    pub fn request_id(&self) -> &str {
+        self.header("x-request-id").unwrap_or_default()
+    }
+
+    // Хочет ли клиент оставить соединение открытым после ответа
+    // HTTP/1.1: да, если нет Connection: close
+    // HTTP/1.0: только если явно прислал Connection: keep-alive
+    pub fn wants_keep_alive(&self) -> bool {
+        match self.version {
+            Version::Http11 => !self.headers.has_token("connection", "close"),
+            Version::Http10 => self.headers.has_token("connection", "keep-alive"),
+        }
+    }
+
     pub fn parse_args(&mut self, path: &str) {
         let request_chunks: Vec<&str> = self.path.split("/").collect();
 
@@ -70,7 +98,9 @@ mod tests {
         let mut request = Request {
             method: Method::GET,
             path: "/container/label/reboot".to_string(),
-            headers: None,
+            query: QueryParams::default(),
+            version: Version::Http11,
+            headers: HeaderMap::new(),
             body: None,
             rest_params: HashMap::new(),
         };
@@ -84,4 +114,20 @@ mod tests {
         assert!(request.is_similar(path));
         assert_eq!(request, expected_request);
     }
+
+    #[test]
+    fn keep_alive_by_version() {
+        let mut request = Request::default();
+        assert!(request.wants_keep_alive());
+
+        request.headers = HeaderMap::from_lines(["Connection: Close"]).unwrap();
+        assert!(!request.wants_keep_alive());
+
+        request.version = Version::Http10;
+        request.headers = HeaderMap::new();
+        assert!(!request.wants_keep_alive());
+
+        request.headers = HeaderMap::from_lines(["connection: keep-alive"]).unwrap();
+        assert!(request.wants_keep_alive());
+    }
 }
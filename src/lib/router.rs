@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+
+use crate::lib::{http_server::HandlerFn, req_res_structs::Method, server_errors::ServerError};
+
+// Маршрутизатор -- префиксное дерево (trie) по сегментам пути.
+//
+// server.GET("/container/", ...);
+// server.GET("/container/stats", ...);
+// server.GET("/container/:id", ...);
+// server.POST("/container/:id/start", ...);
+// server.GET("/files/*path", ...);
+//
+// root
+// ├── "container"
+// │   ├── ""        → { GET }                  "/container/"
+// │   ├── "stats"   → { GET }                  "/container/stats"
+// │   └── :id       → { GET }                  "/container/:id"
+// │       └── "start" → { POST }               "/container/:id/start"
+// └── "files"
+//     └── *path     → { GET }                  "/files/*path"
+//
+// Приоритет при поиске (не зависит от порядка регистрации):
+// 1. статический сегмент ("stats")
+// 2. параметр (":id") -- ровно один любой сегмент
+// 3. wildcard ("*path") -- весь остаток пути (один и более сегментов)
+//
+// Если более приоритетная ветка не привела к хендлеру, пробуем следующую (откат назад)
+
+// Зарегистрированный хендлер вместе с исходным шаблоном пути (для логов)
+#[derive(Clone)]
+struct Route {
+    pattern: &'static str,
+    handler: HandlerFn,
+}
+
+#[derive(Clone, Default)]
+struct Node {
+    static_children: HashMap<String, Node>,
+    param_child: Option<(String, Box<Node>)>, // имя параметра + поддерево
+    wildcard: Option<(String, HashMap<Method, Route>)>, // wildcard всегда последний сегмент
+    routes: HashMap<Method, Route>,           // хендлеры, если путь заканчивается в этом узле
+}
+
+// Результат поиска: хендлер + значения параметров из пути
+pub struct RouteMatch<'a> {
+    pub pattern: &'static str,
+    pub handler: &'a HandlerFn,
+    pub params: HashMap<String, String>,
+}
+
+#[derive(Clone, Default)]
+pub struct Router {
+    root: Node,
+}
+
+// "/container/:id/start" -> ["container", ":id", "start"]
+// "/container/"          -> ["container", ""]
+fn split_path(path: &str) -> Vec<&str> {
+    path.strip_prefix('/').unwrap_or(path).split('/').collect()
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Регистрируем хендлер. Конфликтующий шаблон -- сразу ошибка, а не сюрприз во время работы
+    pub fn add(
+        &mut self,
+        method: Method,
+        pattern: &'static str,
+        handler: HandlerFn,
+    ) -> Result<(), ServerError> {
+        if !pattern.starts_with('/') {
+            return Err(ServerError::HandlerError(format!(
+                "Path '{pattern}' must start with '/'"
+            )));
+        }
+
+        let segments = split_path(pattern);
+        let mut node: &mut Node = &mut self.root;
+
+        for (i, segment) in segments.iter().enumerate() {
+            if let Some(name) = segment.strip_prefix(':') {
+                if name.is_empty() {
+                    return Err(ServerError::HandlerError(format!(
+                        "Empty parameter name in path '{pattern}'"
+                    )));
+                }
+
+                let (existing, child) = node
+                    .param_child
+                    .get_or_insert_with(|| (name.to_string(), Box::default()));
+                // "/container/:id" и "/container/:name/start" -- непонятно, как назвать параметр
+                if existing != name {
+                    return Err(ServerError::HandlerError(format!(
+                        "Parameter ':{name}' in path '{pattern}' conflicts with already registered ':{existing}'"
+                    )));
+                }
+                node = child;
+            } else if let Some(name) = segment.strip_prefix('*') {
+                if i != segments.len() - 1 {
+                    return Err(ServerError::HandlerError(format!(
+                        "Wildcard '*{name}' must be the last segment of path '{pattern}'"
+                    )));
+                }
+                if name.is_empty() {
+                    return Err(ServerError::HandlerError(format!(
+                        "Empty wildcard name in path '{pattern}'"
+                    )));
+                }
+
+                let (existing, routes) = node
+                    .wildcard
+                    .get_or_insert_with(|| (name.to_string(), HashMap::new()));
+                if existing != name {
+                    return Err(ServerError::HandlerError(format!(
+                        "Wildcard '*{name}' in path '{pattern}' conflicts with already registered '*{existing}'"
+                    )));
+                }
+                return insert_route(routes, method, pattern, handler);
+            } else {
+                node = node.static_children.entry(segment.to_string()).or_default();
+            }
+        }
+
+        insert_route(&mut node.routes, method, pattern, handler)
+    }
+
+    // Ищем хендлер для метода и пути с учётом приоритета static > :param > *wildcard
+    pub fn find(&self, method: Method, path: &str) -> Option<RouteMatch<'_>> {
+        let segments = split_path(path);
+        let mut found: Option<RouteMatch<'_>> = None;
+
+        self.root
+            .visit(&segments, &mut Vec::new(), &mut |routes, params| {
+                match routes.get(&method) {
+                    Some(route) => {
+                        found = Some(RouteMatch {
+                            pattern: route.pattern,
+                            handler: &route.handler,
+                            params: params.iter().cloned().collect(),
+                        });
+                        true // нашли -- дальше не ищем
+                    }
+                    None => false,
+                }
+            });
+
+        found
+    }
+
+    // Все методы, для которых по этому пути есть хендлер
+    pub fn allowed_methods(&self, path: &str) -> Vec<Method> {
+        let segments = split_path(path);
+        let mut methods: Vec<Method> = Vec::new();
+
+        self.root
+            .visit(&segments, &mut Vec::new(), &mut |routes, _| {
+                for method in routes.keys() {
+                    if !methods.contains(method) {
+                        methods.push(*method);
+                    }
+                }
+                false // собираем со всех подходящих узлов
+            });
+
+        methods
+    }
+
+    // Все зарегистрированные маршруты (для логов и Debug)
+    pub fn routes(&self) -> Vec<(Method, &'static str)> {
+        let mut routes = Vec::new();
+        self.root.collect_routes(&mut routes);
+        routes
+    }
+}
+
+fn insert_route(
+    routes: &mut HashMap<Method, Route>,
+    method: Method,
+    pattern: &'static str,
+    handler: HandlerFn,
+) -> Result<(), ServerError> {
+    if let Some(existing) = routes.get(&method) {
+        return Err(ServerError::HandlerError(format!(
+            "{method} handler with path '{pattern}' conflicts with already registered '{}'",
+            existing.pattern
+        )));
+    }
+    routes.insert(method, Route { pattern, handler });
+    Ok(())
+}
+
+// Колбэк получает хендлеры подходящего узла и накопленные параметры.
+// Вернул true -- поиск останавливается
+type Visitor<'a, 'f> = dyn FnMut(&'a HashMap<Method, Route>, &[(String, String)]) -> bool + 'f;
+
+impl Node {
+    // Обходим все узлы, которые подходят под путь, в порядке приоритета.
+    // Возвращаем true, если visitor попросил остановиться
+    fn visit<'a>(
+        &'a self,
+        segments: &[&str],
+        params: &mut Vec<(String, String)>,
+        visitor: &mut Visitor<'a, '_>,
+    ) -> bool {
+        let Some((segment, rest)) = segments.split_first() else {
+            // путь закончился в этом узле
+            return !self.routes.is_empty() && visitor(&self.routes, params);
+        };
+
+        // 1. статический сегмент
+        if let Some(child) = self.static_children.get(*segment)
+            && child.visit(rest, params, visitor)
+        {
+            return true;
+        }
+
+        // 2. параметр -- любой непустой сегмент
+        if let Some((name, child)) = &self.param_child
+            && !segment.is_empty()
+        {
+            params.push((name.clone(), segment.to_string()));
+            if child.visit(rest, params, visitor) {
+                return true;
+            }
+            params.pop();
+        }
+
+        // 3. wildcard -- весь остаток пути
+        if let Some((name, routes)) = &self.wildcard
+            && !segment.is_empty()
+        {
+            params.push((name.clone(), segments.join("/")));
+            if visitor(routes, params) {
+                return true;
+            }
+            params.pop();
+        }
+
+        false
+    }
+
+    fn collect_routes(&self, out: &mut Vec<(Method, &'static str)>) {
+        out.extend(self.routes.iter().map(|(m, r)| (*m, r.pattern)));
+        for child in self.static_children.values() {
+            child.collect_routes(out);
+        }
+        if let Some((_, child)) = &self.param_child {
+            child.collect_routes(out);
+        }
+        if let Some((_, routes)) = &self.wildcard {
+            out.extend(routes.iter().map(|(m, r)| (*m, r.pattern)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::lib::{
+        headers::HeaderMap,
+        req_res_structs::{BodyType, Response, StatusCode},
+    };
+
+    fn handler(name: &'static str) -> HandlerFn {
+        Arc::new(move |_| Response {
+            response_code: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Some(BodyType::Plain(name.to_string())),
+        })
+    }
+
+    #[test]
+    fn static_beats_param_beats_wildcard() -> Result<(), ServerError> {
+        let mut router = Router::new();
+        // регистрируем в «неудобном» порядке -- приоритет от него не зависит
+        router.add(Method::GET, "/container/*rest", handler("wildcard"))?;
+        router.add(Method::GET, "/container/:id", handler("param"))?;
+        router.add(Method::GET, "/container/stats", handler("static"))?;
+
+        let found = router.find(Method::GET, "/container/stats").unwrap();
+        assert_eq!(found.pattern, "/container/stats");
+        assert!(found.params.is_empty());
+
+        let found = router.find(Method::GET, "/container/web1").unwrap();
+        assert_eq!(found.pattern, "/container/:id");
+        assert_eq!(found.params.get("id").unwrap(), "web1");
+
+        let found = router
+            .find(Method::GET, "/container/web1/logs/today")
+            .unwrap();
+        assert_eq!(found.pattern, "/container/*rest");
+        assert_eq!(found.params.get("rest").unwrap(), "web1/logs/today");
+
+        Ok(())
+    }
+
+    #[test]
+    fn backtracks_from_static_to_param() -> Result<(), ServerError> {
+        let mut router = Router::new();
+        router.add(Method::GET, "/container/stats", handler("stats"))?;
+        router.add(Method::POST, "/container/:id/start", handler("start"))?;
+
+        // "stats" совпал со статическим сегментом, но дальше пути нет -- откатываемся к :id
+        let found = router.find(Method::POST, "/container/stats/start").unwrap();
+        assert_eq!(found.params.get("id").unwrap(), "stats");
+
+        assert!(router.find(Method::GET, "/container/web1/start").is_none());
+        assert!(router.find(Method::GET, "/container/").is_none());
+        assert_eq!(
+            router.allowed_methods("/container/web1/start"),
+            vec![Method::POST]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn trailing_slash_is_significant() -> Result<(), ServerError> {
+        let mut router = Router::new();
+        router.add(Method::GET, "/container/", handler("list"))?;
+
+        assert!(router.find(Method::GET, "/container/").is_some());
+        assert!(router.find(Method::GET, "/container").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn conflicts_are_detected_on_registration() -> Result<(), ServerError> {
+        let mut router = Router::new();
+        router.add(Method::GET, "/container/:id", handler("a"))?;
+
+        assert!(
+            router
+                .add(Method::GET, "/container/:id", handler("b"))
+                .is_err()
+        );
+        assert!(
+            router
+                .add(Method::POST, "/container/:name/start", handler("c"))
+                .is_err()
+        );
+        assert!(
+            router
+                .add(Method::GET, "/files/*a/b", handler("d"))
+                .is_err()
+        );
+        assert!(router.add(Method::GET, "container", handler("e")).is_err());
+
+        // тот же путь, но другой метод -- не конфликт
+        router.add(Method::DELETE, "/container/:id", handler("f"))?;
+
+        Ok(())
+    }
+}
@@ -5,6 +5,8 @@ pub enum ServerError {
     InitError(String),
     ParseError(String),
     HandlerError(String),
+    ReadError(String),       // не смогли прочитать запрос из сокета
+    PayloadTooLarge(String), // тело запроса больше, чем разрешено в конфиге
 }
 
 impl Display for ServerError {
@@ -19,6 +21,12 @@ impl Display for ServerError {
             Self::InitError(s) => {
                 write!(f, "Init error: {s}")
             }
+            Self::ReadError(s) => {
+                write!(f, "Read error: {s}")
+            }
+            Self::PayloadTooLarge(s) => {
+                write!(f, "Payload too large: {s}")
+            }
         }
     }
 }
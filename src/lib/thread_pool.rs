@@ -0,0 +1,227 @@
+use std::{
+    panic::{AssertUnwindSafe, catch_unwind},
+    sync::{
+        Arc, Mutex,
+        mpsc::{Receiver, SyncSender, TrySendError, sync_channel},
+    },
+    thread::{Builder, JoinHandle, sleep},
+    time::{Duration, Instant},
+};
+
+use crate::lib::{logger::Logger, server_errors::ServerError};
+
+// Пул воркеров с ограниченной очередью задач
+//
+//   accept-цикл ──try_execute(T)──▶ [ очередь на queue_size мест ] ──▶ воркер 1 ─┐
+//                                                                  ──▶ воркер 2 ─┼─▶ job(T)
+//                                                                  ──▶ воркер N ─┘
+//
+// Если очередь заполнена, try_execute сразу возвращает задачу обратно (Err(T)),
+// а не блокирует вызывающий поток -- так сервер может ответить клиенту 503
+pub struct ThreadPool<T: Send + 'static> {
+    workers: Vec<JoinHandle<()>>,
+    sender: Option<SyncSender<T>>, // Option, чтобы в Drop можно было закрыть канал до join
+}
+
+impl<T: Send + 'static> ThreadPool<T> {
+    // log -- логгер сервера: в него пишем о паниках в задачах
+    pub fn new<F>(size: usize, queue_size: usize, log: Logger, job: F) -> Result<Self, ServerError>
+    where
+        F: Fn(T) + Send + Sync + 'static,
+    {
+        if size == 0 {
+            return Err(ServerError::InitError(
+                "Thread pool must have at least one worker".to_string(),
+            ));
+        }
+
+        let (sender, receiver) = sync_channel::<T>(queue_size);
+        // Receiver нельзя клонировать, поэтому воркеры делят его через Mutex
+        let receiver: Arc<Mutex<Receiver<T>>> = Arc::new(Mutex::new(receiver));
+        let job = Arc::new(job);
+
+        let mut workers = Vec::with_capacity(size);
+        for id in 0..size {
+            let receiver = Arc::clone(&receiver);
+            let job = Arc::clone(&job);
+
+            let worker = Builder::new()
+                .name(format!("worker-{id}"))
+                .spawn(move || {
+                    loop {
+                        // блокировку держим только на время recv, а не на время работы задачи
+                        let task = match receiver.lock() {
+                            Ok(receiver) => receiver.recv(),
+                            Err(_) => return,
+                        };
+                        let task = match task {
+                            Ok(task) => task,
+                            Err(_) => return, // канал закрыт -- пул остановлен
+                        };
+                        // паника в задаче не должна убивать воркер: иначе несколько
+                        // "плохих" запросов оставят пул без потоков
+                        if let Err(payload) = catch_unwind(AssertUnwindSafe(|| job(task))) {
+                            log.error(&format!(
+                                "worker-{id}: job panicked: {}",
+                                panic_message(payload.as_ref())
+                            ));
+                        }
+                    }
+                })
+                .map_err(|e| ServerError::InitError(format!("Failed to spawn worker: {e}")))?;
+
+            workers.push(worker);
+        }
+
+        Ok(Self {
+            workers,
+            sender: Some(sender),
+        })
+    }
+
+    // Кладём задачу в очередь. Очередь заполнена -- возвращаем задачу обратно
+    pub fn try_execute(&self, task: T) -> Result<(), T> {
+        match &self.sender {
+            Some(sender) => match sender.try_send(task) {
+                Ok(()) => Ok(()),
+                Err(TrySendError::Full(task)) | Err(TrySendError::Disconnected(task)) => Err(task),
+            },
+            None => Err(task),
+        }
+    }
+
+    // Останавливаем пул: новые задачи больше не принимаются, воркеры доделывают
+    // то, что уже взяли и что лежит в очереди. Ждём их не дольше timeout.
+    // Возвращаем true, если все воркеры успели завершиться; зависшие потоки
+    // остаются работать в фоне (отсоединяются), но пул больше не ждёт их
+    pub fn shutdown(mut self, timeout: Duration) -> bool {
+        drop(self.sender.take());
+
+        let deadline = Instant::now() + timeout;
+        while self.workers.iter().any(|worker| !worker.is_finished()) && Instant::now() < deadline {
+            sleep(Duration::from_millis(10));
+        }
+
+        let mut all_finished = true;
+        for worker in self.workers.drain(..) {
+            if worker.is_finished() {
+                let _ = worker.join();
+            } else {
+                all_finished = false; // JoinHandle уходит в drop -- поток отсоединяется
+            }
+        }
+        all_finished
+    }
+}
+
+// Текст паники: panic!("...") кладёт &str, panic!("{x}") -- String
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+impl<T: Send + 'static> Drop for ThreadPool<T> {
+    fn drop(&mut self) {
+        // закрываем канал: воркеры доделают то, что уже в очереди, и выйдут из цикла
+        drop(self.sender.take());
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Barrier, atomic::AtomicUsize, atomic::Ordering};
+
+    use super::*;
+
+    #[test]
+    fn pool_runs_all_jobs() -> Result<(), ServerError> {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let pool = {
+            let counter = Arc::clone(&counter);
+            ThreadPool::new(4, 16, Logger::default(), move |n: usize| {
+                counter.fetch_add(n, Ordering::SeqCst);
+            })?
+        };
+
+        for _ in 0..10 {
+            while pool.try_execute(1).is_err() {}
+        }
+        drop(pool); // дожидаемся воркеров
+
+        assert_eq!(counter.load(Ordering::SeqCst), 10);
+        Ok(())
+    }
+
+    #[test]
+    fn pool_rejects_when_queue_is_full() -> Result<(), ServerError> {
+        let started = Arc::new(Barrier::new(2));
+        let release = Arc::new(Barrier::new(2));
+        let pool = {
+            let started = Arc::clone(&started);
+            let release = Arc::clone(&release);
+            ThreadPool::new(1, 1, Logger::default(), move |first: bool| {
+                if first {
+                    started.wait();
+                    release.wait();
+                }
+            })?
+        };
+
+        assert!(pool.try_execute(true).is_ok());
+        started.wait(); // единственный воркер занят
+        assert!(pool.try_execute(false).is_ok()); // заняли единственное место в очереди
+        assert_eq!(pool.try_execute(false), Err(false)); // очередь заполнена
+
+        release.wait();
+        Ok(())
+    }
+
+    #[test]
+    fn pool_survives_panicking_job() -> Result<(), ServerError> {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let pool = {
+            let counter = Arc::clone(&counter);
+            ThreadPool::new(1, 4, Logger::default(), move |fail: bool| {
+                if fail {
+                    panic!("job failed");
+                }
+                counter.fetch_add(1, Ordering::SeqCst);
+            })?
+        };
+
+        assert!(pool.try_execute(true).is_ok());
+        assert!(pool.try_execute(false).is_ok());
+        // единственный воркер пережил панику и выполнил следующую задачу
+        assert!(pool.shutdown(Duration::from_secs(5)));
+
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn shutdown_gives_up_after_deadline() -> Result<(), ServerError> {
+        let release = Arc::new(Barrier::new(2));
+        let pool = {
+            let release = Arc::clone(&release);
+            ThreadPool::new(1, 1, Logger::default(), move |_: ()| {
+                release.wait();
+            })?
+        };
+
+        assert!(pool.try_execute(()).is_ok());
+        // воркер висит на барьере -- пул не должен ждать его дольше дедлайна
+        assert!(!pool.shutdown(Duration::from_millis(50)));
+
+        release.wait();
+        Ok(())
+    }
+}
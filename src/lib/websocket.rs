@@ -0,0 +1,981 @@
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{Shutdown, TcpStream},
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use crate::lib::{
+    api_error::ApiError,
+    headers::HeaderMap,
+    http_server::ShutdownHandle,
+    req_res_structs::{
+        BodyType, Method, Response, StatusCode, Upgrade, UpgradedConnection, Version,
+    },
+    request::Request,
+};
+
+// WebSocket (RFC 6455) поверх нашего HTTP-сервера. Хендлер не знает ни про фреймы, ни про сокет:
+//
+//   let handshake = match websocket::accept(request) {
+//       Ok(handshake) => handshake,
+//       Err(e) => return e.into_response(request), // 400 / 426, если это не WebSocket
+//   };
+//   handshake.into_response(|mut ws| {             // замыкание работает уже после ответа 101
+//       while let Ok(Some(message)) = ws.recv() {  // ping/pong, close и фрагменты -- внутри
+//           let _ = ws.sender().send_text("...");  // sender можно клонировать в другие потоки
+//       }
+//   })
+//
+// Всё соединение живёт в одном воркере пула, пока замыкание не вернётся
+
+// GUID из RFC 6455: Sec-WebSocket-Accept = base64(sha1(Sec-WebSocket-Key + GUID))
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const WEBSOCKET_VERSION: &str = "13";
+
+// Молчит клиент столько -- шлём ping; на ping не ответил за столько же -- соединение мёртвое
+pub const DEFAULT_PING_INTERVAL: Duration = Duration::from_secs(30);
+// Сообщение больше этого (после склейки фрагментов) -- закрываем с 1009
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+// Как часто recv() оглядывается на ping и остановку сервера, пока клиент молчит
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+// Сколько ждать ответный close, прежде чем просто закрыть соединение
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+// У управляющих фреймов (close, ping, pong) payload не больше 125 байт
+const MAX_CONTROL_PAYLOAD: usize = 125;
+
+// Коды закрытия (RFC 6455, 7.4.1)
+pub const CLOSE_NORMAL: u16 = 1000;
+pub const CLOSE_GOING_AWAY: u16 = 1001;
+pub const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+pub const CLOSE_INVALID_DATA: u16 = 1007;
+pub const CLOSE_TOO_LARGE: u16 = 1009;
+pub const CLOSE_INTERNAL_ERROR: u16 = 1011;
+
+// ------------------------------------------------------------------
+// ------------------------ Рукопожатие -----------------------------
+// ------------------------------------------------------------------
+
+// Клиент прислал правильный запрос на WebSocket -- осталось ответить 101
+#[derive(Debug, Clone, PartialEq)]
+pub struct Handshake {
+    accept_key: String,
+    ping_interval: Duration,
+    max_message_size: usize,
+}
+
+// Запрос не WebSocket (или не той версии). Отдельный тип, а не ApiError, потому что
+// к ответу нужны заголовки: 426 говорит клиенту, на что переключаться
+#[derive(Debug, Clone, PartialEq)]
+pub struct HandshakeError {
+    pub error: ApiError,
+    upgrade_required: bool,
+}
+
+impl HandshakeError {
+    fn bad_request(message: &str) -> Self {
+        Self {
+            error: ApiError::new(StatusCode::BAD_REQUEST, "bad_handshake", message),
+            upgrade_required: false,
+        }
+    }
+
+    fn upgrade_required(message: &str) -> Self {
+        Self {
+            error: ApiError::new(StatusCode::UPGRADE_REQUIRED, "upgrade_required", message),
+            upgrade_required: true,
+        }
+    }
+
+    pub fn into_response(self, request: &Request) -> Response {
+        let upgrade_required = self.upgrade_required;
+        let mut response = self.error.into_response(request);
+        if upgrade_required {
+            let _ = response.headers.insert("Upgrade", "websocket");
+            let _ = response
+                .headers
+                .insert("Sec-WebSocket-Version", WEBSOCKET_VERSION);
+        }
+        response
+    }
+}
+
+impl std::fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+// Проверяем запрос на переключение протокола:
+//   GET /path HTTP/1.1
+//   Upgrade: websocket
+//   Connection: Upgrade
+//   Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==   <- 16 случайных байт в base64
+//   Sec-WebSocket-Version: 13
+pub fn accept(request: &Request) -> Result<Handshake, HandshakeError> {
+    if !request.headers.has_token("upgrade", "websocket") {
+        return Err(HandshakeError::upgrade_required(
+            "This endpoint only speaks WebSocket",
+        ));
+    }
+    if request.method != Method::GET || request.version != Version::Http11 {
+        return Err(HandshakeError::bad_request(
+            "WebSocket handshake must be a GET request over HTTP/1.1",
+        ));
+    }
+    if !request.headers.has_token("connection", "upgrade") {
+        return Err(HandshakeError::bad_request(
+            "Connection header must contain Upgrade",
+        ));
+    }
+    if request.header("sec-websocket-version").map(str::trim) != Some(WEBSOCKET_VERSION) {
+        return Err(HandshakeError::upgrade_required(
+            "Unsupported Sec-WebSocket-Version, expected 13",
+        ));
+    }
+    let key = request.header("sec-websocket-key").map(str::trim);
+    let Some(key) = key.filter(|key| base64_decode(key).is_some_and(|raw| raw.len() == 16)) else {
+        return Err(HandshakeError::bad_request(
+            "Sec-WebSocket-Key must be 16 bytes in base64",
+        ));
+    };
+
+    Ok(Handshake {
+        accept_key: accept_key(key),
+        ping_interval: DEFAULT_PING_INTERVAL,
+        max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+    })
+}
+
+impl Handshake {
+    pub fn with_ping_interval(mut self, ping_interval: Duration) -> Self {
+        self.ping_interval = ping_interval;
+        self
+    }
+
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self
+    }
+
+    // Ответ 101 Switching Protocols. on_open запустится, когда сервер отправит ответ
+    pub fn into_response<F>(self, on_open: F) -> Response
+    where
+        F: FnOnce(WebSocket) + Send + 'static,
+    {
+        let mut headers = HeaderMap::new();
+        let _ = headers.insert("Upgrade", "websocket");
+        let _ = headers.insert("Connection", "Upgrade");
+        let _ = headers.insert("Sec-WebSocket-Accept", &self.accept_key);
+        Response {
+            response_code: StatusCode::SWITCHING_PROTOCOLS,
+            headers,
+            body: Some(BodyType::Upgrade(Upgrade::new(move |connection| {
+                // не вышло -- соединение просто закроется, ответ 101 уже ушёл
+                if let Ok(ws) =
+                    WebSocket::new(connection, self.ping_interval, self.max_message_size)
+                {
+                    on_open(ws);
+                }
+            }))),
+        }
+    }
+}
+
+pub fn accept_key(key: &str) -> String {
+    base64_encode(&sha1(format!("{key}{HANDSHAKE_GUID}").as_bytes()))
+}
+
+// ------------------------------------------------------------------
+// ------------------------- Фреймы ---------------------------------
+// ------------------------------------------------------------------
+
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-------+-+-------------+-------------------------------+
+// |F|R|R|R| opcode|M| Payload len |    Extended payload length    |
+// |I|S|S|S|  (4)  |A|     (7)     |             (16/64)           |
+// |N|V|V|V|       |S|             |   (if payload len==126/127)   |
+// | |1|2|3|       |K|             |                               |
+// +-+-+-+-+-------+-+-------------+ - - - - - - - - - - - - - - - +
+// |     Masking-key (0 или 4 байта, только от клиента)            |
+// +---------------------------------------------------------------+
+// |                          Payload Data                         |
+// +---------------------------------------------------------------+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+}
+
+impl Opcode {
+    fn from_u8(byte: u8) -> Option<Self> {
+        match byte {
+            0x0 => Some(Self::Continuation),
+            0x1 => Some(Self::Text),
+            0x2 => Some(Self::Binary),
+            0x8 => Some(Self::Close),
+            0x9 => Some(Self::Ping),
+            0xA => Some(Self::Pong),
+            _ => None,
+        }
+    }
+
+    fn as_u8(self) -> u8 {
+        match self {
+            Self::Continuation => 0x0,
+            Self::Text => 0x1,
+            Self::Binary => 0x2,
+            Self::Close => 0x8,
+            Self::Ping => 0x9,
+            Self::Pong => 0xA,
+        }
+    }
+
+    fn is_control(self) -> bool {
+        matches!(self, Self::Close | Self::Ping | Self::Pong)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub fin: bool, // последний фрагмент сообщения
+    pub opcode: Opcode,
+    pub masked: bool,     // клиент обязан маскировать, сервер -- нет
+    pub payload: Vec<u8>, // уже без маски
+}
+
+#[derive(Debug)]
+pub enum FrameError {
+    Io(io::Error),
+    Protocol(&'static str), // фрейм не по RFC -> close 1002
+    TooLarge,               // больше лимита -> close 1009
+}
+
+impl From<io::Error> for FrameError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+// Кодируем один фрейм. mask нужен только клиенту (тестам), сервер шлёт без маски
+pub fn encode_frame(opcode: Opcode, fin: bool, payload: &[u8], mask: Option<[u8; 4]>) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + 14);
+    frame.push(if fin { 0x80 } else { 0 } | opcode.as_u8());
+
+    let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+    match payload.len() {
+        len @ 0..=125 => frame.push(mask_bit | len as u8),
+        len @ 126..=0xFFFF => {
+            frame.push(mask_bit | 126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(mask_bit | 127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+
+    match mask {
+        Some(mask) => {
+            frame.extend_from_slice(&mask);
+            frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        }
+        None => frame.extend_from_slice(payload),
+    }
+    frame
+}
+
+// Читаем один фрейм целиком. max_payload проверяем до того, как выделить под payload память
+pub fn read_frame<R: Read>(reader: &mut R, max_payload: usize) -> Result<Frame, FrameError> {
+    let mut first = [0u8; 1];
+    reader.read_exact(&mut first)?;
+    read_frame_after(first[0], reader, max_payload)
+}
+
+// То же, но первый байт уже прочитан (recv ждёт его с коротким таймаутом)
+fn read_frame_after<R: Read>(
+    first: u8,
+    reader: &mut R,
+    max_payload: usize,
+) -> Result<Frame, FrameError> {
+    if first & 0x70 != 0 {
+        // RSV1-3 -- для расширений, а их мы не согласовывали
+        return Err(FrameError::Protocol("Reserved bits must be zero"));
+    }
+    let fin = first & 0x80 != 0;
+    let opcode = Opcode::from_u8(first & 0x0F).ok_or(FrameError::Protocol("Unknown opcode"))?;
+
+    let mut second = [0u8; 1];
+    reader.read_exact(&mut second)?;
+    let masked = second[0] & 0x80 != 0;
+    let len: u64 = match second[0] & 0x7F {
+        126 => {
+            let mut len = [0u8; 2];
+            reader.read_exact(&mut len)?;
+            u16::from_be_bytes(len) as u64
+        }
+        127 => {
+            let mut len = [0u8; 8];
+            reader.read_exact(&mut len)?;
+            let len = u64::from_be_bytes(len);
+            if len >> 63 != 0 {
+                return Err(FrameError::Protocol("Payload length must fit in 63 bits"));
+            }
+            len
+        }
+        len => len as u64,
+    };
+
+    if opcode.is_control() && (!fin || len > MAX_CONTROL_PAYLOAD as u64) {
+        return Err(FrameError::Protocol(
+            "Control frames must not be fragmented or longer than 125 bytes",
+        ));
+    }
+    if len > max_payload as u64 {
+        return Err(FrameError::TooLarge);
+    }
+
+    let mut mask = [0u8; 4];
+    if masked {
+        reader.read_exact(&mut mask)?;
+    }
+    let mut payload = vec![0u8; len as usize];
+    reader.read_exact(&mut payload)?;
+    if masked {
+        payload
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, b)| *b ^= mask[i % 4]);
+    }
+
+    Ok(Frame {
+        fin,
+        opcode,
+        masked,
+        payload,
+    })
+}
+
+// ------------------------------------------------------------------
+// ------------------------ Соединение ------------------------------
+// ------------------------------------------------------------------
+
+// Сообщение целиком (фрагменты уже склеены)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+// Отправка в WebSocket. Клонируется: читать может только один поток (WebSocket::recv),
+// а писать -- сколько угодно, фреймы не перемешаются
+#[derive(Debug, Clone)]
+pub struct WebSocketSender {
+    inner: Arc<Mutex<SenderState>>,
+}
+
+#[derive(Debug)]
+struct SenderState {
+    stream: TcpStream,
+    closed_at: Option<Instant>, // когда отправили close; после него слать уже нельзя
+}
+
+impl WebSocketSender {
+    fn state(&self) -> io::Result<MutexGuard<'_, SenderState>> {
+        self.inner
+            .lock()
+            .map_err(|_| io::Error::other("WebSocket sender is poisoned"))
+    }
+
+    fn send_frame(&self, opcode: Opcode, payload: &[u8]) -> io::Result<()> {
+        let mut state = self.state()?;
+        if state.closed_at.is_some() {
+            return Err(io::Error::new(
+                ErrorKind::NotConnected,
+                "WebSocket is closed",
+            ));
+        }
+        state
+            .stream
+            .write_all(&encode_frame(opcode, true, payload, None))
+    }
+
+    pub fn send_text(&self, text: &str) -> io::Result<()> {
+        self.send_frame(Opcode::Text, text.as_bytes())
+    }
+
+    pub fn send_binary(&self, data: &[u8]) -> io::Result<()> {
+        self.send_frame(Opcode::Binary, data)
+    }
+
+    pub fn ping(&self, payload: &[u8]) -> io::Result<()> {
+        self.send_frame(
+            Opcode::Ping,
+            &payload[..payload.len().min(MAX_CONTROL_PAYLOAD)],
+        )
+    }
+
+    fn pong(&self, payload: &[u8]) -> io::Result<()> {
+        self.send_frame(Opcode::Pong, payload)
+    }
+
+    // Начинаем закрытие. close уходит только один раз, повторные вызовы ничего не делают.
+    // Дождаться ответного close -- дело WebSocket::recv
+    pub fn close(&self, code: u16, reason: &str) -> io::Result<()> {
+        let mut state = self.state()?;
+        if state.closed_at.is_some() {
+            return Ok(());
+        }
+        state.closed_at = Some(Instant::now());
+
+        // причина не длиннее 123 байт (125 минус код) и не режет символ пополам
+        let mut end = reason.len().min(MAX_CONTROL_PAYLOAD - 2);
+        while !reason.is_char_boundary(end) {
+            end -= 1;
+        }
+        let mut payload = code.to_be_bytes().to_vec();
+        payload.extend_from_slice(&reason.as_bytes()[..end]);
+        state
+            .stream
+            .write_all(&encode_frame(Opcode::Close, true, &payload, None))
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed_at().is_some()
+    }
+
+    fn closed_at(&self) -> Option<Instant> {
+        self.state().ok().and_then(|state| state.closed_at)
+    }
+}
+
+pub struct WebSocket {
+    reader: Box<dyn Read + Send>,
+    stream: TcpStream, // для таймаутов чтения и shutdown
+    sender: WebSocketSender,
+    shutdown: ShutdownHandle,
+    ping_interval: Duration,
+    max_message_size: usize,
+    last_seen: Instant,         // когда клиент последний раз что-то присылал
+    ping_sent: Option<Instant>, // ping без ответа
+    fragments: Option<(Opcode, Vec<u8>)>, // начатое фрагментированное сообщение
+}
+
+impl std::fmt::Debug for WebSocket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebSocket")
+            .field("stream", &self.stream)
+            .field("closed", &self.sender.is_closed())
+            .finish()
+    }
+}
+
+// Что делать после очередного фрейма
+enum Step {
+    Continue,
+    Message(Message),
+    Closed,
+}
+
+impl WebSocket {
+    // Ошибка только если не удалось склонировать сокет (кончились дескрипторы)
+    fn new(
+        connection: UpgradedConnection,
+        ping_interval: Duration,
+        max_message_size: usize,
+    ) -> io::Result<Self> {
+        let sender_stream = connection.stream.try_clone()?;
+        // медленный клиент не должен навсегда подвесить отправку
+        sender_stream.set_write_timeout(Some(ping_interval))?;
+        Ok(Self {
+            reader: connection.reader,
+            stream: connection.stream,
+            sender: WebSocketSender {
+                inner: Arc::new(Mutex::new(SenderState {
+                    stream: sender_stream,
+                    closed_at: None,
+                })),
+            },
+            shutdown: connection.shutdown,
+            ping_interval,
+            max_message_size,
+            last_seen: Instant::now(),
+            ping_sent: None,
+            fragments: None,
+        })
+    }
+
+    pub fn sender(&self) -> WebSocketSender {
+        self.sender.clone()
+    }
+
+    pub fn send_text(&self, text: &str) -> io::Result<()> {
+        self.sender.send_text(text)
+    }
+
+    pub fn send_binary(&self, data: &[u8]) -> io::Result<()> {
+        self.sender.send_binary(data)
+    }
+
+    pub fn close(&self, code: u16, reason: &str) -> io::Result<()> {
+        self.sender.close(code, reason)
+    }
+
+    // Следующее сообщение от клиента:
+    //   Ok(Some(message)) -- пришло сообщение
+    //   Ok(None)          -- соединение закрыто по протоколу (close в обе стороны)
+    //   Err(e)            -- клиент пропал или нарушил протокол (close с кодом ошибки уже отправлен)
+    // Пока ждём, сами отвечаем на ping, шлём свои ping и следим за остановкой сервера
+    pub fn recv(&mut self) -> io::Result<Option<Message>> {
+        loop {
+            if self.shutdown.is_shutdown() {
+                let _ = self
+                    .sender
+                    .close(CLOSE_GOING_AWAY, "Server is shutting down");
+                return Ok(None);
+            }
+            if self
+                .sender
+                .closed_at()
+                .is_some_and(|closed_at| closed_at.elapsed() >= CLOSE_TIMEOUT)
+            {
+                return Ok(None); // клиент так и не ответил на наш close
+            }
+            self.keep_alive()?;
+
+            let Some(first) = self.wait_for_frame()? else {
+                continue;
+            };
+            let frame = match read_frame_after(first, &mut self.reader, self.max_message_size) {
+                Ok(frame) => frame,
+                Err(FrameError::Io(e)) => return Err(e),
+                Err(FrameError::Protocol(reason)) => {
+                    return Err(self.fail(CLOSE_PROTOCOL_ERROR, reason));
+                }
+                Err(FrameError::TooLarge) => {
+                    return Err(self.fail(CLOSE_TOO_LARGE, "Message is too large"));
+                }
+            };
+            self.last_seen = Instant::now();
+            self.ping_sent = None; // любой фрейм доказывает, что клиент жив
+
+            match self.handle_frame(frame)? {
+                Step::Continue => {}
+                Step::Message(message) => return Ok(Some(message)),
+                Step::Closed => return Ok(None),
+            }
+        }
+    }
+
+    // Ждём первый байт фрейма не дольше POLL_INTERVAL. None -- пока тихо.
+    // Таймаут только здесь: посреди фрейма ждём дольше, иначе потеряем его начало
+    fn wait_for_frame(&mut self) -> io::Result<Option<u8>> {
+        self.stream.set_read_timeout(Some(POLL_INTERVAL))?;
+        let mut first = [0u8; 1];
+        let read = self.reader.read(&mut first);
+        match read {
+            Ok(0) => Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "Client closed the connection without a close frame",
+            )),
+            Ok(_) => {
+                self.stream.set_read_timeout(Some(self.ping_interval))?;
+                Ok(Some(first[0]))
+            }
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted
+                ) =>
+            {
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // Клиент долго молчит -- проверяем ping-ом. Не ответил и на него -- соединение мёртвое
+    fn keep_alive(&mut self) -> io::Result<()> {
+        match self.ping_sent {
+            Some(sent) if sent.elapsed() >= self.ping_interval => {
+                let _ = self.sender.close(CLOSE_GOING_AWAY, "Ping timeout");
+                Err(io::Error::new(
+                    ErrorKind::TimedOut,
+                    "Client did not answer ping",
+                ))
+            }
+            None if self.last_seen.elapsed() >= self.ping_interval => {
+                self.sender.ping(b"")?;
+                self.ping_sent = Some(Instant::now());
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn handle_frame(&mut self, frame: Frame) -> io::Result<Step> {
+        if !frame.masked {
+            return Err(self.fail(CLOSE_PROTOCOL_ERROR, "Client frames must be masked"));
+        }
+
+        match frame.opcode {
+            Opcode::Ping => {
+                // после нашего close pong уже не нужен -- send_frame вернёт ошибку, её и пропускаем
+                let _ = self.sender.pong(&frame.payload);
+                Ok(Step::Continue)
+            }
+            Opcode::Pong => Ok(Step::Continue),
+            Opcode::Close => self.handle_close(&frame.payload),
+            Opcode::Text | Opcode::Binary => {
+                if self.fragments.is_some() {
+                    return Err(self.fail(
+                        CLOSE_PROTOCOL_ERROR,
+                        "New message started before the previous one was finished",
+                    ));
+                }
+                if frame.fin {
+                    self.finish_message(frame.opcode, frame.payload)
+                } else {
+                    self.fragments = Some((frame.opcode, frame.payload));
+                    Ok(Step::Continue)
+                }
+            }
+            Opcode::Continuation => {
+                let Some((opcode, mut data)) = self.fragments.take() else {
+                    return Err(
+                        self.fail(CLOSE_PROTOCOL_ERROR, "Continuation frame without a message")
+                    );
+                };
+                if data.len() + frame.payload.len() > self.max_message_size {
+                    return Err(self.fail(CLOSE_TOO_LARGE, "Message is too large"));
+                }
+                data.extend_from_slice(&frame.payload);
+                if frame.fin {
+                    self.finish_message(opcode, data)
+                } else {
+                    self.fragments = Some((opcode, data));
+                    Ok(Step::Continue)
+                }
+            }
+        }
+    }
+
+    fn finish_message(&self, opcode: Opcode, data: Vec<u8>) -> io::Result<Step> {
+        match opcode {
+            Opcode::Text => match String::from_utf8(data) {
+                Ok(text) => Ok(Step::Message(Message::Text(text))),
+                Err(_) => Err(self.fail(CLOSE_INVALID_DATA, "Text message is not valid UTF-8")),
+            },
+            _ => Ok(Step::Message(Message::Binary(data))),
+        }
+    }
+
+    // Клиент закрывает соединение (или отвечает на наш close): отвечаем тем же кодом
+    fn handle_close(&self, payload: &[u8]) -> io::Result<Step> {
+        let code = match payload {
+            [] => CLOSE_NORMAL, // кода нет -- закрываем как обычно
+            [_] => {
+                return Err(self.fail(CLOSE_PROTOCOL_ERROR, "Close frame with a truncated code"));
+            }
+            [high, low, reason @ ..] => {
+                let code = u16::from_be_bytes([*high, *low]);
+                // 1004-1006 и 1015 зарезервированы и по сети не ходят
+                if !(1000..5000).contains(&code) || matches!(code, 1004..=1006 | 1015) {
+                    return Err(self.fail(CLOSE_PROTOCOL_ERROR, "Invalid close code"));
+                }
+                if std::str::from_utf8(reason).is_err() {
+                    return Err(self.fail(CLOSE_INVALID_DATA, "Close reason is not valid UTF-8"));
+                }
+                code
+            }
+        };
+        let _ = self.sender.close(code, "");
+        Ok(Step::Closed)
+    }
+
+    // Нарушение протокола: close с кодом ошибки, а вызывающему -- io::Error
+    fn fail(&self, code: u16, reason: &'static str) -> io::Error {
+        let _ = self.sender.close(code, reason);
+        io::Error::new(ErrorKind::InvalidData, reason)
+    }
+}
+
+// Хендлер вернулся -- соединение закрываем. shutdown, а не просто drop: копии сокета
+// могут остаться у других потоков (WebSocketSender), их запись тоже должна прекратиться
+impl Drop for WebSocket {
+    fn drop(&mut self) {
+        let _ = self.sender.close(CLOSE_NORMAL, "");
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+// ------------------------------------------------------------------
+// ---------------------- SHA-1 и base64 ----------------------------
+// ------------------------------------------------------------------
+
+// SHA-1 (RFC 3174). Для рукопожатия и только для него: как хеш для безопасности SHA-1 не годится
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    // дополняем: 0x80, нули до 56 байт по модулю 64, длина в битах (big endian)
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (h, v) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(v);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (chunk, h) in digest.chunks_exact_mut(4).zip(h) {
+        chunk.copy_from_slice(&h.to_be_bytes());
+    }
+    digest
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+// Строгий base64 с паддингом. None -- не base64
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let bytes = text.as_bytes();
+    if !bytes.len().is_multiple_of(4) {
+        return None;
+    }
+    let mut out = Vec::with_capacity(bytes.len() / 4 * 3);
+    for (index, chunk) in bytes.chunks(4).enumerate() {
+        let last = index == bytes.len() / 4 - 1;
+        let padding = chunk.iter().rev().take_while(|&&b| b == b'=').count();
+        if padding > 2 || (padding > 0 && !last) {
+            return None;
+        }
+        let mut n: u32 = 0;
+        for &b in &chunk[..4 - padding] {
+            let value = BASE64_ALPHABET.iter().position(|&a| a == b)?;
+            n = n << 6 | value as u32;
+        }
+        n <<= 6 * padding as u32;
+        out.extend_from_slice(&n.to_be_bytes()[1..4 - padding]);
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn handshake_accept_key() {
+        // пример из RFC 6455, 1.3
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        assert_eq!(
+            base64_encode(&sha1(b"abc")),
+            base64_encode(&[
+                0xA9, 0x99, 0x3E, 0x36, 0x47, 0x06, 0x81, 0x6A, 0xBA, 0x3E, 0x25, 0x71, 0x78, 0x50,
+                0xC2, 0x6C, 0x9C, 0xD0, 0xD8, 0x9D
+            ])
+        );
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_decode("Zm8="), Some(b"fo".to_vec()));
+        assert_eq!(base64_decode("Zg=="), Some(b"f".to_vec()));
+        assert_eq!(base64_decode("Zg==Zm8="), None);
+        assert_eq!(base64_decode("Zm8"), None);
+        assert_eq!(base64_decode("Zm!="), None);
+    }
+
+    #[test]
+    fn handshake_checks_request() {
+        let request = |headers: &[(&str, &str)]| {
+            let mut request = Request::default();
+            for (name, value) in headers {
+                request.headers.insert(name, value).unwrap();
+            }
+            request
+        };
+        let valid = [
+            ("Upgrade", "websocket"),
+            ("Connection", "keep-alive, Upgrade"),
+            ("Sec-WebSocket-Key", "dGhlIHNhbXBsZSBub25jZQ=="),
+            ("Sec-WebSocket-Version", "13"),
+        ];
+
+        let handshake = accept(&request(&valid)).unwrap();
+        let response = handshake.into_response(|_| {});
+        assert_eq!(response.response_code, StatusCode::SWITCHING_PROTOCOLS);
+        assert_eq!(
+            response.headers.get("sec-websocket-accept"),
+            Some("s3pPLMBiTxaQ9kYGzzhZRbK+xOo=")
+        );
+        assert_eq!(response.headers.get("connection"), Some("Upgrade"));
+        assert!(matches!(response.body, Some(BodyType::Upgrade(_))));
+
+        // обычный HTTP-запрос -- 426 с подсказкой, на что переключаться
+        let error = accept(&request(&[])).unwrap_err();
+        assert_eq!(error.error.status, StatusCode::UPGRADE_REQUIRED);
+        let response = error.into_response(&request(&[]));
+        assert_eq!(response.headers.get("upgrade"), Some("websocket"));
+        assert_eq!(response.headers.get("sec-websocket-version"), Some("13"));
+
+        let old_version = request(&[valid[0], valid[1], valid[2], ("Sec-WebSocket-Version", "8")]);
+        assert_eq!(
+            accept(&old_version).unwrap_err().error.status,
+            StatusCode::UPGRADE_REQUIRED
+        );
+
+        for broken in [
+            [valid[0], ("Connection", "keep-alive"), valid[2], valid[3]],
+            [
+                valid[0],
+                valid[1],
+                ("Sec-WebSocket-Key", "c2hvcnQ="),
+                valid[3],
+            ],
+            [
+                valid[0],
+                valid[1],
+                ("Sec-WebSocket-Key", "not base64 at all!!!!!!="),
+                valid[3],
+            ],
+        ] {
+            let error = accept(&request(&broken)).unwrap_err();
+            assert_eq!(error.error.status, StatusCode::BAD_REQUEST, "{broken:?}");
+        }
+
+        let post = Request {
+            method: Method::POST,
+            ..request(&valid)
+        };
+        assert_eq!(
+            accept(&post).unwrap_err().error.status,
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[test]
+    fn frames_roundtrip() {
+        // маленький, 16-битная и 64-битная длина; с маской и без
+        for (len, mask) in [
+            (5, None),
+            (5, Some([1, 2, 3, 4])),
+            (300, Some([0xAA, 0xBB, 0xCC, 0xDD])),
+            (70_000, None),
+        ] {
+            let payload: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let encoded = encode_frame(Opcode::Binary, true, &payload, mask);
+            let frame = read_frame(&mut Cursor::new(encoded), 100_000).unwrap();
+            assert_eq!(frame.opcode, Opcode::Binary);
+            assert!(frame.fin);
+            assert_eq!(frame.masked, mask.is_some());
+            assert_eq!(frame.payload, payload);
+        }
+
+        // пример из RFC 6455, 5.7: замаскированный "Hello"
+        let hello = [
+            0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58,
+        ];
+        let frame = read_frame(&mut Cursor::new(hello), 125).unwrap();
+        assert_eq!(frame.opcode, Opcode::Text);
+        assert_eq!(frame.payload, b"Hello");
+        assert_eq!(
+            encode_frame(Opcode::Text, true, b"Hello", Some([0x37, 0xfa, 0x21, 0x3d])),
+            hello
+        );
+    }
+
+    #[test]
+    fn frames_violating_protocol() {
+        let read = |bytes: Vec<u8>, max: usize| read_frame(&mut Cursor::new(bytes), max);
+
+        assert!(matches!(
+            read(encode_frame(Opcode::Binary, true, &[0; 200], None), 100),
+            Err(FrameError::TooLarge)
+        ));
+        // фрагментированный ping и слишком длинный close
+        assert!(matches!(
+            read(encode_frame(Opcode::Ping, false, b"", None), 100),
+            Err(FrameError::Protocol(_))
+        ));
+        assert!(matches!(
+            read(encode_frame(Opcode::Close, true, &[0; 126], None), 1000),
+            Err(FrameError::Protocol(_))
+        ));
+        // RSV1 и неизвестный opcode
+        assert!(matches!(
+            read(vec![0xC1, 0x00], 100),
+            Err(FrameError::Protocol(_))
+        ));
+        assert!(matches!(
+            read(vec![0x83, 0x00], 100),
+            Err(FrameError::Protocol(_))
+        ));
+        // фрейм оборвался на середине
+        assert!(matches!(
+            read(vec![0x82, 0x05, 1, 2], 100),
+            Err(FrameError::Io(_))
+        ));
+    }
+}
@@ -1,46 +1,145 @@
-use std::thread::{JoinHandle, spawn};
+use std::{
+    io::{Read, Write},
+    net::{SocketAddr, TcpStream},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread::{JoinHandle, spawn},
+    time::{Duration, Instant},
+};
 
 use backend::lib::{
+    app_state::AppState,
     config::Config,
-    http_server::Server,
-    req_res_structs::{BodyType, Response},
+    docker_works::ContainerStatus,
+    fake_backend::FakeBackend,
+    handlers::handler_attach_container,
+    headers::HeaderMap,
+    http_server::{Server, ShutdownHandle},
+    req_res_structs::{BodyType, Response, StatusCode},
     request::Request,
     server_errors::ServerError,
+    websocket::{self, Frame, Message, Opcode, encode_frame, read_frame},
 };
 
-const IP: &str = "127.0.0.1:8080";
+// Сервер, запущенный в отдельном потоке на свободном порту.
+// При выходе из теста (drop) сервер останавливается, а поток дожидается
+struct TestServer {
+    addr: SocketAddr,
+    shutdown: ShutdownHandle,
+    thread: Option<JoinHandle<Result<(), ServerError>>>,
+}
+
+impl TestServer {
+    fn url(&self, path: &str) -> String {
+        format!("http://127.0.0.1:{}{path}", self.addr.port())
+    }
 
-fn server_start() -> JoinHandle<Result<(), ServerError>> {
-    let config = Config::default().with_port(8080);
+    fn connect(&self) -> TcpStream {
+        TcpStream::connect(("127.0.0.1", self.addr.port())).unwrap()
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.shutdown.shutdown();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn server_start() -> TestServer {
+    let config = Config::default().with_port(0); // порт 0 -- ОС выдаст свободный
 
     let mut server = Server::with_config(config).unwrap();
 
-    // регистрация пары path и handlers в Hash-table
+    // регистрация пары path и handlers в дереве маршрутов
     server.GET("/container/", |_| Response {
-        response_code: 200,
-        headers: None,
+        response_code: StatusCode::OK,
+        headers: HeaderMap::new(),
         body: Some(BodyType::Plain("GET CONTAINERS!!!".to_string())),
     }); // 2ой аргумент это тип HandlerFn
 
     server.POST("/container/:id/reboot", |r: &Request| Response {
-        response_code: 200,
-        headers: None,
+        response_code: StatusCode::OK,
+        headers: HeaderMap::new(),
         body: Some(BodyType::Plain(format!(
             "Container ID is: {}\nRebooting...\nTEST",
             r.rest_params.get("id").unwrap()
         ))),
     });
 
-    spawn(move || server.start()) // запускаем сервер в отдельном потоке
+    server.POST("/container/slow", |_| {
+        std::thread::sleep(Duration::from_secs(2));
+        Response {
+            response_code: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: None,
+        }
+    });
+
+    server.POST("/container/echo", |r: &Request| Response {
+        response_code: StatusCode::OK,
+        headers: HeaderMap::new(),
+        body: r.body.clone(),
+    });
+
+    // тело неизвестной заранее длины
+    server.GET("/export", |_| Response {
+        response_code: StatusCode::OK,
+        headers: HeaderMap::new(),
+        body: Some(BodyType::stream(
+            "application/x-tar",
+            std::io::Cursor::new(vec![b'x'; 20_000]),
+        )),
+    });
+
+    // хендлер-замыкание со своим состоянием
+    let hits = Arc::new(AtomicUsize::new(0));
+    server.GET("/hits", move |_| Response {
+        response_code: StatusCode::OK,
+        headers: HeaderMap::new(),
+        body: Some(BodyType::Plain(
+            (hits.fetch_add(1, Ordering::SeqCst) + 1).to_string(),
+        )),
+    });
+
+    // WebSocket: эхо текстом и бинарно, "bye" -- сервер сам закрывает соединение
+    server.GET("/ws/echo", |r: &Request| match websocket::accept(r) {
+        Ok(handshake) => handshake.into_response(|mut ws| {
+            while let Ok(Some(message)) = ws.recv() {
+                let _ = match message {
+                    Message::Text(text) if text == "bye" => ws.close(4000, "bye"),
+                    Message::Text(text) => ws.send_text(&text),
+                    Message::Binary(data) => ws.send_binary(&data),
+                };
+            }
+        }),
+        Err(e) => e.into_response(r),
+    });
+
+    spawn_server(server)
+}
+
+fn spawn_server(server: Server) -> TestServer {
+    let addr = server.local_addr();
+    let shutdown = server.shutdown_handle();
+    let thread = spawn(move || server.start()); // запускаем сервер в отдельном потоке
+
+    TestServer {
+        addr,
+        shutdown,
+        thread: Some(thread),
+    }
 }
 
 #[test]
 fn get_containers() {
-    let _ = server_start();
+    let server = server_start();
 
-    let response = minreq::get(format!("http://{IP}/container/"))
-        .send()
-        .unwrap();
+    let response = minreq::get(server.url("/container/")).send().unwrap();
 
     assert_eq!(response.status_code, 200); // проверяем что ответ 200
     assert_eq!(
@@ -49,9 +148,23 @@ fn get_containers() {
     ) // проверяем что тело как в хендлере
 }
 
+#[test]
+fn query_string_does_not_break_routing() {
+    let server = server_start();
+
+    let response = minreq::get(server.url("/container/?all=true&status=running"))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status_code, 200);
+    assert_eq!(response.as_str().unwrap(), "GET CONTAINERS!!!");
+}
+
 #[test]
 fn post_containers_id() {
-    let response = minreq::post(format!("http://{IP}/container/256/reboot"))
+    let server = server_start();
+
+    let response = minreq::post(server.url("/container/256/reboot"))
         .send()
         .unwrap();
 
@@ -64,8 +177,469 @@ fn post_containers_id() {
 
 #[test]
 fn get_unknown_path() {
-    let response = minreq::get(format!("http://{IP}/unknown/path"))
+    let server = server_start();
+
+    let response = minreq::get(server.url("/unknown/path"))
+        .with_header("X-Request-Id", "test-404")
         .send()
         .unwrap();
     assert_eq!(response.status_code, 404);
+    assert_eq!(response.headers.get("x-request-id").unwrap(), "test-404");
+
+    let body: serde_json::Value = serde_json::from_str(response.as_str().unwrap()).unwrap();
+    assert_eq!(body["error"]["code"], "route_not_found");
+    assert_eq!(body["error"]["request_id"], "test-404");
+    assert!(body["error"]["container_id"].is_null());
+}
+
+#[test]
+fn malformed_request_gets_json_error() {
+    let server = server_start();
+
+    let mut stream = server.connect();
+    stream
+        .write_all(b"GET /container/%zz HTTP/1.1\r\n\r\n")
+        .unwrap();
+
+    let mut raw = String::new();
+    stream.read_to_string(&mut raw).unwrap();
+
+    assert!(raw.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    assert!(raw.contains("X-Request-Id: "));
+    assert!(raw.contains("\"code\":\"bad_request\""));
+}
+
+#[test]
+fn closure_handler_keeps_state() {
+    let server = server_start();
+
+    for expected in ["1", "2", "3"] {
+        let response = minreq::get(server.url("/hits")).send().unwrap();
+        assert_eq!(response.as_str().unwrap(), expected);
+    }
+}
+
+#[test]
+fn post_with_json_body() {
+    let server = server_start();
+
+    let response = minreq::post(server.url("/container/echo"))
+        .with_body("{\"image\":\"nginx\"}")
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status_code, 200);
+    assert_eq!(response.as_str().unwrap(), "{\"image\":\"nginx\"}");
+}
+
+#[test]
+fn slow_handler_does_not_block_other_clients() {
+    let server = server_start();
+
+    // занимаем один воркер медленным запросом
+    let slow_url = server.url("/container/slow");
+    let slow = spawn(move || minreq::post(slow_url).send().unwrap());
+    std::thread::sleep(Duration::from_millis(100));
+
+    let started = Instant::now();
+    let response = minreq::get(server.url("/container/")).send().unwrap();
+
+    assert_eq!(response.status_code, 200);
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert_eq!(slow.join().unwrap().status_code, 200);
+}
+
+#[test]
+fn busy_server_answers_503_without_reset() {
+    // один воркер и очередь на одно место: третье подключение получит 503
+    let config = Config::default()
+        .with_port(0)
+        .with_workers(1)
+        .with_queue_size(1);
+    let mut server = Server::with_config(config).unwrap();
+    server.POST("/container/slow", |_| {
+        std::thread::sleep(Duration::from_secs(1));
+        Response {
+            response_code: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: None,
+        }
+    });
+    let server = spawn_server(server);
+
+    let request = b"POST /container/slow HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
+    let mut busy = Vec::new();
+    for _ in 0..2 {
+        let mut stream = server.connect();
+        stream.write_all(request).unwrap();
+        busy.push(stream);
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    let mut stream = server.connect();
+    stream.write_all(request).unwrap();
+    let mut raw = String::new();
+    // при RST вместо FIN read_to_string вернул бы ошибку
+    stream.read_to_string(&mut raw).unwrap();
+
+    assert!(raw.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+    assert!(raw.contains("Retry-After: 1\r\n"));
+    assert!(raw.contains("server_busy"));
+
+    for mut stream in busy {
+        let mut raw = String::new();
+        stream.read_to_string(&mut raw).unwrap();
+        assert!(raw.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+}
+
+#[test]
+fn pipelined_requests_on_keep_alive_connection() {
+    let server = server_start();
+
+    let mut stream = server.connect();
+    // два запроса одним пакетом: второй просит закрыть соединение
+    stream
+        .write_all(
+            b"POST /container/7/reboot HTTP/1.1\r\nHost: localhost\r\n\r\n\
+GET /container/ HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        )
+        .unwrap();
+
+    let mut raw = String::new();
+    stream.read_to_string(&mut raw).unwrap(); // сервер сам закроет соединение
+
+    let first = raw.find("Container ID is: 7").unwrap();
+    let second = raw.find("GET CONTAINERS!!!").unwrap();
+    assert!(first < second); // ответы в порядке запросов
+    assert!(raw.contains("Connection: keep-alive\r\n"));
+    assert!(raw.contains("Connection: close\r\n"));
+}
+
+#[test]
+fn http10_connection_is_closed() {
+    let server = server_start();
+
+    let mut stream = server.connect();
+    stream
+        .write_all(b"GET /container/ HTTP/1.0\r\n\r\n")
+        .unwrap();
+
+    let mut raw = String::new();
+    stream.read_to_string(&mut raw).unwrap();
+
+    assert!(raw.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(raw.contains("Connection: close\r\n"));
+}
+
+#[test]
+fn shutdown_waits_for_in_flight_request() {
+    let server = server_start();
+
+    // простаивающее keep-alive соединение не должно задерживать остановку
+    let _idle = server.connect();
+
+    let slow_url = server.url("/container/slow");
+    let slow = spawn(move || minreq::post(slow_url).send().unwrap());
+    std::thread::sleep(Duration::from_millis(200));
+
+    let started = Instant::now();
+    drop(server); // shutdown + join
+
+    // start() вернулся только после того, как медленный запрос получил ответ
+    assert_eq!(slow.join().unwrap().status_code, 200);
+    assert!(started.elapsed() >= Duration::from_secs(1));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn wrong_method_is_405_with_allow() {
+    let server = server_start();
+
+    let response = minreq::get(server.url("/container/7/reboot"))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status_code, 405);
+    assert_eq!(response.headers.get("allow").unwrap(), "POST, OPTIONS");
+}
+
+#[test]
+fn options_lists_allowed_methods() {
+    let server = server_start();
+
+    let response = minreq::Request::new(minreq::Method::Options, server.url("/container/"))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status_code, 204);
+    assert_eq!(response.headers.get("allow").unwrap(), "GET, HEAD, OPTIONS");
+}
+
+#[test]
+fn head_uses_get_handler_without_body() {
+    let server = server_start();
+
+    let mut stream = server.connect();
+    stream
+        .write_all(b"HEAD /container/ HTTP/1.1\r\nConnection: close\r\n\r\n")
+        .unwrap();
+
+    let mut raw = String::new();
+    stream.read_to_string(&mut raw).unwrap();
+
+    assert!(raw.starts_with("HTTP/1.1 200 OK\r\n"));
+    // длина как у GET ("GET CONTAINERS!!!"), но самого тела нет
+    assert!(raw.contains("Content-Length: 17\r\n"));
+    assert!(raw.ends_with("\r\n\r\n"));
+}
+
+#[test]
+fn streamed_body_is_chunked_and_keeps_connection() {
+    let server = server_start();
+
+    let mut stream = server.connect();
+    stream
+        .write_all(b"GET /export HTTP/1.1\r\n\r\nGET /hits HTTP/1.1\r\nConnection: close\r\n\r\n")
+        .unwrap();
+
+    let mut raw: Vec<u8> = Vec::new();
+    stream.read_to_end(&mut raw).unwrap();
+    let raw = String::from_utf8(raw).unwrap();
+
+    assert!(raw.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(raw.contains("Content-Type: application/x-tar\r\n"));
+    assert!(raw.contains("Transfer-Encoding: chunked\r\n"));
+    let (_, rest) = raw.split_once("\r\n\r\n").unwrap();
+    let (body, _) = rest.split_once("HTTP/1.1").unwrap(); // до второго ответа
+    assert_eq!(body.matches('x').count(), 20_000);
+    assert!(body.ends_with("0\r\n\r\n"));
+    // после потокового ответа то же соединение обслужило второй запрос
+    assert!(raw.ends_with("\r\n\r\n1"));
+}
+
+// Потоковое тело, которому всё время нечего отдать; при drop отмечается в dropped
+struct IdleBody {
+    dropped: Arc<AtomicUsize>,
+}
+
+impl Read for IdleBody {
+    fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+        std::thread::sleep(Duration::from_millis(20));
+        Err(std::io::ErrorKind::WouldBlock.into())
+    }
+}
+
+impl Drop for IdleBody {
+    fn drop(&mut self) {
+        self.dropped.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn idle_stream_is_dropped_when_client_leaves() {
+    let mut server = Server::with_config(Config::default().with_port(0)).unwrap();
+    let dropped = Arc::new(AtomicUsize::new(0));
+    let body_dropped = dropped.clone();
+    server.GET("/idle", move |_| Response {
+        response_code: StatusCode::OK,
+        headers: HeaderMap::new(),
+        body: Some(BodyType::stream(
+            "text/plain",
+            IdleBody {
+                dropped: body_dropped.clone(),
+            },
+        )),
+    });
+    let server = spawn_server(server);
+
+    let mut stream = server.connect();
+    stream.write_all(b"GET /idle HTTP/1.1\r\n\r\n").unwrap();
+    let mut head = [0u8; 64];
+    let read = stream.read(&mut head).unwrap();
+    assert!(head[..read].starts_with(b"HTTP/1.1 200 OK\r\n"));
+
+    // пока клиент на месте, молчащий поток не трогаем
+    std::thread::sleep(Duration::from_millis(200));
+    assert_eq!(dropped.load(Ordering::SeqCst), 0);
+
+    // тело молчит, но клиент ушёл -- сервер это замечает и бросает поток
+    drop(stream);
+    let started = Instant::now();
+    while dropped.load(Ordering::SeqCst) == 0 {
+        assert!(
+            started.elapsed() < Duration::from_secs(5),
+            "idle stream is still running"
+        );
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+// Рукопожатие WebSocket руками; extra -- байты, которые клиент шлёт сразу вслед за запросом
+fn websocket_connect(server: &TestServer, path: &str, extra: &[u8]) -> (TcpStream, String) {
+    let mut stream = server.connect();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let mut request = format!(
+        "GET {path} HTTP/1.1\r\nHost: test\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n"
+    )
+    .into_bytes();
+    request.extend_from_slice(extra);
+    stream.write_all(&request).unwrap();
+
+    // заголовки ответа -- по байту, чтобы не съесть начало первого фрейма
+    let mut head: Vec<u8> = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        let mut byte = [0u8; 1];
+        stream.read_exact(&mut byte).unwrap();
+        head.push(byte[0]);
+    }
+    (stream, String::from_utf8(head).unwrap())
+}
+
+fn send_frame(stream: &mut TcpStream, opcode: Opcode, payload: &[u8]) {
+    stream
+        .write_all(&encode_frame(opcode, true, payload, Some([7, 1, 9, 3])))
+        .unwrap();
+}
+
+fn next_frame(stream: &mut TcpStream) -> Frame {
+    read_frame(stream, 1 << 20).unwrap()
+}
+
+#[test]
+fn websocket_echo_ping_and_close() {
+    let server = server_start();
+
+    // первый фрейм пришёл вместе с запросом -- он не должен потеряться при смене протокола
+    let hello = encode_frame(Opcode::Text, true, b"hello", Some([1, 2, 3, 4]));
+    let (mut stream, head) = websocket_connect(&server, "/ws/echo", &hello);
+    assert!(head.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+    assert!(head.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+    assert!(head.contains("Connection: Upgrade\r\n"));
+    assert!(!head.contains("Content-Length"));
+
+    let frame = next_frame(&mut stream);
+    assert_eq!(
+        (frame.opcode, frame.payload),
+        (Opcode::Text, b"hello".to_vec())
+    );
+    assert!(!frame.masked);
+
+    // фрагментированное бинарное сообщение с ping посередине
+    stream
+        .write_all(&encode_frame(
+            Opcode::Binary,
+            false,
+            &[1, 2],
+            Some([9, 9, 9, 9]),
+        ))
+        .unwrap();
+    send_frame(&mut stream, Opcode::Ping, b"are you there");
+    stream
+        .write_all(&encode_frame(
+            Opcode::Continuation,
+            true,
+            &[3],
+            Some([5, 5, 5, 5]),
+        ))
+        .unwrap();
+    let pong = next_frame(&mut stream);
+    assert_eq!(
+        (pong.opcode, pong.payload),
+        (Opcode::Pong, b"are you there".to_vec())
+    );
+    let frame = next_frame(&mut stream);
+    assert_eq!(
+        (frame.opcode, frame.payload),
+        (Opcode::Binary, vec![1, 2, 3])
+    );
+
+    // закрывает сервер: close с его кодом, отвечаем тем же -- соединение закрыто
+    send_frame(&mut stream, Opcode::Text, b"bye");
+    let close = next_frame(&mut stream);
+    assert_eq!(close.opcode, Opcode::Close);
+    assert_eq!(close.payload, b"\x0f\xa0bye");
+    send_frame(&mut stream, Opcode::Close, &close.payload[..2]);
+    let mut rest = Vec::new();
+    stream.read_to_end(&mut rest).unwrap();
+    assert!(rest.is_empty());
+
+    // закрывает клиент; незамаскированный фрейм -- нарушение протокола (1002)
+    let (mut stream, _) = websocket_connect(&server, "/ws/echo", b"");
+    send_frame(&mut stream, Opcode::Close, &1000u16.to_be_bytes());
+    assert_eq!(next_frame(&mut stream).payload, 1000u16.to_be_bytes());
+    let (mut stream, _) = websocket_connect(&server, "/ws/echo", b"");
+    stream
+        .write_all(&encode_frame(Opcode::Text, true, b"plain", None))
+        .unwrap();
+    assert_eq!(next_frame(&mut stream).payload[..2], 1002u16.to_be_bytes());
+}
+
+#[test]
+fn websocket_endpoint_rejects_plain_http() {
+    let server = server_start();
+
+    let response = minreq::get(server.url("/ws/echo")).send().unwrap();
+    assert_eq!(response.status_code, 426);
+    assert_eq!(response.headers.get("upgrade").unwrap(), "websocket");
+    assert_eq!(response.headers.get("sec-websocket-version").unwrap(), "13");
+}
+
+#[test]
+fn attach_bridges_tty_session() {
+    let fake = Arc::new(FakeBackend::new().with_container("web1", "alpine", ContainerStatus::Up));
+    let state = Arc::new(AppState::with_backend(Config::default(), fake.clone()));
+    let mut server = Server::with_config(Config::default().with_port(0)).unwrap();
+    server.GET(
+        "/container/:id/attach",
+        state.handler(handler_attach_container),
+    );
+    let server = spawn_server(server);
+
+    let (mut stream, head) = websocket_connect(&server, "/container/web1/attach?cmd=sh", b"");
+    assert!(head.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+
+    // stdin -- бинарно и текстом; resize и кривое сообщение по пути
+    send_frame(&mut stream, Opcode::Binary, b"echo hi\r");
+    send_frame(
+        &mut stream,
+        Opcode::Text,
+        br#"{"type": "resize", "rows": 40, "cols": 120}"#,
+    );
+    send_frame(&mut stream, Opcode::Text, b"not json");
+    send_frame(
+        &mut stream,
+        Opcode::Text,
+        br#"{"type": "input", "data": "exit 7\r"}"#,
+    );
+
+    let mut terminal: Vec<u8> = Vec::new();
+    let mut texts: Vec<serde_json::Value> = Vec::new();
+    loop {
+        let frame = next_frame(&mut stream);
+        match frame.opcode {
+            Opcode::Binary => terminal.extend(frame.payload),
+            Opcode::Text => texts.push(serde_json::from_slice(&frame.payload).unwrap()),
+            Opcode::Close => {
+                assert_eq!(frame.payload[..2], 1000u16.to_be_bytes());
+                send_frame(&mut stream, Opcode::Close, &frame.payload[..2]);
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    assert_eq!(
+        String::from_utf8(terminal).unwrap(),
+        "$ echo hi\r\nhi\r\n$ exit 7\r\n"
+    );
+    assert_eq!(texts.len(), 2);
+    assert_eq!(texts[0]["type"], "error");
+    assert_eq!(
+        texts[1],
+        serde_json::json!({"type": "exit", "exit_code": 7})
+    );
+    assert!(fake.calls().contains(&"resize web1 40x120".to_string()));
 }